@@ -0,0 +1,127 @@
+use crate::error::{Error, ErrorKind, Result};
+use crate::metadata::{MemoryMetaData, MetaData};
+use crate::{MmapFileExt, MmapFileMutExt};
+use memmapix::MmapMut;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// A private anonymous mmap, which has the page-aligned mmap semantics, but is not backed by a file.
+///
+/// It is usually created by `to_anonymous` of [`MmapFileExt`], which snapshots the content of
+/// another mmap. The path is only kept for reference, nothing is read from or written to it.
+///
+/// [`MmapFileExt`]: crate::MmapFileExt
+pub struct AnonymousMmapFileMut {
+    mmap: MmapMut,
+    path: PathBuf,
+    create_at: SystemTime,
+}
+
+impl AnonymousMmapFileMut {
+    /// Creates a zeroed anonymous mmap of `len` bytes.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::MmapFailed))` if the anonymous mmap cannot be created.
+    pub fn new<P: AsRef<Path>>(path: P, len: usize) -> Result<Self> {
+        Ok(Self {
+            mmap: map_anon(len)?,
+            path: path.as_ref().to_path_buf(),
+            create_at: SystemTime::now(),
+        })
+    }
+
+    /// Creates an anonymous mmap containing a copy of `src`.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::MmapFailed))` if the anonymous mmap cannot be created.
+    pub fn copy_from_slice<P: AsRef<Path>>(path: P, src: &[u8]) -> Result<Self> {
+        let mut this = Self::new(path, src.len())?;
+        this.mmap.copy_from_slice(src);
+        Ok(this)
+    }
+
+    /// Sets the path reported by `path`, which is only a label of the anonymous mmap.
+    pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+        self.path = label.into();
+        self
+    }
+
+    #[inline]
+    pub(crate) fn set_path_label(&mut self, label: PathBuf) {
+        self.path = label;
+    }
+}
+
+fn map_anon(len: usize) -> Result<MmapMut> {
+    MmapMut::map_anon(len).map_err(|e| Error::new(ErrorKind::MmapFailed, e))
+}
+
+impl MmapFileExt for AnonymousMmapFileMut {
+    #[inline]
+    fn len(&self) -> usize {
+        self.mmap.len()
+    }
+
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        self.mmap.as_ref()
+    }
+
+    #[inline]
+    fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    #[inline]
+    fn is_exec(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn kind(&self) -> crate::MapKind {
+        crate::MapKind::Anonymous
+    }
+
+    fn metadata(&self) -> Result<MetaData> {
+        Ok(MetaData::memory(MemoryMetaData::new(
+            self.mmap.len() as u64,
+            self.create_at,
+        )))
+    }
+
+    noop_file_lock!();
+}
+
+impl MmapFileMutExt for AnonymousMmapFileMut {
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.mmap.as_mut()
+    }
+
+    #[inline]
+    fn is_cow(&self) -> bool {
+        false
+    }
+
+    noop_flush!();
+
+    fn truncate(&mut self, max_sz: u64) -> Result<()> {
+        crate::utils::check_map_size(max_sz)?;
+        // an anonymous mmap cannot be resized in place, map a new one and copy the content over
+        let mut mmap = map_anon(max_sz as usize)?;
+        let n = mmap.len().min(self.mmap.len());
+        mmap[..n].copy_from_slice(&self.mmap[..n]);
+        self.mmap = mmap;
+        Ok(())
+    }
+
+    #[inline]
+    fn drop_remove(self) -> Result<()> {
+        Ok(())
+    }
+
+    #[inline]
+    fn close_with_truncate(self, _max_sz: i64) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+#[cfg(feature = "crc32")]
+use crc32fast::Hasher;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "xxh3")]
+use xxhash_rust::xxh3::xxh3_64;
+
+/// The size of the chunks the buffer is split into when computing checksums.
+///
+/// The chunk size is fixed (rather than derived from the number of threads), so
+/// the result of the chunked checksums does not depend on the machine.
+#[cfg(any(feature = "xxh3", all(feature = "crc32", any(feature = "rayon", test))))]
+pub(crate) const CHECKSUM_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Computes the CRC32 (IEEE) of the buffer.
+///
+/// The chunk states are merged with the crc32 combine operation, so the result is
+/// the same as hashing the whole buffer at once.
+#[cfg(all(feature = "crc32", feature = "rayon"))]
+pub(crate) fn crc32(buf: &[u8]) -> u32 {
+    buf.par_chunks(CHECKSUM_CHUNK_SIZE)
+        .map(|chunk| {
+            let mut h = Hasher::new();
+            h.update(chunk);
+            h
+        })
+        .reduce(Hasher::new, |mut acc, h| {
+            acc.combine(&h);
+            acc
+        })
+        .finalize()
+}
+
+/// Computes the CRC32 (IEEE) of the buffer.
+#[cfg(all(feature = "crc32", not(feature = "rayon")))]
+pub(crate) fn crc32(buf: &[u8]) -> u32 {
+    let mut h = Hasher::new();
+    h.update(buf);
+    h.finalize()
+}
+
+/// Computes the chunked XXH3 (64 bit) digest of the buffer.
+///
+/// Buffers no larger than [`CHECKSUM_CHUNK_SIZE`] are hashed directly. Larger buffers
+/// are hashed chunk by chunk, and the digest is the XXH3 of the little-endian
+/// concatenation of the chunk digests.
+#[cfg(feature = "xxh3")]
+pub(crate) fn xxh3(buf: &[u8]) -> u64 {
+    if buf.len() <= CHECKSUM_CHUNK_SIZE {
+        return xxh3_64(buf);
+    }
+
+    #[cfg(feature = "rayon")]
+    let digests: Vec<u64> = buf.par_chunks(CHECKSUM_CHUNK_SIZE).map(xxh3_64).collect();
+    #[cfg(not(feature = "rayon"))]
+    let digests: Vec<u64> = buf.chunks(CHECKSUM_CHUNK_SIZE).map(xxh3_64).collect();
+
+    let mut folded = Vec::with_capacity(digests.len() * 8);
+    digests
+        .iter()
+        .for_each(|d| folded.extend_from_slice(&d.to_le_bytes()));
+    xxh3_64(&folded)
+}
+
+/// Computes the Adler-32 checksum (as used by zlib) of the buffer.
+pub(crate) fn adler32(buf: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    // the largest number of bytes summed before `b` may overflow a u32
+    const NMAX: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in buf.chunks(NMAX) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// A resumable CRC32 (IEEE) state, which makes it possible to maintain the checksum of an
+/// append-only file by hashing only the newly appended bytes.
+///
+/// The state of the existing content can be obtained by `checksum_incremental`, then each
+/// appended block is fed by [`update_from_range`] (or [`update`]), and [`finalize`] returns
+/// the same value as a one-shot CRC32 of all the bytes fed.
+///
+/// [`update_from_range`]: #method.update_from_range
+/// [`update`]: #method.update
+/// [`finalize`]: #method.finalize
+#[cfg(feature = "crc32")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+#[derive(Debug, Clone, Default)]
+pub struct ChecksumState {
+    hasher: Hasher,
+}
+
+#[cfg(feature = "crc32")]
+impl ChecksumState {
+    /// Creates a new state, which has not hashed any bytes.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a state which resumes from the `crc` of `len` bytes.
+    #[inline]
+    pub fn with_initial(crc: u32, len: u64) -> Self {
+        Self {
+            hasher: Hasher::new_with_initial_len(crc, len),
+        }
+    }
+
+    /// Feeds the bytes to the state.
+    #[inline]
+    pub fn update(&mut self, buf: &[u8]) {
+        self.hasher.update(buf);
+    }
+
+    /// Feeds the bytes of the range of the mmap to the state.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap,
+    /// the state is left untouched.
+    #[cfg(feature = "sync")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "sync")))]
+    pub fn update_from_range(
+        &mut self,
+        map: &impl crate::MmapFileExt,
+        range: std::ops::Range<usize>,
+    ) -> crate::error::Result<()> {
+        let buf = map.as_slice().get(range).ok_or_else(|| {
+            crate::error::Error::from(crate::error::ErrorKind::EOF)
+        })?;
+        self.hasher.update(buf);
+        Ok(())
+    }
+
+    /// Returns the CRC32 of all the bytes fed.
+    #[inline]
+    pub fn finalize(self) -> u32 {
+        self.hasher.finalize()
+    }
+}
+
+/// The rsync weak checksum (an Adler32-like checksum) of a window of bytes, which can be
+/// rolled forward by one byte in constant time.
+///
+/// The sender computes the checksum of every block, the receiver rolls a window of the block
+/// size over its data by [`roll`] to find the blocks it already has, and confirms the
+/// candidates by a strong checksum (see `rolling_checksums`).
+///
+/// [`roll`]: #method.roll
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct RollingChecksum {
+    a: u16,
+    b: u16,
+    len: usize,
+}
+
+impl RollingChecksum {
+    /// Computes the checksum of the window.
+    pub fn new(window: &[u8]) -> Self {
+        let len = window.len();
+        let (a, b) = window
+            .iter()
+            .enumerate()
+            .fold((0u16, 0u16), |(a, b), (i, &x)| {
+                (
+                    a.wrapping_add(x as u16),
+                    b.wrapping_add(((len - i) as u16).wrapping_mul(x as u16)),
+                )
+            });
+        Self { a, b, len }
+    }
+
+    /// Returns the checksum, `a + (b << 16)` in rsync terms.
+    #[inline]
+    pub fn value(&self) -> u32 {
+        (self.a as u32) | ((self.b as u32) << 16)
+    }
+
+    /// Slides the window forward by one byte, `out` is the first byte of the window
+    /// and `inc` is the byte following the window.
+    #[inline]
+    pub fn roll(&mut self, out: u8, inc: u8) {
+        self.a = self.a.wrapping_sub(out as u16).wrapping_add(inc as u16);
+        self.b = self
+            .b
+            .wrapping_sub((self.len as u16).wrapping_mul(out as u16))
+            .wrapping_add(self.a);
+    }
+}
+
+/// The iterator returned by `rolling_checksums`, which yields the weak (see [`RollingChecksum`])
+/// and the strong (the first 16 bytes of the BLAKE3 hash) checksums of each block.
+///
+/// [`RollingChecksum`]: struct.RollingChecksum.html
+#[cfg(feature = "blake3")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+#[derive(Debug, Clone)]
+pub struct BlockChecksums<'a> {
+    chunks: std::slice::Chunks<'a, u8>,
+}
+
+#[cfg(feature = "blake3")]
+impl<'a> BlockChecksums<'a> {
+    pub(crate) fn new(buf: &'a [u8], block_size: usize) -> Self {
+        Self {
+            chunks: buf.chunks(block_size),
+        }
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl<'a> Iterator for BlockChecksums<'a> {
+    type Item = (u32, [u8; 16]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(|block| {
+            let mut strong = [0; 16];
+            strong.copy_from_slice(&blake3::hash(block).as_bytes()[..16]);
+            (RollingChecksum::new(block).value(), strong)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+#[cfg(feature = "blake3")]
+impl<'a> ExactSizeIterator for BlockChecksums<'a> {}
+
+/// Returns the leaves of the Merkle tree, i.e. the digests of the `block_size` blocks of the buffer.
+/// An empty buffer has a single leaf, the digest of the empty block.
+#[cfg(feature = "digest")]
+fn merkle_leaves<D: digest::Digest>(buf: &[u8], block_size: usize) -> Vec<digest::Output<D>> {
+    if buf.is_empty() {
+        return vec![D::digest(buf)];
+    }
+    buf.chunks(block_size).map(D::digest).collect()
+}
+
+/// Returns the level above, each node is the digest of the concatenation of its two children.
+/// The last node of a level with an odd number of nodes is promoted as is.
+#[cfg(feature = "digest")]
+fn merkle_parents<D: digest::Digest>(level: &[digest::Output<D>]) -> Vec<digest::Output<D>> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => {
+                let mut h = D::new();
+                h.update(left);
+                h.update(right);
+                h.finalize()
+            }
+            [single] => single.clone(),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Computes the Merkle root over the `block_size` blocks of the buffer.
+#[cfg(feature = "digest")]
+pub(crate) fn merkle_root<D: digest::Digest>(buf: &[u8], block_size: usize) -> digest::Output<D> {
+    let mut level = merkle_leaves::<D>(buf, block_size);
+    while level.len() > 1 {
+        level = merkle_parents::<D>(&level);
+    }
+    level.pop().unwrap()
+}
+
+/// Computes the sibling path of the block at `block_index`, from the leaves to the root.
+/// Returns `None` if the block does not exist.
+#[cfg(feature = "digest")]
+pub(crate) fn merkle_proof<D: digest::Digest>(
+    buf: &[u8],
+    block_size: usize,
+    block_index: usize,
+) -> Option<Vec<digest::Output<D>>> {
+    let mut level = merkle_leaves::<D>(buf, block_size);
+    if block_index >= level.len() {
+        return None;
+    }
+
+    let mut proof = Vec::new();
+    let mut idx = block_index;
+    while level.len() > 1 {
+        // a promoted node has no sibling at this level
+        if let Some(sibling) = level.get(idx ^ 1) {
+            proof.push(sibling.clone());
+        }
+        level = merkle_parents::<D>(&level);
+        idx /= 2;
+    }
+    Some(proof)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "crc32")]
+    #[test]
+    fn test_crc32_matches_single_pass() {
+        let buf = (0..CHECKSUM_CHUNK_SIZE * 2 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        assert_eq!(crc32(&buf), crc32fast::hash(&buf));
+    }
+
+    #[cfg(feature = "xxh3")]
+    #[test]
+    fn test_xxh3_chunked() {
+        let small = b"some data...";
+        assert_eq!(xxh3(small), xxh3_64(small));
+
+        let buf = (0..CHECKSUM_CHUNK_SIZE * 2 + 17)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let mut folded = Vec::new();
+        buf.chunks(CHECKSUM_CHUNK_SIZE)
+            .for_each(|c| folded.extend_from_slice(&xxh3_64(c).to_le_bytes()));
+        assert_eq!(xxh3(&buf), xxh3_64(&folded));
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        // long enough to wrap the sums many times
+        let buf = vec![0xff; 100_000];
+        let (mut a, mut b) = (1u64, 0u64);
+        for &byte in &buf {
+            a = (a + byte as u64) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(adler32(&buf), ((b << 16) | a) as u32);
+    }
+
+    #[cfg(feature = "crc32")]
+    #[test]
+    fn test_checksum_state_incremental() {
+        let buf = (0..10_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+        let (head, tail) = buf.split_at(6_000);
+
+        let mut state = ChecksumState::with_initial(crc32(head), head.len() as u64);
+        tail.chunks(1_000).for_each(|c| state.update(c));
+        assert_eq!(state.finalize(), crc32fast::hash(&buf));
+
+        let mut state = ChecksumState::new();
+        buf.chunks(3_000).for_each(|c| state.update(c));
+        assert_eq!(state.finalize(), crc32fast::hash(&buf));
+    }
+
+    #[test]
+    fn test_rolling_checksum() {
+        let buf = (0..10_000).map(|i| (i * 7 % 251) as u8).collect::<Vec<_>>();
+        let window = 700;
+
+        let mut rolling = RollingChecksum::new(&buf[..window]);
+        for start in 1..=buf.len() - window {
+            rolling.roll(buf[start - 1], buf[start + window - 1]);
+            assert_eq!(rolling, RollingChecksum::new(&buf[start..start + window]));
+        }
+        assert_eq!(RollingChecksum::new(&[]).value(), 0);
+    }
+}
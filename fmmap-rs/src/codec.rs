@@ -0,0 +1,79 @@
+use bytes::{BufMut, BytesMut};
+use std::io::{self, Read};
+
+/// The compression formats which can be decompressed into an in-memory mmap file.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Codec {
+    /// gzip, the members of a multi-member stream are all decompressed
+    #[cfg(feature = "gzip")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+    Gzip,
+    /// zstd
+    #[cfg(feature = "zstd")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zstd")))]
+    Zstd,
+    /// lz4 frame format
+    #[cfg(feature = "lz4")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "lz4")))]
+    Lz4,
+}
+
+/// Decompresses all the data from the reader.
+pub(crate) fn decompress<R: Read>(reader: R, codec: Codec) -> io::Result<BytesMut> {
+    let mut writer = BytesMut::new().writer();
+    match codec {
+        #[cfg(feature = "gzip")]
+        Codec::Gzip => {
+            io::copy(&mut flate2::read::MultiGzDecoder::new(reader), &mut writer)?;
+        }
+        #[cfg(feature = "zstd")]
+        Codec::Zstd => {
+            zstd::stream::copy_decode(reader, &mut writer)?;
+        }
+        #[cfg(feature = "lz4")]
+        Codec::Lz4 => {
+            io::copy(&mut lz4_flex::frame::FrameDecoder::new(reader), &mut writer)?;
+        }
+    }
+    Ok(writer.into_inner())
+}
+
+/// Compresses the data, for the tests to build compressed files.
+#[cfg(test)]
+pub(crate) fn compress(data: &[u8], codec: Codec) -> Vec<u8> {
+    use std::io::Write;
+
+    match codec {
+        #[cfg(feature = "gzip")]
+        Codec::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        #[cfg(feature = "zstd")]
+        Codec::Zstd => zstd::stream::encode_all(data, 0).unwrap(),
+        #[cfg(feature = "lz4")]
+        Codec::Lz4 => {
+            let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    #[test]
+    fn test_decompress_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"some data...").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let data = decompress(compressed.as_slice(), Codec::Gzip).unwrap();
+        assert_eq!(data.as_ref(), b"some data...");
+    }
+}
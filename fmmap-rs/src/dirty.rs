@@ -0,0 +1,106 @@
+use std::ops::Range;
+use std::sync::Mutex;
+
+/// The ranges of a mutable mmap which are written but not flushed yet.
+///
+/// The ranges are kept sorted, and the overlapping or adjacent ones are coalesced.
+/// They sit behind a mutex because the flushes, which clean the ranges, take `&self`.
+#[derive(Default)]
+pub(crate) struct DirtyRanges {
+    ranges: Mutex<Vec<Range<usize>>>,
+}
+
+impl DirtyRanges {
+    /// Marks the range as dirty.
+    pub(crate) fn mark(&self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let mut ranges = self.ranges.lock().unwrap();
+        // the first range which ends at or after the start of the new one
+        let lo = ranges.partition_point(|r| r.end < range.start);
+        // the first range which starts after the end of the new one
+        let hi = ranges.partition_point(|r| r.start <= range.end);
+        let merged = if lo < hi {
+            ranges[lo].start.min(range.start)..ranges[hi - 1].end.max(range.end)
+        } else {
+            range
+        };
+        ranges.splice(lo..hi, std::iter::once(merged));
+    }
+
+    /// Marks the range as clean, e.g. after it is flushed.
+    pub(crate) fn clean(&self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let mut ranges = self.ranges.lock().unwrap();
+        let mut remains = Vec::with_capacity(ranges.len() + 1);
+        for r in ranges.drain(..) {
+            if r.end <= range.start || r.start >= range.end {
+                remains.push(r);
+                continue;
+            }
+            if r.start < range.start {
+                remains.push(r.start..range.start);
+            }
+            if r.end > range.end {
+                remains.push(range.end..r.end);
+            }
+        }
+        *ranges = remains;
+    }
+
+    /// Marks the whole mmap as clean.
+    pub(crate) fn clean_all(&self) {
+        self.ranges.lock().unwrap().clear();
+    }
+
+    /// Drops the dirty bytes beyond `len`, after the mmap is truncated.
+    pub(crate) fn clamp(&self, len: usize) {
+        self.clean(len..usize::MAX);
+    }
+
+    pub(crate) fn is_dirty(&self) -> bool {
+        !self.ranges.lock().unwrap().is_empty()
+    }
+
+    pub(crate) fn to_vec(&self) -> Vec<Range<usize>> {
+        self.ranges.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_ranges() {
+        let dirty = DirtyRanges::default();
+        assert!(!dirty.is_dirty());
+        dirty.mark(10..20);
+        dirty.mark(30..40);
+        dirty.mark(0..0);
+        assert_eq!(dirty.to_vec(), vec![10..20, 30..40]);
+
+        // adjacent and overlapping ranges are coalesced
+        dirty.mark(20..25);
+        dirty.mark(5..12);
+        assert_eq!(dirty.to_vec(), vec![5..25, 30..40]);
+        dirty.mark(24..31);
+        assert_eq!(dirty.to_vec(), vec![5..40]);
+
+        dirty.clean(10..15);
+        assert_eq!(dirty.to_vec(), vec![5..10, 15..40]);
+        dirty.clamp(20);
+        assert_eq!(dirty.to_vec(), vec![5..10, 15..20]);
+        dirty.clean(0..100);
+        assert!(!dirty.is_dirty());
+
+        dirty.mark(0..8);
+        dirty.clean_all();
+        assert!(dirty.to_vec().is_empty());
+    }
+}
@@ -38,27 +38,23 @@ macro_rules! remmap {
 macro_rules! impl_flush {
     () => {
         fn flush(&self) -> crate::error::Result<()> {
-            self.mmap
-                .flush()
-                .map_err(|e| Error::new_source_msg(ErrorKind::FlushFailed, self.path_string(), e))
+            eintr_retry!(self.mmap.flush())
+                .map_err(|e| Error::flush_failed(self.path_string(), 0, self.mmap.len(), e))
         }
 
         fn flush_async(&self) -> crate::error::Result<()> {
-            self.mmap
-                .flush_async()
-                .map_err(|e| Error::new_source_msg(ErrorKind::FlushFailed, self.path_string(), e))
+            eintr_retry!(self.mmap.flush_async())
+                .map_err(|e| Error::flush_failed(self.path_string(), 0, self.mmap.len(), e))
         }
 
         fn flush_range(&self, offset: usize, len: usize) -> crate::error::Result<()> {
-            self.mmap
-                .flush_range(offset, len)
-                .map_err(|e| Error::new_source_msg(ErrorKind::FlushFailed, self.path_string(), e))
+            eintr_retry!(self.mmap.flush_range(offset, len))
+                .map_err(|e| Error::flush_failed(self.path_string(), offset, len, e))
         }
 
         fn flush_async_range(&self, offset: usize, len: usize) -> crate::error::Result<()> {
-            self.mmap
-                .flush_async_range(offset, len)
-                .map_err(|e| Error::new_source_msg(ErrorKind::FlushFailed, self.path_string(), e))
+            eintr_retry!(self.mmap.flush_async_range(offset, len))
+                .map_err(|e| Error::flush_failed(self.path_string(), offset, len, e))
         }
     };
 }
@@ -67,16 +63,12 @@ macro_rules! impl_file_lock {
     () => {
         #[inline]
         fn lock_exclusive(&self) -> crate::error::Result<()> {
-            self.file
-                .lock_exclusive()
-                .map_err(|e| Error::new(ErrorKind::IO, e))
+            eintr_retry!(self.file.lock_exclusive()).map_err(|e| Error::new(ErrorKind::IO, e))
         }
 
         #[inline]
         fn lock_shared(&self) -> crate::error::Result<()> {
-            self.file
-                .lock_shared()
-                .map_err(|e| Error::new(ErrorKind::IO, e))
+            eintr_retry!(self.file.lock_shared()).map_err(|e| Error::new(ErrorKind::IO, e))
         }
 
         #[inline]
@@ -95,7 +87,7 @@ macro_rules! impl_file_lock {
 
         #[inline]
         fn unlock(&self) -> crate::error::Result<()> {
-            self.file.unlock().map_err(|e| Error::new(ErrorKind::IO, e))
+            eintr_retry!(self.file.unlock()).map_err(|e| Error::new(ErrorKind::IO, e))
         }
     };
 }
@@ -133,6 +125,11 @@ cfg_sync! {
                 fn is_exec(&self) -> bool {
                     self.exec
                 }
+
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Disk
+                }
             }
         };
     }
@@ -147,6 +144,11 @@ cfg_sync! {
                 fn is_exec(&self) -> bool {
                     false
                 }
+
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Disk
+                }
             }
         };
     }
@@ -187,6 +189,11 @@ cfg_async! {
                     self.exec
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Disk
+                }
+
                 impl_file_lock!();
             }
         };
@@ -197,11 +204,12 @@ cfg_async! {
             #[async_trait]
             impl AsyncMmapFileExt for $name {
                 fn len(&self) -> usize {
-                    self.mmap.len()
+                    self.logical_len.unwrap_or_else(|| self.mmap.len())
                 }
 
                 fn as_slice(&self) -> &[u8] {
-                    self.mmap.as_ref()
+                    let len = self.len();
+                    &self.mmap[..len]
                 }
 
                 fn path(&self) -> &Path {
@@ -223,6 +231,11 @@ cfg_async! {
                     false
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Disk
+                }
+
                 impl_file_lock!();
             }
         };
@@ -237,6 +250,7 @@ cfg_async! {
                 pub(crate) file: $base_file,
                 pub(crate) path: PathBuf,
                 exec: bool,
+                opts: Option<MmapOptions>,
             }
 
             impl_async_mmap_file_ext!(AsyncDiskMmapFile);
@@ -370,22 +384,54 @@ cfg_async! {
                     Self::open_exec_in(path, Some(opts)).await
                 }
 
+                /// Leaks the memory map, and returns a `'static` slice of its content, e.g. for a lookup
+                /// table which is mapped once at startup and used for the lifetime of the process.
+                ///
+                /// The mmap and the file handle are intentionally never released, unmapped or closed,
+                /// so only call this for a bounded number of files.
+                pub fn leak(self) -> &'static [u8] {
+                    let this: &'static Self = Box::leak(Box::new(self));
+                    this.mmap.as_ref()
+                }
+
+                /// Returns the [`MmapOptions`] the file is mapped with, e.g. the offset and length of
+                /// the window, or `None` if the whole file is mapped by the default options.
+                ///
+                /// [`MmapOptions`]: memmapix::MmapOptions
+                #[inline]
+                pub fn options(&self) -> Option<&MmapOptions> {
+                    self.opts.as_ref()
+                }
+
                 async fn open_in<P: AsRef<Path>>(path: P, opts: Option<AsyncOptions>) -> Result<Self, Error> {
-                    let file = open_read_only_file_async(&path).await.map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
+                    let _permit = crate::limiter::acquire(opts.as_ref().and_then(|opts| opts.limiter.as_ref())).await;
+                    let settings = opts.as_ref().map(|opts| opts.open_settings()).unwrap_or_default();
+
+                    let file = open_file_with_options_async(&path, OpenOptions::new().read(true), settings).await.map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
 
+                    let device_len = crate::utils::device_len(&file)?;
                     match opts  {
                         None => {
+                            let mut mmap_opts = MmapOptions::new();
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut mmap_opts, len, 0, None)?;
+                            }
                             let mmap = unsafe {
-                                Mmap::map(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
+                                mmap_opts.map(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                             };
                             Ok(Self {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
+                                // only a device needs a window other than the whole file
+                                opts: device_len.map(|_| mmap_opts),
                                 exec: false
                             })
                         }
-                        Some(opts) => {
+                        Some(mut opts) => {
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                            }
                             let mmap = unsafe {
                                 opts.mmap_opts.map(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                             };
@@ -393,6 +439,7 @@ cfg_async! {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
+                                opts: Some(opts.mmap_opts),
                                 exec: false,
                             })
                         }
@@ -400,23 +447,36 @@ cfg_async! {
                 }
 
                 async fn open_exec_in<P: AsRef<Path>>(path: P, opts: Option<AsyncOptions>) -> Result<Self, Error> {
-                    let file = open_read_only_file_async(&path)
+                    let _permit = crate::limiter::acquire(opts.as_ref().and_then(|opts| opts.limiter.as_ref())).await;
+                    let settings = opts.as_ref().map(|opts| opts.open_settings()).unwrap_or_default();
+
+                    let file = open_file_with_options_async(&path, OpenOptions::new().read(true), settings)
                         .await
-                        .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
+                        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
 
+                    let device_len = crate::utils::device_len(&file)?;
                     match opts  {
                         None => {
+                            let mut mmap_opts = MmapOptions::new();
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut mmap_opts, len, 0, None)?;
+                            }
                             let mmap = unsafe {
-                                MmapOptions::new().map_exec(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
+                                mmap_opts.map_exec(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                             };
                             Ok(Self {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
+                                // only a device needs a window other than the whole file
+                                opts: device_len.map(|_| mmap_opts),
                                 exec: true,
                             })
                         }
-                        Some(opts) => {
+                        Some(mut opts) => {
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                            }
                             let mmap = unsafe {
                                 opts.mmap_opts.map_exec(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                             };
@@ -424,6 +484,7 @@ cfg_async! {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
+                                opts: Some(opts.mmap_opts),
                                 exec: true
                             })
                         }
@@ -438,7 +499,8 @@ cfg_async! {
             #[async_trait]
             impl AsyncMmapFileMutExt for AsyncDiskMmapFileMut {
                 fn as_mut_slice(&mut self) -> &mut [u8] {
-                    self.mmap.as_mut()
+                    let len = self.len();
+                    &mut self.mmap[..len]
                 }
 
                 fn is_cow(&self) -> bool {
@@ -447,50 +509,161 @@ cfg_async! {
 
                 impl_flush!();
 
-                #[cfg(not(target_os = "linux"))]
+                async fn grow_by(&mut self, additional: u64) -> Result<(), Error> {
+                    let len = self.file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?.len();
+                    self.truncate(crate::utils::grown_len(len, additional)?).await
+                }
+
+                async fn shrink_by(&mut self, bytes: u64) -> Result<(), Error> {
+                    let len = self.file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?.len();
+                    self.truncate(len.saturating_sub(bytes)).await
+                }
+
+                fn truncate_size_for(&self, len: u64) -> Result<u64, Error> {
+                    if self.window {
+                        return Err(Error::new_with_message(ErrorKind::NotSupported, "cannot resize a mmap with a fixed length"));
+                    }
+                    crate::utils::grown_len(self.offset, len)
+                }
+
                 async fn truncate(&mut self, max_sz: u64) -> Result<(), Error> {
                     if self.is_cow() {
                         return Err(Error::new_with_message(ErrorKind::TruncationFailed, "cannot truncate a copy-on-write mmap file"));
                     }
 
-                    // sync data
-                    let meta = self.file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?;
-                    if meta.len() > 0 {
-                        self.flush()?;
+                    // sync data, unless the content is throwaway
+                    #[cfg(not(target_os = "linux"))]
+                    {
+                        if !self.volatile {
+                            let meta = self.file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?;
+                            if meta.len() > 0 {
+                                self.flush()?;
+                            }
+                        }
+                    }
+                    #[cfg(target_os = "linux")]
+                    {
+                        if !self.volatile {
+                            self.flush()?;
+                        }
                     }
 
-                    unsafe {
-                        // unmap
-                        drop_in_place(&mut self.mmap);
+                    // Unmap before the await point. If this future is dropped or fails before
+                    // the remap completes, the file keeps an empty mmap and stays poisoned,
+                    // rather than holding a mapping which is dangling or beyond the file end.
+                    let empty = MmapMut::map_anon(0).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?;
+                    self.poisoned = true;
+                    self.logical_len = None;
+                    drop(std::mem::replace(&mut self.mmap, empty));
 
-                        // truncate
-                        self.file.set_len(max_sz).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, self.path_lossy(), e))?;
+                    // truncate
+                    #[cfg(test)]
+                    crate::utils::hold_set_len().await;
+                    self.file.set_len(max_sz).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, self.path_lossy(), e))?;
 
-                        // remap
-                        let mmap = remmap(self.path(), &self.file, self.opts.as_ref(), self.typ)?;
+                    // remap
+                    self.mmap = remmap(self.path(), &self.file, self.opts.as_ref(), self.typ)?;
+                    self.poisoned = false;
+                    Ok(())
+                }
 
-                        write(&mut self.mmap, mmap);
+                #[inline]
+                fn is_poisoned(&self) -> bool {
+                    self.poisoned
+                }
+
+                async fn promote_to_shared(&mut self) -> Result<(), Error> {
+                    if !self.is_cow() {
+                        return Ok(());
                     }
 
+                    let mut shared = remmap(self.path(), &self.file, self.opts.as_ref(), MmapFileMutType::Normal)?;
+                    if shared.len() != self.mmap.len() {
+                        return Err(Error::from(ErrorKind::LengthMismatch(self.mmap.len(), shared.len())));
+                    }
+                    shared.copy_from_slice(&self.mmap);
+                    shared.flush().map_err(|e| Error::new_source_msg(ErrorKind::FlushFailed, self.path_lossy(), e))?;
+                    self.mmap = shared;
+                    self.typ = MmapFileMutType::Normal;
                     Ok(())
                 }
 
+                #[cfg(unix)]
+                fn set_flush_scheduler(&mut self, scheduler: Option<FlushScheduler>) {
+                    self.flush_scheduler = scheduler;
+                }
+
+                #[cfg(unix)]
+                async fn flush_scheduled(&self) -> Result<(), Error> {
+                    match &self.flush_scheduler {
+                        Some(scheduler) if !self.mmap.is_empty() => {
+                            scheduler
+                                .flush(self.mmap.as_ptr() as usize, self.mmap.len(), self.path_string())
+                                .await
+                        }
+                        _ => self.flush_range_committed(0, self.len()).await,
+                    }
+                }
+
                 #[cfg(target_os = "linux")]
-                async fn truncate(&mut self, max_sz: u64) -> Result<(), Error> {
-                    if self.is_cow() {
-                        return Err(Error::new_with_message(ErrorKind::TruncationFailed, "cannot truncate a copy-on-write mmap file"));
+                async fn sync_file_range(&self, offset: usize, len: usize, flags: crate::SyncRangeFlags) -> Result<(), Error> {
+                    use std::os::unix::io::AsRawFd;
+
+                    match offset.checked_add(len) {
+                        Some(end) if end <= self.len() => {}
+                        _ => return Err(Error::from(ErrorKind::EOF)),
+                    }
+                    // the private pages of a copy-on-write mmap are never written back to the file
+                    if len == 0 || matches!(self.typ, MmapFileMutType::Cow) {
+                        return self.flush_range_committed(offset, len).await;
                     }
 
-                    // sync data
-                    self.flush()?;
+                    // the blocking task owns a duplicated handle, so the descriptor cannot be closed
+                    // and reused by another file if this future is dropped
+                    let path = self.path_string();
+                    let file = crate::utils::dup_file(&self.file)
+                        .map_err(|e| Error::flush_failed(path.clone(), offset, len, e))?;
+                    let file_offset = self.offset + offset as u64;
+                    spawn_blocking(move || {
+                        let rst = unsafe {
+                            libc::sync_file_range(file.as_raw_fd(), file_offset as libc::off64_t, len as libc::off64_t, flags.bits())
+                        };
+                        if rst == 0 {
+                            Ok(())
+                        } else {
+                            Err(std::io::Error::last_os_error())
+                        }
+                    })
+                    .await?
+                    .map_err(|e| Error::flush_failed(path, offset, len, e))
+                }
 
-                    // truncate
-                    self.file.set_len(max_sz).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, self.path_lossy(), e))?;
+                async fn flush_range_committed(&self, offset: usize, len: usize) -> Result<(), Error> {
+                    match offset.checked_add(len) {
+                        Some(end) if end <= self.len() => {}
+                        _ => return Err(Error::from(ErrorKind::EOF)),
+                    }
+                    if len == 0 {
+                        return Ok(());
+                    }
 
-                    // remap
-                    self.mmap = remmap(self.path(), &self.file, self.opts.as_ref(), self.typ)?;
+                    #[cfg(unix)]
+                    {
+                        // the blocking task owns a duplicated handle and maps the range by itself,
+                        // so it never touches the mmap, which can be truncated or dropped if this future is.
+                        let path = self.path_string();
+                        let file = crate::utils::dup_file(&self.file)
+                            .map_err(|e| Error::flush_failed(path.clone(), offset, len, e))?;
+                        let file_offset = self.offset + offset as u64;
+                        spawn_blocking(move || crate::utils::msync_file(&file, file_offset, len))
+                            .await?
+                            .map_err(|e| Error::flush_failed(path, offset, len, e))
+                    }
 
-                    Ok(())
+                    #[cfg(not(unix))]
+                    {
+                        self.flush_range(offset, len)
+                    }
                 }
 
                 /// Remove the underlying file
@@ -547,17 +720,21 @@ cfg_async! {
                 #[doc = "# })"]
                 #[doc = "```"]
                 async fn close_with_truncate(self, max_sz: i64) -> crate::error::Result<()> {
+                    // sync data, unless the content is throwaway
                     #[cfg(not(target_os = "linux"))]
                     {
-                        // sync data
-                        let meta = self.file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?;
-                        if meta.len() > 0 {
-                            self.flush()?;
+                        if !self.volatile {
+                            let meta = self.file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?;
+                            if meta.len() > 0 {
+                                self.flush()?;
+                            }
                         }
                     }
                     #[cfg(target_os = "linux")]
                     {
-                        self.flush()?;
+                        if !self.volatile {
+                            self.flush()?;
+                        }
                     }
 
                     drop(self.mmap);
@@ -581,6 +758,17 @@ cfg_async! {
                 pub(crate) path: PathBuf,
                 opts: Option<MmapOptions>,
                 typ: MmapFileMutType,
+                /// Set by `AsyncOptions::volatile`, skips the flush before truncating.
+                volatile: bool,
+                /// The offset of the mmap in the file, set by `AsyncOptions::offset`.
+                offset: u64,
+                /// Whether the length of the mmap is fixed, set by `AsyncOptions::len`, so `truncate`
+                /// resizes the file but not the mmap.
+                window: bool,
+                poisoned: bool,
+                logical_len: Option<usize>,
+                #[cfg(unix)]
+                flush_scheduler: Option<FlushScheduler>,
             }
 
             impl_async_mmap_file_ext_for_mut!(AsyncDiskMmapFileMut);
@@ -1012,6 +1200,54 @@ cfg_async! {
                     Self::open_cow_in(path, Some(opts)).await
                 }
 
+                /// Set the length of the underlying file, keeping the current mapping when possible.
+                ///
+                /// If `new_len` is not larger than the current mapped length, the mmap is flushed, the
+                /// file is shrunk with `set_len` and the existing mapping is kept, instead of being
+                /// unmapped and mapped again as [`truncate`] does. [`len`], [`as_slice`] and
+                /// [`as_mut_slice`] only cover the first `new_len` bytes afterwards, and
+                /// [`metadata`] reports `new_len`.
+                ///
+                /// If `new_len` is larger than the current mapped length, this is the same as [`truncate`].
+                ///
+                /// # Safety boundary
+                /// The pages after `new_len` stay mapped, but they are no longer backed by the file.
+                /// The safe API of this type never exposes them, but a pointer derived from an
+                /// earlier slice must not be used to access bytes at or after `new_len`: reading or
+                /// writing them is undefined behavior, and on most platforms raises `SIGBUS`.
+                ///
+                /// [`truncate`]: trait.AsyncMmapFileMutExt.html#tymethod.truncate
+                /// [`len`]: trait.AsyncMmapFileExt.html#tymethod.len
+                /// [`as_slice`]: trait.AsyncMmapFileExt.html#tymethod.as_slice
+                /// [`as_mut_slice`]: trait.AsyncMmapFileMutExt.html#tymethod.as_mut_slice
+                /// [`metadata`]: trait.AsyncMmapFileExt.html#tymethod.metadata
+                pub async fn set_file_len(&mut self, new_len: u64) -> Result<(), Error> {
+                    if self.is_cow() {
+                        return Err(Error::new_with_message(ErrorKind::TruncationFailed, "cannot truncate a copy-on-write mmap file"));
+                    }
+
+                    if new_len > self.mmap.len() as u64 {
+                        return self.truncate(new_len).await;
+                    }
+
+                    self.flush()?;
+                    // Shrink the visible region before the await point, so a dropped future
+                    // never leaves bytes beyond the file end reachable.
+                    self.logical_len = Some(new_len as usize);
+                    self.file.set_len(new_len).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, self.path_lossy(), e))
+                }
+
+                /// Remap the file if [`set_file_len`] kept a mapping larger than the file.
+                ///
+                /// [`set_file_len`]: #method.set_file_len
+                fn remap_to_file_len(&mut self) -> Result<(), Error> {
+                    if self.logical_len.is_some() {
+                        self.mmap = remmap(self.path(), &self.file, self.opts.as_ref(), self.typ)?;
+                        self.logical_len = None;
+                    }
+                    Ok(())
+                }
+
                 /// Returns an immutable version of this memory mapped buffer.
                 /// If the memory map is file-backed, the file must have been opened with read permissions.
                 ///
@@ -1037,12 +1273,14 @@ cfg_async! {
                 #[doc = "file.freeze().unwrap();"]
                 #[doc = "# })"]
                 #[doc = "```"]
-                pub fn freeze(self) -> Result<$immutable_file, Error> {
+                pub fn freeze(mut self) -> Result<$immutable_file, Error> {
+                    self.remap_to_file_len()?;
                     Ok($immutable_file {
                         mmap: self.mmap.make_read_only().map_err(|e| Error::new(ErrorKind::IO, e))?,
                         file: self.file,
                         path: self.path,
                         exec: false,
+                        opts: self.opts,
                     })
                 }
 
@@ -1070,12 +1308,14 @@ cfg_async! {
                 #[doc = "file.freeze_exec().unwrap();"]
                 #[doc = "# })"]
                 #[doc = "```"]
-                pub fn freeze_exec(self) -> Result<$immutable_file, Error> {
+                pub fn freeze_exec(mut self) -> Result<$immutable_file, Error> {
+                    self.remap_to_file_len()?;
                     Ok($immutable_file {
                         mmap: self.mmap.make_exec().map_err(|e| Error::new(ErrorKind::IO, e))?,
                         file: self.file,
                         path: self.path,
-                        exec: true
+                        exec: true,
+                        opts: self.opts,
                     })
                 }
             }
@@ -1085,10 +1325,44 @@ cfg_async! {
     macro_rules! impl_async_fmmap_file_mut_private {
         ($name: ident) => {
             impl $name {
+                /// Unmaps the file and sets its length, to close the mmap into the file.
+                ///
+                /// If the length cannot be set, the file is mapped again, so the mmap is left as
+                /// it was, unless the remap fails too, which leaves it poisoned.
+                pub(crate) async fn unmap_and_set_len(&mut self, len: u64) -> Result<(), Error> {
+                    let empty = MmapMut::map_anon(0).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?;
+                    self.poisoned = true;
+                    drop(std::mem::replace(&mut self.mmap, empty));
+
+                    let rst = async {
+                        #[cfg(test)]
+                        crate::utils::fail_set_len()?;
+                        self.file.set_len(len).await
+                    }
+                    .await;
+                    if let Err(e) = rst {
+                        self.mmap = remmap(self.path(), &self.file, self.opts.as_ref(), self.typ)?;
+                        self.logical_len = None;
+                        self.poisoned = false;
+                        return Err(Error::new_source_msg(ErrorKind::TruncationFailed, self.path_lossy(), e));
+                    }
+                    Ok(())
+                }
+
                 async fn create_in<P: AsRef<Path>>(path: P, opts: Option<AsyncOptions>) -> Result<Self, Error> {
-                    let file = create_file_async(&path)
+                    let _permit = crate::limiter::acquire(opts.as_ref().and_then(|opts| opts.limiter.as_ref())).await;
+                    if let Some(opts) = &opts {
+                        crate::utils::check_map_size(opts.max_size)?;
+                    }
+                    let settings = opts.as_ref().map(|opts| opts.open_settings()).unwrap_or_default();
+
+                    let file = open_file_with_options_async(
+                        &path,
+                        OpenOptions::new().create_new(true).read(true).write(true).append(true),
+                        settings,
+                    )
                         .await
-                        .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
+                        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
 
                     match opts {
                         None => {
@@ -1100,16 +1374,35 @@ cfg_async! {
                                 path: path.as_ref().to_path_buf(),
                                 opts: None,
                                 typ: MmapFileMutType::Normal,
+                                volatile: false,
+                                offset: 0,
+                                window: false,
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
                         Some(opts) => {
-                            if opts.max_size > 0 {
-                                file.set_len(opts.max_size).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, path.as_ref().to_string_lossy(), e))?;
+                            let size = opts.initial_contents.as_ref().map_or(opts.max_size, |data| opts.max_size.max(data.len() as u64));
+                            if size > 0 {
+                                file.set_len(size).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, path.as_ref().to_string_lossy(), e))?;
+                                if opts.sync_on_create {
+                                    file.sync_all().await.map_err(|e| Error::new_source_msg(ErrorKind::SyncFileFailed, path.as_ref().to_string_lossy(), e))?;
+                                }
                                 sync_parent_async(&path).await?;
                             }
 
                             let opts_bk = opts.mmap_opts.clone();
-                            let mmap = unsafe { opts.mmap_opts.map_mut(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))? };
+                            let mut mmap = unsafe { opts.mmap_opts.map_mut(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))? };
+
+                            if let Some(data) = &opts.initial_contents {
+                                if mmap.len() < data.len() {
+                                    return Err(Error::from(ErrorKind::EOF));
+                                }
+                                mmap[..data.len()].copy_from_slice(data);
+                                mmap.flush().map_err(|e| Error::new_source_msg(ErrorKind::FlushFailed, path.as_ref().to_string_lossy(), e))?;
+                            }
 
                             Ok(Self {
                                 mmap,
@@ -1117,37 +1410,73 @@ cfg_async! {
                                 path: path.as_ref().to_path_buf(),
                                 opts: Some(opts_bk),
                                 typ: MmapFileMutType::Normal,
+                                volatile: opts.volatile,
+                                offset: opts.offset,
+                                window: opts.len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
                     }
                 }
 
                 async fn open_in<P: AsRef<Path>>(path: P, opts: Option<AsyncOptions>) -> Result<Self, Error> {
+                    let _permit = crate::limiter::acquire(opts.as_ref().and_then(|opts| opts.limiter.as_ref())).await;
+                    if let Some(opts) = &opts {
+                        crate::utils::check_map_size(opts.max_size.max(opts.min_size))?;
+                    }
+                    let settings = opts.as_ref().map(|opts| opts.open_settings()).unwrap_or_default();
+
                     match opts {
                         None => {
-                            let file = open_or_create_file_async(&path)
+                            let file = open_file_with_options_async(
+                                &path,
+                                OpenOptions::new().create(true).read(true).write(true),
+                                settings,
+                            )
                                 .await
-                                .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
+                                .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
 
-                            let mmap = unsafe { MmapMut::map_mut(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))? };
+                            let device_len = crate::utils::device_len(&file)?;
+                            let mut mmap_opts = MmapOptions::new();
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut mmap_opts, len, 0, None)?;
+                            }
+                            let mmap = unsafe { mmap_opts.map_mut(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))? };
                             Ok(Self {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
-                                opts: None,
+                                // only a device needs a window other than the whole file
+                                opts: device_len.map(|_| mmap_opts),
                                 typ: MmapFileMutType::Normal,
+                                volatile: false,
+                                offset: 0,
+                                window: device_len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
                         Some(mut opts) => {
-                            let file = opts.file_opts.create(true).open(&path)
+                            let file = open_file_with_options_async(&path, opts.file_opts.create(true), settings)
                                 .await
-                                .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
-                            let meta = file.metadata().await?;
-                            let file_sz = meta.len();
-                            if file_sz == 0 && opts.max_size > 0 {
-                                file.set_len(opts.max_size).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, path.as_ref().to_string_lossy(), e))?;
+                                .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+                            let device_len = crate::utils::device_len(&file)?;
+                            let file_sz = match device_len {
+                                Some(len) => len,
+                                None => file.metadata().await?.len(),
+                            };
+                            if let Some(len) = opts.open_len(file_sz) {
+                                file.set_len(len).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, path.as_ref().to_string_lossy(), e))?;
                                 sync_parent_async(&path).await?;
                             }
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                            }
 
                             let opts_bk = opts.mmap_opts.clone();
                             let mmap = unsafe {
@@ -1159,34 +1488,69 @@ cfg_async! {
                                 path: path.as_ref().to_path_buf(),
                                 opts: Some(opts_bk),
                                 typ: MmapFileMutType::Normal,
+                                volatile: opts.volatile,
+                                offset: opts.offset,
+                                window: opts.len.is_some() || device_len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
                     }
                 }
 
                 async fn open_exist_in<P: AsRef<Path>>(path: P, opts: Option<AsyncOptions>) -> Result<Self, Error> {
-                    let file = open_exist_file_with_append_async(&path)
+                    let _permit = crate::limiter::acquire(opts.as_ref().and_then(|opts| opts.limiter.as_ref())).await;
+                    if let Some(opts) = &opts {
+                        crate::utils::check_map_size(opts.max_size.max(opts.min_size))?;
+                    }
+                    let settings = opts.as_ref().map(|opts| opts.open_settings()).unwrap_or_default();
+
+                    let file = open_file_with_options_async(
+                        &path,
+                        OpenOptions::new().read(true).write(true).append(true),
+                        settings,
+                    )
                         .await
-                        .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
+                        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+                    let device_len = crate::utils::device_len(&file)?;
 
                     match opts {
                         None => {
-                            let mmap = unsafe { MmapMut::map_mut(&file)? };
+                            let mut mmap_opts = MmapOptions::new();
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut mmap_opts, len, 0, None)?;
+                            }
+                            let mmap = unsafe { mmap_opts.map_mut(&file)? };
                             Ok(Self {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
-                                opts: None,
+                                // only a device needs a window other than the whole file
+                                opts: device_len.map(|_| mmap_opts),
                                 typ: MmapFileMutType::Normal,
+                                volatile: false,
+                                offset: 0,
+                                window: device_len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
-                        Some(opts) => {
-                            let meta = file.metadata().await?;
-                            let file_sz = meta.len();
-                            if file_sz == 0 && opts.max_size > 0 {
-                                file.set_len(opts.max_size).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, path.as_ref().to_string_lossy(), e))?;
+                        Some(mut opts) => {
+                            let file_sz = match device_len {
+                                Some(len) => len,
+                                None => file.metadata().await?.len(),
+                            };
+                            if let Some(len) = opts.open_len(file_sz) {
+                                file.set_len(len).await.map_err(|e| Error::new_source_msg(ErrorKind::TruncationFailed, path.as_ref().to_string_lossy(), e))?;
                                 sync_parent_async(&path).await?;
                             }
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                            }
 
                             let opts_bk = opts.mmap_opts.clone();
                             let mmap = unsafe {
@@ -1198,28 +1562,64 @@ cfg_async! {
                                 path: path.as_ref().to_path_buf(),
                                 opts: Some(opts_bk),
                                 typ: MmapFileMutType::Normal,
+                                volatile: opts.volatile,
+                                offset: opts.offset,
+                                window: opts.len.is_some() || device_len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
                     }
                 }
 
                 async fn open_cow_in<P: AsRef<Path>>(path: P, opts: Option<AsyncOptions>) -> Result<Self, Error> {
-                    let file = open_exist_file_with_append_async(&path)
+                    let _permit = crate::limiter::acquire(opts.as_ref().and_then(|opts| opts.limiter.as_ref())).await;
+                    let settings = opts.as_ref().map(|opts| opts.open_settings()).unwrap_or_default();
+
+                    let file = open_file_with_options_async(
+                        &path,
+                        OpenOptions::new().read(true).write(true).append(true),
+                        settings,
+                    )
                         .await
-                        .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e))?;
+                        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+                    let device_len = crate::utils::device_len(&file)?;
 
                     match opts {
                         None => {
-                            let mmap = unsafe { MmapOptions::new().map_copy(&file)? };
+                            let mut mmap_opts = MmapOptions::new();
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut mmap_opts, len, 0, None)?;
+                            }
+                            let mmap = unsafe { mmap_opts.map_copy(&file)? };
                             Ok(Self {
                                 mmap,
                                 file,
                                 path: path.as_ref().to_path_buf(),
-                                opts: None,
+                                // only a device needs a window other than the whole file
+                                opts: device_len.map(|_| mmap_opts),
                                 typ: MmapFileMutType::Cow,
+                                volatile: false,
+                                offset: 0,
+                                window: device_len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
-                        Some(opts) => {
+                        Some(mut opts) => {
+                            match device_len {
+                                // a character device which cannot be sized is mapped by the given length
+                                Some(0) => {}
+                                Some(len) => opts.check_window(len)?,
+                                None => opts.check_window(file.metadata().await.map_err(|e| Error::new(ErrorKind::IO, e))?.len())?,
+                            }
+                            if let Some(len) = device_len {
+                                crate::utils::map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                            }
                             let opts_bk = opts.mmap_opts.clone();
                             let mmap = unsafe {
                                 opts.mmap_opts.map_copy(&file)? };
@@ -1230,6 +1630,13 @@ cfg_async! {
                                 path: path.as_ref().to_path_buf(),
                                 opts: Some(opts_bk),
                                 typ: MmapFileMutType::Cow,
+                                volatile: opts.volatile,
+                                offset: opts.offset,
+                                window: opts.len.is_some() || device_len.is_some(),
+                                poisoned: false,
+                                logical_len: None,
+                                #[cfg(unix)]
+                                flush_scheduler: None,
                             })
                         }
                     }
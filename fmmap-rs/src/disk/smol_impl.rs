@@ -1,18 +1,17 @@
 use crate::disk::MmapFileMutType;
 use crate::error::{Error, ErrorKind};
 use crate::smol::{AsyncMmapFileExt, AsyncMmapFileMutExt, AsyncOptions};
-use crate::utils::smol::{
-    create_file_async, open_exist_file_with_append_async, open_or_create_file_async,
-    open_read_only_file_async, sync_parent_async,
-};
+use crate::utils::smol::{open_file_with_options_async, sync_parent_async};
+#[cfg(unix)]
+use crate::smol::FlushScheduler;
+#[cfg(unix)]
+use crate::utils::smol::spawn_blocking;
 use crate::MetaData;
 use async_trait::async_trait;
 use fs4::smol::AsyncFileExt;
 use memmapix::{Mmap, MmapAsRawDesc, MmapMut, MmapOptions};
-use smol::fs::{remove_file, File};
+use smol::fs::{remove_file, File, OpenOptions};
 use std::path::{Path, PathBuf};
-#[cfg(not(target_os = "linux"))]
-use std::ptr::{drop_in_place, write};
 
 remmap!(Path);
 
@@ -54,4 +53,56 @@ mod test {
                 .len()
         );
     }
+
+    #[cfg(unix)]
+    #[smol_potat::test]
+    async fn test_flush_scheduler() {
+        use std::time::Duration;
+
+        let paths = [
+            "smol_async_disk_flush_scheduler_test_1.txt",
+            "smol_async_disk_flush_scheduler_test_2.txt",
+            "smol_async_disk_flush_scheduler_test_3.txt",
+        ];
+        defer!(paths.iter().for_each(|path| std::fs::remove_file(path).unwrap()));
+
+        let scheduler = FlushScheduler::new(Duration::from_millis(20), 1);
+        let mut files = Vec::new();
+        for (idx, path) in paths.iter().enumerate() {
+            let mut file = AsyncDiskMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+                .await
+                .unwrap();
+            file.set_flush_scheduler(Some(scheduler.clone()));
+            file.write_all(format!("file {}", idx).as_bytes(), 0).unwrap();
+            files.push(file);
+        }
+
+        let (a, (b, c)) = smol::future::zip(
+            files[0].flush_scheduled(),
+            smol::future::zip(files[1].flush_scheduled(), files[2].flush_scheduled()),
+        )
+        .await;
+        a.unwrap();
+        b.unwrap();
+        c.unwrap();
+        for (idx, path) in paths.iter().enumerate() {
+            assert_eq!(&std::fs::read(path).unwrap()[..6], format!("file {}", idx).as_bytes());
+        }
+
+        // nothing is queued
+        scheduler.flush_now().await.unwrap();
+
+        let scheduler = FlushScheduler::new(Duration::from_secs(3600), 1);
+        files[0].set_flush_scheduler(Some(scheduler.clone()));
+        files[0].write_all(b"flushed now", 0).unwrap();
+        let (res, now) = smol::future::zip(files[0].flush_scheduled(), async {
+            smol::future::yield_now().await;
+            scheduler.flush_now().await
+        })
+        .await;
+        res.unwrap();
+        now.unwrap();
+        assert_eq!(&std::fs::read(paths[0]).unwrap()[..11], b"flushed now");
+        drop(files);
+    }
 }
@@ -2,18 +2,45 @@ use crate::disk::MmapFileMutType;
 use crate::error::{Error, ErrorKind};
 use crate::options::Options;
 use crate::utils::{
-    create_file, open_exist_file_with_append, open_or_create_file, open_read_only_file, sync_parent,
+    check_map_size, create_file, device_len, map_device, open_exist_file_with_append,
+    open_file_with_options, open_or_create_file, open_read_only_file, sync_parent,
 };
 use crate::{MetaData, MmapFileExt, MmapFileMutExt};
 use fs4::FileExt;
 use memmapix::{Mmap, MmapAsRawDesc, MmapMut, MmapOptions};
-use std::fs::{remove_file, File};
+use std::fs::{remove_file, File, OpenOptions};
 use std::path::{Path, PathBuf};
 #[cfg(not(target_os = "linux"))]
 use std::ptr::{drop_in_place, write};
 
 remmap!(Path);
 
+/// Opens the file with `open`, or, if there are options, with the same `flags` and the
+/// file options set by e.g. [`Options::share_mode`] and [`Options::no_follow_symlinks`].
+fn open_file<P: AsRef<Path>>(
+    path: P,
+    opts: Option<&Options>,
+    open: fn(P) -> Result<File, Error>,
+    flags: fn(&mut OpenOptions) -> &mut OpenOptions,
+) -> Result<File, Error> {
+    match opts {
+        Some(opts) => {
+            open_file_with_options(path, flags(&mut OpenOptions::new()), opts.open_settings())
+        }
+        None => open(path),
+    }
+}
+
+/// The file flags of a read-only mmap, which also asks for write permissions
+/// if the file should be upgradable to a writable mmap later.
+fn read_flags(writable: bool) -> fn(&mut OpenOptions) -> &mut OpenOptions {
+    if writable {
+        |o| o.read(true).write(true)
+    } else {
+        |o| o.read(true)
+    }
+}
+
 /// DiskMmapFile contains an immutable mmap buffer
 /// and a read-only file.
 pub struct DiskMmapFile {
@@ -21,6 +48,14 @@ pub struct DiskMmapFile {
     pub(crate) file: File,
     pub(crate) path: PathBuf,
     exec: bool,
+    /// Whether the file is opened with write permissions, see [`Options::writable_handle`].
+    writable: bool,
+    opts: Option<MmapOptions>,
+    /// The offset of the mmap in the file, set by `Options::offset`.
+    offset: u64,
+    /// Whether the length of the mmap is fixed, e.g. set by `Options::len` or the size of a
+    /// device, so `truncate` resizes the file but not the mmap.
+    window: bool,
 }
 
 impl_mmap_file_ext!(DiskMmapFile);
@@ -152,24 +187,104 @@ impl DiskMmapFile {
         Self::open_exec_in(path, Some(opts))
     }
 
+    /// Re-maps the file as a writable memory map, reusing the file handle, so the file is not
+    /// looked up by its path again, and cannot be replaced by another file in between.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::NotSupported))` if the file was not opened with
+    /// [`Options::writable_handle`], and `Err(Error::from(ErrorKind::RemmapFailed))` if the file
+    /// cannot be mapped as writable.
+    ///
+    /// [`Options::writable_handle`]: struct.Options.html#method.writable_handle
+    pub fn upgrade_to_mut(self) -> Result<DiskMmapFileMut, Error> {
+        if !self.writable {
+            return Err(Error::new_with_message(
+                ErrorKind::NotSupported,
+                "the file is not opened with a writable handle",
+            ));
+        }
+
+        // remap the same window, e.g. keep the offset of the read-only mmap, the read-only
+        // mmap is only dropped once the mutable one is mapped
+        let mmap = unsafe {
+            match &self.opts {
+                Some(opts) => opts.map_mut(&self.file),
+                None => MmapMut::map_mut(&self.file),
+            }
+            .map_err(|e| {
+                Error::new_source_msg(ErrorKind::RemmapFailed, self.path.to_string_lossy(), e)
+            })?
+        };
+        drop(self.mmap);
+        Ok(DiskMmapFileMut {
+            mmap,
+            file: self.file,
+            path: self.path,
+            opts: self.opts,
+            offset: self.offset,
+            window: self.window,
+            typ: MmapFileMutType::Normal,
+            volatile: false,
+        })
+    }
+
+    /// Returns the [`MmapOptions`] the file is mapped with, e.g. the offset and length of the
+    /// window, or `None` if the whole file is mapped by the default options.
+    ///
+    /// The options are kept so that remapping the file, e.g. by [`upgrade_to_mut`], maps the
+    /// same window instead of silently dropping the offset.
+    ///
+    /// [`upgrade_to_mut`]: #method.upgrade_to_mut
+    #[inline]
+    pub fn options(&self) -> Option<&MmapOptions> {
+        self.opts.as_ref()
+    }
+
+    /// Leaks the memory map, and returns a `'static` slice of its content, e.g. for a lookup
+    /// table which is mapped once at startup and used for the lifetime of the process.
+    ///
+    /// The mmap and the file handle are intentionally never released, unmapped or closed,
+    /// so only call this for a bounded number of files.
+    pub fn leak(self) -> &'static [u8] {
+        let this: &'static Self = Box::leak(Box::new(self));
+        this.mmap.as_ref()
+    }
+
     fn open_in<P: AsRef<Path>>(path: P, opts: Option<Options>) -> Result<Self, Error> {
-        let file = open_read_only_file(&path).map_err(|e| {
-            Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
-        })?;
+        let writable = matches!(&opts, Some(opts) if opts.writable_handle);
+        let file = open_file(&path, opts.as_ref(), open_read_only_file, read_flags(writable))
+            .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+        let device_len = device_len(&file)?;
         match opts {
             None => {
-                let mmap =
-                    unsafe { Mmap::map(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))? };
+                let mut mmap_opts = MmapOptions::new();
+                if let Some(len) = device_len {
+                    map_device(&mut mmap_opts, len, 0, None)?;
+                }
+                let mmap = unsafe {
+                    mmap_opts
+                        .map(&file)
+                        .map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
+                };
                 Ok(Self {
                     mmap,
                     file,
                     path: path.as_ref().to_path_buf(),
                     exec: false,
+                    writable,
+                    // only a device needs a window other than the whole file
+                    opts: device_len.map(|_| mmap_opts),
+                    offset: 0,
+                    window: device_len.is_some(),
                 })
             }
-            Some(opts) => {
+            Some(mut opts) => {
+                if let Some(len) = device_len {
+                    map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                }
+                let mmap_opts = opts.mmap_opts.clone();
                 let mmap = unsafe {
-                    opts.mmap_opts
+                    mmap_opts
                         .map(&file)
                         .map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                 };
@@ -178,20 +293,29 @@ impl DiskMmapFile {
                     file,
                     path: path.as_ref().to_path_buf(),
                     exec: false,
+                    writable,
+                    opts: Some(mmap_opts),
+                    offset: opts.offset,
+                    window: opts.len.is_some() || device_len.is_some(),
                 })
             }
         }
     }
 
     fn open_exec_in<P: AsRef<Path>>(path: P, opts: Option<Options>) -> Result<Self, Error> {
-        let file = open_read_only_file(&path).map_err(|e| {
-            Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
-        })?;
+        let writable = matches!(&opts, Some(opts) if opts.writable_handle);
+        let file = open_file(&path, opts.as_ref(), open_read_only_file, read_flags(writable))
+            .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
 
+        let device_len = device_len(&file)?;
         match opts {
             None => {
+                let mut mmap_opts = MmapOptions::new();
+                if let Some(len) = device_len {
+                    map_device(&mut mmap_opts, len, 0, None)?;
+                }
                 let mmap = unsafe {
-                    MmapOptions::new()
+                    mmap_opts
                         .map_exec(&file)
                         .map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                 };
@@ -200,11 +324,20 @@ impl DiskMmapFile {
                     file,
                     path: path.as_ref().to_path_buf(),
                     exec: true,
+                    writable,
+                    // only a device needs a window other than the whole file
+                    opts: device_len.map(|_| mmap_opts),
+                    offset: 0,
+                    window: device_len.is_some(),
                 })
             }
-            Some(opts) => {
+            Some(mut opts) => {
+                if let Some(len) = device_len {
+                    map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                }
+                let mmap_opts = opts.mmap_opts.clone();
                 let mmap = unsafe {
-                    opts.mmap_opts
+                    mmap_opts
                         .map_exec(&file)
                         .map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                 };
@@ -213,6 +346,10 @@ impl DiskMmapFile {
                     file,
                     path: path.as_ref().to_path_buf(),
                     exec: true,
+                    writable,
+                    opts: Some(mmap_opts),
+                    offset: opts.offset,
+                    window: opts.len.is_some() || device_len.is_some(),
                 })
             }
         }
@@ -226,7 +363,14 @@ pub struct DiskMmapFileMut {
     pub(crate) file: File,
     pub(crate) path: PathBuf,
     opts: Option<MmapOptions>,
+    /// The offset of the mmap in the file, set by `Options::offset`.
+    offset: u64,
+    /// Whether the length of the mmap is fixed, e.g. set by `Options::len` or the size of a
+    /// device, so `truncate` resizes the file but not the mmap.
+    window: bool,
     typ: MmapFileMutType,
+    /// Set by `Options::volatile`, skips the flush before truncating.
+    volatile: bool,
 }
 
 impl_mmap_file_ext_for_mut!(DiskMmapFileMut);
@@ -242,6 +386,26 @@ impl MmapFileMutExt for DiskMmapFileMut {
 
     impl_flush!();
 
+    fn grow_by(&mut self, additional: u64) -> Result<(), Error> {
+        let len = self.file.metadata().map_err(|e| Error::new(ErrorKind::IO, e))?.len();
+        self.truncate(crate::utils::grown_len(len, additional)?)
+    }
+
+    fn shrink_by(&mut self, bytes: u64) -> Result<(), Error> {
+        let len = self.file.metadata().map_err(|e| Error::new(ErrorKind::IO, e))?.len();
+        self.truncate(len.saturating_sub(bytes))
+    }
+
+    fn truncate_size_for(&self, len: u64) -> Result<u64, Error> {
+        if self.window {
+            return Err(Error::new_with_message(
+                ErrorKind::NotSupported,
+                "cannot resize a mmap with a fixed length",
+            ));
+        }
+        crate::utils::grown_len(self.offset, len)
+    }
+
     #[cfg(not(target_os = "linux"))]
     fn truncate(&mut self, max_sz: u64) -> Result<(), Error> {
         if self.is_cow() {
@@ -251,13 +415,15 @@ impl MmapFileMutExt for DiskMmapFileMut {
             ));
         }
 
-        // sync data
-        let meta = self
-            .file
-            .metadata()
-            .map_err(|e| Error::new(ErrorKind::IO, e))?;
-        if meta.len() > 0 {
-            self.flush()?;
+        // sync data, unless the content is throwaway
+        if !self.volatile {
+            let meta = self
+                .file
+                .metadata()
+                .map_err(|e| Error::new(ErrorKind::IO, e))?;
+            if meta.len() > 0 {
+                self.flush()?;
+            }
         }
 
         unsafe {
@@ -265,7 +431,7 @@ impl MmapFileMutExt for DiskMmapFileMut {
             drop_in_place(&mut self.mmap);
 
             // truncate
-            self.file.set_len(max_sz).map_err(|e| {
+            eintr_retry!(self.file.set_len(max_sz)).map_err(|e| {
                 Error::new_source_msg(ErrorKind::TruncationFailed, self.path_string(), e)
             })?;
 
@@ -287,11 +453,13 @@ impl MmapFileMutExt for DiskMmapFileMut {
             ));
         }
 
-        // sync data
-        self.flush()?;
+        // sync data, unless the content is throwaway
+        if !self.volatile {
+            self.flush()?;
+        }
 
         // truncate
-        self.file.set_len(max_sz).map_err(|e| {
+        eintr_retry!(self.file.set_len(max_sz)).map_err(|e| {
             Error::new_source_msg(ErrorKind::TruncationFailed, self.path_string(), e)
         })?;
 
@@ -323,8 +491,7 @@ impl MmapFileMutExt for DiskMmapFileMut {
     fn drop_remove(self) -> crate::error::Result<()> {
         let path = self.path;
         drop(self.mmap);
-        self.file
-            .set_len(0)
+        eintr_retry!(self.file.set_len(0))
             .map_err(|e| Error::new(ErrorKind::IO, e))?;
         drop(self.file);
         remove_file(path).map_err(|e| Error::new(ErrorKind::IO, e))
@@ -353,19 +520,20 @@ impl MmapFileMutExt for DiskMmapFileMut {
     /// ```
     #[cfg(not(target_os = "linux"))]
     fn close_with_truncate(self, max_sz: i64) -> crate::error::Result<()> {
-        // sync data
-        let meta = self
-            .file
-            .metadata()
-            .map_err(|e| Error::new(ErrorKind::IO, e))?;
-        if meta.len() > 0 {
-            self.flush()?;
+        // sync data, unless the content is throwaway
+        if !self.volatile {
+            let meta = self
+                .file
+                .metadata()
+                .map_err(|e| Error::new(ErrorKind::IO, e))?;
+            if meta.len() > 0 {
+                self.flush()?;
+            }
         }
 
         drop(self.mmap);
         if max_sz >= 0 {
-            self.file
-                .set_len(max_sz as u64)
+            eintr_retry!(self.file.set_len(max_sz as u64))
                 .map_err(|e| Error::new(ErrorKind::IO, e))?;
             let abs = self
                 .path
@@ -403,11 +571,12 @@ impl MmapFileMutExt for DiskMmapFileMut {
     /// ```
     #[cfg(target_os = "linux")]
     fn close_with_truncate(self, max_sz: i64) -> crate::error::Result<()> {
-        self.flush()?;
+        if !self.volatile {
+            self.flush()?;
+        }
         drop(self.mmap);
         if max_sz >= 0 {
-            self.file
-                .set_len(max_sz as u64)
+            eintr_retry!(self.file.set_len(max_sz as u64))
                 .map_err(|e| Error::new(ErrorKind::IO, e))?;
             let abs = self
                 .path
@@ -860,6 +1029,10 @@ impl DiskMmapFileMut {
             file: self.file,
             path: self.path,
             exec: false,
+            writable: matches!(self.typ, MmapFileMutType::Normal),
+            opts: self.opts,
+            offset: self.offset,
+            window: self.window,
         })
     }
 
@@ -894,13 +1067,21 @@ impl DiskMmapFileMut {
             file: self.file,
             path: self.path,
             exec: true,
+            writable: matches!(self.typ, MmapFileMutType::Normal),
+            opts: self.opts,
+            offset: self.offset,
+            window: self.window,
         })
     }
 
     fn create_in<P: AsRef<Path>>(path: P, opts: Option<Options>) -> Result<Self, Error> {
-        let file = create_file(&path).map_err(|e| {
-            Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
-        })?;
+        if let Some(opts) = &opts {
+            check_map_size(opts.max_size)?;
+        }
+        let file = open_file(&path, opts.as_ref(), create_file, |o| {
+            o.create_new(true).read(true).write(true).append(true)
+        })
+        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
 
         match opts {
             None => {
@@ -913,64 +1094,113 @@ impl DiskMmapFileMut {
                     file,
                     path: path.as_ref().to_path_buf(),
                     opts: None,
+                    offset: 0,
+                    window: false,
                     typ: MmapFileMutType::Normal,
+                    volatile: false,
                 })
             }
             Some(opts) => {
-                if opts.max_size > 0 {
-                    file.set_len(opts.max_size).map_err(|e| {
+                let size = opts.initial_contents.as_ref().map_or(opts.max_size, |data| {
+                    opts.max_size.max(data.len() as u64)
+                });
+                if size > 0 {
+                    eintr_retry!(file.set_len(size)).map_err(|e| {
                         Error::new_source_msg(
                             ErrorKind::TruncationFailed,
                             path.as_ref().to_string_lossy(),
                             e,
                         )
                     })?;
+                    if opts.sync_on_create {
+                        eintr_retry!(file.sync_all()).map_err(|e| {
+                            Error::new_source_msg(
+                                ErrorKind::SyncFileFailed,
+                                path.as_ref().to_string_lossy(),
+                                e,
+                            )
+                        })?;
+                    }
                     sync_parent(&path)?;
                 }
 
                 let opts_bk = opts.mmap_opts.clone();
-                let mmap = unsafe {
+                let mut mmap = unsafe {
                     opts.mmap_opts
                         .map_mut(&file)
                         .map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                 };
 
+                if let Some(data) = &opts.initial_contents {
+                    if mmap.len() < data.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    mmap[..data.len()].copy_from_slice(data);
+                    mmap.flush().map_err(|e| {
+                        Error::new_source_msg(
+                            ErrorKind::FlushFailed,
+                            path.as_ref().to_string_lossy(),
+                            e,
+                        )
+                    })?;
+                }
+
                 Ok(Self {
                     mmap,
                     file,
                     path: path.as_ref().to_path_buf(),
                     opts: Some(opts_bk),
+                    offset: opts.offset,
+                    window: opts.len.is_some(),
                     typ: MmapFileMutType::Normal,
+                    volatile: opts.volatile,
                 })
             }
         }
     }
 
     fn open_in<P: AsRef<Path>>(path: P, opts: Option<Options>) -> Result<Self, Error> {
+        if let Some(opts) = &opts {
+            check_map_size(opts.max_size.max(opts.min_size))?;
+        }
         match opts {
             None => {
                 let file = open_or_create_file(&path).map_err(|e| {
-                    Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
+                    Error::open_failed(path.as_ref().to_string_lossy(), e)
                 })?;
+                let device_len = device_len(&file)?;
+                let mut mmap_opts = MmapOptions::new();
+                if let Some(len) = device_len {
+                    map_device(&mut mmap_opts, len, 0, None)?;
+                }
                 let mmap = unsafe {
-                    MmapMut::map_mut(&file).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
+                    mmap_opts
+                        .map_mut(&file)
+                        .map_err(|e| Error::new(ErrorKind::MmapFailed, e))?
                 };
                 Ok(Self {
                     mmap,
                     file,
                     path: path.as_ref().to_path_buf(),
-                    opts: None,
+                    // only a device needs a window other than the whole file
+                    opts: device_len.map(|_| mmap_opts),
+                    offset: 0,
+                    window: device_len.is_some(),
                     typ: MmapFileMutType::Normal,
+                    volatile: false,
                 })
             }
             Some(mut opts) => {
-                let file = opts.file_opts.create(true).open(&path).map_err(|e| {
-                    Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
-                })?;
-                let meta = file.metadata()?;
-                let file_sz = meta.len();
-                if file_sz == 0 && opts.max_size > 0 {
-                    file.set_len(opts.max_size).map_err(|e| {
+                let settings = opts.open_settings();
+                let file = open_file_with_options(&path, opts.file_opts.create(true), settings)
+                    .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+                let device_len = device_len(&file)?;
+                let file_sz = match device_len {
+                    Some(len) => len,
+                    None => file.metadata()?.len(),
+                };
+                if let Some(len) = opts.open_len(file_sz) {
+                    eintr_retry!(file.set_len(len)).map_err(|e| {
                         Error::new_source_msg(
                             ErrorKind::TruncationFailed,
                             path.as_ref().to_string_lossy(),
@@ -979,6 +1209,9 @@ impl DiskMmapFileMut {
                     })?;
                     sync_parent(&path)?;
                 }
+                if let Some(len) = device_len {
+                    map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                }
 
                 let opts_bk = opts.mmap_opts.clone();
                 let mmap = unsafe {
@@ -991,33 +1224,51 @@ impl DiskMmapFileMut {
                     file,
                     path: path.as_ref().to_path_buf(),
                     opts: Some(opts_bk),
+                    offset: opts.offset,
+                    window: opts.len.is_some() || device_len.is_some(),
                     typ: MmapFileMutType::Normal,
+                    volatile: opts.volatile,
                 })
             }
         }
     }
 
     fn open_exist_in<P: AsRef<Path>>(path: P, opts: Option<Options>) -> Result<Self, Error> {
-        let file = open_exist_file_with_append(&path).map_err(|e| {
-            Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
-        })?;
+        if let Some(opts) = &opts {
+            check_map_size(opts.max_size.max(opts.min_size))?;
+        }
+        let file = open_file(&path, opts.as_ref(), open_exist_file_with_append, |o| {
+            o.read(true).write(true).append(true)
+        })
+        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+        let device_len = device_len(&file)?;
 
         match opts {
             None => {
-                let mmap = unsafe { MmapMut::map_mut(&file)? };
+                let mut mmap_opts = MmapOptions::new();
+                if let Some(len) = device_len {
+                    map_device(&mut mmap_opts, len, 0, None)?;
+                }
+                let mmap = unsafe { mmap_opts.map_mut(&file)? };
                 Ok(Self {
                     mmap,
                     file,
                     path: path.as_ref().to_path_buf(),
-                    opts: None,
+                    // only a device needs a window other than the whole file
+                    opts: device_len.map(|_| mmap_opts),
+                    offset: 0,
+                    window: device_len.is_some(),
                     typ: MmapFileMutType::Normal,
+                    volatile: false,
                 })
             }
-            Some(opts) => {
-                let meta = file.metadata()?;
-                let file_sz = meta.len();
-                if file_sz == 0 && opts.max_size > 0 {
-                    file.set_len(opts.max_size).map_err(|e| {
+            Some(mut opts) => {
+                let file_sz = match device_len {
+                    Some(len) => len,
+                    None => file.metadata()?.len(),
+                };
+                if let Some(len) = opts.open_len(file_sz) {
+                    eintr_retry!(file.set_len(len)).map_err(|e| {
                         Error::new_source_msg(
                             ErrorKind::TruncationFailed,
                             path.as_ref().to_string_lossy(),
@@ -1026,6 +1277,9 @@ impl DiskMmapFileMut {
                     })?;
                     sync_parent(&path)?;
                 }
+                if let Some(len) = device_len {
+                    map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                }
                 let opts_bk = opts.mmap_opts.clone();
                 let mmap = unsafe { opts.mmap_opts.map_mut(&file)? };
 
@@ -1034,29 +1288,55 @@ impl DiskMmapFileMut {
                     file,
                     path: path.as_ref().to_path_buf(),
                     opts: Some(opts_bk),
+                    offset: opts.offset,
+                    window: opts.len.is_some() || device_len.is_some(),
                     typ: MmapFileMutType::Normal,
+                    volatile: opts.volatile,
                 })
             }
         }
     }
 
     fn open_cow_in<P: AsRef<Path>>(path: P, opts: Option<Options>) -> Result<Self, Error> {
-        let file = open_exist_file_with_append(&path).map_err(|e| {
-            Error::new_source_msg(ErrorKind::OpenFailed, path.as_ref().to_string_lossy(), e)
-        })?;
+        let file = open_file(&path, opts.as_ref(), open_exist_file_with_append, |o| {
+            o.read(true).write(true).append(true)
+        })
+        .map_err(|e| Error::open_failed(path.as_ref().to_string_lossy(), e))?;
+        let device_len = device_len(&file)?;
 
         match opts {
             None => {
-                let mmap = unsafe { MmapOptions::new().map_copy(&file)? };
+                let mut mmap_opts = MmapOptions::new();
+                if let Some(len) = device_len {
+                    map_device(&mut mmap_opts, len, 0, None)?;
+                }
+                let mmap = unsafe { mmap_opts.map_copy(&file)? };
                 Ok(Self {
                     mmap,
                     file,
                     path: path.as_ref().to_path_buf(),
-                    opts: None,
+                    // only a device needs a window other than the whole file
+                    opts: device_len.map(|_| mmap_opts),
+                    offset: 0,
+                    window: device_len.is_some(),
                     typ: MmapFileMutType::Cow,
+                    volatile: false,
                 })
             }
-            Some(opts) => {
+            Some(mut opts) => {
+                match device_len {
+                    // a character device which cannot be sized is mapped by the given length
+                    Some(0) => {}
+                    Some(len) => opts.check_window(len)?,
+                    None => opts.check_window(
+                        file.metadata()
+                            .map_err(|e| Error::new(ErrorKind::IO, e))?
+                            .len(),
+                    )?,
+                }
+                if let Some(len) = device_len {
+                    map_device(&mut opts.mmap_opts, len, opts.offset, opts.len)?;
+                }
                 let opts_bk = opts.mmap_opts.clone();
                 let mmap = unsafe { opts.mmap_opts.map_copy(&file)? };
 
@@ -1065,7 +1345,10 @@ impl DiskMmapFileMut {
                     file,
                     path: path.as_ref().to_path_buf(),
                     opts: Some(opts_bk),
+                    offset: opts.offset,
+                    window: opts.len.is_some() || device_len.is_some(),
                     typ: MmapFileMutType::Cow,
+                    volatile: opts.volatile,
                 })
             }
         }
@@ -1088,3 +1371,280 @@ fn test_close_with_truncate_on_empty_file() {
             .len()
     );
 }
+
+#[test]
+fn test_mapped_len_with_offset() {
+    use crate::MetaDataExt;
+
+    let path = "disk_mapped_len_with_offset.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, "some data...").unwrap();
+    let file = DiskMmapFile::open_with_options(path, Options::new().offset(5)).unwrap();
+    assert_eq!(file.mapped_len(), 7);
+    assert_eq!(file.as_slice(), b"data...");
+    assert_eq!(file.metadata().unwrap().len(), 12);
+}
+
+#[test]
+fn test_grow_by_shrink_by_with_offset() {
+    use crate::MetaDataExt;
+
+    let path = "disk_grow_by_shrink_by_with_offset.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, "some data...").unwrap();
+    let mut file = DiskMmapFileMut::open_with_options(
+        path,
+        Options::new().read(true).write(true).offset(5),
+    )
+    .unwrap();
+    file.grow_by(4).unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 16);
+    assert_eq!(file.as_slice(), b"data...\0\0\0\0");
+    file.shrink_by(8).unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 8);
+    assert_eq!(file.as_slice(), b"dat");
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"some dat");
+}
+
+#[test]
+fn test_truncate_trailing_zeros_with_offset() {
+    use crate::MetaDataExt;
+
+    let path = "disk_truncate_trailing_zeros_with_offset.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, "some data...\0\0\0\0").unwrap();
+    let mut file = DiskMmapFileMut::open_with_options(
+        path,
+        Options::new().read(true).write(true).offset(5),
+    )
+    .unwrap();
+    assert_eq!(file.truncate_trailing_zeros().unwrap(), 7);
+    assert_eq!(file.as_slice(), b"data...");
+    assert_eq!(file.metadata().unwrap().len(), 12);
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+}
+
+#[test]
+fn test_file_size_and_map_len() {
+    use crate::MetaDataExt;
+
+    // the file is larger than the map window
+    let path = "disk_file_size_larger_than_map_len.txt";
+    let mut file = DiskMmapFileMut::create_with_options(path, Options::new().file_size(100).map_len(12)).unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    assert_eq!(file.len(), 12);
+    assert_eq!(file.metadata().unwrap().len(), 100);
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    assert_eq!(file.write_all("more".as_bytes(), 12).unwrap_err().kind(), ErrorKind::EOF);
+
+    // the map window is equal to the file
+    let path = "disk_file_size_equal_to_map_len.txt";
+    let file = DiskMmapFileMut::create_with_options(path, Options::new().file_size(12).map_len(12)).unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    assert_eq!(file.len(), 12);
+    assert_eq!(file.metadata().unwrap().len(), 12);
+
+    // grow the empty file, then map a small window after an offset
+    let path = "disk_grow_file_then_small_window.txt";
+    std::fs::File::create(path).unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let opts = Options::new()
+        .read(true)
+        .write(true)
+        .file_size(8192)
+        .offset(4096)
+        .map_len(16);
+    let file = DiskMmapFileMut::open_with_options(path, opts).unwrap();
+    assert_eq!(file.len(), 16);
+    assert_eq!(file.metadata().unwrap().len(), 8192);
+    // max_size is an alias of file_size
+    assert_eq!(Options::new().max_size(7).max_size, Options::new().file_size(7).max_size);
+}
+
+#[test]
+fn test_open_exist_not_found() {
+    let path = "disk_open_exist_not_found.txt";
+    let err = DiskMmapFileMut::open_exist(path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let err = DiskMmapFile::open(path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    let err = crate::MmapFileMut::open_exist(path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+#[cfg(windows)]
+fn test_share_mode_delete_while_mapped() {
+    use crate::ShareMode;
+
+    let path = "disk_share_mode_delete_while_mapped.txt";
+    let mut file = DiskMmapFileMut::create_with_options(
+        path,
+        Options::new()
+            .max_size(100)
+            .share_mode(ShareMode::READ | ShareMode::WRITE | ShareMode::DELETE),
+    )
+    .unwrap();
+    file.write_all(b"some data...", 0).unwrap();
+
+    let opts = Options::new().share_mode(ShareMode::default());
+    let reader = DiskMmapFile::open_with_options(path, opts).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(&reader.as_slice()[..12], b"some data...");
+    assert_eq!(ShareMode::default().bits(), 0x7);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_no_follow_symlinks() {
+    use std::os::unix::io::AsRawFd;
+
+    let path = "disk_no_follow_symlinks_target.txt";
+    let link = "disk_no_follow_symlinks_link.txt";
+    std::fs::write(path, b"some data...").unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::os::unix::fs::symlink(path, link).unwrap();
+    scopeguard::defer!(std::fs::remove_file(link).unwrap());
+
+    let err = DiskMmapFile::open_with_options(link, Options::new().no_follow_symlinks(true))
+        .err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+    let opts = Options::new().read(true).write(true).no_follow_symlinks(true);
+    let err = DiskMmapFileMut::open_with_options(link, opts).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+    let opts = Options::new().no_follow_symlinks(true);
+    let err = DiskMmapFileMut::open_exist_with_options(link, opts).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+
+    let file = DiskMmapFile::open_with_options(link, Options::new().no_follow_symlinks(false))
+        .unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+    let file = DiskMmapFile::open_with_options(path, Options::new().no_follow_symlinks(true))
+        .unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+
+    // `O_NOFOLLOW` is added to the custom flags, not replacing them
+    let opts = Options::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_APPEND)
+        .no_follow_symlinks(true);
+    let err = DiskMmapFileMut::open_with_options(link, opts.clone()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+    let file = DiskMmapFileMut::open_with_options(path, opts).unwrap();
+    let flags = unsafe { libc::fcntl(file.file.as_raw_fd(), libc::F_GETFL) };
+    assert_ne!(flags & libc::O_APPEND, 0);
+}
+
+#[test]
+fn test_max_size_too_large() {
+    let path = "disk_max_size_too_large.txt";
+    let opts = Options::new().max_size(u64::MAX);
+    let err = DiskMmapFileMut::create_with_options(path, opts).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::SizeTooLarge);
+    // the validation happens before the file is created
+    assert!(!std::path::Path::new(path).exists());
+}
+
+#[test]
+fn test_open_cow_window() {
+    let path = "disk_open_cow_window.txt";
+    let data: Vec<u8> = (0..64).collect();
+    std::fs::write(path, &data).unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+
+    let mut file =
+        DiskMmapFileMut::open_cow_with_options(path, Options::new().offset(16).len(16)).unwrap();
+    assert!(file.is_cow());
+    assert_eq!(file.len(), 16);
+    assert_eq!(file.as_slice(), &data[16..32]);
+
+    file.write_all(b"private", 0).unwrap();
+    assert_eq!(&file.as_slice()[..7], b"private");
+    assert_eq!(&file.as_slice()[7..], &data[23..32]);
+    file.flush().unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), data);
+    drop(file);
+
+    let err = DiskMmapFileMut::open_cow_with_options(path, Options::new().offset(56).len(16))
+        .err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::MmapFailed);
+    let err = DiskMmapFileMut::open_cow_with_options(path, Options::new().offset(80)).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::MmapFailed);
+}
+
+#[test]
+fn test_options_kept_on_upgrade() {
+    let path = "disk_options_kept_on_upgrade.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, "some data...").unwrap();
+
+    let file = DiskMmapFile::open(path).unwrap();
+    assert!(file.options().is_none());
+
+    let opts = Options::new().offset(5).writable_handle(true);
+    let file = DiskMmapFile::open_with_options(path, opts).unwrap();
+    assert!(file.options().is_some());
+    // the upgraded mmap keeps the window of the read-only one
+    let mut file = file.upgrade_to_mut().unwrap();
+    assert_eq!(file.as_slice(), b"data...");
+    file.write_all(b"DATA", 0).unwrap();
+    file.flush().unwrap();
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"some DATA...");
+}
+
+#[test]
+fn test_create_new() {
+    let path = "disk_create_new.txt";
+    let _ = std::fs::remove_file(path);
+    let opts = Options::new().read(true).write(true).create_new(true).max_size(12);
+    let file = crate::MmapFileMut::open_with_options(path, opts.clone()).unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    assert_eq!(file.len(), 12);
+    drop(file);
+
+    let err = crate::MmapFileMut::open_with_options(path, opts).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    // create is always exclusive
+    let err = DiskMmapFileMut::create(path).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+    // the default open still opens the existing file
+    assert!(crate::MmapFileMut::open(path).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_open_char_device() {
+    // a character device cannot be sized, so it is only mapped with an explicit length
+    let path = "/dev/zero";
+    assert_eq!(device_len(&File::open(path).unwrap()).unwrap(), Some(0));
+    assert_eq!(
+        DiskMmapFile::open(path).err().unwrap().kind(),
+        ErrorKind::MmapFailed
+    );
+    let file = DiskMmapFile::open_with_options(path, Options::new().len(16)).unwrap();
+    assert_eq!(file.as_slice(), &[0; 16]);
+
+    assert_eq!(
+        DiskMmapFileMut::open_exist(path).err().unwrap().kind(),
+        ErrorKind::MmapFailed
+    );
+    let mut file = DiskMmapFileMut::open_exist_with_options(
+        path,
+        Options::new().read(true).write(true).len(16),
+    )
+    .unwrap();
+    file.write_all(b"some data...", 0).unwrap();
+    assert_eq!(&file.as_slice()[..12], b"some data...");
+    // the length of a device is fixed
+    assert_eq!(
+        file.truncate_size_for(32).unwrap_err().kind(),
+        ErrorKind::NotSupported
+    );
+
+    let file = DiskMmapFileMut::open_cow_with_options(path, Options::new().len(16)).unwrap();
+    assert_eq!(file.as_slice(), &[0; 16]);
+}
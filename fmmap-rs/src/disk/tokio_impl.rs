@@ -1,18 +1,17 @@
 use crate::disk::MmapFileMutType;
 use crate::error::{Error, ErrorKind};
 use crate::tokio::{AsyncMmapFileExt, AsyncMmapFileMutExt, AsyncOptions};
-use crate::utils::tokio::{
-    create_file_async, open_exist_file_with_append_async, open_or_create_file_async,
-    open_read_only_file_async, sync_parent_async,
-};
+use crate::utils::tokio::{open_file_with_options_async, sync_parent_async};
+#[cfg(unix)]
+use crate::tokio::FlushScheduler;
+#[cfg(unix)]
+use crate::utils::tokio::spawn_blocking;
 use crate::MetaData;
 use async_trait::async_trait;
 use fs4::tokio::AsyncFileExt;
 use memmapix::{Mmap, MmapAsRawDesc, MmapMut, MmapOptions};
 use std::path::{Path, PathBuf};
-#[cfg(not(target_os = "linux"))]
-use std::ptr::{drop_in_place, write};
-use tokio::fs::{remove_file, File};
+use tokio::fs::{remove_file, File, OpenOptions};
 
 remmap!(Path);
 
@@ -41,6 +40,34 @@ mod test {
     use super::*;
     use scopeguard::defer;
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_no_follow_symlinks() {
+        let path = "tokio_async_disk_no_follow_symlinks_target.txt";
+        let link = "tokio_async_disk_no_follow_symlinks_link.txt";
+        std::fs::write(path, b"some data...").unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        std::os::unix::fs::symlink(path, link).unwrap();
+        defer!(std::fs::remove_file(link).unwrap());
+
+        let opts = AsyncOptions::new().no_follow_symlinks(true);
+        let err = AsyncDiskMmapFile::open_with_options(link, opts).await.err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+        let opts = AsyncOptions::new().read(true).write(true).no_follow_symlinks(true);
+        let err = AsyncDiskMmapFileMut::open_with_options(link, opts).await.err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+        let opts = AsyncOptions::new().no_follow_symlinks(true);
+        let err = AsyncDiskMmapFileMut::open_exist_with_options(link, opts).await.err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::SymlinkRefused);
+
+        let opts = AsyncOptions::new().no_follow_symlinks(false);
+        let file = AsyncDiskMmapFile::open_with_options(link, opts).await.unwrap();
+        assert_eq!(file.as_slice(), b"some data...");
+        let opts = AsyncOptions::new().no_follow_symlinks(true);
+        let file = AsyncDiskMmapFile::open_with_options(path, opts).await.unwrap();
+        assert_eq!(file.as_slice(), b"some data...");
+    }
+
     #[tokio::test]
     async fn test_close_with_truncate_on_empty_file() {
         let file = AsyncDiskMmapFileMut::create("tokio_async_disk_close_with_truncate_test.txt")
@@ -60,4 +87,281 @@ mod test {
                 .len()
         );
     }
+
+    #[tokio::test]
+    async fn test_cancelled_truncate_poisons() {
+        use crate::error::ErrorKind;
+        use crate::tokio::AsyncOptions;
+
+        let path = "tokio_async_disk_cancelled_truncate_test.txt";
+        let mut file = AsyncDiskMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+            .await
+            .unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        file.write_all("some data...".as_bytes(), 0).unwrap();
+        assert!(!file.is_poisoned());
+
+        // hold the truncate right before `set_len` and drop it there
+        crate::utils::HOLD_SET_LEN.with(|hold| hold.set(true));
+        crate::utils::SET_LEN_HELD.with(|held| held.set(false));
+        let mut task = tokio_test::task::spawn(file.truncate(50));
+        while !crate::utils::SET_LEN_HELD.with(|held| held.get()) {
+            assert!(task.poll().is_pending());
+            tokio::task::yield_now().await;
+        }
+        drop(task);
+        crate::utils::HOLD_SET_LEN.with(|hold| hold.set(false));
+
+        assert!(file.is_poisoned());
+        assert!(file.as_slice().is_empty());
+        assert_eq!(file.read_u8(0).unwrap_err().kind(), ErrorKind::EOF);
+        assert_eq!(file.write_all(b"more", 0).unwrap_err().kind(), ErrorKind::EOF);
+
+        file.truncate(50).await.unwrap();
+        assert!(!file.is_poisoned());
+        assert_eq!(file.len(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_set_file_len_keeps_mapping() {
+        use crate::tokio::AsyncOptions;
+        use crate::MetaDataExt;
+
+        let path = "tokio_async_disk_set_file_len_test.txt";
+        let mut file = AsyncDiskMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+            .await
+            .unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        file.write_all("some data...".as_bytes(), 0).unwrap();
+        let ptr = file.as_slice().as_ptr();
+
+        file.set_file_len(12).await.unwrap();
+        assert_eq!(file.as_slice().as_ptr(), ptr);
+        assert_eq!(file.len(), 12);
+        assert_eq!(file.as_slice(), "some data...".as_bytes());
+        assert_eq!(file.metadata().await.unwrap().len(), 12);
+
+        file.set_file_len(50).await.unwrap();
+        assert_eq!(file.len(), 50);
+        assert_eq!(&file.as_slice()[..12], "some data...".as_bytes());
+
+        let file = file.freeze().unwrap();
+        assert_eq!(file.len(), 50);
+    }
+
+    #[tokio::test]
+    async fn test_flush_range_committed() {
+        let path = "tokio_async_disk_flush_range_committed_test.txt";
+        let mut file = AsyncDiskMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(8192))
+            .await
+            .unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        file.write_all("some data...".as_bytes(), 5000).unwrap();
+        file.flush_range_committed(5000, 12).await.unwrap();
+        file.flush_range_committed(8192, 0).await.unwrap();
+        assert_eq!(
+            file.flush_range_committed(8000, 193).await.unwrap_err().kind(),
+            ErrorKind::EOF
+        );
+        assert_eq!(&std::fs::read(path).unwrap()[5000..5012], b"some data...");
+    }
+
+    #[tokio::test]
+    async fn test_promote_to_shared() {
+        let path = "tokio_async_disk_promote_to_shared_test.txt";
+        std::fs::write(path, vec![0u8; 32]).unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+
+        let mut file = AsyncDiskMmapFileMut::open_cow(path).await.unwrap();
+        file.write_all(b"private", 0).unwrap();
+        assert_eq!(&std::fs::read(path).unwrap()[..7], &[0u8; 7]);
+
+        file.promote_to_shared().await.unwrap();
+        assert!(!file.is_cow());
+        assert_eq!(&std::fs::read(path).unwrap()[..7], b"private");
+
+        file.write_all(b"shared", 16).unwrap();
+        file.flush().unwrap();
+        assert_eq!(&std::fs::read(path).unwrap()[16..22], b"shared");
+        drop(file);
+    }
+
+    #[tokio::test]
+    async fn test_create_with_initial_contents() {
+        use crate::tokio::AsyncOptions;
+
+        let path = "tokio_async_disk_create_with_initial_contents_test.txt";
+        defer!(std::fs::remove_file(path).unwrap());
+
+        let opts = AsyncOptions::new()
+            .max_size(64)
+            .initial_contents(bytes::Bytes::from_static(b"header"));
+        let file = AsyncDiskMmapFileMut::create_with_options(path, opts).await.unwrap();
+        assert_eq!(file.len(), 64);
+        assert_eq!(&file.as_slice()[..6], b"header");
+        assert!(file.as_slice()[6..].iter().all(|b| *b == 0));
+        drop(file);
+
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(data.len(), 64);
+        assert_eq!(&data[..6], b"header");
+        assert!(data[6..].iter().all(|b| *b == 0));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_flush_scheduler() {
+        use std::time::Duration;
+
+        let paths = [
+            "tokio_async_disk_flush_scheduler_test_1.txt",
+            "tokio_async_disk_flush_scheduler_test_2.txt",
+            "tokio_async_disk_flush_scheduler_test_3.txt",
+        ];
+        defer!(paths.iter().for_each(|path| std::fs::remove_file(path).unwrap()));
+
+        let scheduler = FlushScheduler::new(Duration::from_millis(20), 1);
+        let mut files = Vec::new();
+        for (idx, path) in paths.iter().enumerate() {
+            let mut file = AsyncDiskMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+                .await
+                .unwrap();
+            file.set_flush_scheduler(Some(scheduler.clone()));
+            file.write_all(format!("file {}", idx).as_bytes(), 0).unwrap();
+            files.push(file);
+        }
+
+        let (a, b, c) = tokio::join!(
+            files[0].flush_scheduled(),
+            files[1].flush_scheduled(),
+            files[2].flush_scheduled(),
+        );
+        a.unwrap();
+        b.unwrap();
+        c.unwrap();
+        for (idx, path) in paths.iter().enumerate() {
+            assert_eq!(&std::fs::read(path).unwrap()[..6], format!("file {}", idx).as_bytes());
+        }
+
+        // nothing is queued
+        scheduler.flush_now().await.unwrap();
+
+        let scheduler = FlushScheduler::new(Duration::from_secs(3600), 1);
+        files[0].set_flush_scheduler(Some(scheduler.clone()));
+        files[0].write_all(b"flushed now", 0).unwrap();
+        let (res, now) = tokio::join!(files[0].flush_scheduled(), async {
+            tokio::task::yield_now().await;
+            scheduler.flush_now().await
+        });
+        res.unwrap();
+        now.unwrap();
+        assert_eq!(&std::fs::read(paths[0]).unwrap()[..11], b"flushed now");
+        drop(files);
+    }
+
+    #[tokio::test]
+    async fn test_sync_on_create() {
+        use crate::tokio::AsyncOptions;
+
+        let path = "tokio_async_disk_sync_on_create_test.txt";
+        let opts = AsyncOptions::new().max_size(100).sync_on_create(true);
+        let file = AsyncDiskMmapFileMut::create_with_options(path, opts).await.unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        assert_eq!(file.len(), 100);
+        assert_eq!(std::fs::metadata(path).unwrap().len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limiter() {
+        use crate::tokio::AsyncOptions;
+        use crate::OpenConcurrencyLimiter;
+        use std::time::Duration;
+
+        let path = "tokio_async_disk_concurrency_limiter_test.txt";
+        let limiter = OpenConcurrencyLimiter::new(1);
+        let opts = AsyncOptions::new()
+            .max_size(100)
+            .concurrency_limiter(limiter.clone());
+
+        // the only permit is taken, the create waits before touching the file system
+        let permit = limiter.acquire().await;
+        let create = AsyncDiskMmapFileMut::create_with_options(path, opts);
+        tokio::pin!(create);
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut create)
+            .await
+            .is_err());
+        assert!(!std::path::Path::new(path).exists());
+
+        drop(permit);
+        let file = create.await.unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        assert_eq!(file.len(), 100);
+        // the permit is released once the file is opened
+        assert_eq!(limiter.in_use(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_file_range() {
+        use crate::tokio::AsyncOptions;
+        use crate::SyncRangeFlags;
+
+        let path = "tokio_async_disk_sync_file_range_test.txt";
+        let opts = AsyncOptions::new().max_size(8192);
+        let mut file = AsyncDiskMmapFileMut::create_with_options(path, opts).await.unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        file.write_all(b"some data...", 4096).unwrap();
+
+        let flags = SyncRangeFlags::WAIT_BEFORE | SyncRangeFlags::WRITE | SyncRangeFlags::WAIT_AFTER;
+        assert!(flags.contains(SyncRangeFlags::WRITE));
+        file.sync_file_range(4096, 12, flags).await.unwrap();
+        file.sync_file_range(0, 0, SyncRangeFlags::WRITE).await.unwrap();
+        let err = file.sync_file_range(8000, 200, flags).await.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::EOF);
+        drop(file);
+        assert_eq!(&std::fs::read(path).unwrap()[4096..4108], b"some data...");
+
+        // the range is relative to the mmap, which starts at the offset of the file
+        let opts = AsyncOptions::new().read(true).write(true).offset(4096);
+        let file = AsyncDiskMmapFileMut::open_with_options(path, opts).await.unwrap();
+        assert_eq!(file.len(), 4096);
+        file.sync_file_range(0, 4096, flags).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_truncate_trailing_zeros_with_offset() {
+        let path = "tokio_async_disk_truncate_trailing_zeros_with_offset.txt";
+        std::fs::write(path, "some data...\0\0\0\0").unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+        let opts = AsyncOptions::new().read(true).write(true).offset(5);
+        let mut file = AsyncDiskMmapFileMut::open_with_options(path, opts).await.unwrap();
+        assert_eq!(file.truncate_trailing_zeros().await.unwrap(), 7);
+        assert_eq!(file.as_slice(), b"data...");
+        drop(file);
+        assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_open_char_device() {
+        use crate::error::ErrorKind;
+        use crate::tokio::AsyncOptions;
+
+        // a character device cannot be sized, so it is only mapped with an explicit length
+        let path = "/dev/zero";
+        assert_eq!(AsyncDiskMmapFile::open(path).await.err().unwrap().kind(), ErrorKind::MmapFailed);
+        let file = AsyncDiskMmapFile::open_with_options(path, AsyncOptions::new().len(16)).await.unwrap();
+        assert_eq!(file.as_slice(), &[0; 16]);
+
+        assert_eq!(AsyncDiskMmapFileMut::open_exist(path).await.err().unwrap().kind(), ErrorKind::MmapFailed);
+        let mut file = AsyncDiskMmapFileMut::open_exist_with_options(path, AsyncOptions::new().read(true).write(true).len(16))
+            .await
+            .unwrap();
+        file.write_all(b"some data...", 0).unwrap();
+        assert_eq!(&file.as_slice()[..12], b"some data...");
+        // the length of a device is fixed
+        assert_eq!(file.truncate_size_for(32).unwrap_err().kind(), ErrorKind::NotSupported);
+
+        let file = AsyncDiskMmapFileMut::open_cow_with_options(path, AsyncOptions::new().len(16)).await.unwrap();
+        assert_eq!(file.as_slice(), &[0; 16]);
+    }
 }
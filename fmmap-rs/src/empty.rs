@@ -39,6 +39,16 @@ cfg_async! {
                     false
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Empty
+                }
+
+                #[inline]
+                fn is_closed(&self) -> bool {
+                    true
+                }
+
                 #[inline]
                 async fn metadata(&self) -> Result<MetaData> {
                     Ok(MetaData::empty(EmptyMetaData))
@@ -114,6 +124,14 @@ cfg_async! {
 
                 noop_flush!();
 
+                #[inline]
+                async fn flush_range_committed(&self, offset: usize, len: usize) -> Result<()> {
+                    match offset.checked_add(len) {
+                        Some(end) if end <= self.len() => Ok(()),
+                        _ => Err(Error::from(ErrorKind::EOF)),
+                    }
+                }
+
                 #[inline]
                 async fn truncate(&mut self, _max_sz: u64) -> Result<()> {
                     Ok(())
@@ -35,6 +35,14 @@ impl MmapFileExt for EmptyMmapFile {
         false
     }
 
+    fn kind(&self) -> crate::MapKind {
+        crate::MapKind::Empty
+    }
+
+    fn is_closed(&self) -> bool {
+        true
+    }
+
     fn copy_all_to_vec(&self) -> Vec<u8> {
         self.inner.to_vec()
     }
@@ -25,6 +25,14 @@ pub enum ErrorKind {
     #[display("unable to open file")]
     OpenFailed,
 
+    /// the file does not exist
+    #[display("file not found")]
+    NotFound,
+
+    /// the file already exists, e.g. when creating it exclusively
+    #[display("file already exists")]
+    AlreadyExists,
+
     /// unable to open dir
     #[display("unable to open dir")]
     OpenDirFailed,
@@ -61,6 +69,50 @@ pub enum ErrorKind {
     #[display("call on an empty mmap file")]
     InvokeEmptyMmap,
 
+    /// the data is not properly aligned for the requested type
+    #[display("misaligned")]
+    Misaligned,
+
+    /// the operation is not supported by the underlying mmap file
+    #[display("operation not supported")]
+    NotSupported,
+
+    /// the data does not match the expected format
+    #[display("bad format")]
+    BadFormat,
+
+    /// the lengths of two buffers which should be equal are different
+    #[display("length mismatch: {0} != {1}")]
+    LengthMismatch(usize, usize),
+
+    /// the checksum stored in the data does not match the computed one
+    #[display("checksum mismatch")]
+    ChecksumMismatch,
+
+    /// the string contains an interior NUL byte, so it cannot be written as a C string
+    #[display("string contains an interior NUL byte")]
+    InvalidCStr,
+
+    /// two ranges which must not overlap are overlapping
+    #[display("overlapping ranges")]
+    Overlap,
+
+    /// the requested size is larger than what the platform can mmap
+    #[display("size too large")]
+    SizeTooLarge,
+
+    /// the final component of the path is a symlink (or a junction on Windows), which is refused
+    #[display("refused to follow a symlink")]
+    SymlinkRefused,
+
+    /// the operation did not complete in time
+    #[display("operation timed out")]
+    Timeout,
+
+    /// the operation succeeded on the primary mmap, but failed on its mirror
+    #[display("mirror failed")]
+    MirrorFailed,
+
     /// not a directory
     #[cfg(not(feature = "nightly"))]
     #[display("not a directory")]
@@ -92,6 +144,46 @@ impl Error {
         Self::_new(kd, src.into())
     }
 
+    /// Converts the `io::Error` returned when opening a file, the OS `NotFound` and
+    /// `AlreadyExists` are mapped to `ErrorKind::NotFound` and `ErrorKind::AlreadyExists`,
+    /// other errors are `ErrorKind::IO`.
+    pub(crate) fn from_open_error(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => Self::new(ErrorKind::NotFound, err),
+            io::ErrorKind::AlreadyExists => Self::new(ErrorKind::AlreadyExists, err),
+            _ => Self::new(ErrorKind::IO, err),
+        }
+    }
+
+    /// Wraps the error returned when opening the file at `path`. The kind is `ErrorKind::NotFound`
+    /// if the file does not exist, `ErrorKind::AlreadyExists` if it exists but must be created,
+    /// `ErrorKind::SymlinkRefused` if the path is a refused symlink, otherwise `ErrorKind::OpenFailed`.
+    pub(crate) fn open_failed<M: Into<String>>(path: M, err: Error) -> Self {
+        let kd = match err.kind() {
+            ErrorKind::NotFound => ErrorKind::NotFound,
+            ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+            ErrorKind::SymlinkRefused => ErrorKind::SymlinkRefused,
+            _ => ErrorKind::OpenFailed,
+        };
+        Self::new_source_msg(kd, path, err)
+    }
+
+    /// Returns an `ErrorKind::FlushFailed` error, whose message contains the path and
+    /// the byte range which failed to flush.
+    pub(crate) fn flush_failed<M, E>(path: M, offset: usize, len: usize, err: E) -> Self
+    where
+        M: Into<String>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let msg = format!(
+            "{} (bytes {}..{})",
+            path.into(),
+            offset,
+            offset.saturating_add(len)
+        );
+        Self::new_source_msg(ErrorKind::FlushFailed, msg, err)
+    }
+
     fn _new(kind: ErrorKind, error: Box<dyn std::error::Error + Send + Sync>) -> Self {
         Error {
             repr: Repr::Source(Box::new(Source { kind, error })),
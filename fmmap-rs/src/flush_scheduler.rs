@@ -0,0 +1,342 @@
+use crate::error::{Error, ErrorKind, Result};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// The shared state of a flush scheduler, independent of the async runtime.
+///
+/// The scheduler never holds a map, only the address ranges of the flushes which are
+/// awaited. A range is queued by [`submit`] and flushed by a batch, which is run on the
+/// blocking thread pool by one of the waiters (the leader). When a batch finishes, the waiters
+/// of the requests queued meanwhile are nudged, so one of them leads the next batch.
+///
+/// [`submit`]: #method.submit
+pub(crate) struct SchedulerCore {
+    window: Duration,
+    max_in_flight: usize,
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    pending: Vec<Request>,
+    /// When the oldest pending request was queued.
+    first: Option<Instant>,
+    /// The number of the running batches.
+    in_flight: usize,
+    /// Set by `flush_now`, the next batch does not wait for the deadline.
+    urgent: bool,
+}
+
+struct Request {
+    addr: usize,
+    len: usize,
+    path: String,
+    slots: Vec<Arc<Slot>>,
+}
+
+#[derive(Default)]
+struct Slot {
+    state: Mutex<SlotState>,
+}
+
+#[derive(Default)]
+struct SlotState {
+    result: Option<Result<()>>,
+    nudged: bool,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    fn complete(&self, result: Result<()>) {
+        let mut state = self.state.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn nudge(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.nudged = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl SchedulerCore {
+    pub(crate) fn new(window: Duration, max_in_flight: usize) -> Self {
+        Self {
+            window,
+            max_in_flight: max_in_flight.max(1),
+            state: Mutex::new(State::default()),
+            cond: Condvar::new(),
+        }
+    }
+
+    /// Queues the flush of the range, it is coalesced with a pending overlapping or adjacent range
+    /// of the same file, so an error of the flush names the file the range belongs to.
+    pub(crate) fn submit(self: &Arc<Self>, addr: usize, len: usize, path: String) -> Ticket {
+        let slot = Arc::new(Slot::default());
+        let mut state = self.state.lock().unwrap();
+        if state.pending.is_empty() {
+            state.first = Some(Instant::now());
+        }
+
+        match state
+            .pending
+            .iter_mut()
+            .find(|r| r.path == path && r.addr <= addr + len && addr <= r.addr + r.len)
+        {
+            Some(r) => {
+                let end = (r.addr + r.len).max(addr + len);
+                r.addr = r.addr.min(addr);
+                r.len = end - r.addr;
+                r.slots.push(slot.clone());
+            }
+            None => state.pending.push(Request {
+                addr,
+                len,
+                path,
+                slots: vec![slot.clone()],
+            }),
+        }
+
+        Ticket {
+            core: self.clone(),
+            slot,
+        }
+    }
+
+    /// Marks the pending requests as urgent, and returns a ticket of each of them.
+    pub(crate) fn expedite(self: &Arc<Self>) -> Vec<Ticket> {
+        let mut state = self.state.lock().unwrap();
+        state.urgent = !state.pending.is_empty();
+        let tickets = state
+            .pending
+            .iter_mut()
+            .map(|r| {
+                let slot = Arc::new(Slot::default());
+                r.slots.push(slot.clone());
+                r.slots.iter().for_each(|s| s.nudge());
+                Ticket {
+                    core: self.clone(),
+                    slot,
+                }
+            })
+            .collect();
+        self.cond.notify_all();
+        tickets
+    }
+
+    /// Reserves a batch, returns `None` if there is nothing to flush or there are
+    /// already `max_in_flight` batches running.
+    pub(crate) fn try_lead(self: &Arc<Self>) -> Option<Lead> {
+        let mut state = self.state.lock().unwrap();
+        if state.pending.is_empty() || state.in_flight >= self.max_in_flight {
+            return None;
+        }
+        state.in_flight += 1;
+        Some(Lead(self.clone()))
+    }
+
+    /// Waits for the deadline of the oldest pending request (or for `flush_now`),
+    /// then flushes all the pending requests. Blocks the current thread.
+    fn run_batch(&self) {
+        let mut state = self.state.lock().unwrap();
+        while let (Some(first), false) = (state.first, state.urgent) {
+            let now = Instant::now();
+            let deadline = first + self.window;
+            if now >= deadline {
+                break;
+            }
+            state = self.cond.wait_timeout(state, deadline - now).unwrap().0;
+        }
+        let batch = std::mem::take(&mut state.pending);
+        state.first = None;
+        state.urgent = false;
+        drop(state);
+
+        for r in batch {
+            let res = crate::utils::msync(r.addr, r.len);
+            for slot in r.slots {
+                slot.complete(res.as_ref().map(|_| ()).map_err(|e| {
+                    Error::new_source_msg(
+                        ErrorKind::FlushFailed,
+                        r.path.clone(),
+                        std::io::Error::new(e.kind(), e.to_string()),
+                    )
+                }));
+            }
+        }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+        state
+            .pending
+            .iter()
+            .flat_map(|r| r.slots.iter())
+            .for_each(|s| s.nudge());
+    }
+}
+
+/// A reserved batch, which is released when it is dropped, whether it has run or not
+/// (e.g. the blocking task is cancelled before it starts).
+pub(crate) struct Lead(Arc<SchedulerCore>);
+
+impl Lead {
+    pub(crate) fn run(self) {
+        self.0.run_batch();
+    }
+}
+
+impl Drop for Lead {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+/// A queued flush. If it is dropped before the flush is done, it is removed from the queue.
+pub(crate) struct Ticket {
+    core: Arc<SchedulerCore>,
+    slot: Arc<Slot>,
+}
+
+impl Ticket {
+    /// Resolves to `Some` with the result of the flush, or to `None` if the ticket is nudged
+    /// to try to lead a batch.
+    pub(crate) fn wait(&self) -> Wait<'_> {
+        Wait { slot: &self.slot }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        if self.slot.state.lock().unwrap().result.is_some() {
+            return;
+        }
+
+        let mut state = self.core.state.lock().unwrap();
+        for r in state.pending.iter_mut() {
+            r.slots.retain(|s| !Arc::ptr_eq(s, &self.slot));
+        }
+        state.pending.retain(|r| !r.slots.is_empty());
+        if state.pending.is_empty() {
+            state.first = None;
+            state.urgent = false;
+        }
+    }
+}
+
+pub(crate) struct Wait<'a> {
+    slot: &'a Slot,
+}
+
+impl<'a> Future for Wait<'a> {
+    type Output = Option<Result<()>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.state.lock().unwrap();
+        if let Some(result) = state.result.take() {
+            return Poll::Ready(Some(result));
+        }
+        if state.nudged {
+            state.nudged = false;
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+macro_rules! declare_flush_scheduler {
+    ($runtime: literal) => {
+        /// A shared scheduler which coalesces and rate-limits the flushes of many mutable maps.
+        ///
+        /// A map is registered by [`set_flush_scheduler`], then [`flush_scheduled`] queues the flush
+        /// of the map instead of running `msync` right away. The flushes queued within `window` of
+        /// each other are run in one batch on the blocking thread pool, and overlapping ranges
+        #[doc = concat!("are flushed once. At most `max_in_flight` batches (so blocking threads of ", $runtime, ")")]
+        /// run at the same time, which keeps thousands of small maps flushing concurrently from
+        /// thrashing the executor and the disk.
+        ///
+        /// The scheduler does not keep the maps alive. A queued flush belongs to the
+        /// [`flush_scheduled`] future which borrows the map, if the future is dropped, the flush
+        /// is dequeued (unless its batch has already started).
+        ///
+        /// [`set_flush_scheduler`]: trait.AsyncMmapFileMutExt.html#method.set_flush_scheduler
+        /// [`flush_scheduled`]: trait.AsyncMmapFileMutExt.html#method.flush_scheduled
+        #[derive(Clone)]
+        pub struct FlushScheduler {
+            core: Arc<SchedulerCore>,
+        }
+
+        impl FlushScheduler {
+            /// Creates a scheduler, which batches the flushes queued within `window`, and runs
+            /// at most `max_in_flight` batches at the same time (at least 1).
+            pub fn new(window: Duration, max_in_flight: usize) -> Self {
+                Self {
+                    core: Arc::new(SchedulerCore::new(window, max_in_flight)),
+                }
+            }
+
+            /// Flushes all the queued flushes now, without waiting for the end of their window.
+            ///
+            /// # Errors
+            /// Returns the first error of the queued flushes, all of them are run anyway.
+            pub async fn flush_now(&self) -> Result<()> {
+                let mut res = Ok(());
+                for ticket in self.core.expedite() {
+                    if let Err(e) = self.drive(ticket).await {
+                        res = res.and(Err(e));
+                    }
+                }
+                res
+            }
+
+            /// Queues the flush of the range and waits until it is done.
+            pub(crate) async fn flush(&self, addr: usize, len: usize, path: String) -> Result<()> {
+                let ticket = self.core.submit(addr, len, path);
+                self.drive(ticket).await
+            }
+
+            async fn drive(&self, ticket: Ticket) -> Result<()> {
+                loop {
+                    if let Some(lead) = self.core.try_lead() {
+                        spawn_blocking(move || lead.run()).await?;
+                    }
+                    if let Some(res) = ticket.wait().await {
+                        return res;
+                    }
+                }
+            }
+        }
+
+        impl Debug for FlushScheduler {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.debug_struct("FlushScheduler")
+                    .field("window", &self.core.window)
+                    .field("max_in_flight", &self.core.max_in_flight)
+                    .finish()
+            }
+        }
+    };
+}
+
+cfg_async_std!(
+    pub mod async_std_impl;
+);
+
+cfg_smol!(
+    pub mod smol_impl;
+);
+
+cfg_tokio!(
+    pub mod tokio_impl;
+);
@@ -0,0 +1,8 @@
+use super::{SchedulerCore, Ticket};
+use crate::error::Result;
+use crate::utils::smol::spawn_blocking;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+declare_flush_scheduler!("smol");
@@ -0,0 +1,154 @@
+use crate::error::{Error, ErrorKind, Result};
+use memmapix::{Mmap, MmapMut};
+use std::fmt::{Debug, Formatter};
+
+/// An anonymous read-only and executable memory region, created by [`make_executable_region`].
+///
+/// The region is unmapped when it is dropped, so every function pointer derived from it
+/// must not be called after that.
+///
+/// [`make_executable_region`]: fn.make_executable_region.html
+pub struct ExecutableRegion {
+    mmap: Mmap,
+}
+
+impl ExecutableRegion {
+    /// Returns the base address of the region.
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.mmap.as_ptr()
+    }
+
+    /// Returns the length of the region.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.mmap.len()
+    }
+
+    /// Returns whether the region is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.mmap.is_empty()
+    }
+
+    /// Returns the content of the region.
+    #[inline]
+    pub fn as_slice(&self) -> &[u8] {
+        self.mmap.as_ref()
+    }
+}
+
+impl Debug for ExecutableRegion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecutableRegion")
+            .field("ptr", &self.as_ptr())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Copies `code` into a new anonymous mmap, makes it executable and returns the region,
+/// with a function pointer to its base address.
+///
+/// The region is mapped readable and writable while `code` is copied in, then it is
+/// transitioned to readable and executable. It is never writable and executable at the
+/// same time (W^X). On architectures where the instruction cache is not coherent with
+/// the data cache (e.g. aarch64), the caches are synchronized before returning.
+///
+/// # Safety
+/// - `code` must be valid machine code for the target architecture, which can be called
+///   as an `extern "C" fn()`.
+/// - The function pointer must not be called after the returned region is dropped.
+///
+/// # Errors
+/// Returns `Err(Error::from(ErrorKind::MmapFailed))` if `code` is empty or the region cannot be
+/// mapped, and `Err(Error::from(ErrorKind::IO))` if the region cannot be made executable.
+///
+/// # Examples
+///
+/// ```ignore
+/// use fmmap::raw::make_executable_region;
+///
+/// // x86_64: ret
+/// let (region, f) = unsafe { make_executable_region(&[0xC3]).unwrap() };
+/// f();
+/// drop(region);
+/// ```
+pub unsafe fn make_executable_region(code: &[u8]) -> Result<(ExecutableRegion, extern "C" fn())> {
+    if code.is_empty() {
+        return Err(Error::new_with_message(
+            ErrorKind::MmapFailed,
+            "cannot make an empty executable region",
+        ));
+    }
+
+    let mut mmap = MmapMut::map_anon(code.len()).map_err(|e| Error::new(ErrorKind::MmapFailed, e))?;
+    mmap.copy_from_slice(code);
+    let mmap = mmap.make_exec().map_err(|e| Error::new(ErrorKind::IO, e))?;
+    sync_instruction_cache(mmap.as_ptr(), mmap.len());
+
+    let f = std::mem::transmute::<*const u8, extern "C" fn()>(mmap.as_ptr());
+    Ok((ExecutableRegion { mmap }, f))
+}
+
+/// Cleans the data cache and invalidates the instruction cache for the range,
+/// so the instructions written through the data cache are visible to the instruction fetch.
+#[cfg(target_arch = "aarch64")]
+fn sync_instruction_cache(start: *const u8, len: usize) {
+    use std::arch::asm;
+
+    let start = start as usize;
+    let end = start + len;
+    unsafe {
+        let ctr: u64;
+        asm!("mrs {}, ctr_el0", out(reg) ctr, options(nomem, nostack));
+        let dline = 4usize << ((ctr >> 16) & 0xF);
+        let iline = 4usize << (ctr & 0xF);
+
+        let mut addr = start & !(dline - 1);
+        while addr < end {
+            asm!("dc cvau, {}", in(reg) addr, options(nostack));
+            addr += dline;
+        }
+        asm!("dsb ish", options(nostack));
+
+        let mut addr = start & !(iline - 1);
+        while addr < end {
+            asm!("ic ivau, {}", in(reg) addr, options(nostack));
+            addr += iline;
+        }
+        asm!("dsb ish", "isb", options(nostack));
+    }
+}
+
+/// The instruction cache is coherent with the data cache, nothing to do.
+#[cfg(not(target_arch = "aarch64"))]
+#[inline(always)]
+fn sync_instruction_cache(_start: *const u8, _len: usize) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    const RET: &[u8] = &[0xC3];
+
+    #[cfg(target_arch = "aarch64")]
+    const RET: &[u8] = &[0xC0, 0x03, 0x5F, 0xD6];
+
+    #[test]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64"))]
+    fn test_ret_stub() {
+        let (region, f) = unsafe { make_executable_region(RET).unwrap() };
+        assert_eq!(region.as_slice(), RET);
+        assert_eq!(region.as_ptr(), f as *const u8);
+        f();
+        drop(region);
+    }
+
+    #[test]
+    fn test_empty_code() {
+        let err = unsafe { make_executable_region(&[]).unwrap_err() };
+        assert_eq!(err.kind(), ErrorKind::MmapFailed);
+    }
+}
@@ -155,6 +155,16 @@ macro_rules! cfg_sync {
     }
 }
 
+macro_rules! cfg_codec {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+            #[cfg_attr(docsrs, doc(cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))))]
+            $item
+        )*
+    }
+}
+
 macro_rules! cfg_async {
     ($($item:item)*) => {
         $(
@@ -201,6 +211,25 @@ macro_rules! cfg_test {
     }
 }
 
+/// Retries the blocking operation while it fails with `io::ErrorKind::Interrupted` (EINTR),
+/// at most `utils::EINTR_RETRIES` times, then the result of the last attempt is returned.
+macro_rules! eintr_retry {
+    ($op: expr) => {{
+        let mut retries = 0;
+        loop {
+            match $op {
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::Interrupted
+                        && retries < crate::utils::EINTR_RETRIES =>
+                {
+                    retries += 1
+                }
+                res => break res,
+            }
+        }
+    }};
+}
+
 macro_rules! noop_flush {
     () => {
         #[inline(always)]
@@ -275,6 +304,335 @@ cfg_sync! {
                     file1.flush_async().unwrap();
                 }
 
+                #[test]
+                fn test_checksum_parallel() {
+                    let path = concat!($filename_prefix, "_checksum_parallel.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(100)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all(vec![7; 100].as_slice(), 0).unwrap();
+                    #[cfg(feature = "crc32")]
+                    assert_eq!(file.crc32_parallel(), crc32fast::hash(&[7; 100]));
+                    #[cfg(feature = "xxh3")]
+                    assert_eq!(file.xxh3_parallel(), xxhash_rust::xxh3::xxh3_64(&[7; 100]));
+                }
+
+                #[cfg(feature = "crc32")]
+                #[test]
+                fn test_checksum_incremental() {
+                    let path = concat!($filename_prefix, "_checksum_incremental.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(100)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all(vec![7; 40].as_slice(), 0).unwrap();
+                    file.truncate(40).unwrap();
+                    let mut state = file.checksum_incremental();
+
+                    file.truncate(100).unwrap();
+                    file.write_all(vec![9; 60].as_slice(), 40).unwrap();
+                    state.update_from_range(&file, 40..70).unwrap();
+                    state.update_from_range(&file, 70..100).unwrap();
+                    assert_eq!(state.update_from_range(&file, 90..101).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(state.finalize(), crc32fast::hash(file.as_slice()));
+                }
+
+                #[cfg(feature = "crc32")]
+                #[test]
+                fn test_trailing_crc32() {
+                    let path = concat!($filename_prefix, "_trailing_crc32.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(16)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    file.write_u32(crc32fast::hash(b"some data..."), 12).unwrap();
+                    assert!(file.verify_trailing_crc32().unwrap());
+                    file.assert_trailing_crc32().unwrap();
+
+                    file.write_u8(b'S', 0).unwrap();
+                    assert!(!file.verify_trailing_crc32().unwrap());
+                    assert_eq!(file.assert_trailing_crc32().unwrap_err().kind(), ErrorKind::ChecksumMismatch);
+
+                    file.truncate(3).unwrap();
+                    assert_eq!(file.verify_trailing_crc32().unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[cfg(feature = "crc32")]
+                #[test]
+                fn test_bytes_verified_crc32() {
+                    let path = concat!($filename_prefix, "_bytes_verified_crc32.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(20)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 2).unwrap();
+                    file.write_u32(crc32fast::hash(b"some data..."), 14).unwrap();
+                    assert_eq!(file.bytes_verified_crc32(2, 12).unwrap(), b"some data...");
+                    assert_eq!(file.bytes_verified_crc32(2, 11).unwrap_err().kind(), ErrorKind::ChecksumMismatch);
+                    assert_eq!(file.bytes_verified_crc32(5, 12).unwrap_err().kind(), ErrorKind::EOF);
+
+                    file.write_u8(b'S', 2).unwrap();
+                    assert_eq!(file.bytes_verified_crc32(2, 12).unwrap_err().kind(), ErrorKind::ChecksumMismatch);
+                }
+
+                #[test]
+                fn test_grow_by_shrink_by() {
+                    let path = concat!($filename_prefix, "_grow_by_shrink_by.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    file.grow_by(8).unwrap();
+                    assert_eq!(file.len(), 20);
+                    assert_eq!(&file.as_slice()[..12], b"some data...");
+                    file.shrink_by(16).unwrap();
+                    assert_eq!(file.as_slice(), b"some");
+                    file.shrink_by(100).unwrap();
+                    assert_eq!(file.len(), 0);
+                    file.grow_by(4).unwrap();
+                    assert_eq!(file.as_slice(), &[0; 4]);
+                    assert_eq!(file.grow_by(u64::MAX).unwrap_err().kind(), ErrorKind::TruncationFailed);
+                }
+
+                #[test]
+                fn test_truncate_trailing_zeros() {
+                    let path = concat!($filename_prefix, "_truncate_trailing_zeros.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(100)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    file.write_u8(1, 37).unwrap();
+                    assert_eq!(file.truncate_trailing_zeros().unwrap(), 38);
+                    assert_eq!(file.len(), 38);
+                    assert_eq!(file.truncate_trailing_zeros().unwrap(), 38);
+                    file.zero_range(0, 38);
+                    assert_eq!(file.truncate_trailing_zeros().unwrap(), 0);
+                    assert_eq!(file.len(), 0);
+                }
+
+                #[test]
+                fn test_swap_ranges() {
+                    let path = concat!($filename_prefix, "_swap_ranges.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("aaaabbbbcccc".as_bytes(), 0).unwrap();
+                    file.swap_ranges(8..12, 0..4).unwrap();
+                    assert_eq!(file.as_slice(), b"ccccbbbbaaaa");
+                    file.swap_ranges(4..6, 4..6).unwrap();
+                    assert_eq!(file.as_slice(), b"ccccbbbbaaaa");
+                    assert_eq!(file.swap_ranges(0..4, 2..6).unwrap_err().kind(), ErrorKind::Overlap);
+                    assert_eq!(file.swap_ranges(0..4, 4..6).unwrap_err().kind(), ErrorKind::LengthMismatch(4, 2));
+                    assert_eq!(file.swap_ranges(0..4, 10..14).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_apply_patch() {
+                    let path = concat!($filename_prefix, "_apply_patch.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    file.apply_patch(&[(0, b"SOME"), (5, b"DATA"), (11, b"!"), (12, b"")]).unwrap();
+                    assert_eq!(file.as_slice(), b"SOME DATA..!");
+
+                    let err = file.apply_patch(&[(0, b"some"), (10, b"!!!")]).unwrap_err();
+                    assert_eq!(err.kind(), ErrorKind::EOF);
+                    assert_eq!(file.as_slice(), b"SOME DATA..!");
+                    assert_eq!(file.apply_patch(&[(usize::MAX, b"!")]).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_write_str() {
+                    let path = concat!($filename_prefix, "_write_str.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(24)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    assert_eq!(file.write_str("some", 0).unwrap(), 4);
+                    assert_eq!(file.write_cstr("data", 4).unwrap(), 5);
+                    assert_eq!(file.write_str_prefixed("more", 9).unwrap(), 8);
+                    assert_eq!(&file.as_slice()[..17], b"somedata\0\0\0\0\x04more");
+
+                    assert_eq!(file.write_cstr("da\0ta", 0).unwrap_err().kind(), ErrorKind::InvalidCStr);
+                    assert_eq!(file.write_str("too long", 20).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(file.write_cstr("data", 20).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(file.write_str_prefixed("data", 17).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(&file.as_slice()[17..], &[0; 7]);
+                }
+
+                #[test]
+                fn test_copy_to_bytes() {
+                    let path = concat!($filename_prefix, "_copy_to_bytes.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    assert_eq!(file.copy_all_to_bytes().as_ref(), "some data...".as_bytes());
+                    assert_eq!(file.copy_range_to_bytes(5, 4).unwrap().as_ref(), "data".as_bytes());
+                    assert!(file.copy_range_to_bytes(12, 0).unwrap().is_empty());
+                    assert_eq!(file.copy_range_to_bytes(10, 3).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(file.copy_range_to_bytes(usize::MAX, 2).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_fill_pattern() {
+                    let path = concat!($filename_prefix, "_fill_pattern.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.fill_pattern(&[0xFF], 0..12).unwrap();
+                    assert_eq!(file.as_slice(), &[0xFF; 12]);
+                    file.fill_pattern(&[0xDE, 0xAD, 0xBE, 0xEF], 1..11).unwrap();
+                    assert_eq!(
+                        file.as_slice(),
+                        &[0xFF, 0xDE, 0xAD, 0xBE, 0xEF, 0xDE, 0xAD, 0xBE, 0xEF, 0xDE, 0xAD, 0xFF]
+                    );
+                    assert_eq!(file.fill_pattern(&[], 0..4).unwrap_err().kind(), ErrorKind::BadFormat);
+                    assert_eq!(file.fill_pattern(&[1], 8..13).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_copy_range_to_arc() {
+                    let path = concat!($filename_prefix, "_copy_range_to_arc.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    let arc = file.copy_range_to_arc(5, 4).unwrap();
+                    let shared = arc.clone();
+                    assert_eq!(shared.as_ref(), "data".as_bytes());
+                    assert_eq!(arc.as_ptr(), shared.as_ptr());
+                    assert!(file.copy_range_to_arc(12, 0).unwrap().is_empty());
+                    assert_eq!(file.copy_range_to_arc(10, 3).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_bytes_until() {
+                    let path = concat!($filename_prefix, "_bytes_until.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(11)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("ab,,cde,fgh".as_bytes(), 0).unwrap();
+                    assert_eq!(file.bytes_until(0, b',').unwrap(), (&b"ab"[..], Some(3)));
+                    assert_eq!(file.bytes_until(3, b',').unwrap(), (&b""[..], Some(4)));
+                    assert_eq!(file.bytes_until(4, b',').unwrap(), (&b"cde"[..], Some(8)));
+                    assert_eq!(file.bytes_until(8, b',').unwrap(), (&b"fgh"[..], None));
+                    assert_eq!(file.bytes_until(11, b',').unwrap(), (&b""[..], None));
+                    assert_eq!(file.bytes_until(12, b',').unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_byte_positions() {
+                    let path = concat!($filename_prefix, "_byte_positions.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("a\nbc\n\ndef\ngh".as_bytes(), 0).unwrap();
+                    assert_eq!(file.byte_positions(b'\n').collect::<Vec<_>>(), vec![1, 4, 5, 9]);
+                    assert_eq!(file.count_byte(b'\n'), 4);
+                    assert_eq!(file.count_byte(b'z'), 0);
+                }
+
+                #[test]
+                fn test_read_array() {
+                    let path = concat!($filename_prefix, "_read_array.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    assert_eq!(file.read_array::<4>(5).unwrap(), *b"data");
+                    assert_eq!(file.read_array::<0>(12).unwrap(), []);
+                    assert_eq!(file.read_array::<4>(10).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(file.read_array::<1>(13).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_prefix_suffix() {
+                    let path = concat!($filename_prefix, "_prefix_suffix.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    assert_eq!(file.prefix(4).unwrap(), b"some");
+                    assert_eq!(file.suffix(3).unwrap(), b"...");
+                    assert_eq!(file.suffix(12).unwrap(), b"some data...");
+                    assert_eq!(file.prefix(13).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(file.suffix(13).unwrap_err().kind(), ErrorKind::EOF);
+                    assert_eq!(file.strip_prefix(b"some ").unwrap(), b"data...");
+                    assert_eq!(file.strip_prefix(b"data").unwrap_err().kind(), ErrorKind::BadFormat);
+                }
+
+                #[test]
+                fn test_compare_and_swap() {
+                    let path = concat!($filename_prefix, "_compare_and_swap.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(12)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    file.write_all("some data...".as_bytes(), 0).unwrap();
+                    assert!(!file.compare_and_swap(5, b"text", b"DATA").unwrap());
+                    assert_eq!(file.as_slice(), b"some data...");
+                    assert!(file.compare_and_swap(5, b"data", b"DATA").unwrap());
+                    assert_eq!(file.as_slice(), b"some DATA...");
+                    assert_eq!(file.compare_and_swap(5, b"DATA", b"D").unwrap_err().kind(), ErrorKind::LengthMismatch(4, 1));
+                    assert_eq!(file.compare_and_swap(10, b"...", b"!!!").unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[test]
+                fn test_zero_scan() {
+                    let path = concat!($filename_prefix, "_zero_scan.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(100)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    assert!(file.is_zero_range(0..100).unwrap());
+                    assert_eq!(file.first_nonzero(0), None);
+
+                    file.write_u8(1, 37).unwrap();
+                    file.write_u8(1, 98).unwrap();
+                    assert!(file.is_zero_range(0..37).unwrap());
+                    assert!(!file.is_zero_range(0..38).unwrap());
+                    assert!(file.is_zero_range(38..98).unwrap());
+                    assert_eq!(file.first_nonzero(0), Some(37));
+                    assert_eq!(file.first_nonzero(38), Some(98));
+                    assert_eq!(file.first_nonzero(99), None);
+                    assert_eq!(file.first_nonzero(100), None);
+                    assert_eq!(file.is_zero_range(90..101).unwrap_err().kind(), ErrorKind::EOF);
+                }
+
+                #[cfg(feature = "zerocopy")]
+                #[test]
+                fn test_header() {
+                    #[derive(zerocopy::FromBytes, zerocopy::IntoBytes, zerocopy::KnownLayout, zerocopy::Immutable)]
+                    #[repr(C)]
+                    struct Header {
+                        magic: u32,
+                        version: u32,
+                    }
+
+                    let path = concat!($filename_prefix, "_header.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(100)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    let header = file.header_mut::<Header>().unwrap();
+                    header.magic = 0xdead_beef;
+                    header.version = 1;
+                    let header = file.header::<Header>().unwrap();
+                    assert_eq!(header.magic, 0xdead_beef);
+                    assert_eq!(header.version, 1);
+
+                    file.truncate(4).unwrap();
+                    assert_eq!(file.header::<Header>().err().unwrap().kind(), ErrorKind::EOF);
+                }
+
+                #[cfg(feature = "zerocopy")]
+                #[test]
+                fn test_as_slice_of() {
+                    #[derive(zerocopy::FromBytes, zerocopy::IntoBytes, zerocopy::KnownLayout, zerocopy::Immutable)]
+                    #[repr(C)]
+                    struct Record {
+                        id: u32,
+                        value: u32,
+                    }
+
+                    let path = concat!($filename_prefix, "_as_slice_of.txt");
+                    let mut file = <$mmap_file_mut>::create_with_options(path, Options::new().max_size(8 * 4)).unwrap();
+                    defer!(std::fs::remove_file(path).unwrap(););
+                    let records = file.as_mut_slice_of::<Record>().unwrap();
+                    assert_eq!(records.len(), 4);
+                    for (i, record) in records.iter_mut().enumerate() {
+                        record.id = i as u32;
+                        record.value = i as u32 * 10;
+                    }
+                    records[2].value = 99;
+                    file.flush().unwrap();
+                    drop(file);
+
+                    let file = <$mmap_file>::open(path).unwrap();
+                    let records = file.as_slice_of::<Record>().unwrap();
+                    assert_eq!(records.len(), 4);
+                    assert_eq!((records[1].id, records[1].value), (1, 10));
+                    assert_eq!((records[2].id, records[2].value), (2, 99));
+                    assert_eq!(file.as_slice_of::<[u8; 3]>().unwrap_err().kind(), ErrorKind::EOF);
+                }
+
                 #[test]
                 fn test_lock_shared() {
                     let path = concat!($filename_prefix, "_lock_shared.txt");
@@ -312,6 +670,29 @@ cfg_sync! {
                     file2.lock_exclusive().unwrap();
                 }
 
+                #[test]
+                fn test_lock_guard() {
+                    let path = concat!($filename_prefix, "_lock_guard.txt");
+                    defer!(std::fs::remove_file(path).unwrap());
+                    let file1 = <$mmap_file_mut>::open(path).unwrap();
+                    let file2 = <$mmap_file>::open(path).unwrap();
+
+                    let guard = file1.lock_exclusive_guard().unwrap();
+                    assert!(file2.try_lock_shared().is_err());
+                    drop(guard);
+                    file2.lock_shared_guard().unwrap().unlock().unwrap();
+
+                    // the lock is released even if the closure fails
+                    let err = file1
+                        .with_exclusive_lock(|| -> crate::error::Result<()> {
+                            assert!(file2.try_lock_shared().is_err());
+                            Err(Error::from(ErrorKind::EOF))
+                        })
+                        .unwrap_err();
+                    assert_eq!(err.kind(), ErrorKind::EOF);
+                    assert_eq!(file2.with_shared_lock(|| Ok(1)).unwrap(), 1);
+                }
+
                 #[test]
                 fn test_lock_cleanup() {
                     let path = concat!($filename_prefix, "_lock_cleanup.txt");
@@ -1149,16 +1530,47 @@ cfg_async! {
     }
 }
 
+cfg_sync! {
+    mod anonymous;
+}
+mod checksum;
+pub use checksum::RollingChecksum;
+#[cfg(feature = "crc32")]
+#[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+pub use checksum::ChecksumState;
+#[cfg(feature = "blake3")]
+#[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+pub use checksum::BlockChecksums;
+cfg_codec! {
+    mod codec;
+    pub use codec::Codec;
+}
+mod dirty;
 mod disk;
 mod empty;
 /// Errors in this crate
 pub mod error;
+cfg_async! {
+    #[cfg(unix)]
+    mod flush_scheduler;
+}
+cfg_async! {
+    mod limiter;
+    pub use limiter::{set_open_concurrency, OpenConcurrencyLimiter};
+    pub use mmap_file::SyncRangeFlags;
+}
+mod magic;
+pub use magic::KnownMagic;
 mod memory;
 mod metadata;
 pub use metadata::{MetaData, MetaDataExt};
 mod mmap_file;
+pub use mmap_file::{
+    FindAll, LenPrefixed, Lines, LinesStr, MapKind, PageRanges, RecordsByMagic,
+};
 #[allow(dead_code)]
 mod options;
+pub use options::{FlushPolicy, GrowStrategy, WipePattern};
 mod reader;
 #[cfg(test)]
 pub mod tests;
@@ -1166,21 +1578,38 @@ pub mod tests;
 pub mod utils;
 mod writer;
 
+cfg_async! {
+    #[cfg(feature = "notify")]
+    mod watch;
+}
+
+cfg_sync! {
+    #[cfg(feature = "serde")]
+    mod serde_impl;
+}
+
+#[cfg(feature = "jit")]
+mod jit;
+
 cfg_sync!(
     /// std based mmap file
     pub mod sync {
-        pub use crate::mmap_file::{MmapFile, MmapFileExt, MmapFileMut, MmapFileMutExt};
+        pub use crate::mmap_file::{LockGuard, MmapFile, MmapFileExt, MmapFileMut, MmapFileMutExt};
         pub use crate::options::Options;
-        pub use crate::reader::{MmapFileReader, MmapFileReaderExt};
-        pub use crate::writer::{MmapFileWriter, MmapFileWriterExt};
+        pub use crate::reader::{MmapBufReader, MmapFileReader, MmapFileReaderExt, OwnedMmapReader};
+        pub use crate::writer::{BufferedMmapWriter, MmapFileWriter, MmapFileWriterExt};
     }
 
-    pub use reader::{MmapFileReader, MmapFileReaderExt};
-    pub use writer::{MmapFileWriter, MmapFileWriterExt};
-    pub use mmap_file::{MmapFileExt, MmapFileMutExt, MmapFile, MmapFileMut};
+    pub use reader::{MmapBufReader, MmapFileReader, MmapFileReaderExt, OwnedMmapReader};
+    pub use writer::{BufferedMmapWriter, MmapFileWriter, MmapFileWriterExt};
+    pub use mmap_file::{LockGuard, MmapFileExt, MmapFileMutExt, MmapFile, MmapFileMut};
     pub use options::Options;
 );
 
+cfg_windows!(
+    pub use options::ShareMode;
+);
+
 cfg_async!(
     #[macro_use]
     extern crate async_trait;
@@ -1190,8 +1619,10 @@ cfg_async_std!(
     /// async_std based mmap file
     pub mod async_std {
         pub use crate::mmap_file::async_std_impl::{
-            AsyncMmapFile, AsyncMmapFileExt, AsyncMmapFileMut, AsyncMmapFileMutExt,
+            AsyncMmapFile, AsyncMmapFileExt, AsyncMmapFileMut, AsyncMmapFileMutExt, TempHandle,
         };
+        #[cfg(unix)]
+        pub use crate::flush_scheduler::async_std_impl::FlushScheduler;
         pub use crate::options::async_std_impl::AsyncOptions;
         pub use crate::reader::async_std_impl::AsyncMmapFileReader;
         pub use crate::writer::async_std_impl::AsyncMmapFileWriter;
@@ -1202,8 +1633,10 @@ cfg_smol!(
     /// smol based mmap file
     pub mod smol {
         pub use crate::mmap_file::smol_impl::{
-            AsyncMmapFile, AsyncMmapFileExt, AsyncMmapFileMut, AsyncMmapFileMutExt,
+            AsyncMmapFile, AsyncMmapFileExt, AsyncMmapFileMut, AsyncMmapFileMutExt, TempHandle,
         };
+        #[cfg(unix)]
+        pub use crate::flush_scheduler::smol_impl::FlushScheduler;
         pub use crate::options::smol_impl::AsyncOptions;
         pub use crate::reader::smol_impl::AsyncMmapFileReader;
         pub use crate::writer::smol_impl::AsyncMmapFileWriter;
@@ -1214,8 +1647,10 @@ cfg_tokio!(
     /// tokio based mmap file
     pub mod tokio {
         pub use crate::mmap_file::tokio_impl::{
-            AsyncMmapFile, AsyncMmapFileExt, AsyncMmapFileMut, AsyncMmapFileMutExt,
+            AsyncMmapFile, AsyncMmapFileExt, AsyncMmapFileMut, AsyncMmapFileMutExt, TempHandle,
         };
+        #[cfg(unix)]
+        pub use crate::flush_scheduler::tokio_impl::FlushScheduler;
         pub use crate::options::tokio_impl::AsyncOptions;
         pub use crate::reader::tokio_impl::AsyncMmapFileReader;
         pub use crate::writer::tokio_impl::AsyncMmapFileWriter;
@@ -1232,13 +1667,19 @@ pub mod raw {
         /// [`MmapFile`]: struct.MmapFile.html
         /// [`MmapFileMut`]: struct.MmapFileMut.html
         pub mod sync {
+            pub use crate::anonymous::AnonymousMmapFileMut;
             pub use crate::disk::{DiskMmapFile, DiskMmapFileMut};
             pub use crate::memory::{MemoryMmapFile, MemoryMmapFileMut};
         }
+        pub use crate::anonymous::AnonymousMmapFileMut;
         pub use crate::disk::{DiskMmapFile, DiskMmapFileMut};
         pub use crate::memory::{MemoryMmapFile, MemoryMmapFileMut};
     );
 
+    #[cfg(feature = "jit")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jit")))]
+    pub use crate::jit::{make_executable_region, ExecutableRegion};
+
     cfg_async_std!(
         /// async_std based raw mmap file
         ///
@@ -0,0 +1,165 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// A semaphore which limits how many files are being opened or created concurrently by the
+/// async memory maps, e.g. to stay under the file descriptor limit when a lot of files are
+/// opened at once.
+///
+/// The async `open*` and `create*` constructors acquire a permit before touching the file system,
+/// and release it once the file is opened and mapped, so the limit bounds the in-flight opens,
+/// not the opened files. A limit of 0 means unlimited.
+///
+/// The crate-level limiter is configured by [`set_open_concurrency`], and is unlimited by default.
+/// A limiter passed by `concurrency_limiter` of `AsyncOptions` is used instead of the crate-level
+/// one for the opens with those options. The limit applies to each open call separately, so
+/// opening many files in a loop or by joining many open futures is throttled the same way.
+///
+/// [`set_open_concurrency`]: crate::set_open_concurrency
+#[derive(Clone)]
+pub struct OpenConcurrencyLimiter {
+    inner: Arc<Mutex<State>>,
+}
+
+struct State {
+    limit: usize,
+    in_use: usize,
+    waiters: Vec<Waker>,
+}
+
+impl std::fmt::Debug for OpenConcurrencyLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.inner.lock().unwrap();
+        f.debug_struct("OpenConcurrencyLimiter")
+            .field("limit", &state.limit)
+            .field("in_use", &state.in_use)
+            .finish()
+    }
+}
+
+impl OpenConcurrencyLimiter {
+    /// Creates a limiter which allows at most `limit` concurrent opens, 0 means unlimited.
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(State {
+                limit,
+                in_use: 0,
+                waiters: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns the limit of the concurrent opens, 0 means unlimited.
+    pub fn limit(&self) -> usize {
+        self.inner.lock().unwrap().limit
+    }
+
+    /// Changes the limit of the concurrent opens, 0 means unlimited.
+    ///
+    /// The opens which are already in flight are not affected, if there are more of them than
+    /// the new limit, the waiting ones are blocked until enough of them finish.
+    pub fn set_limit(&self, limit: usize) {
+        let waiters = {
+            let mut state = self.inner.lock().unwrap();
+            state.limit = limit;
+            std::mem::take(&mut state.waiters)
+        };
+        waiters.into_iter().for_each(Waker::wake);
+    }
+
+    /// Returns how many opens are holding a permit now.
+    pub fn in_use(&self) -> usize {
+        self.inner.lock().unwrap().in_use
+    }
+
+    #[inline]
+    pub(crate) fn acquire(&self) -> Acquire<'_> {
+        Acquire { limiter: self }
+    }
+}
+
+/// The future returned by `acquire` of [`OpenConcurrencyLimiter`].
+pub(crate) struct Acquire<'a> {
+    limiter: &'a OpenConcurrencyLimiter,
+}
+
+impl Future for Acquire<'_> {
+    type Output = OpenPermit;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.limiter.inner.lock().unwrap();
+        if state.limit == 0 || state.in_use < state.limit {
+            state.in_use += 1;
+            return Poll::Ready(OpenPermit {
+                inner: self.limiter.inner.clone(),
+            });
+        }
+
+        if !state.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            state.waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+/// A permit of [`OpenConcurrencyLimiter`], which is released when dropped.
+pub(crate) struct OpenPermit {
+    inner: Arc<Mutex<State>>,
+}
+
+impl Drop for OpenPermit {
+    fn drop(&mut self) {
+        // wake all the waiters, the ones which do not get the permit register themselves again
+        let waiters = {
+            let mut state = self.inner.lock().unwrap();
+            state.in_use -= 1;
+            std::mem::take(&mut state.waiters)
+        };
+        waiters.into_iter().for_each(Waker::wake);
+    }
+}
+
+static GLOBAL: AtomicPtr<OpenConcurrencyLimiter> = AtomicPtr::new(ptr::null_mut());
+
+/// Returns the crate-level limiter, which is created on the first use and never freed.
+fn global() -> &'static OpenConcurrencyLimiter {
+    let mut ptr = GLOBAL.load(Ordering::Acquire);
+    if ptr.is_null() {
+        let new = Box::into_raw(Box::new(OpenConcurrencyLimiter::new(0)));
+        ptr = match GLOBAL.compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(current) => {
+                // another thread won the race, drop ours
+                drop(unsafe { Box::from_raw(new) });
+                current
+            }
+        };
+    }
+    // SAFETY: the pointer comes from `Box::into_raw` and is never freed once published.
+    unsafe { &*ptr }
+}
+
+/// Limits how many files are being opened or created concurrently by the async memory maps
+/// across the whole process, 0 means unlimited, which is the default.
+///
+/// It only applies to the opens without a limiter set by `concurrency_limiter` of
+/// `AsyncOptions`. See [`OpenConcurrencyLimiter`] for the details.
+pub fn set_open_concurrency(limit: usize) {
+    global().set_limit(limit);
+}
+
+/// Waits for a permit of `limiter`, or of the crate-level limiter if it is `None`.
+pub(crate) async fn acquire(limiter: Option<&OpenConcurrencyLimiter>) -> OpenPermit {
+    match limiter {
+        Some(limiter) => limiter.acquire().await,
+        None => global().acquire().await,
+    }
+}
@@ -0,0 +1,71 @@
+/// The file formats which can be detected by their magic number at the start of the file,
+/// see the `sniff` method of `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum KnownMagic {
+    /// Executable and Linkable Format
+    Elf,
+    /// Portable Network Graphics
+    Png,
+    /// ZIP archive (also jar, docx, apk, etc.)
+    Zip,
+    /// gzip compressed data
+    Gzip,
+    /// Zstandard compressed data
+    Zstd,
+    /// Portable Document Format
+    Pdf,
+    /// SQLite 3 database
+    Sqlite,
+}
+
+impl KnownMagic {
+    /// All the known formats, in the order they are checked.
+    pub const ALL: &'static [KnownMagic] = &[
+        KnownMagic::Elf,
+        KnownMagic::Png,
+        KnownMagic::Zip,
+        KnownMagic::Gzip,
+        KnownMagic::Zstd,
+        KnownMagic::Pdf,
+        KnownMagic::Sqlite,
+    ];
+
+    /// Returns the magic number the files of the format start with.
+    pub const fn magic(&self) -> &'static [u8] {
+        match self {
+            KnownMagic::Elf => b"\x7FELF",
+            KnownMagic::Png => b"\x89PNG\r\n\x1A\n",
+            KnownMagic::Zip => b"PK\x03\x04",
+            KnownMagic::Gzip => b"\x1F\x8B",
+            KnownMagic::Zstd => b"\x28\xB5\x2F\xFD",
+            KnownMagic::Pdf => b"%PDF-",
+            KnownMagic::Sqlite => b"SQLite format 3\0",
+        }
+    }
+
+    /// Detects the format of the data by its first bytes.
+    pub fn detect(data: &[u8]) -> Option<KnownMagic> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|magic| data.starts_with(magic.magic()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        for magic in KnownMagic::ALL {
+            let mut data = magic.magic().to_vec();
+            data.extend_from_slice(b"some data...");
+            assert_eq!(KnownMagic::detect(&data), Some(*magic));
+            assert_eq!(KnownMagic::detect(&magic.magic()[1..]), None);
+        }
+        assert_eq!(KnownMagic::detect(b""), None);
+        assert_eq!(KnownMagic::detect(b"some data..."), None);
+    }
+}
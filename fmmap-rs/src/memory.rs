@@ -1,3 +1,21 @@
+// Overrides of the `copy_*_to_bytes` methods, which share the underlying `Bytes` instead of copying.
+macro_rules! impl_zero_copy_bytes {
+    () => {
+        #[inline]
+        fn copy_all_to_bytes(&self) -> Bytes {
+            self.mmap.clone()
+        }
+
+        #[inline]
+        fn copy_range_to_bytes(&self, offset: usize, len: usize) -> crate::error::Result<Bytes> {
+            match offset.checked_add(len) {
+                Some(end) if end <= self.mmap.len() => Ok(self.mmap.slice(offset..end)),
+                _ => Err(crate::error::Error::from(crate::error::ErrorKind::EOF)),
+            }
+        }
+    };
+}
+
 macro_rules! define_impl_constructor_for_mmap_file {
     ($name: ident, $name_str: literal, $path_str: literal) => {
         /// Use [`Bytes`] to mock a mmap, which is useful for test and in-memory storage engine.
@@ -122,6 +140,25 @@ macro_rules! define_impl_constructor_for_mmap_file {
             pub fn into_bytes(self) -> Bytes {
                 self.mmap
             }
+
+            #[doc = "Sets the path reported by `path`, which is only a label of the in-memory data,"]
+            #[doc = "e.g. a logical name. An owned `PathBuf` is moved in without copying."]
+            pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+                self.path = label.into();
+                self
+            }
+
+            #[inline]
+            pub(crate) fn set_path_label(&mut self, label: PathBuf) {
+                self.path = label;
+            }
+
+            #[doc = "Leaks the inner bytes, and returns a `'static` slice of them, e.g. for a lookup"]
+            #[doc = "table which is used for the lifetime of the process. The bytes are never freed."]
+            pub fn leak(self) -> &'static [u8] {
+                let bytes: &'static Bytes = Box::leak(Box::new(self.mmap));
+                bytes.as_ref()
+            }
         }
     };
 }
@@ -267,6 +304,21 @@ macro_rules! define_and_impl_constructor_for_mmap_file_mut {
                 self.mmap.freeze()
             }
 
+            #[doc = "Returns the inner buffer as a `Vec`."]
+            #[doc = ""]
+            #[doc = "The buffer is moved into the `Vec` without copying when it is uniquely owned"]
+            #[doc = "(which is the case unless it was split off another buffer), otherwise it is copied."]
+            #[doc = "# Examples"]
+            #[doc = "```rust"]
+            #[doc = concat!("use fmmap::raw::", $path_str, $name_str, ";")]
+            #[doc = ""]
+            #[doc = concat!("let v = ", $name_str, "::from_slice(\"foo.mem\", \"some data...\".as_bytes()).into_vec();")]
+            #[doc = "assert_eq!(v, \"some data...\".as_bytes());"]
+            #[doc = "```"]
+            pub fn into_vec(self) -> Vec<u8> {
+                Vec::from(self.mmap)
+            }
+
 
             #[doc = "Make the memory mmap file immutable"]
             #[doc = "# Examples"]
@@ -290,13 +342,25 @@ macro_rules! define_and_impl_constructor_for_mmap_file_mut {
                     create_at: self.create_at,
                 }
             }
+
+            #[doc = "Sets the path reported by `path`, which is only a label of the in-memory data,"]
+            #[doc = "e.g. a logical name. An owned `PathBuf` is moved in without copying."]
+            pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+                self.path = label.into();
+                self
+            }
+
+            #[inline]
+            pub(crate) fn set_path_label(&mut self, label: PathBuf) {
+                self.path = label;
+            }
         }
     };
 }
 
 cfg_sync! {
     macro_rules! impl_mmap_file_ext {
-        ($name: ident) => {
+        ($name: ident $(, $extra: ident)?) => {
             impl MmapFileExt for $name {
                 fn len(&self) -> usize {
                     self.mmap.len()
@@ -314,6 +378,10 @@ cfg_sync! {
                     false
                 }
 
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Memory
+                }
+
                 fn metadata(&self) -> crate::error::Result<MetaData> {
                     Ok(MetaData::memory(MemoryMetaData::new(
                         self.mmap.len() as u64,
@@ -322,6 +390,8 @@ cfg_sync! {
                 }
 
                 noop_file_lock!();
+
+                $($extra!();)?
             }
         };
     }
@@ -331,7 +401,7 @@ cfg_sync! {
 
 cfg_async! {
     macro_rules! impl_async_mmap_file_ext {
-        ($name: ident) => {
+        ($name: ident $(, $extra: ident)?) => {
             #[async_trait]
             impl AsyncMmapFileExt for $name {
                 fn len(&self) -> usize {
@@ -350,6 +420,10 @@ cfg_async! {
                     false
                 }
 
+                fn kind(&self) -> crate::MapKind {
+                    crate::MapKind::Memory
+                }
+
                 noop_file_lock!();
 
                 async fn metadata(&self) -> crate::error::Result<MetaData> {
@@ -358,6 +432,8 @@ cfg_async! {
                         self.create_at,
                     )))
                 }
+
+                $($extra!();)?
             }
         };
     }
@@ -378,6 +454,14 @@ cfg_async! {
 
                 noop_flush!();
 
+                #[inline]
+                async fn flush_range_committed(&self, offset: usize, len: usize) -> crate::error::Result<()> {
+                    match offset.checked_add(len) {
+                        Some(end) if end <= self.len() => Ok(()),
+                        _ => Err(crate::error::Error::from(crate::error::ErrorKind::EOF)),
+                    }
+                }
+
                 #[inline]
                 async fn truncate(&mut self, max_sz: u64) -> crate::error::Result<()> {
                     self.mmap.resize(max_sz as usize, 0);
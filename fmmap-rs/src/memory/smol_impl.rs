@@ -8,7 +8,7 @@ use crate::metadata::MemoryMetaData;
 
 define_impl_constructor_for_mmap_file!(AsyncMemoryMmapFile, "AsyncMemoryMmapFile", "smol::");
 
-impl_async_mmap_file_ext!(AsyncMemoryMmapFile);
+impl_async_mmap_file_ext!(AsyncMemoryMmapFile, impl_zero_copy_bytes);
 
 define_and_impl_constructor_for_mmap_file_mut!(AsyncMemoryMmapFileMut, "AsyncMemoryMmapFileMut", AsyncMemoryMmapFile, "AsyncMemoryMmapFile", "AsyncMmapFileExt", "smol::");
 
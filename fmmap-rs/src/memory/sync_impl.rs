@@ -6,7 +6,7 @@ use crate::metadata::MemoryMetaData;
 
 define_impl_constructor_for_mmap_file!(MemoryMmapFile, "MemoryMmapFile", "");
 
-impl_mmap_file_ext!(MemoryMmapFile);
+impl_mmap_file_ext!(MemoryMmapFile, impl_zero_copy_bytes);
 
 define_and_impl_constructor_for_mmap_file_mut!(MemoryMmapFileMut, "MemoryMmapFileMut", MemoryMmapFile, "MemoryMmapFile", "MmapFileExt", "");
 
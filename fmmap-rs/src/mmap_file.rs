@@ -11,6 +11,15 @@ macro_rules! read_impl {
     }};
 }
 
+/// Converts the `ErrorKind::NotFound` error returned by the open methods to `Ok(None)`.
+fn none_if_not_found<T>(rst: crate::error::Result<T>) -> crate::error::Result<Option<T>> {
+    match rst {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.kind() == crate::error::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
 macro_rules! impl_from {
     ($outer: ident, $enum_inner: ident, [$($inner: ident), +$(,)?]) => {
         $(
@@ -24,17 +33,30 @@ macro_rules! impl_from {
 }
 
 macro_rules! impl_from_mut {
-    ($outer: ident, $enum_inner: ident, [$($inner: ident), +$(,)?]) => {
-        $(
-        impl From<$inner> for $outer {
-            fn from(file: $inner) -> Self {
+    ($outer: ident, $enum_inner: ident, [$($inner: ident), +$(,)?] $(, $field: ident: $val: expr)* $(,)?) => {
+        impl $outer {
+            fn from_inner(inner: $enum_inner) -> Self {
+                // the existing content counts as written, freshly created files reset it
+                let high_water = inner.len();
                 $outer{
-                    inner: <$enum_inner>::from(file),
+                    inner,
                     remove_on_drop: false,
+                    flush_on_drop: false,
+                    high_water,
+                    dirty: Default::default(),
+                    grow: Default::default(),
                     deleted: false,
+                    $($field: $val,)*
                 }
             }
         }
+
+        $(
+        impl From<$inner> for $outer {
+            fn from(file: $inner) -> Self {
+                Self::from_inner(<$enum_inner>::from(file))
+            }
+        }
         )*
     };
 }
@@ -43,6 +65,11 @@ macro_rules! impl_drop {
     ($name: ident, $inner: ident, $empty: ident) => {
         impl Drop for $name {
             fn drop(&mut self) {
+                if self.flush_on_drop && !self.remove_on_drop && !self.deleted {
+                    // do flush and ignore the result
+                    let _ = self.inner.flush();
+                }
+
                 if self.remove_on_drop && !self.deleted {
                     let empty = <$inner>::Empty(<$empty>::default());
                     // swap the inner to empty
@@ -265,6 +292,328 @@ macro_rules! impl_constructor_for_memory_mmap_file_mut {
     };
 }
 
+/// The kind of the storage backing a mmap file, returned by the `kind` method of
+/// `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum MapKind {
+    /// mmap to a real file
+    Disk,
+    /// an in-memory buffer mocking a mmap
+    Memory,
+    /// a private anonymous mmap, which is not backed by a file
+    Anonymous,
+    /// a null mmap, e.g. after the mmap file is closed or removed
+    Empty,
+}
+
+impl MapKind {
+    /// Returns whether the mmap is backed by a real file.
+    #[inline]
+    pub const fn is_disk(&self) -> bool {
+        matches!(self, MapKind::Disk)
+    }
+
+    /// Returns whether the mmap is an in-memory buffer.
+    #[inline]
+    pub const fn is_memory(&self) -> bool {
+        matches!(self, MapKind::Memory)
+    }
+
+    /// Returns whether the mmap is a private anonymous mmap.
+    #[inline]
+    pub const fn is_anonymous(&self) -> bool {
+        matches!(self, MapKind::Anonymous)
+    }
+
+    /// Returns whether the mmap is empty.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        matches!(self, MapKind::Empty)
+    }
+}
+
+/// An iterator over the page-sized sub-ranges covering a mmap, the last range is clamped
+/// to the length of the mmap.
+///
+/// This struct is created by the `page_ranges` method of `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug, Clone)]
+pub struct PageRanges {
+    start: usize,
+    len: usize,
+    page_size: usize,
+}
+
+impl PageRanges {
+    pub(crate) fn new(len: usize, page_size: usize) -> Self {
+        Self {
+            start: 0,
+            len,
+            page_size,
+        }
+    }
+}
+
+impl Iterator for PageRanges {
+    type Item = std::ops::Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start >= self.len {
+            return None;
+        }
+        let start = self.start;
+        let end = start.saturating_add(self.page_size).min(self.len);
+        self.start = end;
+        Some(start..end)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len.saturating_sub(self.start);
+        let n = remaining / self.page_size + usize::from(remaining % self.page_size != 0);
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for PageRanges {}
+
+impl std::iter::FusedIterator for PageRanges {}
+
+/// The encoding of the length header of a [`LenPrefixed`] record.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum LengthPrefix {
+    U32Be,
+    U32Le,
+    Varint,
+}
+
+/// An iterator over the length-prefixed records (`[len][payload: len bytes]`) of a mmap,
+/// which yields the borrowed payloads.
+///
+/// The iteration stops at the end of the mmap. A truncated last record (its header or its
+/// payload goes past the end of the mmap) yields `Err(Error::from(ErrorKind::EOF))`, and a
+/// malformed varint header yields `Err(Error::from(ErrorKind::BadFormat))`, then the
+/// iteration stops.
+///
+/// This struct is created by the `iter_len_prefixed`, `iter_len_prefixed_le` and
+/// `iter_len_prefixed_varint` methods of `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug, Clone)]
+pub struct LenPrefixed<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    prefix: LengthPrefix,
+    done: bool,
+}
+
+impl<'a> LenPrefixed<'a> {
+    pub(crate) fn u32_be(buf: &'a [u8], from: usize) -> Self {
+        Self::new(buf, from, LengthPrefix::U32Be)
+    }
+
+    pub(crate) fn u32_le(buf: &'a [u8], from: usize) -> Self {
+        Self::new(buf, from, LengthPrefix::U32Le)
+    }
+
+    pub(crate) fn varint(buf: &'a [u8], from: usize) -> Self {
+        Self::new(buf, from, LengthPrefix::Varint)
+    }
+
+    fn new(buf: &'a [u8], from: usize, prefix: LengthPrefix) -> Self {
+        Self {
+            buf,
+            pos: from,
+            prefix,
+            done: false,
+        }
+    }
+
+    /// Returns the offset (related to the mmap) of the next record.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads the length header at the current position, returns the length and the header size.
+    fn header(&self) -> crate::error::Result<(u64, usize)> {
+        use crate::error::{Error, ErrorKind};
+
+        let rest = self.buf.get(self.pos..).ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        match self.prefix {
+            LengthPrefix::U32Be => rest
+                .get(..4)
+                .map(|b| (u32::from_be_bytes(b.try_into().unwrap()) as u64, 4))
+                .ok_or_else(|| Error::from(ErrorKind::EOF)),
+            LengthPrefix::U32Le => rest
+                .get(..4)
+                .map(|b| (u32::from_le_bytes(b.try_into().unwrap()) as u64, 4))
+                .ok_or_else(|| Error::from(ErrorKind::EOF)),
+            LengthPrefix::Varint => {
+                let mut len = 0u64;
+                for (i, b) in rest.iter().enumerate() {
+                    // a u64 takes at most 10 bytes, and only 1 bit of the last one
+                    if i == 9 && *b > 1 {
+                        return Err(Error::from(ErrorKind::BadFormat));
+                    }
+                    len |= ((b & 0x7F) as u64) << (7 * i);
+                    if b & 0x80 == 0 {
+                        return Ok((len, i + 1));
+                    }
+                }
+                Err(Error::from(ErrorKind::EOF))
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for LenPrefixed<'a> {
+    type Item = crate::error::Result<&'a [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.pos == self.buf.len() {
+            return None;
+        }
+
+        let res = self.header().and_then(|(len, header)| {
+            let start = self.pos + header;
+            usize::try_from(len)
+                .ok()
+                .and_then(|len| start.checked_add(len))
+                .filter(|end| *end <= self.buf.len())
+                .map(|end| (start, end))
+                .ok_or_else(|| crate::error::Error::from(crate::error::ErrorKind::EOF))
+        });
+        match res {
+            Ok((start, end)) => {
+                self.pos = end;
+                Some(Ok(&self.buf[start..end]))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for LenPrefixed<'_> {}
+
+/// Splits the first line off `buf`, returns the line without its `\n` (or `\r\n`) terminator
+/// and the bytes after the terminator. `None` if `buf` is empty.
+pub(crate) fn split_first_line(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    if buf.is_empty() {
+        return None;
+    }
+
+    let (line, rest) = match memchr::memchr(b'\n', buf) {
+        Some(pos) => (&buf[..pos], &buf[pos + 1..]),
+        None => (buf, &buf[buf.len()..]),
+    };
+    Some((line.strip_suffix(b"\r").unwrap_or(line), rest))
+}
+
+/// An iterator over the lines of a mmap, which yields the borrowed lines.
+///
+/// The lines are split on `\n`, and a trailing `\r` is stripped from each line, so LF and
+/// CRLF files give the same lines. The last line is yielded even if it has no terminator,
+/// and a terminator at the end of the mmap does not give an empty last line.
+///
+/// This struct is created by the `lines` method of `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug, Clone)]
+pub struct Lines<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { rest: buf }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, rest) = split_first_line(self.rest)?;
+        self.rest = rest;
+        Some(line)
+    }
+}
+
+impl std::iter::FusedIterator for Lines<'_> {}
+
+/// An iterator over the offsets of the non-overlapping occurrences of a needle in a mmap.
+///
+/// This struct is created by the `find_all` method of `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug)]
+pub struct FindAll<'a, 'n> {
+    iter: Option<memchr::memmem::FindIter<'a, 'n>>,
+    base: usize,
+}
+
+impl<'a, 'n> FindAll<'a, 'n> {
+    pub(crate) fn new(buf: &'a [u8], needle: &'n [u8], from: usize) -> Self {
+        // an empty needle would match at every offset
+        if needle.is_empty() || from >= buf.len() {
+            return Self {
+                iter: None,
+                base: from,
+            };
+        }
+        Self {
+            iter: Some(memchr::memmem::find_iter(&buf[from..], needle)),
+            base: from,
+        }
+    }
+}
+
+impl Iterator for FindAll<'_, '_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.as_mut()?.next().map(|pos| self.base + pos)
+    }
+}
+
+impl std::iter::FusedIterator for FindAll<'_, '_> {}
+
+/// An iterator over the records of a mmap framed by a start magic, each of which spans from one
+/// occurrence of the magic up to the next one, or the end of the mmap.
+///
+/// This struct is created by the `records_by_magic` method of `MmapFileExt` and `AsyncMmapFileExt`.
+#[derive(Debug)]
+pub struct RecordsByMagic<'a, 'n> {
+    buf: &'a [u8],
+    magics: FindAll<'a, 'n>,
+    start: Option<usize>,
+}
+
+impl<'a, 'n> RecordsByMagic<'a, 'n> {
+    pub(crate) fn new(buf: &'a [u8], magic: &'n [u8]) -> Self {
+        let mut magics = FindAll::new(buf, magic, 0);
+        let start = magics.next();
+        Self { buf, magics, start }
+    }
+}
+
+impl<'a> Iterator for RecordsByMagic<'a, '_> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.start?;
+        self.start = self.magics.next();
+        match self.start {
+            Some(end) => Some(&self.buf[start..end]),
+            None => Some(&self.buf[start..]),
+        }
+    }
+}
+
+impl std::iter::FusedIterator for RecordsByMagic<'_, '_> {}
+
+/// The iterator returned by the `lines_str` method of `MmapFileExt` and `AsyncMmapFileExt`,
+/// which validates each line of [`Lines`] as UTF-8.
+pub type LinesStr<'a> =
+    std::iter::Map<Lines<'a>, fn(&[u8]) -> std::result::Result<&str, std::str::Utf8Error>>;
+
 cfg_sync! {
     macro_rules! impl_mmap_file_ext {
         ($name: ident) => {
@@ -289,21 +638,91 @@ cfg_sync! {
                     self.inner.is_exec()
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    self.inner.kind()
+                }
+
+                #[inline]
+                fn is_closed(&self) -> bool {
+                    self.inner.is_closed()
+                }
+
                 #[inline]
                 fn metadata(&self) -> Result<MetaData> {
                     self.inner.metadata()
                 }
 
+                #[inline]
+                fn mapped_page_size(&self) -> usize {
+                    self.inner.mapped_page_size()
+                }
+
+                #[inline]
+                fn copy_all_to_bytes(&self) -> bytes::Bytes {
+                    self.inner.copy_all_to_bytes()
+                }
+
+                #[inline]
+                fn copy_range_to_bytes(&self, offset: usize, len: usize) -> Result<bytes::Bytes> {
+                    self.inner.copy_range_to_bytes(offset, len)
+                }
+
                 impl_file_lock!();
             }
         };
     }
 
     mod sync_impl;
-    pub use sync_impl::{MmapFileExt, MmapFileMutExt, MmapFile, MmapFileMut};
+    pub use sync_impl::{LockGuard, MmapFileExt, MmapFileMutExt, MmapFile, MmapFileMut};
 }
 
 cfg_async! {
+    /// The flags of `sync_file_range` of `AsyncMmapFileMutExt`, which mirror the flags of
+    /// [`sync_file_range(2)`] on Linux.
+    ///
+    /// [`sync_file_range(2)`]: https://man7.org/linux/man-pages/man2/sync_file_range.2.html
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+    pub struct SyncRangeFlags(u32);
+
+    impl SyncRangeFlags {
+        /// `SYNC_FILE_RANGE_WAIT_BEFORE`, waits for the write back of the pages in the range
+        /// which is already started before writing anything.
+        pub const WAIT_BEFORE: SyncRangeFlags = SyncRangeFlags(1);
+        /// `SYNC_FILE_RANGE_WRITE`, starts the write back of the dirty pages in the range
+        /// which are not under write back yet, without waiting for it.
+        pub const WRITE: SyncRangeFlags = SyncRangeFlags(2);
+        /// `SYNC_FILE_RANGE_WAIT_AFTER`, waits for the write back of the pages in the range
+        /// after writing.
+        pub const WAIT_AFTER: SyncRangeFlags = SyncRangeFlags(4);
+
+        /// Returns the raw flags.
+        #[inline]
+        pub const fn bits(&self) -> u32 {
+            self.0
+        }
+
+        /// Returns whether all the flags in `other` are set.
+        #[inline]
+        pub const fn contains(&self, other: SyncRangeFlags) -> bool {
+            self.0 & other.0 == other.0
+        }
+    }
+
+    impl core::ops::BitOr for SyncRangeFlags {
+        type Output = SyncRangeFlags;
+
+        fn bitor(self, rhs: Self) -> Self::Output {
+            SyncRangeFlags(self.0 | rhs.0)
+        }
+    }
+
+    impl core::ops::BitOrAssign for SyncRangeFlags {
+        fn bitor_assign(&mut self, rhs: Self) {
+            self.0 |= rhs.0;
+        }
+    }
+
     macro_rules! impl_async_mmap_file_ext {
         ($name: ident) => {
             #[async_trait]
@@ -328,6 +747,26 @@ cfg_async! {
                     self.inner.is_exec()
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    self.inner.kind()
+                }
+
+                #[inline]
+                fn is_closed(&self) -> bool {
+                    self.inner.is_closed()
+                }
+
+                #[inline]
+                fn copy_all_to_bytes(&self) -> bytes::Bytes {
+                    self.inner.copy_all_to_bytes()
+                }
+
+                #[inline]
+                fn copy_range_to_bytes(&self, offset: usize, len: usize) -> Result<bytes::Bytes> {
+                    self.inner.copy_range_to_bytes(offset, len)
+                }
+
                 #[inline]
                 async fn metadata(&self) -> Result<MetaData> {
                     self.inner.metadata().await
@@ -344,7 +783,11 @@ cfg_async! {
             impl AsyncMmapFileMutExt for AsyncMmapFileMut {
                 #[inline]
                 fn as_mut_slice(&mut self) -> &mut [u8] {
-                    self.inner.as_mut_slice()
+                    // the writes through the raw slice cannot be observed
+                    let slice = self.inner.as_mut_slice();
+                    self.dirty.mark(0..slice.len());
+                    self.generation += 1;
+                    slice
                 }
 
                 #[inline]
@@ -352,11 +795,112 @@ cfg_async! {
                     self.inner.is_cow()
                 }
 
-                impl_flush!();
+                #[inline]
+                fn is_poisoned(&self) -> bool {
+                    self.inner.is_poisoned()
+                }
+
+                #[inline]
+                fn grow_strategy(&self) -> crate::GrowStrategy {
+                    self.grow
+                }
+
+                #[inline]
+                async fn flush_range_committed(&self, offset: usize, len: usize) -> Result<()> {
+                    self.inner.flush_range_committed(offset, len).await?;
+                    self.dirty.clean(offset..offset.saturating_add(len));
+                    Ok(())
+                }
+
+                #[inline]
+                async fn sync_file_range(&self, offset: usize, len: usize, flags: crate::SyncRangeFlags) -> Result<()> {
+                    self.inner.sync_file_range(offset, len, flags).await
+                }
+
+                #[inline]
+                async fn promote_to_shared(&mut self) -> Result<()> {
+                    self.inner.promote_to_shared().await
+                }
+
+                #[cfg(unix)]
+                #[inline]
+                fn set_flush_scheduler(&mut self, scheduler: Option<FlushScheduler>) {
+                    self.inner.set_flush_scheduler(scheduler)
+                }
+
+                #[cfg(unix)]
+                #[inline]
+                async fn flush_scheduled(&self) -> Result<()> {
+                    self.inner.flush_scheduled().await?;
+                    self.dirty.clean_all();
+                    Ok(())
+                }
+
+                fn flush(&self) -> Result<()> {
+                    if !self.flush_policy.is_sync_due(&self.last_sync) {
+                        // the dirty ranges are kept, the data is not durable yet
+                        return self.inner.flush_async();
+                    }
+                    self.inner.flush()?;
+                    self.dirty.clean_all();
+                    Ok(())
+                }
+
+                fn flush_async(&self) -> Result<()> {
+                    self.inner.flush_async()
+                }
+
+                fn flush_range(&self, offset: usize, len: usize) -> Result<()> {
+                    self.inner.flush_range(offset, len)?;
+                    self.dirty.clean(offset..offset.saturating_add(len));
+                    Ok(())
+                }
+
+                fn flush_async_range(&self, offset: usize, len: usize) -> Result<()> {
+                    self.inner.flush_async_range(offset, len)
+                }
 
                 #[inline]
                 async fn truncate(&mut self, max_sz: u64) -> Result<()> {
-                    self.inner.truncate(max_sz).await
+                    self.inner.truncate(max_sz).await?;
+                    self.high_water = self.high_water.min(max_sz as usize);
+                    self.dirty.clamp(max_sz as usize);
+                    self.generation += 1;
+                    Ok(())
+                }
+
+                async fn grow_by(&mut self, additional: u64) -> Result<()> {
+                    let len = crate::MetaDataExt::len(&self.inner.metadata().await?);
+                    self.truncate(crate::utils::grown_len(len, additional)?).await
+                }
+
+                async fn shrink_by(&mut self, bytes: u64) -> Result<()> {
+                    let len = crate::MetaDataExt::len(&self.inner.metadata().await?);
+                    self.truncate(len.saturating_sub(bytes)).await
+                }
+
+                fn truncate_size_for(&self, len: u64) -> Result<u64> {
+                    self.inner.truncate_size_for(len)
+                }
+
+                fn write(&mut self, src: &[u8], offset: usize) -> usize {
+                    let n = self.inner.write(src, offset);
+                    if n > 0 {
+                        self.high_water = self.high_water.max(offset + n);
+                        self.dirty.mark(offset..offset + n);
+                        self.generation += 1;
+                    }
+                    n
+                }
+
+                fn write_all(&mut self, src: &[u8], offset: usize) -> Result<()> {
+                    self.inner.write_all(src, offset)?;
+                    if !src.is_empty() {
+                        self.high_water = self.high_water.max(offset + src.len());
+                        self.dirty.mark(offset..offset + src.len());
+                        self.generation += 1;
+                    }
+                    Ok(())
                 }
 
                 /// Remove the underlying file
@@ -413,7 +957,13 @@ cfg_async! {
                 /// assert_eq!(meta.len(), 50);
                 /// # })
                 /// ```
+                ///
+                /// If `max_sz` is [`AsyncMmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`], the file is truncated to the [high-water mark].
+                ///
+                #[doc = concat!("[`AsyncMmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`]: ", $path_str, "/struct.AsyncMmapFileMut.html#associatedconstant.TRUNCATE_TO_HIGH_WATER_MARK")]
+                #[doc = concat!("[high-water mark]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.high_water_mark")]
                 async fn close_with_truncate(mut self, max_sz: i64) -> Result<()> {
+                    let max_sz = self.resolve_truncate_size(max_sz);
                     let empty = AsyncMmapFileMutInner::Empty(AsyncEmptyMmapFile::default());
                     // swap the inner to empty
                     let inner = mem::replace(&mut self.inner, empty);
@@ -439,9 +989,70 @@ cfg_async! {
                     self.len() == 0
                 }
 
+                /// Returns the length of the mapped region, which is the same as [`len`].
+                ///
+                /// When the mmap is created with an `offset` or a `len` in the options, only a part of the
+                /// file is mapped, so the mapped length can be smaller than the file length reported by
+                /// [`metadata`]. Always use the mapped length as the bound when reading the mmap.
+                ///
+                /// [`len`]: #tymethod.len
+                /// [`metadata`]: #tymethod.metadata
+                fn mapped_len(&self) -> usize {
+                    self.len()
+                }
+
                 /// Returns the underlying slice of the mmap
                 fn as_slice(&self) -> &[u8];
 
+                /// Returns the underlying slice of the mmap, or `None` if the mmap is closed.
+                ///
+                /// Unlike [`as_slice`], which returns an empty slice for a closed mmap, this method
+                /// lets the caller distinguish a genuinely empty file from an invalidated mmap.
+                /// See [`is_closed`] for the states which produce `None`.
+                ///
+                /// [`as_slice`]: #tymethod.as_slice
+                /// [`is_closed`]: #method.is_closed
+                fn as_slice_checked(&self) -> Option<&[u8]> {
+                    if self.is_closed() {
+                        None
+                    } else {
+                        Some(self.as_slice())
+                    }
+                }
+
+                /// Returns whether the mmap is closed.
+                ///
+                /// A mmap is closed when its inner has been swapped to the empty placeholder, which
+                /// happens after [`AsyncMmapFileMut::close`], [`AsyncMmapFileMut::close_into_file`] or
+                /// [`AsyncMmapFileMut::remove`], including when those methods return an error part way through.
+                ///
+                /// [`AsyncMmapFileMut::close`]: struct.AsyncMmapFileMut.html#method.close
+                /// [`AsyncMmapFileMut::close_into_file`]: struct.AsyncMmapFileMut.html#method.close_into_file
+                /// [`AsyncMmapFileMut::remove`]: struct.AsyncMmapFileMut.html#method.remove
+                fn is_closed(&self) -> bool {
+                    false
+                }
+
+                /// Returns the page size of the system, which is the granularity of [`page_ranges`].
+                ///
+                /// [`page_ranges`]: #method.page_ranges
+                #[inline]
+                fn page_size(&self) -> usize {
+                    crate::utils::page_size()
+                }
+
+                /// Returns an iterator over the page-sized sub-ranges covering the mmap,
+                /// i.e. `[0, PAGE)`, `[PAGE, 2 * PAGE)`, ..., the last range is clamped to [`len`].
+                ///
+                /// The ranges are relative to the start of the mmap, which is page aligned unless
+                /// the mmap was created with an offset which is not a multiple of the page size.
+                ///
+                /// [`len`]: #tymethod.len
+                #[inline]
+                fn page_ranges(&self) -> crate::PageRanges {
+                    crate::PageRanges::new(self.len(), self.page_size())
+                }
+
                 /// slice returns data starting from offset off of size sz.
                 ///
                 /// # Panics
@@ -451,6 +1062,149 @@ cfg_async! {
                     &self.as_slice()[offset..offset+sz]
                 }
 
+                /// Returns the first `n` bytes of the mmap.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the mmap is shorter than `n`.
+                fn prefix(&self, n: usize) -> Result<&[u8]> {
+                    self.as_slice()
+                        .get(..n)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))
+                }
+
+                /// Returns the last `n` bytes of the mmap.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the mmap is shorter than `n`.
+                fn suffix(&self, n: usize) -> Result<&[u8]> {
+                    let buf = self.as_slice();
+                    buf.len()
+                        .checked_sub(n)
+                        .map(|start| &buf[start..])
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))
+                }
+
+                /// Verifies the mmap starts with `expected` (e.g. the magic number of a format),
+                /// and returns the remaining bytes after it.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::BadFormat))` if the mmap does not start with `expected`.
+                fn strip_prefix(&self, expected: &[u8]) -> Result<&[u8]> {
+                    self.as_slice()
+                        .strip_prefix(expected)
+                        .ok_or_else(|| Error::from(ErrorKind::BadFormat))
+                }
+
+                /// Detects the format of the file by the magic number at its start (e.g. ELF, PNG, ZIP),
+                /// see [`KnownMagic`] for the known formats. Returns `None` if the format is unknown.
+                ///
+                /// [`KnownMagic`]: crate::KnownMagic
+                fn sniff(&self) -> Option<crate::KnownMagic> {
+                    crate::KnownMagic::detect(self.as_slice())
+                }
+
+                /// Returns an iterator over the length-prefixed records from `from`, each record is a big-endian
+                /// `u32` length followed by the payload, see [`LenPrefixed`].
+                ///
+                /// [`LenPrefixed`]: crate::LenPrefixed
+                fn iter_len_prefixed(&self, from: usize) -> crate::LenPrefixed<'_> {
+                    crate::LenPrefixed::u32_be(self.as_slice(), from)
+                }
+
+                /// The same as [`iter_len_prefixed`], but the lengths are little-endian `u32`.
+                ///
+                /// [`iter_len_prefixed`]: #method.iter_len_prefixed
+                fn iter_len_prefixed_le(&self, from: usize) -> crate::LenPrefixed<'_> {
+                    crate::LenPrefixed::u32_le(self.as_slice(), from)
+                }
+
+                /// The same as [`iter_len_prefixed`], but the lengths are unsigned LEB128 varints.
+                ///
+                /// [`iter_len_prefixed`]: #method.iter_len_prefixed
+                fn iter_len_prefixed_varint(&self, from: usize) -> crate::LenPrefixed<'_> {
+                    crate::LenPrefixed::varint(self.as_slice(), from)
+                }
+
+                /// Loads the length published by [`atomic_append`] in the 8-byte header at `len_header_offset`,
+                /// with the `Acquire` ordering, the published records are the bytes right after the header
+                /// up to that length.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the header is out of the bound of the mmap,
+                /// and `Err(Error::from(ErrorKind::Misaligned))` if the header is not 8-byte aligned.
+                ///
+                /// [`atomic_append`]: trait.MmapFileMutExt.html#method.atomic_append
+                #[cfg(target_has_atomic = "64")]
+                fn published_len(&self, len_header_offset: usize) -> Result<u64> {
+                    crate::utils::load_acquire_u64_at(self.as_slice(), len_header_offset)
+                }
+
+                /// Returns an `xxd`-style dump of the bytes in `range`: 16 bytes per line, the offset in
+                /// the mmap, the bytes in hex and the printable ASCII bytes. This is a debug aid, e.g. to
+                /// inspect a file format under development, the whole range is formatted into one `String`.
+                ///
+                /// The range is clamped to the length of the mmap instead of panicking, an empty or out of
+                /// bounds range gives an empty dump.
+                fn hexdump_range(&self, range: std::ops::Range<usize>) -> String {
+                    let len = self.len();
+                    let end = range.end.min(len);
+                    let start = range.start.min(end);
+                    crate::utils::hexdump(&self.as_slice()[start..end], start)
+                }
+
+                /// Returns an `xxd`-style dump of the mmap, see [`hexdump_range`].
+                ///
+                /// At most the first 4 KiB are dumped, the number of the bytes left out is reported on
+                /// the last line, use [`hexdump_range`] to dump more.
+                ///
+                /// [`hexdump_range`]: #method.hexdump_range
+                fn hexdump(&self) -> String {
+                    let cap = crate::utils::HEXDUMP_DEFAULT_CAP;
+                    let mut out = self.hexdump_range(0..cap);
+                    if self.len() > cap {
+                        out.push_str(&format!("... {} more bytes\n", self.len() - cap));
+                    }
+                    out
+                }
+
+                /// Returns the first line of the mmap without its `\n` (or `\r\n`) terminator, and the bytes
+                /// after the terminator. Returns `None` if the mmap is empty.
+                fn split_first_line(&self) -> Option<(&[u8], &[u8])> {
+                    crate::mmap_file::split_first_line(self.as_slice())
+                }
+
+                /// Returns a zero-copy iterator over the lines of the mmap, see [`Lines`].
+                ///
+                /// A trailing `\r` is stripped from each line, so CRLF and LF text behave the same.
+                ///
+                /// [`Lines`]: crate::Lines
+                fn lines(&self) -> crate::Lines<'_> {
+                    crate::Lines::new(self.as_slice())
+                }
+
+                /// Returns an iterator over the lines of the mmap as `&str`, each line is validated
+                /// as UTF-8 on its own, see [`lines`].
+                ///
+                /// [`lines`]: #method.lines
+                fn lines_str(&self) -> crate::LinesStr<'_> {
+                    let from_utf8: fn(&[u8]) -> std::result::Result<&str, std::str::Utf8Error> = std::str::from_utf8;
+                    self.lines().map(from_utf8)
+                }
+
+                /// Faults in the pages of `range` so the following reads do not block on the disk, e.g. before
+                /// a latency-sensitive scan. Returns an accumulator of the bytes touched, which only exists to
+                /// keep the touches from being optimized away.
+                ///
+                /// One byte of each page is read with a volatile read, which works on all platforms (including
+                /// macOS and Windows, which lack a good prefetch system call). On Linux, `madvise(MADV_WILLNEED)`
+                /// is issued first, so the pages are read ahead in batches. The range is clamped to the length
+                /// of the mmap.
+                fn warm(&self, range: std::ops::Range<usize>) -> u64 {
+                    let end = range.end.min(self.len());
+                    let start = range.start.min(end);
+                    crate::utils::warm(&self.as_slice()[start..end])
+                }
+
                 /// bytes returns data starting from offset off of size sz.
                 ///
                 /// # Errors
@@ -465,6 +1219,61 @@ cfg_async! {
                     }
                 }
 
+                /// Returns the data starting from offset of size sz, borrowed if it is contiguous in the
+                /// backing storage, otherwise copied.
+                ///
+                /// A single mmap is always contiguous, so it always returns `Cow::Borrowed`, the same as
+                /// [`bytes`]. It is for the code generic over both single mmaps and segmented backings,
+                /// which have to copy a range crossing the boundary of the segments.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if there's not enough data.
+                ///
+                /// [`bytes`]: #method.bytes
+                #[inline]
+                fn get(&self, offset: usize, sz: usize) -> Result<std::borrow::Cow<'_, [u8]>> {
+                    self.bytes(offset, sz).map(std::borrow::Cow::Borrowed)
+                }
+
+                /// Returns a reference to the header of type `H` stored in the first `size_of::<H>()` bytes of the mmap.
+                ///
+                /// # Errors
+                /// If the mmap is shorter than `H`, it would return `Err(Error::from(ErrorKind::EOF))`.
+                /// If the mmap is not properly aligned for `H`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+                #[cfg(feature = "zerocopy")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+                fn header<H>(&self) -> Result<&H>
+                where
+                    H: zerocopy::FromBytes + zerocopy::KnownLayout + zerocopy::Immutable,
+                {
+                    let buf = self.as_slice();
+                    if buf.len() < std::mem::size_of::<H>() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    H::ref_from_prefix(buf)
+                        .map(|(h, _)| h)
+                        .map_err(|_| Error::from(ErrorKind::Misaligned))
+                }
+
+                /// Returns the whole mmap as a slice of `T`, e.g. an array of fixed-size records.
+                ///
+                /// # Errors
+                /// If the length of the mmap is not a multiple of `size_of::<T>()`, it would return `Err(Error::from(ErrorKind::EOF))`.
+                /// If the mmap is not properly aligned for `T`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+                #[cfg(feature = "zerocopy")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+                fn as_slice_of<T>(&self) -> Result<&[T]>
+                where
+                    T: zerocopy::FromBytes + zerocopy::Immutable,
+                {
+                    let buf = self.as_slice();
+                    let size = std::mem::size_of::<T>();
+                    if size != 0 && buf.len() % size != 0 {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    <[T] as zerocopy::FromBytes>::ref_from_bytes(buf).map_err(|_| Error::from(ErrorKind::Misaligned))
+                }
+
                 /// Returns the path of the inner file.
                 fn path(&self) -> &Path;
 
@@ -486,24 +1295,601 @@ cfg_async! {
                 /// Whether the mmap is executable
                 fn is_exec(&self) -> bool;
 
+                /// Returns the kind of the storage backing the mmap, e.g. to skip durability
+                /// assertions for in-memory mmaps, whose flushes do nothing.
+                fn kind(&self) -> crate::MapKind;
+
+                /// Returns whether the mmap is backed by a real file.
+                #[inline]
+                fn is_disk(&self) -> bool {
+                    self.kind().is_disk()
+                }
+
+                /// Returns whether the mmap is an in-memory buffer.
+                #[inline]
+                fn is_memory(&self) -> bool {
+                    self.kind().is_memory()
+                }
+
                 /// Returns the metadata of file metadata
                 ///
                 /// Metadata information about a file.
                 /// This structure is returned from the metadata or
                 /// symlink_metadata function or method and represents
                 /// known metadata about a file such as its permissions, size, modification times, etc
+                ///
+                /// # Notes
+                /// The length in the metadata is the length of the whole underlying file, which can be larger than
+                /// the mapped region, see [`mapped_len`].
+                ///
+                /// [`mapped_len`]: #method.mapped_len
                 async fn metadata(&self) -> Result<MetaData>;
 
+                /// Wait until the underlying file is modified, e.g. written or resized.
+                ///
+                /// The mmap itself is not updated, reopen the file to observe the new data.
+                ///
+                /// The watch is registered with the recommended backend of the platform
+                /// (inotify on Linux, FSEvents on macOS, kqueue on BSDs and ReadDirectoryChangesW on Windows),
+                /// and is removed when the returned future completes or is dropped.
+                ///
+                /// # Errors
+                /// Returns an error if the watch cannot be registered, e.g. the mmap is in-memory
+                /// or the underlying file has been removed.
+                #[cfg(feature = "notify")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "notify")))]
+                async fn wait_for_change(&self) -> Result<()> {
+                    crate::watch::wait_for_change(self.path()).await
+                }
+
+                /// Splits the mmap into `n` contiguous, disjoint shards, e.g. to process them in parallel
+                /// by threads or rayon, since the shards are shared borrows.
+                ///
+                /// The boundaries between the shards are rounded down to multiples of [`page_size`], so the
+                /// shards are near-equal and no page is shared by two shards, the last shard absorbs the remainder.
+                /// When the mmap is shorter than `n` pages, some shards are empty.
+                ///
+                /// # Panics
+                /// Panics if `n` is 0.
+                ///
+                /// [`page_size`]: #method.page_size
+                fn shards(&self, n: usize) -> Vec<&[u8]> {
+                    assert!(n > 0, "the number of shards must be greater than 0");
+                    let buf = self.as_slice();
+                    let page = self.page_size();
+                    let mut shards = Vec::with_capacity(n);
+                    let mut start = 0;
+                    for i in 1..n {
+                        let end = (buf.len() as u128 * i as u128 / n as u128) as usize;
+                        let end = (end / page * page).max(start);
+                        shards.push(&buf[start..end]);
+                        start = end;
+                    }
+                    shards.push(&buf[start..]);
+                    shards
+                }
+
                 /// Copy the content of the mmap file to Vec
                 #[inline]
                 fn copy_all_to_vec(&self) -> Vec<u8> {
                     self.as_slice().to_vec()
                 }
 
-                /// Copy a range of content of the mmap file to Vec
-                #[inline]
-                fn copy_range_to_vec(&self, offset: usize, len: usize) -> Vec<u8> {
-                    self.slice(offset, len).to_vec()
+                /// Copy a range of content of the mmap file to Vec
+                #[inline]
+                fn copy_range_to_vec(&self, offset: usize, len: usize) -> Vec<u8> {
+                    self.slice(offset, len).to_vec()
+                }
+
+                /// Copy the content of the mmap file to [`Bytes`].
+                ///
+                /// An in-memory mmap file shares its underlying [`Bytes`] without copying,
+                /// otherwise the content is copied into a new [`Bytes`] in one allocation.
+                ///
+                /// [`Bytes`]: https://docs.rs/bytes/1.1.0/bytes/struct.Bytes.html
+                #[inline]
+                fn copy_all_to_bytes(&self) -> bytes::Bytes {
+                    bytes::Bytes::copy_from_slice(self.as_slice())
+                }
+
+                /// Copy a range of content of the mmap file to [`Bytes`].
+                ///
+                /// An in-memory mmap file shares the range of its underlying [`Bytes`] without copying,
+                /// otherwise the range is copied into a new [`Bytes`] in one allocation.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                ///
+                /// [`Bytes`]: https://docs.rs/bytes/1.1.0/bytes/struct.Bytes.html
+                #[inline]
+                fn copy_range_to_bytes(&self, offset: usize, len: usize) -> Result<bytes::Bytes> {
+                    let buf = self.as_slice();
+                    offset
+                        .checked_add(len)
+                        .and_then(|end| buf.get(offset..end))
+                        .map(bytes::Bytes::copy_from_slice)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))
+                }
+
+                /// Copies a range of content of the mmap file into an [`Arc<[u8]>`] in one allocation,
+                /// which can be handed to many owners without copying again.
+                ///
+                /// The range is always copied, even for an in-memory mmap file, because an [`Arc<[u8]>`]
+                /// cannot share the buffer of [`Bytes`]. Use [`copy_range_to_bytes`] to avoid the copy there.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                ///
+                /// [`Arc<[u8]>`]: std::sync::Arc
+                /// [`Bytes`]: https://docs.rs/bytes/1.1.0/bytes/struct.Bytes.html
+                /// [`copy_range_to_bytes`]: #method.copy_range_to_bytes
+                fn copy_range_to_arc(&self, offset: usize, len: usize) -> Result<std::sync::Arc<[u8]>> {
+                    let buf = self.as_slice();
+                    offset
+                        .checked_add(len)
+                        .and_then(|end| buf.get(offset..end))
+                        .map(std::sync::Arc::from)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))
+                }
+
+                /// Folds `f` over the successive `chunk`-sized chunks of the mmap (the last chunk may be shorter),
+                /// starting from `init`.
+                ///
+                /// # Panics
+                /// Panics if `chunk` is 0.
+                ///
+                /// # Examples
+                ///
+                /// ```ignore
+                /// let zeros = file.fold_chunks(4096, 0usize, |acc, chunk| acc + chunk.iter().filter(|b| **b == 0).count());
+                /// ```
+                fn fold_chunks<T, F>(&self, chunk: usize, init: T, f: F) -> T
+                where
+                    F: Fn(T, &[u8]) -> T,
+                {
+                    self.as_slice().chunks(chunk).fold(init, f)
+                }
+
+                /// The parallel version of [`fold_chunks`], the chunks are folded on the rayon thread pool,
+                /// and the partial results are merged by `combine`.
+                ///
+                /// `init` is cloned as the starting value of every partial fold, so it must be
+                /// the identity of `combine` (e.g. 0 for a sum), otherwise the result depends on how the
+                /// chunks are split between threads.
+                ///
+                /// # Panics
+                /// Panics if `chunk` is 0.
+                ///
+                /// [`fold_chunks`]: #method.fold_chunks
+                #[cfg(feature = "rayon")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+                fn fold_chunks_par<T, F, C>(&self, chunk: usize, init: T, f: F, combine: C) -> T
+                where
+                    T: Clone + Send + Sync,
+                    F: Fn(T, &[u8]) -> T + Send + Sync,
+                    C: Fn(T, T) -> T + Send + Sync,
+                {
+                    use rayon::prelude::*;
+
+                    self.as_slice()
+                        .par_chunks(chunk)
+                        .fold(|| init.clone(), &f)
+                        .reduce(|| init.clone(), &combine)
+                }
+
+                /// Returns the CRC32 (IEEE) checksum of the whole mmap.
+                ///
+                /// When the `rayon` feature is enabled, the mmap is split into chunks which are
+                /// hashed in parallel and combined, otherwise the mmap is hashed on the current thread.
+                /// The result is the same in both cases.
+                #[cfg(feature = "crc32")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+                #[inline]
+                fn crc32_parallel(&self) -> u32 {
+                    crate::checksum::crc32(self.as_slice())
+                }
+
+                /// Returns the chunked XXH3 (64 bit) digest of the whole mmap.
+                ///
+                /// The mmap is split into fixed-size chunks (hashed in parallel when the `rayon`
+                /// feature is enabled), and the chunk digests are folded into the final digest.
+                /// For mmaps larger than one chunk, the result is therefore not the plain XXH3 of the content,
+                /// but it does not depend on whether the `rayon` feature is enabled.
+                #[cfg(feature = "xxh3")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "xxh3")))]
+                #[inline]
+                fn xxh3_parallel(&self) -> u64 {
+                    crate::checksum::xxh3(self.as_slice())
+                }
+
+                /// Returns the Adler-32 checksum (as used by zlib) of the whole mmap.
+                #[inline]
+                fn adler32(&self) -> u32 {
+                    crate::checksum::adler32(self.as_slice())
+                }
+
+                /// Returns the Adler-32 checksum (as used by zlib) of the range, e.g. to verify
+                /// the trailer of a zlib stream stored in the mmap.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                fn adler32_range(&self, range: std::ops::Range<usize>) -> Result<u32> {
+                    let buf = self.as_slice();
+                    if range.start > range.end || range.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    Ok(crate::checksum::adler32(&buf[range]))
+                }
+
+                /// Returns the digest of the whole mmap, computed by any [`Digest`] implementation
+                /// (e.g. `sha2::Sha256`).
+                ///
+                /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+                #[cfg(feature = "digest")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+                #[inline]
+                fn digest<D: digest::Digest>(&self) -> digest::Output<D> {
+                    D::digest(self.as_slice())
+                }
+
+                /// Returns the digest of the range of the mmap, computed by any [`Digest`] implementation.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                ///
+                /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+                #[cfg(feature = "digest")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+                fn digest_range<D: digest::Digest>(&self, range: std::ops::Range<usize>) -> Result<digest::Output<D>> {
+                    self.as_slice()
+                        .get(range)
+                        .map(D::digest)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))
+                }
+
+                /// Returns the Merkle root over the `block_size` blocks of the mmap, computed by any
+                /// [`Digest`] implementation.
+                ///
+                /// Each leaf is the digest of a block (the last block may be shorter), and each parent is the
+                /// digest of the concatenation of its two children. The last node of a level with an odd number
+                /// of nodes is promoted to the level above as is. An empty mmap has a single empty block.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::BadFormat))` if `block_size` is zero.
+                ///
+                /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+                #[cfg(feature = "digest")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+                fn merkle_root<D: digest::Digest>(&self, block_size: usize) -> Result<digest::Output<D>> {
+                    if block_size == 0 {
+                        return Err(Error::new_with_message(ErrorKind::BadFormat, "block size must not be zero"));
+                    }
+                    Ok(crate::checksum::merkle_root::<D>(self.as_slice(), block_size))
+                }
+
+                /// Returns the Merkle proof of the block at `block_index`, i.e. the digests of its siblings
+                /// from the leaves up to (excluding) the root, as defined in [`merkle_root`].
+                ///
+                /// A promoted node has no sibling at its level, so the proof can be shorter than the height
+                /// of the tree; the verifier derives the promoted levels from the block index and the number of blocks.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::BadFormat))` if `block_size` is zero,
+                /// and `Err(Error::from(ErrorKind::EOF))` if the block is out of the bound of the mmap.
+                ///
+                /// [`merkle_root`]: #method.merkle_root
+                #[cfg(feature = "digest")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+                fn merkle_proof<D: digest::Digest>(&self, block_index: usize, block_size: usize) -> Result<Vec<digest::Output<D>>> {
+                    if block_size == 0 {
+                        return Err(Error::new_with_message(ErrorKind::BadFormat, "block size must not be zero"));
+                    }
+                    crate::checksum::merkle_proof::<D>(self.as_slice(), block_size, block_index)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))
+                }
+
+                /// Returns the BLAKE3 hash of the whole mmap.
+                ///
+                /// When the `rayon` feature is enabled, the mmap is hashed in parallel by BLAKE3's own
+                /// multithreaded implementation. The result is the same in both cases.
+                #[cfg(feature = "blake3")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+                fn blake3(&self) -> blake3::Hash {
+                    let mut hasher = blake3::Hasher::new();
+                    #[cfg(feature = "rayon")]
+                    hasher.update_rayon(self.as_slice());
+                    #[cfg(not(feature = "rayon"))]
+                    hasher.update(self.as_slice());
+                    hasher.finalize()
+                }
+
+                /// Returns the rsync-style checksums of the `block_size` blocks of the mmap (the last block may be
+                /// shorter): the weak rolling checksum (see [`RollingChecksum`]) and the strong checksum
+                /// (the first 16 bytes of the BLAKE3 hash) of each block.
+                ///
+                /// # Panics
+                /// Panics if `block_size` is 0.
+                ///
+                /// [`RollingChecksum`]: crate::RollingChecksum
+                #[cfg(feature = "blake3")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+                fn rolling_checksums(&self, block_size: usize) -> crate::BlockChecksums<'_> {
+                    crate::BlockChecksums::new(self.as_slice(), block_size)
+                }
+
+                /// Returns the resumable CRC32 (IEEE) state of the whole mmap.
+                ///
+                /// The state can be fed with the bytes appended later (see [`ChecksumState::update_from_range`]),
+                /// so the checksum of an append-only file is maintained without rehashing the whole mmap.
+                ///
+                /// [`ChecksumState::update_from_range`]: struct.ChecksumState.html#method.update_from_range
+                #[cfg(feature = "crc32")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+                #[inline]
+                fn checksum_incremental(&self) -> crate::ChecksumState {
+                    let buf = self.as_slice();
+                    crate::ChecksumState::with_initial(crate::checksum::crc32(buf), buf.len() as u64)
+                }
+
+                /// Verifies the trailing crc32 of the mmap.
+                ///
+                /// The last 4 bytes are read as a big-endian crc32 (IEEE) of the preceding bytes,
+                /// returns whether it matches the computed one.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the mmap is shorter than 4 bytes.
+                #[cfg(feature = "crc32")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+                fn verify_trailing_crc32(&self) -> Result<bool> {
+                    let buf = self.as_slice();
+                    let payload_len = buf
+                        .len()
+                        .checked_sub(4)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    let (payload, footer) = buf.split_at(payload_len);
+                    let expected = u32::from_be_bytes(footer.try_into().unwrap());
+                    Ok(crate::checksum::crc32(payload) == expected)
+                }
+
+                /// Same as [`verify_trailing_crc32`], but returns
+                /// `Err(Error::from(ErrorKind::ChecksumMismatch))` if the crc32 does not match.
+                ///
+                /// [`verify_trailing_crc32`]: #method.verify_trailing_crc32
+                #[cfg(feature = "crc32")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+                fn assert_trailing_crc32(&self) -> Result<()> {
+                    if self.verify_trailing_crc32()? {
+                        Ok(())
+                    } else {
+                        Err(Error::from(ErrorKind::ChecksumMismatch))
+                    }
+                }
+
+                /// Reads the record of `payload_len` bytes at `offset`, which is followed by the big-endian
+                /// crc32 (IEEE) of the payload, and returns the payload only if the crc32 matches.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the payload and the crc32 are out of the bound
+                /// of the mmap, and `Err(Error::from(ErrorKind::ChecksumMismatch))` if the crc32 does not match,
+                /// e.g. the record is torn.
+                #[cfg(feature = "crc32")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+                fn bytes_verified_crc32(&self, offset: usize, payload_len: usize) -> Result<&[u8]> {
+                    let buf = self.as_slice();
+                    let end = offset
+                        .checked_add(payload_len)
+                        .filter(|end| matches!(end.checked_add(4), Some(n) if n <= buf.len()))
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    let payload = &buf[offset..end];
+                    let expected = u32::from_be_bytes(buf[end..end + 4].try_into().unwrap());
+                    if crate::checksum::crc32(payload) == expected {
+                        Ok(payload)
+                    } else {
+                        Err(Error::from(ErrorKind::ChecksumMismatch))
+                    }
+                }
+
+                /// Returns an iterator over the offsets of all occurrences of `needle` in the mmap.
+                ///
+                /// The offsets are yielded lazily in ascending order, which makes this method suitable
+                /// for building indexes (e.g. the offsets of all newlines) without allocating.
+                #[inline]
+                fn byte_positions(&self, needle: u8) -> memchr::Memchr<'_> {
+                    memchr::memchr_iter(needle, self.as_slice())
+                }
+
+                /// Returns an iterator over the offsets of all the non-overlapping occurrences of `needle`
+                /// in the mmap, starting the search at `from`.
+                ///
+                /// The offsets are absolute, and yielded lazily in ascending order, e.g. to rescan a corrupted
+                /// log for the record magic. The search uses the two-way algorithm of `memchr::memmem`.
+                /// An empty `needle`, or `from` out of the bound of the mmap, gives an empty iterator.
+                #[inline]
+                fn find_all<'n>(&self, needle: &'n [u8], from: usize) -> crate::FindAll<'_, 'n> {
+                    crate::FindAll::new(self.as_slice(), needle, from)
+                }
+
+                /// Returns an iterator over the records framed by `magic`, each of which is the slice from
+                /// one occurrence of `magic` (included) up to the next one, or the end of the mmap.
+                ///
+                /// Unlike walking the length prefixes, it does not trust any length field, so it is the tool
+                /// to salvage the records of a log whose headers are corrupted. The bytes before the first
+                /// `magic` are skipped, and a `magic` which happens to occur inside a payload splits the record,
+                /// so the records should still be validated, e.g. by their checksums. The occurrences are found
+                /// by [`find_all`], an empty `magic` gives an empty iterator.
+                ///
+                /// [`find_all`]: #method.find_all
+                #[inline]
+                fn records_by_magic<'n>(&self, magic: &'n [u8]) -> crate::RecordsByMagic<'_, 'n> {
+                    crate::RecordsByMagic::new(self.as_slice(), magic)
+                }
+
+                /// Returns how many times `needle` occurs in the mmap.
+                #[inline]
+                fn count_byte(&self, needle: u8) -> usize {
+                    memchr::memchr_iter(needle, self.as_slice()).count()
+                }
+
+                /// Returns how many bytes in the range satisfy `pred`, in a single pass over the range.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                fn count_bytes_where<F: Fn(u8) -> bool>(&self, range: std::ops::Range<usize>, pred: F) -> Result<usize> {
+                    let buf = self.as_slice();
+                    if range.start > range.end || range.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    Ok(buf[range].iter().filter(|&&b| pred(b)).count())
+                }
+
+                /// Returns how many bytes in the range are in `set`, a lookup table indexed by the byte value,
+                /// e.g. to count the whitespaces. This is faster than [`count_bytes_where`] with a predicate
+                /// testing several values.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                ///
+                /// [`count_bytes_where`]: #method.count_bytes_where
+                fn count_in_set(&self, range: std::ops::Range<usize>, set: &[bool; 256]) -> Result<usize> {
+                    let buf = self.as_slice();
+                    if range.start > range.end || range.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    Ok(buf[range].iter().map(|&b| set[b as usize] as usize).sum())
+                }
+
+                /// Returns the bytes from `offset` up to (not including) the next `delim`, and the offset
+                /// just past the delimiter, which is where the next scan should start.
+                ///
+                /// If there is no delimiter after `offset`, the bytes up to the end of the mmap are returned,
+                /// and the offset is `None` to flag that the scan hit the end.
+                ///
+                /// # Examples
+                /// ```ignore
+                /// let mut offset = 0;
+                /// while let (field, Some(next)) = file.bytes_until(offset, b',')? {
+                ///     // process the field
+                ///     offset = next;
+                /// }
+                /// ```
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if `offset` is greater than the length of the mmap.
+                fn bytes_until(&self, offset: usize, delim: u8) -> Result<(&[u8], Option<usize>)> {
+                    let buf = self
+                        .as_slice()
+                        .get(offset..)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    Ok(match memchr::memchr(delim, buf) {
+                        Some(pos) => (&buf[..pos], Some(offset + pos + 1)),
+                        None => (buf, None),
+                    })
+                }
+
+                /// Returns the most frequent byte in the range and its count, in one pass over the range,
+                /// e.g. to pick the fill byte of a sparse or run-length encoding. The smallest byte wins a tie,
+                /// the count saturates at `u32::MAX`, and an empty range gives `(0, 0)`.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                fn most_common_byte(&self, range: std::ops::Range<usize>) -> Result<(u8, u32)> {
+                    let buf = self.as_slice();
+                    if range.start > range.end || range.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+
+                    let mut freq = [0u64; 256];
+                    buf[range].iter().for_each(|b| freq[*b as usize] += 1);
+                    let (byte, count) = freq
+                        .iter()
+                        .enumerate()
+                        .fold((0, 0), |max, (b, &n)| if n > max.1 { (b, n) } else { max });
+                    Ok((byte as u8, count.min(u32::MAX as u64) as u32))
+                }
+
+                /// Returns the longest run of identical bytes in the range as `(byte, length, offset)`, the
+                /// offset is related to the mmap. The first of the equally long runs wins, and an empty range
+                /// gives `(0, 0, range.start)`.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                fn longest_run(&self, range: std::ops::Range<usize>) -> Result<(u8, usize, usize)> {
+                    let buf = self.as_slice();
+                    if range.start > range.end || range.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+
+                    let mut longest = (0, 0, range.start);
+                    let mut run_start = range.start;
+                    for pos in range.clone() {
+                        if buf[pos] != buf[run_start] {
+                            run_start = pos;
+                        }
+                        if pos + 1 - run_start > longest.1 {
+                            longest = (buf[run_start], pos + 1 - run_start, run_start);
+                        }
+                    }
+                    Ok(longest)
+                }
+
+                /// Returns the number of leading bytes which are equal in the mmap starting at `self_offset`
+                /// and in `other`, i.e. the length of their longest common prefix.
+                ///
+                /// The result is clamped to both lengths, it is `0` if `self_offset` is out of the bound of the mmap.
+                /// The bytes are compared a word at a time.
+                fn common_prefix_len(&self, self_offset: usize, other: &[u8]) -> usize {
+                    let buf = self.as_slice();
+                    if self_offset >= buf.len() {
+                        return 0;
+                    }
+                    crate::utils::common_prefix_len(&buf[self_offset..], other)
+                }
+
+                /// Returns the sub-slices of the ranges as [`IoSlice`]s, e.g. to write the non-contiguous
+                /// regions of the mmap by `write_vectored` of a file or a socket without copying them.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if any range is out of the bound of the mmap,
+                /// the message of which contains the index of the range.
+                ///
+                /// [`IoSlice`]: std::io::IoSlice
+                fn io_slices(&self, ranges: &[std::ops::Range<usize>]) -> Result<Vec<std::io::IoSlice<'_>>> {
+                    let buf = self.as_slice();
+                    ranges
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, range)| {
+                            if range.start > range.end || range.end > buf.len() {
+                                return Err(Error::new_with_message(
+                                    ErrorKind::EOF,
+                                    format!("range {} ({}..{}) is out of bound", idx, range.start, range.end),
+                                ));
+                            }
+                            Ok(std::io::IoSlice::new(&buf[range.clone()]))
+                        })
+                        .collect()
+                }
+
+                /// Returns whether all the bytes in the range are zero.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                fn is_zero_range(&self, range: std::ops::Range<usize>) -> Result<bool> {
+                    let buf = self.as_slice();
+                    if range.start > range.end || range.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    Ok(crate::utils::first_nonzero(&buf[range]).is_none())
+                }
+
+                /// Returns the offset of the first non-zero byte at or after `from`,
+                /// or `None` if all the remaining bytes are zero.
+                fn first_nonzero(&self, from: usize) -> Option<usize> {
+                    let buf = self.as_slice();
+                    if from >= buf.len() {
+                        return None;
+                    }
+                    crate::utils::first_nonzero(&buf[from..]).map(|pos| from + pos)
                 }
 
                 /// Write the content of the mmap file to a new file.
@@ -612,6 +1998,23 @@ cfg_async! {
                     }
                 }
 
+                /// Reads as many bytes as available to the dst buf from the offset, returns how many bytes are
+                /// read, and whether the dst buf is filled.
+                ///
+                /// Unlike [`read_exact`], a short read still copies the available bytes, e.g. for an
+                /// incremental reader which tolerates a partial final read. An offset beyond the end reads
+                /// nothing.
+                ///
+                /// [`read_exact`]: #method.read_exact
+                fn read_available(&self, dst: &mut [u8], offset: usize) -> (usize, bool) {
+                    let buf = self.as_slice();
+                    let n = buf.len().saturating_sub(offset).min(dst.len());
+                    if n > 0 {
+                        dst[..n].copy_from_slice(&buf[offset..offset + n]);
+                    }
+                    (n, n == dst.len())
+                }
+
                 /// Read the exact number of bytes required to fill buf.
                 fn read_exact(&self, dst: &mut [u8], offset: usize) -> Result<()> {
                     let buf = self.as_slice();
@@ -630,6 +2033,15 @@ cfg_async! {
                     }
                 }
 
+                /// Read exactly `N` bytes from offset into a fixed size array.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if there are less than `N` bytes after offset.
+                fn read_array<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+                    let mut buf = [0; N];
+                    self.read_exact(&mut buf, offset).map(|_| buf)
+                }
+
                 /// Read a signed 8 bit integer from offset.
                 fn read_i8(&self, offset: usize) -> Result<i8> {
                     let buf = self.as_slice();
@@ -814,6 +2226,18 @@ cfg_async! {
                 /// Whether mmap is copy on write
                 fn is_cow(&self) -> bool;
 
+                /// Whether the mmap is poisoned.
+                ///
+                /// A mmap is poisoned when a [`truncate`] is cancelled (the future is dropped) or fails
+                /// after the old mapping has been released and before the new one is created.
+                /// A poisoned mmap is empty, so the reads and writes return errors instead of touching
+                /// the released mapping. Call [`truncate`] again or reopen the file to use it again.
+                ///
+                /// [`truncate`]: #tymethod.truncate
+                fn is_poisoned(&self) -> bool {
+                    false
+                }
+
                 /// bytes_mut returns mutable data starting from offset off of size sz.
                 ///
                 /// # Errors
@@ -828,6 +2252,46 @@ cfg_async! {
                     }
                 }
 
+                /// Returns a mutable reference to the header of type `H` stored in the first `size_of::<H>()` bytes of the mmap.
+                ///
+                /// # Errors
+                /// If the mmap is shorter than `H`, it would return `Err(Error::from(ErrorKind::EOF))`.
+                /// If the mmap is not properly aligned for `H`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+                #[cfg(feature = "zerocopy")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+                fn header_mut<H>(&mut self) -> Result<&mut H>
+                where
+                    H: zerocopy::FromBytes + zerocopy::IntoBytes + zerocopy::KnownLayout,
+                {
+                    let buf = self.as_mut_slice();
+                    if buf.len() < std::mem::size_of::<H>() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    H::mut_from_prefix(buf)
+                        .map(|(h, _)| h)
+                        .map_err(|_| Error::from(ErrorKind::Misaligned))
+                }
+
+                /// Returns the whole mmap as a mutable slice of `T`, so an array of fixed-size records can be
+                /// modified in place. The read-only counterpart is `as_slice_of`.
+                ///
+                /// # Errors
+                /// If the length of the mmap is not a multiple of `size_of::<T>()`, it would return `Err(Error::from(ErrorKind::EOF))`.
+                /// If the mmap is not properly aligned for `T`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+                #[cfg(feature = "zerocopy")]
+                #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+                fn as_mut_slice_of<T>(&mut self) -> Result<&mut [T]>
+                where
+                    T: zerocopy::FromBytes + zerocopy::IntoBytes,
+                {
+                    let buf = self.as_mut_slice();
+                    let size = std::mem::size_of::<T>();
+                    if size != 0 && buf.len() % size != 0 {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    <[T] as zerocopy::FromBytes>::mut_from_bytes(buf).map_err(|_| Error::from(ErrorKind::Misaligned))
+                }
+
                 /// Fill 0 to the specific range
                 fn zero_range(&mut self, start: usize, end: usize) {
                     let buf = self.as_mut_slice();
@@ -835,6 +2299,25 @@ cfg_async! {
                     buf[start..end].fill(0);
                 }
 
+                /// Fills the range with `pattern` repeated, the last copy is truncated if the length
+                /// of the range is not a multiple of the length of `pattern`.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::BadFormat))` if `pattern` is empty,
+                /// and `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                fn fill_pattern(&mut self, pattern: &[u8], range: std::ops::Range<usize>) -> Result<()> {
+                    if pattern.is_empty() {
+                        return Err(Error::new_with_message(ErrorKind::BadFormat, "pattern must not be empty"));
+                    }
+                    let buf = self
+                        .as_mut_slice()
+                        .get_mut(range)
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    buf.chunks_mut(pattern.len())
+                        .for_each(|chunk| chunk.copy_from_slice(&pattern[..chunk.len()]));
+                    Ok(())
+                }
+
                 /// Flushes outstanding memory map modifications to disk (if the inner is a real file).
                 ///
                 /// When this method returns with a non-error result,
@@ -872,10 +2355,235 @@ cfg_async! {
                 /// other outstanding changes to the memory map may be flushed as well.
                 fn flush_async_range(&self, offset: usize, len: usize) -> Result<()>;
 
+                /// Flushes outstanding memory map modifications in the range to disk(if the inner is a real file),
+                /// and resolves when the flush is complete.
+                ///
+                /// Unlike [`flush_range`], the blocking `msync` of a disk mmap runs on the blocking thread pool
+                /// of the runtime, so it does not stall the executor thread. On non-unix platforms, this
+                /// is the same as [`flush_range`].
+                ///
+                /// # Notes
+                /// The start of the range is rounded down to the page boundary, so the flushed range
+                /// may be widened to cover whole pages, and other modifications in those pages are
+                /// flushed as well. If the future is dropped before it resolves, the flush still runs
+                /// to completion on the blocking thread pool, through a mapping of its own, so the mmap
+                /// can be truncated or dropped meanwhile.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                ///
+                /// [`flush_range`]: #tymethod.flush_range
+                async fn flush_range_committed(&self, offset: usize, len: usize) -> Result<()> {
+                    self.flush_range(offset, len)
+                }
+
+                /// Writes back the dirty pages of the file in the range by [`sync_file_range(2)`] on Linux,
+                /// with the finer control of `flags`, e.g. [`SyncRangeFlags::WRITE`] alone starts the write
+                /// back without waiting for it, which is cheaper than `msync` for a large streaming writer.
+                /// The call runs on the blocking thread pool of the runtime.
+                ///
+                /// # Notes
+                /// It is **not** a durability guarantee, as it flushes neither the metadata of the file nor
+                /// the write cache of the disk, use [`flush_range_committed`] for that. The range is relative
+                /// to the mmap, not the file. On other platforms, and for the mmaps which are not shared
+                /// mappings of a file, it falls back to [`flush_range_committed`], which waits for the range
+                /// to be written back regardless of `flags`.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+                ///
+                /// [`sync_file_range(2)`]: https://man7.org/linux/man-pages/man2/sync_file_range.2.html
+                /// [`SyncRangeFlags::WRITE`]: crate::SyncRangeFlags::WRITE
+                /// [`flush_range_committed`]: #method.flush_range_committed
+                async fn sync_file_range(&self, offset: usize, len: usize, flags: crate::SyncRangeFlags) -> Result<()> {
+                    let _ = flags;
+                    self.flush_range_committed(offset, len).await
+                }
+
+                /// Promotes a copy-on-write mmap to a shared one, so the private changes are
+                /// committed to the file and the subsequent writes go to the file.
+                ///
+                /// The full mapping is copied back to the file, not only the dirtied pages,
+                /// and the writers of the file in other processes are not coordinated with.
+                /// An mmap which is not copy-on-write is left unchanged.
+                async fn promote_to_shared(&mut self) -> Result<()> {
+                    Ok(())
+                }
+
+                /// Registers the mmap with a shared [`FlushScheduler`], so [`flush_scheduled`] is batched
+                /// with the flushes of the other registered mmaps. `None` unregisters it.
+                ///
+                /// This only has effect on a disk mmap.
+                ///
+                /// [`FlushScheduler`]: struct.FlushScheduler.html
+                /// [`flush_scheduled`]: #method.flush_scheduled
+                #[cfg(unix)]
+                fn set_flush_scheduler(&mut self, _scheduler: Option<FlushScheduler>) {}
+
+                /// Flushes the whole mmap through the registered [`FlushScheduler`], the future
+                /// resolves when the batch containing the flush is done. Without a scheduler,
+                /// this is the same as [`flush_range_committed`] over the whole mmap.
+                ///
+                /// [`FlushScheduler`]: struct.FlushScheduler.html
+                /// [`flush_range_committed`]: #method.flush_range_committed
+                #[cfg(unix)]
+                async fn flush_scheduled(&self) -> Result<()> {
+                    self.flush()
+                }
+
                 /// Truncates the file to the `max_size`, which will lead to
                 /// do re-mmap and sync_dir if the inner is a real file.
                 async fn truncate(&mut self, max_sz: u64) -> Result<()>;
 
+                /// Returns the size [`truncate`] takes to resize the mmap to `len` bytes.
+                ///
+                /// [`truncate`] sets the size of the file, so the size for a disk mmap is `len` plus the offset
+                /// of the mmap in the file. For the other kinds of mmap, it is `len`.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::NotSupported))` if the length of the mmap is fixed, e.g. it
+                /// is mapped with a `len` in the options, since [`truncate`] does not resize it, and
+                /// `Err(Error::from(ErrorKind::TruncationFailed))` if the size overflows `u64`.
+                ///
+                /// [`truncate`]: #tymethod.truncate
+                fn truncate_size_for(&self, len: u64) -> Result<u64> {
+                    Ok(len)
+                }
+
+                /// Grows the mmap by `additional` bytes, relative to the current length of the mmap.
+                ///
+                /// The new size is computed internally, then [`truncate`] does the resizing. The new size of
+                /// a disk mmap is computed from the size of the file, as [`truncate`] sets the size of the file,
+                /// so a mmap which starts at a non-zero offset keeps the data before the offset.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::TruncationFailed))` if the new size overflows `u64`,
+                /// or the error returned by [`truncate`].
+                ///
+                /// [`truncate`]: #tymethod.truncate
+                async fn grow_by(&mut self, additional: u64) -> Result<()> {
+                    let len = self.as_mut_slice().len() as u64;
+                    self.truncate(crate::utils::grown_len(len, additional)?).await
+                }
+
+                /// Shrinks the mmap by `bytes` bytes, relative to the current length of the mmap,
+                /// the new size is clamped at 0.
+                ///
+                /// The new size is computed internally, then [`truncate`] does the resizing. The new size of
+                /// a disk mmap is computed from the size of the file, the same as [`grow_by`].
+                ///
+                /// [`grow_by`]: #method.grow_by
+                /// [`truncate`]: #tymethod.truncate
+                async fn shrink_by(&mut self, bytes: u64) -> Result<()> {
+                    let len = self.as_mut_slice().len() as u64;
+                    self.truncate(len.saturating_sub(bytes)).await
+                }
+
+                /// Truncates the mmap to just after its last non-zero byte, i.e. drops the trailing run
+                /// of zeros, and returns the new length. An all-zero mmap is truncated to 0.
+                ///
+                /// The mmap is scanned backward a word at a time, then [`truncate`] does the resizing, to the
+                /// size given by [`truncate_size_for`], so a disk mmap starting at a non-zero offset keeps the
+                /// data before the offset.
+                ///
+                /// [`truncate`]: #tymethod.truncate
+                /// [`truncate_size_for`]: #method.truncate_size_for
+                async fn truncate_trailing_zeros(&mut self) -> Result<u64> {
+                    let new_len = crate::utils::last_nonzero(self.as_mut_slice()).map_or(0, |pos| pos as u64 + 1);
+                    self.truncate(self.truncate_size_for(new_len)?).await?;
+                    Ok(new_len)
+                }
+
+                /// Resizes the mmap to `new_len` bytes with the same contract for all the kinds of mmap:
+                /// the content in `[0, min(old, new_len))` is preserved, the grown tail is zeroed and the
+                /// shrunk tail is dropped.
+                ///
+                /// An in-memory mmap reallocates its buffer keeping the prefix, an anonymous mmap maps a new
+                /// region and copies the prefix over, and a disk mmap is resized by [`truncate`], which flushes
+                /// before remapping the file.
+                ///
+                /// A disk mmap starting at a non-zero offset is resized to the size given by [`truncate_size_for`],
+                /// so the data before the offset is kept.
+                ///
+                /// # Errors
+                /// Returns the error returned by [`truncate_size_for`] before resizing anything, e.g. if the mmap
+                /// has a fixed length, the error returned by [`truncate`], or `Err(Error::from(ErrorKind::TruncationFailed))`
+                /// if the mmap cannot hold `new_len` bytes afterwards, e.g. an empty mmap.
+                ///
+                /// [`truncate`]: #tymethod.truncate
+                /// [`truncate_size_for`]: #method.truncate_size_for
+                async fn resize_preserving(&mut self, new_len: u64) -> Result<()> {
+                    let size = self.truncate_size_for(new_len)?;
+                    self.truncate(size).await?;
+                    let len = self.as_mut_slice().len() as u64;
+                    if len != new_len {
+                        return Err(Error::new_with_message(
+                            ErrorKind::TruncationFailed,
+                            format!("the mmap is resized to {} bytes instead of {} bytes", len, new_len),
+                        ));
+                    }
+                    Ok(())
+                }
+
+                /// Returns the strategy to grow the mmap when a write needs more room than its length,
+                /// see [`GrowStrategy`]. It is configured by `grow_increment` of the options, the default
+                /// one is [`GrowStrategy::Double`].
+                ///
+                /// [`GrowStrategy`]: crate::GrowStrategy
+                /// [`GrowStrategy::Double`]: crate::GrowStrategy::Double
+                fn grow_strategy(&self) -> crate::GrowStrategy {
+                    crate::GrowStrategy::default()
+                }
+
+                /// Grows the mmap, following [`grow_strategy`], so it holds at least `required` bytes.
+                /// Does nothing if the mmap is already long enough.
+                ///
+                /// The mmap is resized to the size given by [`truncate_size_for`], so a disk mmap starting at
+                /// a non-zero offset keeps the data before the offset.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::TruncationFailed))` if the new length overflows `u64`,
+                /// or the error returned by [`truncate_size_for`] or [`truncate`].
+                ///
+                /// [`grow_strategy`]: #method.grow_strategy
+                /// [`truncate_size_for`]: #method.truncate_size_for
+                /// [`truncate`]: #tymethod.truncate
+                async fn grow_to_fit(&mut self, required: u64) -> Result<()> {
+                    let len = self.as_mut_slice().len() as u64;
+                    if len >= required {
+                        return Ok(());
+                    }
+                    let new_len = self.grow_strategy().checked_next_len(len, required).ok_or_else(|| {
+                        Error::new_with_message(ErrorKind::TruncationFailed, "the new size overflows u64")
+                    })?;
+                    self.truncate(self.truncate_size_for(new_len)?).await
+                }
+
+                /// Writes all of `src` at `offset`, growing the mmap first if it ends before `offset + src.len()`.
+                ///
+                /// Unlike [`write_all`], the write is not limited by the current length. The mmap grows by
+                /// [`grow_to_fit`], so it may grow beyond `offset + src.len()`, and the gap between the old end
+                /// and `offset`, like all the new room, is zero-filled.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::NotSupported))` if the mmap is copy-on-write, since the
+                /// new room would never reach the file, and the error returned by [`truncate`] if it cannot grow.
+                ///
+                /// [`write_all`]: #method.write_all
+                /// [`grow_to_fit`]: #method.grow_to_fit
+                /// [`truncate`]: #tymethod.truncate
+                async fn put(&mut self, src: &[u8], offset: usize) -> Result<()> {
+                    if self.is_cow() {
+                        return Err(Error::new_with_message(
+                            ErrorKind::NotSupported,
+                            "cannot grow a copy-on-write mmap",
+                        ));
+                    }
+                    let end = offset.checked_add(src.len()).ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    self.grow_to_fit(end as u64).await?;
+                    self.write_all(src, offset)
+                }
+
                 /// Remove the underlying file
                 async fn drop_remove(self) -> Result<()>;
 
@@ -970,6 +2678,141 @@ cfg_async! {
                     }
                 }
 
+                /// Writes `new` to the mmap from the offset only if the bytes there are equal to `expected`,
+                /// then flushes the written range. Returns whether the bytes were swapped.
+                ///
+                /// # Notes
+                /// The compare and the write are not atomic, so without a lock (see [`lock_exclusive`])
+                /// this is racy when other processes write the same region. It is intended for the
+                /// single-writer optimistic versioning.
+                ///
+                /// # Errors
+                /// - `Err(Error::from(ErrorKind::LengthMismatch(expected.len(), new.len())))` if the lengths of `expected` and `new` are different.
+                /// - `Err(Error::from(ErrorKind::EOF))` if the region is out of the bound of the mmap.
+                ///
+                /// [`lock_exclusive`]: trait.AsyncMmapFileExt.html#tymethod.lock_exclusive
+                fn compare_and_swap(&mut self, offset: usize, expected: &[u8], new: &[u8]) -> Result<bool> {
+                    let len = new.len();
+                    if expected.len() != len {
+                        return Err(Error::from(ErrorKind::LengthMismatch(expected.len(), len)));
+                    }
+
+                    let region = offset
+                        .checked_add(len)
+                        .and_then(|end| self.as_mut_slice().get_mut(offset..end))
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    if region != expected {
+                        return Ok(false);
+                    }
+                    region.copy_from_slice(new);
+                    self.flush_range(offset, len).map(|_| true)
+                }
+
+                /// Swaps the bytes of two non-overlapping ranges of the same length in place,
+                /// e.g. to reorder the fixed-size records of a file.
+                ///
+                /// # Errors
+                /// - `Err(Error::from(ErrorKind::LengthMismatch(a.len(), b.len())))` if the lengths of the ranges are different.
+                /// - `Err(Error::from(ErrorKind::EOF))` if any range is out of the bound of the mmap.
+                /// - `Err(Error::from(ErrorKind::Overlap))` if the ranges overlap.
+                fn swap_ranges(&mut self, a: std::ops::Range<usize>, b: std::ops::Range<usize>) -> Result<()> {
+                    let buf = self.as_mut_slice();
+                    if a.start > a.end || b.start > b.end || a.end > buf.len() || b.end > buf.len() {
+                        return Err(Error::from(ErrorKind::EOF));
+                    }
+                    if a.len() != b.len() {
+                        return Err(Error::from(ErrorKind::LengthMismatch(a.len(), b.len())));
+                    }
+                    if a.is_empty() || a == b {
+                        return Ok(());
+                    }
+
+                    let (lo, hi) = if a.start < b.start { (a, b) } else { (b, a) };
+                    if lo.end > hi.start {
+                        return Err(Error::from(ErrorKind::Overlap));
+                    }
+                    let (head, tail) = buf.split_at_mut(hi.start);
+                    head[lo].swap_with_slice(&mut tail[..hi.len()]);
+                    Ok(())
+                }
+
+                /// Appends `data` to a single-producer log, and publishes it atomically.
+                ///
+                /// The log is an 8-byte native-endian length header at `len_header_offset`, followed by the
+                /// published bytes. `data` is written right after the published bytes and flushed, then the
+                /// new length is stored in the header with the `Release` ordering, and returned.
+                ///
+                /// # Memory ordering
+                /// A consumer (in this or another process mapping the same file) which loads the length with
+                /// the `Acquire` ordering (see `published_len`) sees all the bytes up to that length, so it
+                /// never reads a torn record. The header is only stored after the data is flushed, so after a
+                /// crash it never covers unflushed data. There must be only one producer at a time, the
+                /// concurrent producers must be serialized (e.g. by a file lock).
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if the header or `data` is out of the bound of the mmap,
+                /// and `Err(Error::from(ErrorKind::Misaligned))` if the header is not 8-byte aligned.
+                #[cfg(target_has_atomic = "64")]
+                fn atomic_append(&mut self, data: &[u8], len_header_offset: usize) -> Result<u64> {
+                    use std::sync::atomic::Ordering;
+
+                    let buf = self.as_mut_slice();
+                    let published = crate::utils::atomic_u64_at(buf, len_header_offset)?.load(Ordering::Acquire);
+                    let start = usize::try_from(published)
+                        .ok()
+                        .and_then(|published| (len_header_offset + 8).checked_add(published))
+                        .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+                    match start.checked_add(data.len()) {
+                        Some(end) if end <= buf.len() => buf[start..end].copy_from_slice(data),
+                        _ => return Err(Error::from(ErrorKind::EOF)),
+                    }
+                    self.flush_range(start, data.len())?;
+
+                    let len = published + data.len() as u64;
+                    crate::utils::atomic_u64_at(self.as_mut_slice(), len_header_offset)?.store(len, Ordering::Release);
+                    Ok(len)
+                }
+
+                /// Applies a batch of `(offset, bytes)` edits in order, then flushes the union of the touched ranges.
+                ///
+                /// All the edits are validated before any of them is applied, so the mmap is left untouched
+                /// if any edit is out of bounds. This is useful when replaying a write-ahead log into a snapshot.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))`, with the index of the first offending edit in the
+                /// message, if any edit does not fit in the mmap.
+                fn apply_patch(&mut self, edits: &[(usize, &[u8])]) -> Result<()> {
+                    let len = self.as_mut_slice().len();
+                    let mut touched: Option<(usize, usize)> = None;
+                    for (idx, (offset, src)) in edits.iter().enumerate() {
+                        match offset.checked_add(src.len()) {
+                            Some(end) if end <= len => {
+                                if !src.is_empty() {
+                                    touched = Some(match touched {
+                                        None => (*offset, end),
+                                        Some((start, stop)) => (start.min(*offset), stop.max(end)),
+                                    });
+                                }
+                            }
+                            _ => {
+                                return Err(Error::new_with_message(
+                                    ErrorKind::EOF,
+                                    format!("edit {} is out of bounds", idx),
+                                ))
+                            }
+                        }
+                    }
+
+                    for (offset, src) in edits {
+                        self.write_all(src, *offset)?;
+                    }
+
+                    match touched {
+                        Some((start, end)) => self.flush_range(start, end - start),
+                        None => Ok(()),
+                    }
+                }
+
                 /// Writes a signed 8 bit integer to mmap from the offset.
                 fn write_i8(&mut self, val: i8, offset: usize) -> Result<()> {
                     self.write_all(&[val as u8], offset)
@@ -1099,6 +2942,57 @@ cfg_async! {
                 fn write_f64_le(&mut self, val: f64, offset: usize) -> Result<()> {
                     self.write_all(&val.to_le_bytes(), offset)
                 }
+
+                /// Writes the UTF-8 bytes of `s` to the mmap from the offset, returns the number of bytes written.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` and writes nothing if the bytes do not fit.
+                fn write_str(&mut self, s: &str, offset: usize) -> Result<usize> {
+                    self.write_all(s.as_bytes(), offset)?;
+                    Ok(s.len())
+                }
+
+                /// Writes the UTF-8 bytes of `s` followed by a NUL byte to the mmap from the offset,
+                /// returns the number of bytes written (including the NUL byte).
+                ///
+                /// # Errors
+                /// Nothing is written on error.
+                /// - `Err(Error::from(ErrorKind::InvalidCStr))` if `s` contains a NUL byte.
+                /// - `Err(Error::from(ErrorKind::EOF))` if the bytes do not fit.
+                fn write_cstr(&mut self, s: &str, offset: usize) -> Result<usize> {
+                    if s.as_bytes().contains(&0) {
+                        return Err(Error::from(ErrorKind::InvalidCStr));
+                    }
+                    let n = s.len() + 1;
+                    match offset.checked_add(n) {
+                        Some(end) if end <= self.as_mut_slice().len() => {}
+                        _ => return Err(Error::from(ErrorKind::EOF)),
+                    }
+                    self.write_all(s.as_bytes(), offset)?;
+                    self.write_u8(0, offset + s.len())?;
+                    Ok(n)
+                }
+
+                /// Writes the length of `s` as a big-endian `u32`, followed by the UTF-8 bytes of `s`, to the
+                /// mmap from the offset, returns the number of bytes written (including the 4 bytes prefix).
+                ///
+                /// # Errors
+                /// Nothing is written on error.
+                /// - `Err(Error::from(ErrorKind::BadFormat))` if the length of `s` does not fit in a `u32`.
+                /// - `Err(Error::from(ErrorKind::EOF))` if the bytes do not fit.
+                fn write_str_prefixed(&mut self, s: &str, offset: usize) -> Result<usize> {
+                    let len = u32::try_from(s.len()).map_err(|_| {
+                        Error::new_with_message(ErrorKind::BadFormat, "string length does not fit in a u32 prefix")
+                    })?;
+                    let n = s.len() + 4;
+                    match offset.checked_add(n) {
+                        Some(end) if end <= self.as_mut_slice().len() => {}
+                        _ => return Err(Error::from(ErrorKind::EOF)),
+                    }
+                    self.write_u32(len, offset)?;
+                    self.write_all(s.as_bytes(), offset + 4)?;
+                    Ok(n)
+                }
             }
         };
     }
@@ -1165,6 +3059,38 @@ cfg_async! {
                     }
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    match self {
+                        AsyncMmapFileInner::Empty(_) => crate::MapKind::Empty,
+                        AsyncMmapFileInner::Memory(_) => crate::MapKind::Memory,
+                        AsyncMmapFileInner::Disk(_) => crate::MapKind::Disk,
+                    }
+                }
+
+                #[inline]
+                fn is_closed(&self) -> bool {
+                    matches!(self, AsyncMmapFileInner::Empty(_))
+                }
+
+                #[inline]
+                fn copy_all_to_bytes(&self) -> bytes::Bytes {
+                    match self {
+                        AsyncMmapFileInner::Empty(inner) => AsyncMmapFileExt::copy_all_to_bytes(inner),
+                        AsyncMmapFileInner::Memory(inner) => AsyncMmapFileExt::copy_all_to_bytes(inner),
+                        AsyncMmapFileInner::Disk(inner) => AsyncMmapFileExt::copy_all_to_bytes(inner),
+                    }
+                }
+
+                #[inline]
+                fn copy_range_to_bytes(&self, offset: usize, len: usize) -> Result<bytes::Bytes> {
+                    match self {
+                        AsyncMmapFileInner::Empty(inner) => AsyncMmapFileExt::copy_range_to_bytes(inner, offset, len),
+                        AsyncMmapFileInner::Memory(inner) => AsyncMmapFileExt::copy_range_to_bytes(inner, offset, len),
+                        AsyncMmapFileInner::Disk(inner) => AsyncMmapFileExt::copy_range_to_bytes(inner, offset, len),
+                    }
+                }
+
                 #[inline]
                 async fn metadata(&self) -> Result<MetaData> {
                     match self {
@@ -1282,6 +3208,38 @@ cfg_async! {
                     }
                 }
 
+                #[inline]
+                fn kind(&self) -> crate::MapKind {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(_) => crate::MapKind::Empty,
+                        AsyncMmapFileMutInner::Memory(_) => crate::MapKind::Memory,
+                        AsyncMmapFileMutInner::Disk(_) => crate::MapKind::Disk,
+                    }
+                }
+
+                #[inline]
+                fn is_closed(&self) -> bool {
+                    matches!(self, AsyncMmapFileMutInner::Empty(_))
+                }
+
+                #[inline]
+                fn copy_all_to_bytes(&self) -> bytes::Bytes {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(inner) => AsyncMmapFileExt::copy_all_to_bytes(inner),
+                        AsyncMmapFileMutInner::Memory(inner) => AsyncMmapFileExt::copy_all_to_bytes(inner),
+                        AsyncMmapFileMutInner::Disk(inner) => AsyncMmapFileExt::copy_all_to_bytes(inner),
+                    }
+                }
+
+                #[inline]
+                fn copy_range_to_bytes(&self, offset: usize, len: usize) -> Result<bytes::Bytes> {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(inner) => AsyncMmapFileExt::copy_range_to_bytes(inner, offset, len),
+                        AsyncMmapFileMutInner::Memory(inner) => AsyncMmapFileExt::copy_range_to_bytes(inner, offset, len),
+                        AsyncMmapFileMutInner::Disk(inner) => AsyncMmapFileExt::copy_range_to_bytes(inner, offset, len),
+                    }
+                }
+
                 #[inline]
                 async fn metadata(&self) -> Result<MetaData> {
                     match self {
@@ -1352,12 +3310,84 @@ cfg_async! {
                     }
                 }
 
-                #[inline]
-                fn is_cow(&self) -> bool {
+                #[inline]
+                fn is_cow(&self) -> bool {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(inner) => AsyncMmapFileMutExt::is_cow(inner),
+                        AsyncMmapFileMutInner::Memory(inner) => AsyncMmapFileMutExt::is_cow(inner),
+                        AsyncMmapFileMutInner::Disk(inner) => AsyncMmapFileMutExt::is_cow(inner),
+                    }
+                }
+
+                #[inline]
+                fn is_poisoned(&self) -> bool {
+                    match self {
+                        AsyncMmapFileMutInner::Disk(inner) => AsyncMmapFileMutExt::is_poisoned(inner),
+                        _ => false,
+                    }
+                }
+
+                async fn promote_to_shared(&mut self) -> Result<()> {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(inner) => {
+                            AsyncMmapFileMutExt::promote_to_shared(inner).await
+                        }
+                        AsyncMmapFileMutInner::Memory(inner) => {
+                            AsyncMmapFileMutExt::promote_to_shared(inner).await
+                        }
+                        AsyncMmapFileMutInner::Disk(inner) => {
+                            AsyncMmapFileMutExt::promote_to_shared(inner).await
+                        }
+                    }
+                }
+
+                async fn flush_range_committed(&self, offset: usize, len: usize) -> Result<()> {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(inner) => {
+                            AsyncMmapFileMutExt::flush_range_committed(inner, offset, len).await
+                        }
+                        AsyncMmapFileMutInner::Memory(inner) => {
+                            AsyncMmapFileMutExt::flush_range_committed(inner, offset, len).await
+                        }
+                        AsyncMmapFileMutInner::Disk(inner) => {
+                            AsyncMmapFileMutExt::flush_range_committed(inner, offset, len).await
+                        }
+                    }
+                }
+
+                async fn sync_file_range(&self, offset: usize, len: usize, flags: crate::SyncRangeFlags) -> Result<()> {
+                    match self {
+                        AsyncMmapFileMutInner::Empty(inner) => {
+                            AsyncMmapFileMutExt::sync_file_range(inner, offset, len, flags).await
+                        }
+                        AsyncMmapFileMutInner::Memory(inner) => {
+                            AsyncMmapFileMutExt::sync_file_range(inner, offset, len, flags).await
+                        }
+                        AsyncMmapFileMutInner::Disk(inner) => {
+                            AsyncMmapFileMutExt::sync_file_range(inner, offset, len, flags).await
+                        }
+                    }
+                }
+
+                #[cfg(unix)]
+                fn set_flush_scheduler(&mut self, scheduler: Option<FlushScheduler>) {
+                    if let AsyncMmapFileMutInner::Disk(inner) = self {
+                        AsyncMmapFileMutExt::set_flush_scheduler(inner, scheduler)
+                    }
+                }
+
+                #[cfg(unix)]
+                async fn flush_scheduled(&self) -> Result<()> {
                     match self {
-                        AsyncMmapFileMutInner::Empty(inner) => AsyncMmapFileMutExt::is_cow(inner),
-                        AsyncMmapFileMutInner::Memory(inner) => AsyncMmapFileMutExt::is_cow(inner),
-                        AsyncMmapFileMutInner::Disk(inner) => AsyncMmapFileMutExt::is_cow(inner),
+                        AsyncMmapFileMutInner::Empty(inner) => {
+                            AsyncMmapFileMutExt::flush_scheduled(inner).await
+                        }
+                        AsyncMmapFileMutInner::Memory(inner) => {
+                            AsyncMmapFileMutExt::flush_scheduled(inner).await
+                        }
+                        AsyncMmapFileMutInner::Disk(inner) => {
+                            AsyncMmapFileMutExt::flush_scheduled(inner).await
+                        }
                     }
                 }
 
@@ -1435,6 +3465,13 @@ cfg_async! {
                     }
                 }
 
+                fn truncate_size_for(&self, len: u64) -> Result<u64> {
+                    match self {
+                        AsyncMmapFileMutInner::Disk(inner) => AsyncMmapFileMutExt::truncate_size_for(inner, len),
+                        _ => Ok(len),
+                    }
+                }
+
                 async fn drop_remove(self) -> Result<()> {
                     match self {
                         AsyncMmapFileMutInner::Empty(inner) => AsyncMmapFileMutExt::drop_remove(inner).await,
@@ -1509,6 +3546,37 @@ cfg_async! {
                     Ok(Self::from(AsyncDiskMmapFile::open(path).await?))
                 }
 
+                /// Sets the path reported by `path` of an in-memory map, e.g. to give a map
+                /// created by `memory_from_vec` a logical name for logs and errors.
+                ///
+                /// The label has no effect on a disk map, which always reports the path of its file.
+                pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+                    if let AsyncMmapFileInner::Memory(m) = &mut self.inner {
+                        m.set_path_label(label.into());
+                    }
+                    self
+                }
+
+                /// Leaks the mmap, and returns a `'static` slice of its content, e.g. for a config or
+                /// a lookup table which is mapped once at startup and never dropped.
+                ///
+                /// For a disk mmap, the mapping and the file handle are intentionally never released,
+                /// so only call this for a bounded number of files.
+                ///
+                /// # Examples
+                #[doc = "```ignore"]
+                #[doc = concat!("use fmmap::", $path_str, "::AsyncMmapFile;")]
+                #[doc = ""]
+                #[doc = "let table: &'static [u8] = AsyncMmapFile::open(\"table.bin\").await.unwrap().leak();"]
+                #[doc = "```"]
+                pub fn leak(self) -> &'static [u8] {
+                    match self.inner {
+                        AsyncMmapFileInner::Empty(_) => &[],
+                        AsyncMmapFileInner::Memory(memory) => memory.leak(),
+                        AsyncMmapFileInner::Disk(disk) => disk.leak(),
+                    }
+                }
+
                 /// Open a readable memory map backed by a file with [`Options`]
                 ///
                 /// # Examples
@@ -1544,6 +3612,52 @@ cfg_async! {
                     Ok(Self::from(AsyncDiskMmapFile::open_with_options(path, opts).await?))
                 }
 
+                /// Like [`open_with_options`], but gives up if opening and mapping the file does not
+                /// complete within `timeout`, e.g. when the file is on a hung network mount (NFS, SMB).
+                ///
+                /// The open is run on a new task, as the blocking open and mmap system calls cannot be
+                /// interrupted. On timeout the task is abandoned rather than cancelled: the orphaned
+                /// open may still complete in the background, then the map is dropped.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::Timeout))` if the open does not complete in time,
+                /// or the error returned by [`open_with_options`].
+                ///
+                /// [`open_with_options`]: #method.open_with_options
+                pub async fn open_with_timeout<P: AsRef<Path>>(path: P, opts: AsyncOptions, timeout: std::time::Duration) -> Result<Self> {
+                    let path = path.as_ref().to_path_buf();
+                    spawn_with_timeout(async move { Self::open_with_options(path, opts).await }, timeout).await
+                }
+
+                /// Open a readable memory map backed by a file if the file exists.
+                ///
+                /// Returns `Ok(None)` if the file does not exist, other errors are returned as `Err`.
+                /// This is handy for optional files, e.g. sidecar or index files.
+                ///
+                /// # Examples
+                ///
+                #[doc = "```ignore"]
+                #[doc = concat!("use fmmap::", $path_str, "::AsyncMmapFile;")]
+                ///
+                #[doc = concat!("# ", $doc_test_runtime, "::block_on(async {")]
+                /// let index = AsyncMmapFile::try_open("data.idx").await.unwrap();
+                /// if let Some(index) = index {
+                ///     // use the index
+                /// }
+                /// # })
+                #[doc = "```"]
+                pub async fn try_open<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+                    super::none_if_not_found(Self::open(path).await)
+                }
+
+                /// Same as [`try_open`], but open the file with [`AsyncOptions`].
+                ///
+                /// [`try_open`]: #method.try_open
+                #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
+                pub async fn try_open_with_options<P: AsRef<Path>>(path: P, opts: AsyncOptions) -> Result<Option<Self>> {
+                    super::none_if_not_found(Self::open_with_options(path, opts).await)
+                }
+
                 /// Open a readable and executable memory map backed by a file
                 ///
                 /// # Examples
@@ -1605,6 +3719,39 @@ cfg_async! {
                 pub async fn open_exec_with_options<P: AsRef<Path>>(path: P, opts: AsyncOptions) -> Result<Self> {
                     Ok(Self::from(AsyncDiskMmapFile::open_exec_with_options(path, opts).await?))
                 }
+
+                /// Open a compressed file and decompress it into an in-memory mmap file.
+                ///
+                /// The compressed file is mmapped and decompressed in a streaming way, but the result
+                /// cannot be backed by the file, so the returned `AsyncMmapFile` is in-memory (see [`AsyncMmapFile::memory`]),
+                /// and its path is the path of the compressed file.
+                ///
+                /// # Examples
+                ///
+                #[doc = "```ignore"]
+                #[doc = concat!("use fmmap::", $path_str, "::{AsyncMmapFile, AsyncMmapFileExt};")]
+                /// use fmmap::Codec;
+                ///
+                #[doc = concat!("# ", $doc_test_runtime, "::block_on(async {")]
+                /// let file = AsyncMmapFile::open_decompressed("assets.bin.gz", Codec::Gzip).await.unwrap();
+                /// let magic = file.prefix(4).unwrap();
+                /// # })
+                #[doc = "```"]
+                ///
+                #[doc = concat!("[`AsyncMmapFile::memory`]: ", $path_str, "/struct.AsyncMmapFile.html#method.memory")]
+                #[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+                #[cfg_attr(docsrs, doc(cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))))]
+                pub async fn open_decompressed<P: AsRef<Path>>(path: P, codec: crate::Codec) -> Result<Self> {
+                    let path = path.as_ref();
+                    let compressed = Self::open(path).await?;
+                    // decompressing is CPU bound, keep it off the executor
+                    let data = spawn_blocking(move || {
+                        crate::codec::decompress(compressed.as_slice(), codec)
+                            .map_err(|e| Error::new_source_msg(ErrorKind::IO, compressed.path_string(), e))
+                    })
+                    .await??;
+                    Ok(Self::memory(path, data.freeze()))
+                }
             }
 
             impl_constructor_for_memory_mmap_file!(AsyncMemoryMmapFile, AsyncMmapFile, "AsyncMmapFile", $path_str);
@@ -1624,10 +3771,24 @@ cfg_async! {
             pub struct AsyncMmapFileMut {
                 inner: AsyncMmapFileMutInner,
                 remove_on_drop: bool,
+                flush_on_drop: bool,
+                high_water: usize,
+                dirty: crate::dirty::DirtyRanges,
+                grow: crate::GrowStrategy,
                 deleted: bool,
+                flush_policy: crate::FlushPolicy,
+                last_sync: std::sync::Mutex<std::time::Instant>,
+                generation: u64,
             }
 
-            impl_from_mut!(AsyncMmapFileMut, AsyncMmapFileMutInner, [AsyncEmptyMmapFile, AsyncMemoryMmapFileMut, AsyncDiskMmapFileMut]);
+            impl_from_mut!(
+                AsyncMmapFileMut,
+                AsyncMmapFileMutInner,
+                [AsyncEmptyMmapFile, AsyncMemoryMmapFileMut, AsyncDiskMmapFileMut],
+                flush_policy: crate::FlushPolicy::Sync,
+                last_sync: std::sync::Mutex::new(std::time::Instant::now()),
+                generation: 0,
+            );
 
             impl_async_mmap_file_ext!(AsyncMmapFileMut);
 
@@ -1658,7 +3819,9 @@ cfg_async! {
                 #[doc = concat!("[`create_with_options`]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.create_with_options")]
                 #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
                 pub async fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
-                    Ok(Self::from(AsyncDiskMmapFileMut::create(path).await?))
+                    let mut file = Self::from(AsyncDiskMmapFileMut::create(path).await?);
+                    file.high_water = 0;
+                    Ok(file)
                 }
 
                 /// Create a new file and mmap this file with [`AsyncOptions`]
@@ -1682,7 +3845,35 @@ cfg_async! {
                 ///
                 #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
                 pub async fn create_with_options<P: AsRef<Path>>(path: P, opts: AsyncOptions) -> Result<Self> {
-                    Ok(Self::from(AsyncDiskMmapFileMut::create_with_options(path, opts).await?))
+                    let grow = opts.grow;
+                    let flush_policy = opts.flush_policy;
+                    let written = opts.initial_contents.as_ref().map_or(0, |data| data.len());
+                    let mut file = Self::from(AsyncDiskMmapFileMut::create_with_options(path, opts).await?);
+                    file.high_water = written;
+                    file.set_grow_strategy(grow);
+                    file.set_flush_policy(flush_policy);
+                    Ok(file)
+                }
+
+                /// Create a new file with [`AsyncOptions`] and mmap this file, then copy the attributes
+                /// of the `template` file to it, e.g. to match the permissions of an existing segment.
+                ///
+                /// On unix, the owner, the group and the mode bits (including the setuid, setgid and
+                /// sticky bits) are copied, an unprivileged process silently keeps the owner of the new
+                /// file. On Windows, only the read-only flag is copied, the ACLs are not. If the attributes
+                /// cannot be copied, the new file is removed.
+                ///
+                #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
+                pub async fn create_like<P: AsRef<Path>, T: AsRef<Path>>(path: P, template: T, opts: AsyncOptions) -> Result<Self> {
+                    let file = Self::create_with_options(&path, opts).await?;
+                    let path: &std::path::Path = path.as_ref().as_ref();
+                    let template: &std::path::Path = template.as_ref().as_ref();
+                    if let Err(e) = crate::utils::copy_attributes(template, path) {
+                        drop(file);
+                        let _ = std::fs::remove_file(path);
+                        return Err(e);
+                    }
+                    Ok(file)
                 }
 
                 /// Open or Create(if not exists) a file and mmap this file.
@@ -1859,7 +4050,29 @@ cfg_async! {
                 ///
                 #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
                 pub async fn open_with_options<P: AsRef<Path>>(path: P, opts: AsyncOptions) -> Result<Self> {
-                    Ok(Self::from(AsyncDiskMmapFileMut::open_with_options(path, opts).await?))
+                    let grow = opts.grow;
+                    let flush_policy = opts.flush_policy;
+                    let mut file = Self::from(AsyncDiskMmapFileMut::open_with_options(path, opts).await?);
+                    file.set_grow_strategy(grow);
+                    file.set_flush_policy(flush_policy);
+                    Ok(file)
+                }
+
+                /// Like [`open_with_options`], but gives up if opening and mapping the file does not
+                /// complete within `timeout`, e.g. when the file is on a hung network mount (NFS, SMB).
+                ///
+                /// The open is run on a new task, as the blocking open and mmap system calls cannot be
+                /// interrupted. On timeout the task is abandoned rather than cancelled: the orphaned
+                /// open may still complete in the background, then the map is dropped.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::Timeout))` if the open does not complete in time,
+                /// or the error returned by [`open_with_options`].
+                ///
+                /// [`open_with_options`]: #method.open_with_options
+                pub async fn open_with_timeout<P: AsRef<Path>>(path: P, opts: AsyncOptions, timeout: std::time::Duration) -> Result<Self> {
+                    let path = path.as_ref().to_path_buf();
+                    spawn_with_timeout(async move { Self::open_with_options(path, opts).await }, timeout).await
                 }
 
                 /// Open an existing file and mmap this file
@@ -1951,7 +4164,12 @@ cfg_async! {
                 ///
                 #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
                 pub async fn open_exist_with_options<P: AsRef<Path>>(path: P, opts: AsyncOptions) -> Result<Self> {
-                    Ok(Self::from(AsyncDiskMmapFileMut::open_exist_with_options(path, opts).await?))
+                    let grow = opts.grow;
+                    let flush_policy = opts.flush_policy;
+                    let mut file = Self::from(AsyncDiskMmapFileMut::open_exist_with_options(path, opts).await?);
+                    file.set_grow_strategy(grow);
+                    file.set_flush_policy(flush_policy);
+                    Ok(file)
                 }
 
                 /// Open and mmap an existing file in copy-on-write mode(copy-on-write memory map backed by a file).
@@ -2048,7 +4266,23 @@ cfg_async! {
                 ///
                 #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
                 pub async fn open_cow_with_options<P: AsRef<Path>>(path: P, opts: AsyncOptions) -> Result<Self> {
-                    Ok(Self::from(AsyncDiskMmapFileMut::open_cow_with_options(path, opts).await?))
+                    let grow = opts.grow;
+                    let flush_policy = opts.flush_policy;
+                    let mut file = Self::from(AsyncDiskMmapFileMut::open_cow_with_options(path, opts).await?);
+                    file.set_grow_strategy(grow);
+                    file.set_flush_policy(flush_policy);
+                    Ok(file)
+                }
+
+                /// Sets the path reported by `path` of an in-memory map, e.g. to give a map
+                /// created by `memory_from_vec` a logical name for logs and errors.
+                ///
+                /// The label has no effect on a disk map, which always reports (and removes) the path of its file.
+                pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+                    if let AsyncMmapFileMutInner::Memory(m) = &mut self.inner {
+                        m.set_path_label(label.into());
+                    }
+                    self
                 }
 
                 /// Make the mmap file read-only.
@@ -2156,9 +4390,231 @@ cfg_async! {
                     self.remove_on_drop = val;
                 }
 
+                /// Returns whether flush the mmap on drop.
+                #[inline]
+                pub fn get_flush_on_drop(&self) -> bool {
+                    self.flush_on_drop
+                }
+
+                /// Whether flush the mmap on drop.
+                /// Default is false.
+                ///
+                /// # Notes
+                /// The flush on drop is best-effort, any error is ignored, just like the
+                /// removal of `remove_on_drop`. Call [`flush`] explicitly if you need to handle the error.
+                ///
+                /// If `remove_on_drop` is also set to `true`, the mmap will not be flushed, since
+                /// the underlying file is removed anyway.
+                ///
+                #[doc = concat!("[`flush`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.flush")]
+                #[inline]
+                pub fn set_flush_on_drop(&mut self, val: bool) {
+                    self.flush_on_drop = val;
+                }
+
+                /// The sentinel `max_sz` for [`close`] and [`close_with_truncate`], which truncates
+                /// the file to the [high-water mark] instead of a given size.
+                ///
+                #[doc = concat!("[`close`]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.close")]
+                #[doc = concat!("[`close_with_truncate`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.close_with_truncate")]
+                #[doc = concat!("[high-water mark]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.high_water_mark")]
+                pub const TRUNCATE_TO_HIGH_WATER_MARK: i64 = i64::MIN;
+
+                /// Returns the high-water mark, which is the end of the furthest bytes written.
+                ///
+                /// A created file starts at zero (or at the end of its initial contents), while an opened
+                /// or in-memory file starts at the end of the mmap, so its existing content counts as written.
+                ///
+                /// The high-water mark is advanced by [`write`], [`write_all`] (and the `write_*` methods built on them)
+                /// and the writer returned by [`writer_at_end`], it is lowered by [`truncate`] when the mmap shrinks.
+                /// Bytes written through [`as_mut_slice`] or the other writers are not tracked.
+                ///
+                #[doc = concat!("[`write`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#method.write")]
+                #[doc = concat!("[`write_all`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#method.write_all")]
+                #[doc = concat!("[`writer_at_end`]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.writer_at_end")]
+                #[doc = concat!("[`truncate`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.truncate")]
+                #[doc = concat!("[`as_mut_slice`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.as_mut_slice")]
+                #[inline]
+                pub fn high_water_mark(&self) -> usize {
+                    self.high_water
+                }
+
+                /// Returns the generation of the content, which is bumped by every mutation, e.g. to
+                /// invalidate a cached checksum by comparing the generation it is computed at.
+                ///
+                /// The mutations are the ones which mark the [dirty ranges], plus [`truncate`]. The writes
+                /// through the raw slice cannot be observed, so any call of [`as_mut_slice`] (including by
+                /// the writers and other methods built on it) conservatively bumps it, even if nothing is written.
+                ///
+                #[doc = concat!("[dirty ranges]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.dirty_ranges")]
+                #[doc = concat!("[`truncate`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.truncate")]
+                #[doc = concat!("[`as_mut_slice`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.as_mut_slice")]
+                #[inline]
+                pub fn generation(&self) -> u64 {
+                    self.generation
+                }
+
+                /// Returns the ranges written since they were last flushed, sorted and coalesced,
+                /// e.g. to report how much is unflushed or to build a custom flush policy.
+                ///
+                /// The ranges are marked by [`write`], [`write_all`] (and the `write_*` methods built on them),
+                /// and cleaned by [`flush`], [`flush_range`], [`flush_range_committed`] and `flush_scheduled`.
+                /// The asynchronous flushes do not clean them, as they do not wait for the data to be durable.
+                ///
+                /// The writes through the raw slice of [`as_mut_slice`] cannot be observed, so any call of it
+                /// (including by the writers and other methods built on it) conservatively marks the whole
+                /// mmap as dirty.
+                ///
+                #[doc = concat!("[`write`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#method.write")]
+                #[doc = concat!("[`write_all`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#method.write_all")]
+                #[doc = concat!("[`flush`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.flush")]
+                #[doc = concat!("[`flush_range`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.flush_range")]
+                #[doc = concat!("[`flush_range_committed`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#method.flush_range_committed")]
+                #[doc = concat!("[`as_mut_slice`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.as_mut_slice")]
+                pub fn dirty_ranges(&self) -> Vec<std::ops::Range<usize>> {
+                    self.dirty.to_vec()
+                }
+
+                /// Returns whether there are written bytes not flushed yet, see [`dirty_ranges`].
+                ///
+                #[doc = concat!("[`dirty_ranges`]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.dirty_ranges")]
+                #[inline]
+                pub fn is_dirty(&self) -> bool {
+                    self.dirty.is_dirty()
+                }
+
+                /// Sets the strategy to grow the mmap, which is also configured by `grow_increment`
+                /// of [`AsyncOptions`] when the mmap is created or opened, see [`grow_to_fit`].
+                ///
+                #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
+                #[doc = concat!("[`grow_to_fit`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#method.grow_to_fit")]
+                #[inline]
+                pub fn set_grow_strategy(&mut self, strategy: crate::GrowStrategy) {
+                    self.grow = strategy;
+                }
+
+                /// Sets the durability of [`flush`], which is also configured by `flush_policy` of
+                /// [`AsyncOptions`] when the mmap is created or opened. See [`FlushPolicy`] for the trade-offs.
+                ///
+                #[doc = concat!("[`flush`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.flush")]
+                #[doc = concat!("[`AsyncOptions`]: ", $path_str, "/struct.AsyncOptions.html")]
+                /// [`FlushPolicy`]: crate::FlushPolicy
+                #[inline]
+                pub fn set_flush_policy(&mut self, policy: crate::FlushPolicy) {
+                    self.flush_policy = policy;
+                }
+
+                /// Returns a [`AsyncMmapFileWriter`] starting at the [high-water mark], which advances
+                /// the high-water mark as it writes.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::EOF))` if there is no room after the high-water mark.
+                ///
+                #[doc = concat!("[`AsyncMmapFileWriter`]: ", $path_str, "/struct.AsyncMmapFileWriter.html")]
+                #[doc = concat!("[high-water mark]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.high_water_mark")]
+                pub fn writer_at_end(&mut self) -> Result<AsyncMmapFileWriter<'_>> {
+                    let offset = self.high_water;
+                    let buf = self.inner.as_mut_slice();
+                    let buf_len = buf.len();
+                    if buf_len <= offset {
+                        Err(Error::from(ErrorKind::EOF))
+                    } else {
+                        self.dirty.mark(offset..buf_len);
+                        self.generation += 1;
+                        Ok(AsyncMmapFileWriter::with_high_water(
+                            Cursor::new(&mut buf[offset..]),
+                            offset,
+                            buf_len - offset,
+                            &mut self.high_water,
+                        ))
+                    }
+                }
+
+                /// Zeroes the bytes from the [high-water mark] to the end of the mmap, then flushes them.
+                ///
+                /// The tail of an over-allocated mmap (e.g. created with a `max_size` larger than the
+                /// bytes written) is not guaranteed to be zero, so this gives deterministic content
+                /// before flushing, freezing or hashing the mmap. Only the writes tracked by the
+                /// high-water mark are preserved.
+                ///
+                /// [high-water mark]: structs.AsyncMmapFileMut.html#method.high_water_mark
+                pub fn zero_unwritten(&mut self) -> Result<()> {
+                    let offset = self.high_water;
+                    let buf = self.inner.as_mut_slice();
+                    let buf_len = buf.len();
+                    if buf_len <= offset {
+                        return Ok(());
+                    }
+                    buf[offset..].fill(0);
+                    self.flush_range(offset, buf_len - offset)
+                }
+
+                /// Returns the number of bytes written, which is the [high-water mark] [`zero_unwritten`]
+                /// zeroes the tail from. The bytes after it are the unwritten tail of the mmap.
+                ///
+                /// [high-water mark]: structs.AsyncMmapFileMut.html#method.high_water_mark
+                /// [`zero_unwritten`]: structs.AsyncMmapFileMut.html#method.zero_unwritten
+                #[inline]
+                pub fn written_len(&self) -> usize {
+                    self.high_water
+                }
+
+                /// Shrinks the mmap to the [high-water mark], reclaiming the room allocated but never written,
+                /// e.g. after creating the file with an over-estimated `max_size`.
+                ///
+                /// - __Disk__: the file is truncated and remapped, then its parent directory is synced,
+                ///   so the new length is durable.
+                /// - __Disk (copy-on-write)__: the file cannot be truncated, so the written bytes are copied
+                ///   into an in-memory mmap, which replaces the mapping. The file is left untouched.
+                /// - __Memory__: the buffer is shrunk.
+                ///
+                /// Does nothing if there is no room after the high-water mark.
+                ///
+                /// # Errors
+                /// Returns the error returned by [`truncate`], or by syncing the parent directory.
+                ///
+                #[doc = concat!("[high-water mark]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.high_water_mark")]
+                #[doc = concat!("[`truncate`]: ", $path_str, "/trait.AsyncMmapFileMutExt.html#tymethod.truncate")]
+                pub async fn shrink_to_fit(&mut self) -> Result<()> {
+                    let len = self.high_water;
+                    if self.inner.len() <= len {
+                        return Ok(());
+                    }
+
+                    match &self.inner {
+                        AsyncMmapFileMutInner::Disk(disk) if disk.is_cow() => {
+                            let memory = AsyncMemoryMmapFileMut::from_slice(disk.path(), &disk.as_slice()[..len]);
+                            self.inner = AsyncMmapFileMutInner::Memory(memory);
+                            self.dirty.clamp(len);
+                            self.generation += 1;
+                            Ok(())
+                        }
+                        AsyncMmapFileMutInner::Disk(_) => {
+                            self.truncate(len as u64).await?;
+                            sync_parent_async(self.path()).await
+                        }
+                        _ => self.truncate(len as u64).await,
+                    }
+                }
+
+                #[inline]
+                fn resolve_truncate_size(&self, max_sz: i64) -> i64 {
+                    if max_sz == Self::TRUNCATE_TO_HIGH_WATER_MARK {
+                        self.high_water as i64
+                    } else {
+                        max_sz
+                    }
+                }
+
                 /// Close the file. It would also truncate the file if max_sz >= 0.
+                ///
+                /// If `max_sz` is [`AsyncMmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`], the file is truncated to the [high-water mark].
+                ///
+                #[doc = concat!("[`AsyncMmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`]: ", $path_str, "/struct.AsyncMmapFileMut.html#associatedconstant.TRUNCATE_TO_HIGH_WATER_MARK")]
+                #[doc = concat!("[high-water mark]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.high_water_mark")]
                 #[inline]
                 pub async fn close(&mut self, max_sz: i64) -> Result<()> {
+                    let max_sz = self.resolve_truncate_size(max_sz);
                     let empty = AsyncMmapFileMutInner::Empty(AsyncEmptyMmapFile::default());
                     // swap the inner to empty
                     let inner = mem::replace(&mut self.inner, empty);
@@ -2175,13 +4631,66 @@ cfg_async! {
                     }
                 }
 
+                /// Close the mmap and returns the underlying file, so the file can be reused
+                /// (e.g. for further appends) without reopening it by path.
+                /// It would also truncate the file if max_sz >= 0.
+                ///
+                /// # Errors
+                /// If the inner is not a real file, e.g. in-memory, it would return
+                /// `Err(Error::from(ErrorKind::NotSupported))` and leave the mmap untouched.
+                /// If the file cannot be flushed or truncated, the mmap is left mapped as well.
+                pub async fn close_into_file(&mut self, max_sz: i64) -> Result<File> {
+                    let max_sz = self.resolve_truncate_size(max_sz);
+                    match &mut self.inner {
+                        // flush and truncate before the swap, so the mmap is left untouched if either fails
+                        AsyncMmapFileMutInner::Disk(disk) => {
+                            disk.flush()?;
+                            if max_sz >= 0 {
+                                disk.unmap_and_set_len(max_sz as u64).await?;
+                            }
+                        },
+                        _ => return Err(Error::from(ErrorKind::NotSupported)),
+                    }
+
+                    let empty = AsyncMmapFileMutInner::Empty(AsyncEmptyMmapFile::default());
+                    // swap the inner to empty
+                    let inner = mem::replace(&mut self.inner, empty);
+                    match inner {
+                        AsyncMmapFileMutInner::Disk(disk) => {
+                            drop(disk.mmap);
+                            Ok(disk.file)
+                        },
+                        _ => unreachable!(),
+                    }
+                }
+
                 /// Remove the underlying file without dropping, leaving an [`AsyncEmptyMmapFile`].
+                ///
+                /// # Errors
+                /// On unix, the file is removed before it is unmapped, so if the removal fails
+                /// (e.g. no permission), the mmap is left intact and the removal can be retried,
+                /// or deferred by [`set_remove_on_drop`]. On other platforms, the file must be closed
+                /// before it is removed, so the mmap is left empty even if the removal fails.
+                ///
+                /// [`set_remove_on_drop`]: structs.AsyncMmapFileMut.html#method.set_remove_on_drop
                 #[inline]
                 pub async fn remove(&mut self) -> Result<()> {
                     let empty = AsyncMmapFileMutInner::Empty(AsyncEmptyMmapFile::default());
                     // swap the inner to empty
                     let inner = mem::replace(&mut self.inner, empty);
                     match inner {
+                        #[cfg(unix)]
+                        AsyncMmapFileMutInner::Disk(disk) => {
+                            if let Err(e) = remove_file(&disk.path).await {
+                                // put the mmap back, so the caller can retry
+                                self.inner = AsyncMmapFileMutInner::Disk(disk);
+                                return Err(e.into());
+                            }
+                            drop(disk.mmap);
+                            // release the space even if the file is still open elsewhere
+                            disk.file.set_len(0).await.map_err(From::from)
+                        },
+                        #[cfg(not(unix))]
                         AsyncMmapFileMutInner::Disk(disk) => {
                             let path = disk.path;
                             drop(disk.mmap);
@@ -2196,11 +4705,231 @@ cfg_async! {
                         _ => Ok(()),
                     }
                 }
+
+                /// Set the length of the underlying file, keeping the current mapping when it is
+                /// shrinking within the mapped region.
+                ///
+                /// This is a faster path than [`truncate`] for logically truncating a disk mmap, e.g. in
+                /// append-truncate loops. For in-memory mmaps, this is the same as [`truncate`].
+                ///
+                /// See [`AsyncDiskMmapFileMut::set_file_len`] for the safety boundary.
+                ///
+                /// [`truncate`]: trait.AsyncMmapFileMutExt.html#tymethod.truncate
+                #[doc = concat!("[`AsyncDiskMmapFileMut::set_file_len`]: ../raw/", $path_str, "/struct.AsyncDiskMmapFileMut.html#method.set_file_len")]
+                pub async fn set_file_len(&mut self, new_len: u64) -> Result<()> {
+                    match &mut self.inner {
+                        AsyncMmapFileMutInner::Disk(disk) => disk.set_file_len(new_len).await?,
+                        inner => inner.truncate(new_len).await?,
+                    }
+                    self.high_water = self.high_water.min(new_len as usize);
+                    Ok(())
+                }
+
+                /// Overwrite the content of the mmap, then remove the underlying file.
+                ///
+                /// For a disk mmap, the content is overwritten `passes` times (at least once), the
+                /// patterns alternate between `0xFF` and `0x00` and the last pass always writes zeros.
+                /// The mmap is flushed and the file is fsynced after each pass. Then the file is removed
+                /// and its parent directory is fsynced.
+                ///
+                /// For in-memory and copy-on-write mmaps, the content is zeroed and the mmap is dropped,
+                /// the underlying file of a copy-on-write mmap is left untouched.
+                ///
+                /// # Notes
+                /// Overwriting in place does not guarantee the old data is unrecoverable. SSDs
+                /// (wear-leveling), copy-on-write or journaling filesystems (e.g. btrfs, ZFS) and
+                /// snapshots may keep the old blocks elsewhere on the device.
+                pub async fn secure_remove(self, passes: u8) -> Result<()> {
+                    self.secure_remove_with(passes, crate::WipePattern::Alternating).await
+                }
+
+                /// Overwrite the content of the mmap with the pattern, then remove the underlying file.
+                ///
+                /// The same as [`secure_remove`], except the passes before the last one write the
+                /// [`WipePattern`], e.g. pseudo-random bytes. The last pass always writes zeros.
+                ///
+                /// [`secure_remove`]: #method.secure_remove
+                /// [`WipePattern`]: crate::WipePattern
+                pub async fn secure_remove_with(mut self, passes: u8, pattern: crate::WipePattern) -> Result<()> {
+                    let path = match &mut self.inner {
+                        AsyncMmapFileMutInner::Disk(disk) if !disk.is_cow() => {
+                            let path = canonicalize(&disk.path)
+                                .await
+                                .map_err(|e| Error::new(ErrorKind::IO, e))?;
+                            for pass in (0..passes.max(1)).rev() {
+                                pattern.fill(disk.as_mut_slice(), pass);
+                                disk.flush()?;
+                                disk.file
+                                    .sync_all()
+                                    .await
+                                    .map_err(|e| Error::new_source_msg(ErrorKind::SyncFileFailed, path.to_string_lossy(), e))?;
+                            }
+                            path
+                        },
+                        inner => {
+                            inner.as_mut_slice().fill(0);
+                            // nothing to remove, the mmap is dropped
+                            self.deleted = true;
+                            return Ok(());
+                        }
+                    };
+
+                    let empty = AsyncMmapFileMutInner::Empty(AsyncEmptyMmapFile::default());
+                    // swap the inner to empty
+                    let inner = mem::replace(&mut self.inner, empty);
+                    inner.drop_remove().await?;
+                    self.deleted = true;
+                    sync_dir_async(path.parent().unwrap()).await
+                }
+
+                /// Replace the file at `path` crash-atomically.
+                ///
+                /// A temporary file is created next to `path` and mapped with `opts`, then `fill`
+                /// writes the new content into it. After the map is flushed and the temporary file
+                /// is fsynced, it is renamed over `path` and the parent directory is fsynced, so
+                /// readers observe either the old content or the new content, never a mix.
+                ///
+                /// If any step fails, the temporary file is removed and `path` is left untouched.
+                ///
+                /// # Examples
+                ///
+                #[doc = "```ignore"]
+                #[doc = concat!("use fmmap::", $path_str, "::{AsyncMmapFileMut, AsyncMmapFileMutExt, AsyncOptions};")]
+                /// # use scopeguard::defer;
+                ///
+                #[doc = concat!("# ", $doc_test_runtime, "::block_on(async {")]
+                #[doc = concat!("AsyncMmapFileMut::replace_atomically(\"", $filename_prefix, "_replace_atomically_test.txt\", AsyncOptions::new().max_size(12), |file| {")]
+                ///     file.write_all("some data...".as_bytes(), 0)
+                /// }).await.unwrap();
+                #[doc = concat!("# defer!(std::fs::remove_file(\"", $filename_prefix, "_replace_atomically_test.txt\").unwrap());")]
+                /// # })
+                #[doc = "```"]
+                pub async fn replace_atomically<P, F>(path: P, opts: AsyncOptions, fill: F) -> Result<()>
+                where
+                    P: AsRef<Path>,
+                    F: FnOnce(&mut AsyncMmapFileMut) -> Result<()>,
+                {
+                    let path = path.as_ref();
+                    let mut tmp_name = std::ffi::OsString::from(".");
+                    tmp_name.push(path.file_name().unwrap_or_default());
+                    tmp_name.push(format!(".{}.{}.tmp", std::process::id(), crate::utils::next_temp_seq()));
+                    let tmp_path = path.with_file_name(tmp_name);
+
+                    let mut file = Self::create_with_options(&tmp_path, opts).await?;
+                    let rst = async {
+                        fill(&mut file)?;
+                        let tmp = file.close_into_file(-1).await?;
+                        tmp.sync_all()
+                            .await
+                            .map_err(|e| Error::new(ErrorKind::SyncFileFailed, e))?;
+                        drop(tmp);
+                        rename(&tmp_path, path)
+                            .await
+                            .map_err(|e| Error::new(ErrorKind::IO, e))
+                    }.await;
+                    drop(file);
+
+                    match rst {
+                        Ok(_) => sync_parent_async(path).await,
+                        Err(e) => {
+                            let _ = remove_file(&tmp_path).await;
+                            Err(e)
+                        }
+                    }
+                }
+
+                /// Creates a hidden temporary file in `dir` and maps it with `opts`, for building a file
+                /// which only becomes visible once it is complete.
+                ///
+                /// Write the content through the returned map, flush it, then publish the file with
+                /// [`TempHandle::persist_as`]. If the handle is dropped without being persisted, the
+                /// temporary file is removed. Unlike [`replace_atomically`], the content is not built
+                /// in a closure, and the final path is only chosen when the file is published.
+                ///
+                /// The map stays valid after the file is persisted (or removed), but its `path`
+                /// still reports the temporary path.
+                ///
+                /// # Examples
+                ///
+                #[doc = "```ignore"]
+                #[doc = concat!("use fmmap::", $path_str, "::{AsyncMmapFileMut, AsyncMmapFileMutExt, AsyncOptions};")]
+                /// # use scopeguard::defer;
+                ///
+                #[doc = concat!("# ", $doc_test_runtime, "::block_on(async {")]
+                /// let (mut file, handle) = AsyncMmapFileMut::open_temp(".", AsyncOptions::new().max_size(12)).await.unwrap();
+                /// file.write_all("some data...".as_bytes(), 0).unwrap();
+                /// file.flush().unwrap();
+                #[doc = concat!("handle.persist_as(\"", $filename_prefix, "_open_temp_test.txt\").await.unwrap();")]
+                #[doc = concat!("# defer!(std::fs::remove_file(\"", $filename_prefix, "_open_temp_test.txt\").unwrap());")]
+                /// # })
+                #[doc = "```"]
+                ///
+                #[doc = concat!("[`TempHandle::persist_as`]: ", $path_str, "/struct.TempHandle.html#method.persist_as")]
+                /// [`replace_atomically`]: #method.replace_atomically
+                pub async fn open_temp<P: AsRef<Path>>(dir: P, opts: AsyncOptions) -> Result<(Self, TempHandle)> {
+                    let seq = crate::utils::next_temp_seq();
+                    let path = dir.as_ref().join(format!(".fmmap.{}.{}.tmp", std::process::id(), seq));
+                    let file = Self::create_with_options(&path, opts).await?;
+                    Ok((file, TempHandle { path, persisted: false }))
+                }
             }
 
             impl_constructor_for_memory_mmap_file_mut!(AsyncMemoryMmapFileMut, AsyncMmapFileMut, "AsyncMmapFileMut", $path_str);
 
             impl_drop!(AsyncMmapFileMut, AsyncMmapFileMutInner, AsyncEmptyMmapFile);
+
+            /// The guard of a temporary file created by [`AsyncMmapFileMut::open_temp`], which
+            /// publishes the file with [`persist_as`], or removes it when dropped.
+            ///
+            #[doc = concat!("[`AsyncMmapFileMut::open_temp`]: ", $path_str, "/struct.AsyncMmapFileMut.html#method.open_temp")]
+            /// [`persist_as`]: #method.persist_as
+            #[must_use = "if unused the temporary file will immediately be removed"]
+            #[derive(Debug)]
+            pub struct TempHandle {
+                path: PathBuf,
+                persisted: bool,
+            }
+
+            impl TempHandle {
+                /// Returns the path of the temporary file.
+                #[inline]
+                pub fn path(&self) -> &Path {
+                    self.path.as_ref()
+                }
+
+                /// Publishes the temporary file at `path`: the file is fsynced, atomically renamed to
+                /// `path` (replacing an existing file), then the parent directory of `path` is fsynced.
+                ///
+                /// The map should be flushed before, the fsync only makes the flushed content durable.
+                ///
+                /// # Errors
+                /// Returns `Err(Error::from(ErrorKind::SyncFileFailed))` if the file cannot be fsynced,
+                /// `Err(Error::from(ErrorKind::IO))` if it cannot be renamed, in both cases the temporary
+                /// file is removed. An error of fsyncing the parent directory is returned after the rename.
+                pub async fn persist_as<P: AsRef<Path>>(mut self, path: P) -> Result<()> {
+                    let path = path.as_ref();
+                    File::open(&self.path)
+                        .await
+                        .map_err(|e| Error::new_source_msg(ErrorKind::SyncFileFailed, self.path.to_string_lossy(), e))?
+                        .sync_all()
+                        .await
+                        .map_err(|e| Error::new_source_msg(ErrorKind::SyncFileFailed, self.path.to_string_lossy(), e))?;
+                    rename(&self.path, path)
+                        .await
+                        .map_err(|e| Error::new_source_msg(ErrorKind::IO, path.to_string_lossy(), e))?;
+                    self.persisted = true;
+                    sync_parent_async(path).await
+                }
+            }
+
+            impl Drop for TempHandle {
+                fn drop(&mut self) {
+                    if !self.persisted {
+                        // best-effort, like the removal of `remove_on_drop`
+                        let _ = std::fs::remove_file(&self.path);
+                    }
+                }
+            }
         };
     }
 
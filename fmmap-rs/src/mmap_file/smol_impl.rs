@@ -2,14 +2,19 @@ use std::borrow::Cow;
 use std::mem;
 use std::path::{Path, PathBuf};
 use async_trait::async_trait;
-use smol::fs::remove_file;
+use smol::fs::{canonicalize, remove_file, rename, File};
 use smol::io::{Cursor, AsyncWriteExt};
 use crate::smol::{AsyncMmapFileReader, AsyncMmapFileWriter, AsyncOptions};
+#[cfg(unix)]
+use crate::smol::FlushScheduler;
 use crate::disk::smol_impl::{AsyncDiskMmapFile, AsyncDiskMmapFileMut};
 use crate::empty::smol_impl::AsyncEmptyMmapFile;
 use crate::error::{Error, ErrorKind, Result};
 use crate::memory::smol_impl::{AsyncMemoryMmapFile, AsyncMemoryMmapFileMut};
 use crate::metadata::MetaData;
+use crate::utils::smol::{spawn_with_timeout, sync_dir_async, sync_parent_async};
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+use crate::utils::smol::spawn_blocking;
 
 declare_async_mmap_file_ext!(AsyncDiskMmapFileMut, AsyncOptions, AsyncMmapFileReader);
 
@@ -21,4 +26,37 @@ declare_and_impl_async_mmap_file!("smol_async", "smol", "smol");
 
 delcare_and_impl_async_mmap_file_mut!("smol_async", "smol", "smol");
 
-impl_async_tests!("smol_async", smol_potat::test, smol, AsyncMmapFile, AsyncMmapFileMut);
\ No newline at end of file
+impl_async_tests!("smol_async", smol_potat::test, smol, AsyncMmapFile, AsyncMmapFileMut);
+
+#[smol_potat::test]
+async fn test_close_into_file() {
+    use smol::io::AsyncReadExt;
+
+    let path = "smol_async_close_into_file.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+        .await
+        .unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    let mut f = file.close_into_file(12).await.unwrap();
+    assert!(file.is_empty());
+    assert_eq!(f.metadata().await.unwrap().len(), 12);
+    f.write_all("more data...".as_bytes()).await.unwrap();
+    f.flush().await.unwrap();
+    drop(f);
+
+    let mut buf = String::new();
+    File::open(path)
+        .await
+        .unwrap()
+        .read_to_string(&mut buf)
+        .await
+        .unwrap();
+    assert_eq!(buf, "some data...more data...");
+
+    let mut file = AsyncMmapFileMut::memory_with_capacity("foo.mem", 10);
+    assert_eq!(
+        file.close_into_file(-1).await.unwrap_err().kind(),
+        ErrorKind::NotSupported
+    );
+}
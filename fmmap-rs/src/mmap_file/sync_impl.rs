@@ -1,15 +1,60 @@
+use crate::anonymous::AnonymousMmapFileMut;
+use crate::dirty::DirtyRanges;
 use crate::disk::{DiskMmapFile, DiskMmapFileMut};
 use crate::empty::EmptyMmapFile;
 use crate::error::{Error, ErrorKind, Result};
 use crate::memory::{MemoryMmapFile, MemoryMmapFileMut};
 use crate::metadata::MetaData;
-use crate::options::Options;
-use crate::{MmapFileReader, MmapFileWriter};
+use crate::options::{Options, WipePattern};
+use crate::{BufferedMmapWriter, MmapBufReader, MmapFileReader, MmapFileWriter, OwnedMmapReader};
 use std::borrow::Cow;
+use std::fs::File;
 use std::io::{Cursor, Write};
 use std::mem;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
+/// A RAII guard of a lock on the file, the file is unlocked when the guard is dropped.
+///
+/// This struct is created by the `lock_exclusive_guard` and `lock_shared_guard` methods of [`MmapFileExt`].
+#[must_use = "if unused the file will immediately unlock"]
+pub struct LockGuard<'a> {
+    file: &'a dyn Unlock,
+}
+
+impl<'a> LockGuard<'a> {
+    /// Unlocks the file, returning the error of the unlock, which is ignored when the guard is dropped.
+    pub fn unlock(self) -> Result<()> {
+        let file = self.file;
+        mem::forget(self);
+        file.unlock_file()
+    }
+}
+
+impl<'a> Drop for LockGuard<'a> {
+    fn drop(&mut self) {
+        // unlock and ignore the result
+        let _ = self.file.unlock_file();
+    }
+}
+
+impl<'a> std::fmt::Debug for LockGuard<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LockGuard").finish_non_exhaustive()
+    }
+}
+
+/// `MmapFileExt` is not object safe, so the guard unlocks the file through this trait.
+trait Unlock {
+    fn unlock_file(&self) -> Result<()>;
+}
+
+impl<T: MmapFileExt> Unlock for T {
+    fn unlock_file(&self) -> Result<()> {
+        self.unlock()
+    }
+}
+
 /// Utility methods to [`MmapFile`]
 ///
 /// [`MmapFile`]: structs.MmapFile.html
@@ -23,9 +68,49 @@ pub trait MmapFileExt {
         self.len() == 0
     }
 
+    /// Returns the length of the mapped region, which is the same as [`len`].
+    ///
+    /// When the mmap is created with an `offset` or a `len` in the options, only a part of the
+    /// file is mapped, so the mapped length can be smaller than the file length reported by
+    /// [`metadata`]. Always use the mapped length as the bound when reading the mmap.
+    ///
+    /// [`len`]: #tymethod.len
+    /// [`metadata`]: #tymethod.metadata
+    fn mapped_len(&self) -> usize {
+        self.len()
+    }
+
     /// Returns the underlying slice of the mmap
     fn as_slice(&self) -> &[u8];
 
+    /// Returns the underlying slice of the mmap, or `None` if the mmap is closed.
+    ///
+    /// Unlike [`as_slice`], which returns an empty slice for a closed mmap, this method
+    /// lets the caller distinguish a genuinely empty file from an invalidated mmap.
+    /// See [`is_closed`] for the states which produce `None`.
+    ///
+    /// [`as_slice`]: #tymethod.as_slice
+    /// [`is_closed`]: #method.is_closed
+    fn as_slice_checked(&self) -> Option<&[u8]> {
+        if self.is_closed() {
+            None
+        } else {
+            Some(self.as_slice())
+        }
+    }
+
+    /// Returns whether the mmap is closed.
+    ///
+    /// A mmap is closed when its inner has been swapped to the empty placeholder, which
+    /// happens after [`MmapFileMut::close`] or [`MmapFileMut::remove`], including when
+    /// those methods return an error part way through.
+    ///
+    /// [`MmapFileMut::close`]: struct.MmapFileMut.html#method.close
+    /// [`MmapFileMut::remove`]: struct.MmapFileMut.html#method.remove
+    fn is_closed(&self) -> bool {
+        false
+    }
+
     /// slice returns data starting from offset off of size sz.
     ///
     /// # Panics
@@ -35,6 +120,149 @@ pub trait MmapFileExt {
         &self.as_slice()[offset..offset + sz]
     }
 
+    /// Returns the first `n` bytes of the mmap.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the mmap is shorter than `n`.
+    fn prefix(&self, n: usize) -> Result<&[u8]> {
+        self.as_slice()
+            .get(..n)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))
+    }
+
+    /// Returns the last `n` bytes of the mmap.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the mmap is shorter than `n`.
+    fn suffix(&self, n: usize) -> Result<&[u8]> {
+        let buf = self.as_slice();
+        buf.len()
+            .checked_sub(n)
+            .map(|start| &buf[start..])
+            .ok_or_else(|| Error::from(ErrorKind::EOF))
+    }
+
+    /// Verifies the mmap starts with `expected` (e.g. the magic number of a format),
+    /// and returns the remaining bytes after it.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::BadFormat))` if the mmap does not start with `expected`.
+    fn strip_prefix(&self, expected: &[u8]) -> Result<&[u8]> {
+        self.as_slice()
+            .strip_prefix(expected)
+            .ok_or_else(|| Error::from(ErrorKind::BadFormat))
+    }
+
+    /// Detects the format of the file by the magic number at its start (e.g. ELF, PNG, ZIP),
+    /// see [`KnownMagic`] for the known formats. Returns `None` if the format is unknown.
+    ///
+    /// [`KnownMagic`]: crate::KnownMagic
+    fn sniff(&self) -> Option<crate::KnownMagic> {
+        crate::KnownMagic::detect(self.as_slice())
+    }
+
+    /// Returns an iterator over the length-prefixed records from `from`, each record is a big-endian
+    /// `u32` length followed by the payload, see [`LenPrefixed`].
+    ///
+    /// [`LenPrefixed`]: crate::LenPrefixed
+    fn iter_len_prefixed(&self, from: usize) -> crate::LenPrefixed<'_> {
+        crate::LenPrefixed::u32_be(self.as_slice(), from)
+    }
+
+    /// The same as [`iter_len_prefixed`], but the lengths are little-endian `u32`.
+    ///
+    /// [`iter_len_prefixed`]: #method.iter_len_prefixed
+    fn iter_len_prefixed_le(&self, from: usize) -> crate::LenPrefixed<'_> {
+        crate::LenPrefixed::u32_le(self.as_slice(), from)
+    }
+
+    /// The same as [`iter_len_prefixed`], but the lengths are unsigned LEB128 varints.
+    ///
+    /// [`iter_len_prefixed`]: #method.iter_len_prefixed
+    fn iter_len_prefixed_varint(&self, from: usize) -> crate::LenPrefixed<'_> {
+        crate::LenPrefixed::varint(self.as_slice(), from)
+    }
+
+    /// Loads the length published by [`atomic_append`] in the 8-byte header at `len_header_offset`,
+    /// with the `Acquire` ordering, the published records are the bytes right after the header
+    /// up to that length.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the header is out of the bound of the mmap,
+    /// and `Err(Error::from(ErrorKind::Misaligned))` if the header is not 8-byte aligned.
+    ///
+    /// [`atomic_append`]: trait.MmapFileMutExt.html#method.atomic_append
+    #[cfg(target_has_atomic = "64")]
+    fn published_len(&self, len_header_offset: usize) -> Result<u64> {
+        crate::utils::load_acquire_u64_at(self.as_slice(), len_header_offset)
+    }
+
+    /// Returns an `xxd`-style dump of the bytes in `range`: 16 bytes per line, the offset in
+    /// the mmap, the bytes in hex and the printable ASCII bytes. This is a debug aid, e.g. to
+    /// inspect a file format under development, the whole range is formatted into one `String`.
+    ///
+    /// The range is clamped to the length of the mmap instead of panicking, an empty or out of
+    /// bounds range gives an empty dump.
+    fn hexdump_range(&self, range: std::ops::Range<usize>) -> String {
+        let len = self.len();
+        let end = range.end.min(len);
+        let start = range.start.min(end);
+        crate::utils::hexdump(&self.as_slice()[start..end], start)
+    }
+
+    /// Returns an `xxd`-style dump of the mmap, see [`hexdump_range`].
+    ///
+    /// At most the first 4 KiB are dumped, the number of the bytes left out is reported on
+    /// the last line, use [`hexdump_range`] to dump more.
+    ///
+    /// [`hexdump_range`]: #method.hexdump_range
+    fn hexdump(&self) -> String {
+        let cap = crate::utils::HEXDUMP_DEFAULT_CAP;
+        let mut out = self.hexdump_range(0..cap);
+        if self.len() > cap {
+            out.push_str(&format!("... {} more bytes\n", self.len() - cap));
+        }
+        out
+    }
+
+    /// Returns the first line of the mmap without its `\n` (or `\r\n`) terminator, and the bytes
+    /// after the terminator. Returns `None` if the mmap is empty.
+    fn split_first_line(&self) -> Option<(&[u8], &[u8])> {
+        crate::mmap_file::split_first_line(self.as_slice())
+    }
+
+    /// Returns a zero-copy iterator over the lines of the mmap, see [`Lines`].
+    ///
+    /// A trailing `\r` is stripped from each line, so CRLF and LF text behave the same.
+    ///
+    /// [`Lines`]: crate::Lines
+    fn lines(&self) -> crate::Lines<'_> {
+        crate::Lines::new(self.as_slice())
+    }
+
+    /// Returns an iterator over the lines of the mmap as `&str`, each line is validated
+    /// as UTF-8 on its own, see [`lines`].
+    ///
+    /// [`lines`]: #method.lines
+    fn lines_str(&self) -> crate::LinesStr<'_> {
+        let from_utf8: fn(&[u8]) -> std::result::Result<&str, std::str::Utf8Error> = std::str::from_utf8;
+        self.lines().map(from_utf8)
+    }
+
+    /// Faults in the pages of `range` so the following reads do not block on the disk, e.g. before
+    /// a latency-sensitive scan. Returns an accumulator of the bytes touched, which only exists to
+    /// keep the touches from being optimized away.
+    ///
+    /// One byte of each page is read with a volatile read, which works on all platforms (including
+    /// macOS and Windows, which lack a good prefetch system call). On Linux, `madvise(MADV_WILLNEED)`
+    /// is issued first, so the pages are read ahead in batches. The range is clamped to the length
+    /// of the mmap.
+    fn warm(&self, range: std::ops::Range<usize>) -> u64 {
+        let end = range.end.min(self.len());
+        let start = range.start.min(end);
+        crate::utils::warm(&self.as_slice()[start..end])
+    }
+
     /// bytes returns data starting from offset off of size sz.
     ///
     /// # Errors
@@ -49,6 +277,62 @@ pub trait MmapFileExt {
         }
     }
 
+    /// Returns the data starting from offset of size sz, borrowed if it is contiguous in the
+    /// backing storage, otherwise copied.
+    ///
+    /// A single mmap is always contiguous, so it always returns `Cow::Borrowed`, the same as
+    /// [`bytes`]. It is for the code generic over both single mmaps and segmented backings,
+    /// which have to copy a range crossing the boundary of the segments.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if there's not enough data.
+    ///
+    /// [`bytes`]: #method.bytes
+    #[inline]
+    fn get(&self, offset: usize, sz: usize) -> Result<Cow<'_, [u8]>> {
+        self.bytes(offset, sz).map(Cow::Borrowed)
+    }
+
+    /// Returns a reference to the header of type `H` stored in the first `size_of::<H>()` bytes of the mmap.
+    ///
+    /// # Errors
+    /// If the mmap is shorter than `H`, it would return `Err(Error::from(ErrorKind::EOF))`.
+    /// If the mmap is not properly aligned for `H`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+    #[cfg(feature = "zerocopy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+    fn header<H>(&self) -> Result<&H>
+    where
+        H: zerocopy::FromBytes + zerocopy::KnownLayout + zerocopy::Immutable,
+    {
+        let buf = self.as_slice();
+        if buf.len() < mem::size_of::<H>() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        H::ref_from_prefix(buf)
+            .map(|(h, _)| h)
+            .map_err(|_| Error::from(ErrorKind::Misaligned))
+    }
+
+    /// Returns the whole mmap as a slice of `T`, e.g. an array of fixed-size records.
+    ///
+    /// # Errors
+    /// If the length of the mmap is not a multiple of `size_of::<T>()`, it would return `Err(Error::from(ErrorKind::EOF))`.
+    /// If the mmap is not properly aligned for `T`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+    #[cfg(feature = "zerocopy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+    fn as_slice_of<T>(&self) -> Result<&[T]>
+    where
+        T: zerocopy::FromBytes + zerocopy::Immutable,
+    {
+        let buf = self.as_slice();
+        let size = mem::size_of::<T>();
+        if size != 0 && buf.len() % size != 0 {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        <[T] as zerocopy::FromBytes>::ref_from_bytes(buf)
+            .map_err(|_| Error::from(ErrorKind::Misaligned))
+    }
+
     /// Returns the path of the inner file.
     fn path(&self) -> &Path;
 
@@ -73,11 +357,87 @@ pub trait MmapFileExt {
     /// This structure is returned from the metadata or
     /// symlink_metadata function or method and represents
     /// known metadata about a file such as its permissions, size, modification times, etc
+    ///
+    /// # Notes
+    /// The length in the metadata is the length of the whole underlying file, which can be larger than
+    /// the mapped region, see [`mapped_len`].
+    ///
+    /// [`mapped_len`]: #method.mapped_len
     fn metadata(&self) -> Result<MetaData>;
 
     /// Whether the mmap is executable.
     fn is_exec(&self) -> bool;
 
+    /// Returns the kind of the storage backing the mmap, e.g. to skip durability
+    /// assertions for in-memory mmaps, whose flushes do nothing.
+    fn kind(&self) -> crate::MapKind;
+
+    /// Returns whether the mmap is backed by a real file.
+    #[inline]
+    fn is_disk(&self) -> bool {
+        self.kind().is_disk()
+    }
+
+    /// Returns whether the mmap is an in-memory buffer.
+    #[inline]
+    fn is_memory(&self) -> bool {
+        self.kind().is_memory()
+    }
+
+    /// Returns the page size (in bytes) which the mmap actually uses.
+    ///
+    /// Huge pages are never requested, so it is the page size of the system for all the kinds of mmap.
+    fn mapped_page_size(&self) -> usize {
+        crate::utils::page_size()
+    }
+
+    /// Returns the page size of the system, which is the granularity of [`page_ranges`].
+    ///
+    /// [`page_ranges`]: #method.page_ranges
+    #[inline]
+    fn page_size(&self) -> usize {
+        crate::utils::page_size()
+    }
+
+    /// Returns an iterator over the page-sized sub-ranges covering the mmap,
+    /// i.e. `[0, PAGE)`, `[PAGE, 2 * PAGE)`, ..., the last range is clamped to [`len`].
+    ///
+    /// The ranges are relative to the start of the mmap, which is page aligned unless
+    /// the mmap was created with an offset which is not a multiple of the page size.
+    ///
+    /// [`len`]: #tymethod.len
+    #[inline]
+    fn page_ranges(&self) -> crate::PageRanges {
+        crate::PageRanges::new(self.len(), self.page_size())
+    }
+
+    /// Splits the mmap into `n` contiguous, disjoint shards, e.g. to process them in parallel
+    /// by threads or rayon, since the shards are shared borrows.
+    ///
+    /// The boundaries between the shards are rounded down to multiples of [`page_size`], so the
+    /// shards are near-equal and no page is shared by two shards, the last shard absorbs the remainder.
+    /// When the mmap is shorter than `n` pages, some shards are empty.
+    ///
+    /// # Panics
+    /// Panics if `n` is 0.
+    ///
+    /// [`page_size`]: #method.page_size
+    fn shards(&self, n: usize) -> Vec<&[u8]> {
+        assert!(n > 0, "the number of shards must be greater than 0");
+        let buf = self.as_slice();
+        let page = self.page_size();
+        let mut shards = Vec::with_capacity(n);
+        let mut start = 0;
+        for i in 1..n {
+            let end = (buf.len() as u128 * i as u128 / n as u128) as usize;
+            let end = (end / page * page).max(start);
+            shards.push(&buf[start..end]);
+            start = end;
+        }
+        shards.push(&buf[start..]);
+        shards
+    }
+
     /// Copy the content of the mmap file to Vec
     #[inline]
     fn copy_all_to_vec(&self) -> Vec<u8> {
@@ -90,129 +450,737 @@ pub trait MmapFileExt {
         self.slice(offset, len).to_vec()
     }
 
-    /// Write the content of the mmap file to a new file.
+    /// Copy the content of the mmap file to [`Bytes`].
+    ///
+    /// An in-memory mmap file shares its underlying [`Bytes`] without copying,
+    /// otherwise the content is copied into a new [`Bytes`] in one allocation.
+    ///
+    /// [`Bytes`]: https://docs.rs/bytes/1.1.0/bytes/struct.Bytes.html
     #[inline]
-    fn write_all_to_new_file<P: AsRef<Path>>(&self, new_file_path: P) -> Result<()> {
-        let buf = self.as_slice();
-        let opts = Options::new().max_size(buf.len() as u64);
-        let mut mmap = DiskMmapFileMut::create_with_options(new_file_path, opts)?;
-        mmap.writer(0)?.write_all(buf)?;
-        mmap.flush()
+    fn copy_all_to_bytes(&self) -> bytes::Bytes {
+        bytes::Bytes::copy_from_slice(self.as_slice())
     }
 
-    /// Write a range of content of the mmap file to new file.
+    /// Copy a range of content of the mmap file to [`Bytes`].
+    ///
+    /// An in-memory mmap file shares the range of its underlying [`Bytes`] without copying,
+    /// otherwise the range is copied into a new [`Bytes`] in one allocation.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    ///
+    /// [`Bytes`]: https://docs.rs/bytes/1.1.0/bytes/struct.Bytes.html
     #[inline]
-    fn write_range_to_new_file<P: AsRef<Path>>(
-        &self,
-        new_file_path: P,
-        offset: usize,
-        len: usize,
-    ) -> Result<()> {
+    fn copy_range_to_bytes(&self, offset: usize, len: usize) -> Result<bytes::Bytes> {
         let buf = self.as_slice();
-        if buf.len() < offset + len {
-            return Err(Error::from(ErrorKind::EOF));
-        }
-        let opts = Options::new().max_size(len as u64);
-        let mut mmap = DiskMmapFileMut::create_with_options(new_file_path, opts)?;
-        mmap.writer(0)?.write_all(&buf[offset..offset + len])?;
-        mmap.flush()
+        offset
+            .checked_add(len)
+            .and_then(|end| buf.get(offset..end))
+            .map(bytes::Bytes::copy_from_slice)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))
     }
 
-    /// Returns a [`MmapFileReader`] which helps read data from mmap like a normal File.
+    /// Copies a range of content of the mmap file into an [`Arc<[u8]>`] in one allocation,
+    /// which can be handed to many owners without copying again.
+    ///
+    /// The range is always copied, even for an in-memory mmap file, because an [`Arc<[u8]>`]
+    /// cannot share the buffer of [`Bytes`]. Use [`copy_range_to_bytes`] to avoid the copy there.
     ///
     /// # Errors
-    /// If there's not enough data, it would return
-    ///  `Err(Error::from(ErrorKind::EOF))`.
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
     ///
-    /// [`MmapFileReader`]: structs.MmapFileReader.html
-    fn reader(&self, offset: usize) -> Result<MmapFileReader> {
+    /// [`Arc<[u8]>`]: std::sync::Arc
+    /// [`Bytes`]: https://docs.rs/bytes/1.1.0/bytes/struct.Bytes.html
+    /// [`copy_range_to_bytes`]: #method.copy_range_to_bytes
+    fn copy_range_to_arc(&self, offset: usize, len: usize) -> Result<std::sync::Arc<[u8]>> {
         let buf = self.as_slice();
-        if buf.len() < offset {
-            Err(Error::from(ErrorKind::EOF))
-        } else {
-            Ok(MmapFileReader::new(
-                Cursor::new(&buf[offset..]),
-                offset,
-                buf.len() - offset,
-            ))
-        }
+        offset
+            .checked_add(len)
+            .and_then(|end| buf.get(offset..end))
+            .map(std::sync::Arc::from)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))
     }
 
-    /// Returns a [`MmapFileReader`] base on the given `offset` and `len`, which helps read data from mmap like a normal File.
+    /// Folds `f` over the successive `chunk`-sized chunks of the mmap (the last chunk may be shorter),
+    /// starting from `init`.
     ///
-    /// # Errors
-    /// If there's not enough data, it would return
-    ///  `Err(Error::from(ErrorKind::EOF))`.
+    /// # Panics
+    /// Panics if `chunk` is 0.
     ///
-    /// [`MmapFileReader`]: structs.MmapFileReader.html
-    fn range_reader(&self, offset: usize, len: usize) -> Result<MmapFileReader> {
-        let buf = self.as_slice();
-        if buf.len() < offset + len {
-            Err(Error::from(ErrorKind::EOF))
-        } else {
-            Ok(MmapFileReader::new(
-                Cursor::new(&buf[offset..offset + len]),
-                offset,
-                len,
-            ))
-        }
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let zeros = file.fold_chunks(4096, 0usize, |acc, chunk| acc + chunk.iter().filter(|b| **b == 0).count());
+    /// ```
+    fn fold_chunks<T, F>(&self, chunk: usize, init: T, f: F) -> T
+    where
+        F: Fn(T, &[u8]) -> T,
+    {
+        self.as_slice().chunks(chunk).fold(init, f)
     }
 
-    /// Locks the file for exclusively usage, blocking if the file is currently locked.
+    /// The parallel version of [`fold_chunks`], the chunks are folded on the rayon thread pool,
+    /// and the partial results are merged by `combine`.
     ///
-    /// # Notes
-    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
-    fn lock_exclusive(&self) -> Result<()>;
+    /// `init` is cloned as the starting value of every partial fold, so it must be
+    /// the identity of `combine` (e.g. 0 for a sum), otherwise the result depends on how the
+    /// chunks are split between threads.
+    ///
+    /// # Panics
+    /// Panics if `chunk` is 0.
+    ///
+    /// [`fold_chunks`]: #method.fold_chunks
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    fn fold_chunks_par<T, F, C>(&self, chunk: usize, init: T, f: F, combine: C) -> T
+    where
+        T: Clone + Send + Sync,
+        F: Fn(T, &[u8]) -> T + Send + Sync,
+        C: Fn(T, T) -> T + Send + Sync,
+    {
+        use rayon::prelude::*;
+
+        self.as_slice()
+            .par_chunks(chunk)
+            .fold(|| init.clone(), &f)
+            .reduce(|| init.clone(), &combine)
+    }
 
-    /// Locks the file for shared usage, blocking if the file is currently locked exclusively.
+    /// Returns the CRC32 (IEEE) checksum of the whole mmap.
     ///
-    /// # Notes
-    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
-    fn lock_shared(&self) -> Result<()>;
+    /// When the `rayon` feature is enabled, the mmap is split into chunks which are
+    /// hashed in parallel and combined, otherwise the mmap is hashed on the current thread.
+    /// The result is the same in both cases.
+    #[cfg(feature = "crc32")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+    #[inline]
+    fn crc32_parallel(&self) -> u32 {
+        crate::checksum::crc32(self.as_slice())
+    }
 
-    /// Locks the file for exclusively usage, or returns a an error if the file is currently locked (see lock_contended_error).
+    /// Returns the chunked XXH3 (64 bit) digest of the whole mmap.
     ///
-    /// # Notes
-    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
-    fn try_lock_exclusive(&self) -> Result<()>;
+    /// The mmap is split into fixed-size chunks (hashed in parallel when the `rayon`
+    /// feature is enabled), and the chunk digests are folded into the final digest.
+    /// For mmaps larger than one chunk, the result is therefore not the plain XXH3 of the content,
+    /// but it does not depend on whether the `rayon` feature is enabled.
+    #[cfg(feature = "xxh3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "xxh3")))]
+    #[inline]
+    fn xxh3_parallel(&self) -> u64 {
+        crate::checksum::xxh3(self.as_slice())
+    }
 
-    /// Locks the file for shared usage, or returns a an error if the file is currently locked exclusively (see lock_contended_error).
+    /// Returns the Adler-32 checksum (as used by zlib) of the whole mmap.
+    #[inline]
+    fn adler32(&self) -> u32 {
+        crate::checksum::adler32(self.as_slice())
+    }
+
+    /// Returns the Adler-32 checksum (as used by zlib) of the range, e.g. to verify
+    /// the trailer of a zlib stream stored in the mmap.
     ///
-    /// # Notes
-    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
-    fn try_lock_shared(&self) -> Result<()>;
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    fn adler32_range(&self, range: Range<usize>) -> Result<u32> {
+        let buf = self.as_slice();
+        if range.start > range.end || range.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        Ok(crate::checksum::adler32(&buf[range]))
+    }
 
-    /// Unlocks the file.
+    /// Returns the digest of the whole mmap, computed by any [`Digest`] implementation
+    /// (e.g. `sha2::Sha256`).
     ///
-    /// # Notes
-    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
-    fn unlock(&self) -> Result<()>;
+    /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    #[inline]
+    fn digest<D: digest::Digest>(&self) -> digest::Output<D> {
+        D::digest(self.as_slice())
+    }
 
-    /// Read bytes to the dst buf from the offset, returns how many bytes read.
-    fn read(&self, dst: &mut [u8], offset: usize) -> usize {
-        let buf = self.as_slice();
+    /// Returns the digest of the range of the mmap, computed by any [`Digest`] implementation.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    ///
+    /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    fn digest_range<D: digest::Digest>(&self, range: std::ops::Range<usize>) -> Result<digest::Output<D>> {
+        self.as_slice()
+            .get(range)
+            .map(D::digest)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))
+    }
 
-        if buf.len() < offset {
-            0
-        } else {
-            let remaining = buf.len() - offset;
-            let dst_len = dst.len();
-            if remaining > dst_len {
-                dst.copy_from_slice(&buf[offset..offset + dst_len]);
-                dst_len
-            } else {
-                dst.copy_from_slice(&buf[offset..offset + remaining]);
-                remaining
-            }
+    /// Returns the Merkle root over the `block_size` blocks of the mmap, computed by any
+    /// [`Digest`] implementation.
+    ///
+    /// Each leaf is the digest of a block (the last block may be shorter), and each parent is the
+    /// digest of the concatenation of its two children. The last node of a level with an odd number
+    /// of nodes is promoted to the level above as is. An empty mmap has a single empty block.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::BadFormat))` if `block_size` is zero.
+    ///
+    /// [`Digest`]: https://docs.rs/digest/0.10/digest/trait.Digest.html
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    fn merkle_root<D: digest::Digest>(&self, block_size: usize) -> Result<digest::Output<D>> {
+        if block_size == 0 {
+            return Err(Error::new_with_message(ErrorKind::BadFormat, "block size must not be zero"));
         }
+        Ok(crate::checksum::merkle_root::<D>(self.as_slice(), block_size))
     }
 
-    /// Read the exact number of bytes required to fill buf.
-    fn read_exact(&self, dst: &mut [u8], offset: usize) -> Result<()> {
-        let buf = self.as_slice();
-        let remaining = buf.len().checked_sub(offset);
-        match remaining {
-            None => Err(Error::from(ErrorKind::EOF)),
+    /// Returns the Merkle proof of the block at `block_index`, i.e. the digests of its siblings
+    /// from the leaves up to (excluding) the root, as defined in [`merkle_root`].
+    ///
+    /// A promoted node has no sibling at its level, so the proof can be shorter than the height
+    /// of the tree; the verifier derives the promoted levels from the block index and the number of blocks.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::BadFormat))` if `block_size` is zero,
+    /// and `Err(Error::from(ErrorKind::EOF))` if the block is out of the bound of the mmap.
+    ///
+    /// [`merkle_root`]: #method.merkle_root
+    #[cfg(feature = "digest")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "digest")))]
+    fn merkle_proof<D: digest::Digest>(&self, block_index: usize, block_size: usize) -> Result<Vec<digest::Output<D>>> {
+        if block_size == 0 {
+            return Err(Error::new_with_message(ErrorKind::BadFormat, "block size must not be zero"));
+        }
+        crate::checksum::merkle_proof::<D>(self.as_slice(), block_size, block_index)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))
+    }
+
+    /// Returns the BLAKE3 hash of the whole mmap.
+    ///
+    /// When the `rayon` feature is enabled, the mmap is hashed in parallel by BLAKE3's own
+    /// multithreaded implementation. The result is the same in both cases.
+    #[cfg(feature = "blake3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+    fn blake3(&self) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        #[cfg(feature = "rayon")]
+        hasher.update_rayon(self.as_slice());
+        #[cfg(not(feature = "rayon"))]
+        hasher.update(self.as_slice());
+        hasher.finalize()
+    }
+
+    /// Returns the rsync-style checksums of the `block_size` blocks of the mmap (the last block may be
+    /// shorter): the weak rolling checksum (see [`RollingChecksum`]) and the strong checksum
+    /// (the first 16 bytes of the BLAKE3 hash) of each block.
+    ///
+    /// # Panics
+    /// Panics if `block_size` is 0.
+    ///
+    /// [`RollingChecksum`]: crate::RollingChecksum
+    #[cfg(feature = "blake3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blake3")))]
+    fn rolling_checksums(&self, block_size: usize) -> crate::BlockChecksums<'_> {
+        crate::BlockChecksums::new(self.as_slice(), block_size)
+    }
+
+    /// Copies the content into a new private anonymous mmap, which is detached from the file:
+    /// it has the page-aligned mmap semantics, and can be modified independently, but nothing
+    /// is ever written back to the file.
+    ///
+    /// Unlike `copy_all_to_vec`, the copy is backed by an mmap, not by the heap.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::MmapFailed))` if the anonymous mmap cannot be created.
+    fn to_anonymous(&self) -> Result<MmapFileMut> {
+        AnonymousMmapFileMut::copy_from_slice(self.path(), self.as_slice()).map(MmapFileMut::from)
+    }
+
+    /// Returns the resumable CRC32 (IEEE) state of the whole mmap.
+    ///
+    /// The state can be fed with the bytes appended later (see [`ChecksumState::update_from_range`]),
+    /// so the checksum of an append-only file is maintained without rehashing the whole mmap.
+    ///
+    /// [`ChecksumState::update_from_range`]: struct.ChecksumState.html#method.update_from_range
+    #[cfg(feature = "crc32")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+    #[inline]
+    fn checksum_incremental(&self) -> crate::ChecksumState {
+        let buf = self.as_slice();
+        crate::ChecksumState::with_initial(crate::checksum::crc32(buf), buf.len() as u64)
+    }
+
+    /// Verifies the trailing crc32 of the mmap.
+    ///
+    /// The last 4 bytes are read as a big-endian crc32 (IEEE) of the preceding bytes,
+    /// returns whether it matches the computed one.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the mmap is shorter than 4 bytes.
+    #[cfg(feature = "crc32")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+    fn verify_trailing_crc32(&self) -> Result<bool> {
+        let buf = self.as_slice();
+        let payload_len = buf
+            .len()
+            .checked_sub(4)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        let (payload, footer) = buf.split_at(payload_len);
+        let expected = u32::from_be_bytes(footer.try_into().unwrap());
+        Ok(crate::checksum::crc32(payload) == expected)
+    }
+
+    /// Same as [`verify_trailing_crc32`], but returns
+    /// `Err(Error::from(ErrorKind::ChecksumMismatch))` if the crc32 does not match.
+    ///
+    /// [`verify_trailing_crc32`]: #method.verify_trailing_crc32
+    #[cfg(feature = "crc32")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+    fn assert_trailing_crc32(&self) -> Result<()> {
+        if self.verify_trailing_crc32()? {
+            Ok(())
+        } else {
+            Err(Error::from(ErrorKind::ChecksumMismatch))
+        }
+    }
+
+    /// Reads the record of `payload_len` bytes at `offset`, which is followed by the big-endian
+    /// crc32 (IEEE) of the payload, and returns the payload only if the crc32 matches.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the payload and the crc32 are out of the bound
+    /// of the mmap, and `Err(Error::from(ErrorKind::ChecksumMismatch))` if the crc32 does not match,
+    /// e.g. the record is torn.
+    #[cfg(feature = "crc32")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "crc32")))]
+    fn bytes_verified_crc32(&self, offset: usize, payload_len: usize) -> Result<&[u8]> {
+        let buf = self.as_slice();
+        let end = offset
+            .checked_add(payload_len)
+            .filter(|end| matches!(end.checked_add(4), Some(n) if n <= buf.len()))
+            .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        let payload = &buf[offset..end];
+        let expected = u32::from_be_bytes(buf[end..end + 4].try_into().unwrap());
+        if crate::checksum::crc32(payload) == expected {
+            Ok(payload)
+        } else {
+            Err(Error::from(ErrorKind::ChecksumMismatch))
+        }
+    }
+
+    /// Returns an iterator over the offsets of all occurrences of `needle` in the mmap.
+    ///
+    /// The offsets are yielded lazily in ascending order, which makes this method suitable
+    /// for building indexes (e.g. the offsets of all newlines) without allocating.
+    #[inline]
+    fn byte_positions(&self, needle: u8) -> memchr::Memchr<'_> {
+        memchr::memchr_iter(needle, self.as_slice())
+    }
+
+    /// Returns an iterator over the offsets of all the non-overlapping occurrences of `needle`
+    /// in the mmap, starting the search at `from`.
+    ///
+    /// The offsets are absolute, and yielded lazily in ascending order, e.g. to rescan a corrupted
+    /// log for the record magic. The search uses the two-way algorithm of `memchr::memmem`.
+    /// An empty `needle`, or `from` out of the bound of the mmap, gives an empty iterator.
+    #[inline]
+    fn find_all<'n>(&self, needle: &'n [u8], from: usize) -> crate::FindAll<'_, 'n> {
+        crate::FindAll::new(self.as_slice(), needle, from)
+    }
+
+    /// Returns an iterator over the records framed by `magic`, each of which is the slice from
+    /// one occurrence of `magic` (included) up to the next one, or the end of the mmap.
+    ///
+    /// Unlike walking the length prefixes, it does not trust any length field, so it is the tool
+    /// to salvage the records of a log whose headers are corrupted. The bytes before the first
+    /// `magic` are skipped, and a `magic` which happens to occur inside a payload splits the record,
+    /// so the records should still be validated, e.g. by their checksums. The occurrences are found
+    /// by [`find_all`], an empty `magic` gives an empty iterator.
+    ///
+    /// [`find_all`]: #method.find_all
+    #[inline]
+    fn records_by_magic<'n>(&self, magic: &'n [u8]) -> crate::RecordsByMagic<'_, 'n> {
+        crate::RecordsByMagic::new(self.as_slice(), magic)
+    }
+
+    /// Returns how many times `needle` occurs in the mmap.
+    #[inline]
+    fn count_byte(&self, needle: u8) -> usize {
+        memchr::memchr_iter(needle, self.as_slice()).count()
+    }
+
+    /// Returns how many bytes in the range satisfy `pred`, in a single pass over the range.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    fn count_bytes_where<F: Fn(u8) -> bool>(&self, range: Range<usize>, pred: F) -> Result<usize> {
+        let buf = self.as_slice();
+        if range.start > range.end || range.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        Ok(buf[range].iter().filter(|&&b| pred(b)).count())
+    }
+
+    /// Returns how many bytes in the range are in `set`, a lookup table indexed by the byte value,
+    /// e.g. to count the whitespaces. This is faster than [`count_bytes_where`] with a predicate
+    /// testing several values.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    ///
+    /// [`count_bytes_where`]: #method.count_bytes_where
+    fn count_in_set(&self, range: Range<usize>, set: &[bool; 256]) -> Result<usize> {
+        let buf = self.as_slice();
+        if range.start > range.end || range.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        Ok(buf[range].iter().map(|&b| set[b as usize] as usize).sum())
+    }
+
+    /// Returns the bytes from `offset` up to (not including) the next `delim`, and the offset
+    /// just past the delimiter, which is where the next scan should start.
+    ///
+    /// If there is no delimiter after `offset`, the bytes up to the end of the mmap are returned,
+    /// and the offset is `None` to flag that the scan hit the end.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let mut offset = 0;
+    /// while let (field, Some(next)) = file.bytes_until(offset, b',')? {
+    ///     // process the field
+    ///     offset = next;
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if `offset` is greater than the length of the mmap.
+    fn bytes_until(&self, offset: usize, delim: u8) -> Result<(&[u8], Option<usize>)> {
+        let buf = self
+            .as_slice()
+            .get(offset..)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        Ok(match memchr::memchr(delim, buf) {
+            Some(pos) => (&buf[..pos], Some(offset + pos + 1)),
+            None => (buf, None),
+        })
+    }
+
+    /// Returns the most frequent byte in the range and its count, in one pass over the range,
+    /// e.g. to pick the fill byte of a sparse or run-length encoding. The smallest byte wins a tie,
+    /// the count saturates at `u32::MAX`, and an empty range gives `(0, 0)`.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    fn most_common_byte(&self, range: std::ops::Range<usize>) -> Result<(u8, u32)> {
+        let buf = self.as_slice();
+        if range.start > range.end || range.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+
+        let mut freq = [0u64; 256];
+        buf[range].iter().for_each(|b| freq[*b as usize] += 1);
+        let (byte, count) = freq
+            .iter()
+            .enumerate()
+            .fold((0, 0), |max, (b, &n)| if n > max.1 { (b, n) } else { max });
+        Ok((byte as u8, count.min(u32::MAX as u64) as u32))
+    }
+
+    /// Returns the longest run of identical bytes in the range as `(byte, length, offset)`, the
+    /// offset is related to the mmap. The first of the equally long runs wins, and an empty range
+    /// gives `(0, 0, range.start)`.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    fn longest_run(&self, range: std::ops::Range<usize>) -> Result<(u8, usize, usize)> {
+        let buf = self.as_slice();
+        if range.start > range.end || range.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+
+        let mut longest = (0, 0, range.start);
+        let mut run_start = range.start;
+        for pos in range.clone() {
+            if buf[pos] != buf[run_start] {
+                run_start = pos;
+            }
+            if pos + 1 - run_start > longest.1 {
+                longest = (buf[run_start], pos + 1 - run_start, run_start);
+            }
+        }
+        Ok(longest)
+    }
+
+    /// Returns the number of leading bytes which are equal in the mmap starting at `self_offset`
+    /// and in `other`, i.e. the length of their longest common prefix.
+    ///
+    /// The result is clamped to both lengths, it is `0` if `self_offset` is out of the bound of the mmap.
+    /// The bytes are compared a word at a time.
+    fn common_prefix_len(&self, self_offset: usize, other: &[u8]) -> usize {
+        let buf = self.as_slice();
+        if self_offset >= buf.len() {
+            return 0;
+        }
+        crate::utils::common_prefix_len(&buf[self_offset..], other)
+    }
+
+    /// Returns the sub-slices of the ranges as [`IoSlice`]s, e.g. to write the non-contiguous
+    /// regions of the mmap by `write_vectored` of a file or a socket without copying them.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if any range is out of the bound of the mmap,
+    /// the message of which contains the index of the range.
+    ///
+    /// [`IoSlice`]: std::io::IoSlice
+    fn io_slices(&self, ranges: &[Range<usize>]) -> Result<Vec<std::io::IoSlice<'_>>> {
+        let buf = self.as_slice();
+        ranges
+            .iter()
+            .enumerate()
+            .map(|(idx, range)| {
+                if range.start > range.end || range.end > buf.len() {
+                    return Err(Error::new_with_message(
+                        ErrorKind::EOF,
+                        format!("range {} ({}..{}) is out of bound", idx, range.start, range.end),
+                    ));
+                }
+                Ok(std::io::IoSlice::new(&buf[range.clone()]))
+            })
+            .collect()
+    }
+
+    /// Returns whether all the bytes in the range are zero.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    fn is_zero_range(&self, range: Range<usize>) -> Result<bool> {
+        let buf = self.as_slice();
+        if range.start > range.end || range.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        Ok(crate::utils::first_nonzero(&buf[range]).is_none())
+    }
+
+    /// Returns the offset of the first non-zero byte at or after `from`,
+    /// or `None` if all the remaining bytes are zero.
+    fn first_nonzero(&self, from: usize) -> Option<usize> {
+        let buf = self.as_slice();
+        if from >= buf.len() {
+            return None;
+        }
+        crate::utils::first_nonzero(&buf[from..]).map(|pos| from + pos)
+    }
+
+    /// Write the content of the mmap file to a new file.
+    #[inline]
+    fn write_all_to_new_file<P: AsRef<Path>>(&self, new_file_path: P) -> Result<()> {
+        let buf = self.as_slice();
+        let opts = Options::new().max_size(buf.len() as u64);
+        let mut mmap = DiskMmapFileMut::create_with_options(new_file_path, opts)?;
+        mmap.writer(0)?.write_all(buf)?;
+        mmap.flush()
+    }
+
+    /// Write a range of content of the mmap file to new file.
+    #[inline]
+    fn write_range_to_new_file<P: AsRef<Path>>(
+        &self,
+        new_file_path: P,
+        offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        let buf = self.as_slice();
+        if buf.len() < offset + len {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        let opts = Options::new().max_size(len as u64);
+        let mut mmap = DiskMmapFileMut::create_with_options(new_file_path, opts)?;
+        mmap.writer(0)?.write_all(&buf[offset..offset + len])?;
+        mmap.flush()
+    }
+
+    /// Returns a [`MmapFileReader`] which helps read data from mmap like a normal File.
+    ///
+    /// # Errors
+    /// If there's not enough data, it would return
+    ///  `Err(Error::from(ErrorKind::EOF))`.
+    ///
+    /// [`MmapFileReader`]: structs.MmapFileReader.html
+    fn reader(&self, offset: usize) -> Result<MmapFileReader> {
+        let buf = self.as_slice();
+        if buf.len() < offset {
+            Err(Error::from(ErrorKind::EOF))
+        } else {
+            Ok(MmapFileReader::new(
+                Cursor::new(&buf[offset..]),
+                offset,
+                buf.len() - offset,
+            ))
+        }
+    }
+
+    /// Returns a [`MmapBufReader`] from `offset` to the end of the mmap, which implements
+    /// `Read`, `BufRead` and `Seek`.
+    ///
+    /// This is the recommended way to feed the mmap to parsers expecting a `BufRead`
+    /// (e.g. `serde_json::from_reader`), as `fill_buf` returns the whole remaining mapped
+    /// slice without copying.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if `offset` is out of the bound of the mmap.
+    ///
+    /// [`MmapBufReader`]: type.MmapBufReader.html
+    #[inline]
+    fn bufread(&self, offset: usize) -> Result<MmapBufReader<'_>> {
+        self.reader(offset)
+    }
+
+    /// Returns a [`MmapFileReader`] base on the given `offset` and `len`, which helps read data from mmap like a normal File.
+    ///
+    /// # Errors
+    /// If there's not enough data, it would return
+    ///  `Err(Error::from(ErrorKind::EOF))`.
+    ///
+    /// [`MmapFileReader`]: structs.MmapFileReader.html
+    fn range_reader(&self, offset: usize, len: usize) -> Result<MmapFileReader> {
+        let buf = self.as_slice();
+        if buf.len() < offset + len {
+            Err(Error::from(ErrorKind::EOF))
+        } else {
+            Ok(MmapFileReader::new(
+                Cursor::new(&buf[offset..offset + len]),
+                offset,
+                len,
+            ))
+        }
+    }
+
+    /// Locks the file for exclusively usage, blocking if the file is currently locked.
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn lock_exclusive(&self) -> Result<()>;
+
+    /// Locks the file for shared usage, blocking if the file is currently locked exclusively.
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn lock_shared(&self) -> Result<()>;
+
+    /// Locks the file for exclusively usage, or returns a an error if the file is currently locked (see lock_contended_error).
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn try_lock_exclusive(&self) -> Result<()>;
+
+    /// Locks the file for shared usage, or returns a an error if the file is currently locked exclusively (see lock_contended_error).
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn try_lock_shared(&self) -> Result<()>;
+
+    /// Unlocks the file.
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn unlock(&self) -> Result<()>;
+
+    /// Locks the file for exclusively usage, blocking if the file is currently locked,
+    /// and returns a guard which unlocks the file when it is dropped.
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn lock_exclusive_guard(&self) -> Result<LockGuard<'_>>
+    where
+        Self: Sized,
+    {
+        self.lock_exclusive()?;
+        Ok(LockGuard { file: self })
+    }
+
+    /// Locks the file for shared usage, blocking if the file is currently locked exclusively,
+    /// and returns a guard which unlocks the file when it is dropped.
+    ///
+    /// # Notes
+    /// This function will do nothing if the underlying is not a real file, e.g. in-memory.
+    fn lock_shared_guard(&self) -> Result<LockGuard<'_>>
+    where
+        Self: Sized,
+    {
+        self.lock_shared()?;
+        Ok(LockGuard { file: self })
+    }
+
+    /// Runs `f` while holding the exclusive lock of the file, and returns its result.
+    ///
+    /// The file is unlocked after `f` returns, even if it returns an error or panics.
+    /// If `f` succeeds, the error of the unlock is returned, otherwise the error of `f`.
+    fn with_exclusive_lock<R, F: FnOnce() -> Result<R>>(&self, f: F) -> Result<R>
+    where
+        Self: Sized,
+    {
+        let guard = self.lock_exclusive_guard()?;
+        let rst = f()?;
+        guard.unlock().map(|_| rst)
+    }
+
+    /// Runs `f` while holding the shared lock of the file, and returns its result.
+    ///
+    /// The file is unlocked after `f` returns, even if it returns an error or panics.
+    /// If `f` succeeds, the error of the unlock is returned, otherwise the error of `f`.
+    fn with_shared_lock<R, F: FnOnce() -> Result<R>>(&self, f: F) -> Result<R>
+    where
+        Self: Sized,
+    {
+        let guard = self.lock_shared_guard()?;
+        let rst = f()?;
+        guard.unlock().map(|_| rst)
+    }
+
+    /// Read bytes to the dst buf from the offset, returns how many bytes read.
+    fn read(&self, dst: &mut [u8], offset: usize) -> usize {
+        let buf = self.as_slice();
+
+        if buf.len() < offset {
+            0
+        } else {
+            let remaining = buf.len() - offset;
+            let dst_len = dst.len();
+            if remaining > dst_len {
+                dst.copy_from_slice(&buf[offset..offset + dst_len]);
+                dst_len
+            } else {
+                dst.copy_from_slice(&buf[offset..offset + remaining]);
+                remaining
+            }
+        }
+    }
+
+    /// Reads as many bytes as available to the dst buf from the offset, returns how many bytes are
+    /// read, and whether the dst buf is filled.
+    ///
+    /// Unlike [`read_exact`], a short read still copies the available bytes, e.g. for an
+    /// incremental reader which tolerates a partial final read. An offset beyond the end reads
+    /// nothing.
+    ///
+    /// [`read_exact`]: #method.read_exact
+    fn read_available(&self, dst: &mut [u8], offset: usize) -> (usize, bool) {
+        let buf = self.as_slice();
+        let n = buf.len().saturating_sub(offset).min(dst.len());
+        if n > 0 {
+            dst[..n].copy_from_slice(&buf[offset..offset + n]);
+        }
+        (n, n == dst.len())
+    }
+
+    /// Read the exact number of bytes required to fill buf.
+    fn read_exact(&self, dst: &mut [u8], offset: usize) -> Result<()> {
+        let buf = self.as_slice();
+        let remaining = buf.len().checked_sub(offset);
+        match remaining {
+            None => Err(Error::from(ErrorKind::EOF)),
             Some(remaining) => {
                 let dst_len = dst.len();
                 if remaining < dst_len {
@@ -225,6 +1193,15 @@ pub trait MmapFileExt {
         }
     }
 
+    /// Read exactly `N` bytes from offset into a fixed size array.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if there are less than `N` bytes after offset.
+    fn read_array<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.read_exact(&mut buf, offset).map(|_| buf)
+    }
+
     /// Read a signed 8 bit integer from offset.
     fn read_i8(&self, offset: usize) -> Result<i8> {
         let buf = self.as_slice();
@@ -418,6 +1395,47 @@ pub trait MmapFileMutExt {
         }
     }
 
+    /// Returns a mutable reference to the header of type `H` stored in the first `size_of::<H>()` bytes of the mmap.
+    ///
+    /// # Errors
+    /// If the mmap is shorter than `H`, it would return `Err(Error::from(ErrorKind::EOF))`.
+    /// If the mmap is not properly aligned for `H`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+    #[cfg(feature = "zerocopy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+    fn header_mut<H>(&mut self) -> Result<&mut H>
+    where
+        H: zerocopy::FromBytes + zerocopy::IntoBytes + zerocopy::KnownLayout,
+    {
+        let buf = self.as_mut_slice();
+        if buf.len() < mem::size_of::<H>() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        H::mut_from_prefix(buf)
+            .map(|(h, _)| h)
+            .map_err(|_| Error::from(ErrorKind::Misaligned))
+    }
+
+    /// Returns the whole mmap as a mutable slice of `T`, so an array of fixed-size records can be
+    /// modified in place. The read-only counterpart is `as_slice_of`.
+    ///
+    /// # Errors
+    /// If the length of the mmap is not a multiple of `size_of::<T>()`, it would return `Err(Error::from(ErrorKind::EOF))`.
+    /// If the mmap is not properly aligned for `T`, it would return `Err(Error::from(ErrorKind::Misaligned))`.
+    #[cfg(feature = "zerocopy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "zerocopy")))]
+    fn as_mut_slice_of<T>(&mut self) -> Result<&mut [T]>
+    where
+        T: zerocopy::FromBytes + zerocopy::IntoBytes,
+    {
+        let buf = self.as_mut_slice();
+        let size = mem::size_of::<T>();
+        if size != 0 && buf.len() % size != 0 {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        <[T] as zerocopy::FromBytes>::mut_from_bytes(buf)
+            .map_err(|_| Error::from(ErrorKind::Misaligned))
+    }
+
     /// Fill 0 to the specific range
     fn zero_range(&mut self, start: usize, end: usize) {
         let buf = self.as_mut_slice();
@@ -425,6 +1443,25 @@ pub trait MmapFileMutExt {
         buf[start..end].fill(0);
     }
 
+    /// Fills the range with `pattern` repeated, the last copy is truncated if the length
+    /// of the range is not a multiple of the length of `pattern`.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::BadFormat))` if `pattern` is empty,
+    /// and `Err(Error::from(ErrorKind::EOF))` if the range is out of the bound of the mmap.
+    fn fill_pattern(&mut self, pattern: &[u8], range: std::ops::Range<usize>) -> Result<()> {
+        if pattern.is_empty() {
+            return Err(Error::new_with_message(ErrorKind::BadFormat, "pattern must not be empty"));
+        }
+        let buf = self
+            .as_mut_slice()
+            .get_mut(range)
+            .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        buf.chunks_mut(pattern.len())
+            .for_each(|chunk| chunk.copy_from_slice(&pattern[..chunk.len()]));
+        Ok(())
+    }
+
     /// Flushes outstanding memory map modifications to disk (if the inner is a real file).
     ///
     /// When this method returns with a non-error result,
@@ -466,6 +1503,155 @@ pub trait MmapFileMutExt {
     /// do re-mmap and sync_dir if the inner is a real file.
     fn truncate(&mut self, max_sz: u64) -> Result<()>;
 
+    /// Returns the size [`truncate`] takes to resize the mmap to `len` bytes.
+    ///
+    /// [`truncate`] sets the size of the file, so the size for a disk mmap is `len` plus the offset
+    /// of the mmap in the file. For the other kinds of mmap, it is `len`.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::NotSupported))` if the length of the mmap is fixed, e.g. it
+    /// is mapped with a `len` in the options, since [`truncate`] does not resize it, and
+    /// `Err(Error::from(ErrorKind::TruncationFailed))` if the size overflows `u64`.
+    ///
+    /// [`truncate`]: #tymethod.truncate
+    fn truncate_size_for(&self, len: u64) -> Result<u64> {
+        Ok(len)
+    }
+
+    /// Grows the mmap by `additional` bytes, relative to the current length of the mmap.
+    ///
+    /// The new size is computed internally, then [`truncate`] does the resizing. The new size of
+    /// a disk mmap is computed from the size of the file, as [`truncate`] sets the size of the file,
+    /// so a mmap which starts at a non-zero offset keeps the data before the offset.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::TruncationFailed))` if the new size overflows `u64`,
+    /// or the error returned by [`truncate`].
+    ///
+    /// [`truncate`]: #tymethod.truncate
+    fn grow_by(&mut self, additional: u64) -> Result<()> {
+        let len = self.as_mut_slice().len() as u64;
+        self.truncate(crate::utils::grown_len(len, additional)?)
+    }
+
+    /// Shrinks the mmap by `bytes` bytes, relative to the current length of the mmap,
+    /// the new size is clamped at 0.
+    ///
+    /// The new size is computed internally, then [`truncate`] does the resizing. The new size of
+    /// a disk mmap is computed from the size of the file, the same as [`grow_by`].
+    ///
+    /// [`grow_by`]: #method.grow_by
+    /// [`truncate`]: #tymethod.truncate
+    fn shrink_by(&mut self, bytes: u64) -> Result<()> {
+        let len = self.as_mut_slice().len() as u64;
+        self.truncate(len.saturating_sub(bytes))
+    }
+
+    /// Truncates the mmap to just after its last non-zero byte, i.e. drops the trailing run
+    /// of zeros, and returns the new length. An all-zero mmap is truncated to 0.
+    ///
+    /// The mmap is scanned backward a word at a time, then [`truncate`] does the resizing, to the
+    /// size given by [`truncate_size_for`], so a disk mmap starting at a non-zero offset keeps the
+    /// data before the offset.
+    ///
+    /// [`truncate`]: #tymethod.truncate
+    /// [`truncate_size_for`]: #method.truncate_size_for
+    fn truncate_trailing_zeros(&mut self) -> Result<u64> {
+        let new_len = crate::utils::last_nonzero(self.as_mut_slice()).map_or(0, |pos| pos as u64 + 1);
+        self.truncate(self.truncate_size_for(new_len)?)?;
+        Ok(new_len)
+    }
+
+    /// Resizes the mmap to `new_len` bytes with the same contract for all the kinds of mmap:
+    /// the content in `[0, min(old, new_len))` is preserved, the grown tail is zeroed and the
+    /// shrunk tail is dropped.
+    ///
+    /// An in-memory mmap reallocates its buffer keeping the prefix, an anonymous mmap maps a new
+    /// region and copies the prefix over, and a disk mmap is resized by [`truncate`], which flushes
+    /// before remapping the file.
+    ///
+    /// A disk mmap starting at a non-zero offset is resized to the size given by [`truncate_size_for`],
+    /// so the data before the offset is kept.
+    ///
+    /// # Errors
+    /// Returns the error returned by [`truncate_size_for`] before resizing anything, e.g. if the mmap
+    /// has a fixed length, the error returned by [`truncate`], or `Err(Error::from(ErrorKind::TruncationFailed))`
+    /// if the mmap cannot hold `new_len` bytes afterwards, e.g. an empty mmap.
+    ///
+    /// [`truncate`]: #tymethod.truncate
+    /// [`truncate_size_for`]: #method.truncate_size_for
+    fn resize_preserving(&mut self, new_len: u64) -> Result<()> {
+        let size = self.truncate_size_for(new_len)?;
+        self.truncate(size)?;
+        let len = self.as_mut_slice().len() as u64;
+        if len != new_len {
+            return Err(Error::new_with_message(
+                ErrorKind::TruncationFailed,
+                format!("the mmap is resized to {} bytes instead of {} bytes", len, new_len),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the strategy to grow the mmap when a write needs more room than its length,
+    /// see [`GrowStrategy`]. It is configured by `grow_increment` of the options, the default
+    /// one is [`GrowStrategy::Double`].
+    ///
+    /// [`GrowStrategy`]: crate::GrowStrategy
+    /// [`GrowStrategy::Double`]: crate::GrowStrategy::Double
+    fn grow_strategy(&self) -> crate::GrowStrategy {
+        crate::GrowStrategy::default()
+    }
+
+    /// Grows the mmap, following [`grow_strategy`], so it holds at least `required` bytes.
+    /// Does nothing if the mmap is already long enough.
+    ///
+    /// The mmap is resized to the size given by [`truncate_size_for`], so a disk mmap starting at
+    /// a non-zero offset keeps the data before the offset.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::TruncationFailed))` if the new length overflows `u64`,
+    /// or the error returned by [`truncate_size_for`] or [`truncate`].
+    ///
+    /// [`grow_strategy`]: #method.grow_strategy
+    /// [`truncate_size_for`]: #method.truncate_size_for
+    /// [`truncate`]: #tymethod.truncate
+    fn grow_to_fit(&mut self, required: u64) -> Result<()> {
+        let len = self.as_mut_slice().len() as u64;
+        if len >= required {
+            return Ok(());
+        }
+        let new_len = self.grow_strategy().checked_next_len(len, required).ok_or_else(|| {
+            Error::new_with_message(ErrorKind::TruncationFailed, "the new size overflows u64")
+        })?;
+        self.truncate(self.truncate_size_for(new_len)?)
+    }
+
+    /// Writes all of `src` at `offset`, growing the mmap first if it ends before `offset + src.len()`.
+    ///
+    /// Unlike [`write_all`], the write is not limited by the current length. The mmap grows by
+    /// [`grow_to_fit`], so it may grow beyond `offset + src.len()`, and the gap between the old end
+    /// and `offset`, like all the new room, is zero-filled.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::NotSupported))` if the mmap is copy-on-write, since the
+    /// new room would never reach the file, and the error returned by [`truncate`] if it cannot grow.
+    ///
+    /// [`write_all`]: #method.write_all
+    /// [`grow_to_fit`]: #method.grow_to_fit
+    /// [`truncate`]: #tymethod.truncate
+    fn put(&mut self, src: &[u8], offset: usize) -> Result<()> {
+        if self.is_cow() {
+            return Err(Error::new_with_message(
+                ErrorKind::NotSupported,
+                "cannot grow a copy-on-write mmap",
+            ));
+        }
+        let end = offset.checked_add(src.len()).ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        self.grow_to_fit(end as u64)?;
+        self.write_all(src, offset)
+    }
+
     /// Remove the underlying file
     fn drop_remove(self) -> Result<()>;
 
@@ -531,6 +1717,28 @@ pub trait MmapFileMutExt {
         }
     }
 
+    /// Returns a [`BufferedMmapWriter`] from the offset, which stages the writes in a buffer of
+    /// `buf_capacity` bytes, and commits the contiguous runs to the mmap (flushing them), which
+    /// helps the write patterns with many tiny or scattered writes.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the offset is out of the bound of the mmap.
+    ///
+    /// [`BufferedMmapWriter`]: structs.BufferedMmapWriter.html
+    fn buffered_writer(
+        &mut self,
+        offset: usize,
+        buf_capacity: usize,
+    ) -> Result<BufferedMmapWriter<'_>>
+    where
+        Self: Sized,
+    {
+        if self.as_mut_slice().len() < offset {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        Ok(BufferedMmapWriter::new(self, offset, buf_capacity))
+    }
+
     /// Write bytes to the mmap from the offset.
     fn write(&mut self, src: &[u8], offset: usize) -> usize {
         let buf = self.as_mut_slice();
@@ -567,6 +1775,141 @@ pub trait MmapFileMutExt {
         }
     }
 
+    /// Writes `new` to the mmap from the offset only if the bytes there are equal to `expected`,
+    /// then flushes the written range. Returns whether the bytes were swapped.
+    ///
+    /// # Notes
+    /// The compare and the write are not atomic, so without a lock (see [`lock_exclusive`])
+    /// this is racy when other processes write the same region. It is intended for the
+    /// single-writer optimistic versioning.
+    ///
+    /// # Errors
+    /// - `Err(Error::from(ErrorKind::LengthMismatch(expected.len(), new.len())))` if the lengths of `expected` and `new` are different.
+    /// - `Err(Error::from(ErrorKind::EOF))` if the region is out of the bound of the mmap.
+    ///
+    /// [`lock_exclusive`]: trait.MmapFileExt.html#tymethod.lock_exclusive
+    fn compare_and_swap(&mut self, offset: usize, expected: &[u8], new: &[u8]) -> Result<bool> {
+        let len = new.len();
+        if expected.len() != len {
+            return Err(Error::from(ErrorKind::LengthMismatch(expected.len(), len)));
+        }
+
+        let region = offset
+            .checked_add(len)
+            .and_then(|end| self.as_mut_slice().get_mut(offset..end))
+            .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        if region != expected {
+            return Ok(false);
+        }
+        region.copy_from_slice(new);
+        self.flush_range(offset, len).map(|_| true)
+    }
+
+    /// Swaps the bytes of two non-overlapping ranges of the same length in place,
+    /// e.g. to reorder the fixed-size records of a file.
+    ///
+    /// # Errors
+    /// - `Err(Error::from(ErrorKind::LengthMismatch(a.len(), b.len())))` if the lengths of the ranges are different.
+    /// - `Err(Error::from(ErrorKind::EOF))` if any range is out of the bound of the mmap.
+    /// - `Err(Error::from(ErrorKind::Overlap))` if the ranges overlap.
+    fn swap_ranges(&mut self, a: std::ops::Range<usize>, b: std::ops::Range<usize>) -> Result<()> {
+        let buf = self.as_mut_slice();
+        if a.start > a.end || b.start > b.end || a.end > buf.len() || b.end > buf.len() {
+            return Err(Error::from(ErrorKind::EOF));
+        }
+        if a.len() != b.len() {
+            return Err(Error::from(ErrorKind::LengthMismatch(a.len(), b.len())));
+        }
+        if a.is_empty() || a == b {
+            return Ok(());
+        }
+
+        let (lo, hi) = if a.start < b.start { (a, b) } else { (b, a) };
+        if lo.end > hi.start {
+            return Err(Error::from(ErrorKind::Overlap));
+        }
+        let (head, tail) = buf.split_at_mut(hi.start);
+        head[lo].swap_with_slice(&mut tail[..hi.len()]);
+        Ok(())
+    }
+
+    /// Appends `data` to a single-producer log, and publishes it atomically.
+    ///
+    /// The log is an 8-byte native-endian length header at `len_header_offset`, followed by the
+    /// published bytes. `data` is written right after the published bytes and flushed, then the
+    /// new length is stored in the header with the `Release` ordering, and returned.
+    ///
+    /// # Memory ordering
+    /// A consumer (in this or another process mapping the same file) which loads the length with
+    /// the `Acquire` ordering (see `published_len`) sees all the bytes up to that length, so it
+    /// never reads a torn record. The header is only stored after the data is flushed, so after a
+    /// crash it never covers unflushed data. There must be only one producer at a time, the
+    /// concurrent producers must be serialized (e.g. by a file lock).
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if the header or `data` is out of the bound of the mmap,
+    /// and `Err(Error::from(ErrorKind::Misaligned))` if the header is not 8-byte aligned.
+    #[cfg(target_has_atomic = "64")]
+    fn atomic_append(&mut self, data: &[u8], len_header_offset: usize) -> Result<u64> {
+        use std::sync::atomic::Ordering;
+
+        let buf = self.as_mut_slice();
+        let published = crate::utils::atomic_u64_at(buf, len_header_offset)?.load(Ordering::Acquire);
+        let start = usize::try_from(published)
+            .ok()
+            .and_then(|published| (len_header_offset + 8).checked_add(published))
+            .ok_or_else(|| Error::from(ErrorKind::EOF))?;
+        match start.checked_add(data.len()) {
+            Some(end) if end <= buf.len() => buf[start..end].copy_from_slice(data),
+            _ => return Err(Error::from(ErrorKind::EOF)),
+        }
+        self.flush_range(start, data.len())?;
+
+        let len = published + data.len() as u64;
+        crate::utils::atomic_u64_at(self.as_mut_slice(), len_header_offset)?.store(len, Ordering::Release);
+        Ok(len)
+    }
+
+    /// Applies a batch of `(offset, bytes)` edits in order, then flushes the union of the touched ranges.
+    ///
+    /// All the edits are validated before any of them is applied, so the mmap is left untouched
+    /// if any edit is out of bounds. This is useful when replaying a write-ahead log into a snapshot.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))`, with the index of the first offending edit in the
+    /// message, if any edit does not fit in the mmap.
+    fn apply_patch(&mut self, edits: &[(usize, &[u8])]) -> Result<()> {
+        let len = self.as_mut_slice().len();
+        let mut touched: Option<(usize, usize)> = None;
+        for (idx, (offset, src)) in edits.iter().enumerate() {
+            match offset.checked_add(src.len()) {
+                Some(end) if end <= len => {
+                    if !src.is_empty() {
+                        touched = Some(match touched {
+                            None => (*offset, end),
+                            Some((start, stop)) => (start.min(*offset), stop.max(end)),
+                        });
+                    }
+                }
+                _ => {
+                    return Err(Error::new_with_message(
+                        ErrorKind::EOF,
+                        format!("edit {} is out of bounds", idx),
+                    ))
+                }
+            }
+        }
+
+        for (offset, src) in edits {
+            self.write_all(src, *offset)?;
+        }
+
+        match touched {
+            Some((start, end)) => self.flush_range(start, end - start),
+            None => Ok(()),
+        }
+    }
+
     /// Writes a signed 8 bit integer to mmap from the offset.
     fn write_i8(&mut self, val: i8, offset: usize) -> Result<()> {
         self.write_all(&[val as u8], offset)
@@ -696,6 +2039,57 @@ pub trait MmapFileMutExt {
     fn write_f64_le(&mut self, val: f64, offset: usize) -> Result<()> {
         self.write_all(&val.to_le_bytes(), offset)
     }
+
+    /// Writes the UTF-8 bytes of `s` to the mmap from the offset, returns the number of bytes written.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` and writes nothing if the bytes do not fit.
+    fn write_str(&mut self, s: &str, offset: usize) -> Result<usize> {
+        self.write_all(s.as_bytes(), offset)?;
+        Ok(s.len())
+    }
+
+    /// Writes the UTF-8 bytes of `s` followed by a NUL byte to the mmap from the offset,
+    /// returns the number of bytes written (including the NUL byte).
+    ///
+    /// # Errors
+    /// Nothing is written on error.
+    /// - `Err(Error::from(ErrorKind::InvalidCStr))` if `s` contains a NUL byte.
+    /// - `Err(Error::from(ErrorKind::EOF))` if the bytes do not fit.
+    fn write_cstr(&mut self, s: &str, offset: usize) -> Result<usize> {
+        if s.as_bytes().contains(&0) {
+            return Err(Error::from(ErrorKind::InvalidCStr));
+        }
+        let n = s.len() + 1;
+        match offset.checked_add(n) {
+            Some(end) if end <= self.as_mut_slice().len() => {}
+            _ => return Err(Error::from(ErrorKind::EOF)),
+        }
+        self.write_all(s.as_bytes(), offset)?;
+        self.write_u8(0, offset + s.len())?;
+        Ok(n)
+    }
+
+    /// Writes the length of `s` as a big-endian `u32`, followed by the UTF-8 bytes of `s`, to the
+    /// mmap from the offset, returns the number of bytes written (including the 4 bytes prefix).
+    ///
+    /// # Errors
+    /// Nothing is written on error.
+    /// - `Err(Error::from(ErrorKind::BadFormat))` if the length of `s` does not fit in a `u32`.
+    /// - `Err(Error::from(ErrorKind::EOF))` if the bytes do not fit.
+    fn write_str_prefixed(&mut self, s: &str, offset: usize) -> Result<usize> {
+        let len = u32::try_from(s.len()).map_err(|_| {
+            Error::new_with_message(ErrorKind::BadFormat, "string length does not fit in a u32 prefix")
+        })?;
+        let n = s.len() + 4;
+        match offset.checked_add(n) {
+            Some(end) if end <= self.as_mut_slice().len() => {}
+            _ => return Err(Error::from(ErrorKind::EOF)),
+        }
+        self.write_u32(len, offset)?;
+        self.write_all(s.as_bytes(), offset + 4)?;
+        Ok(n)
+    }
 }
 
 #[enum_dispatch(MmapFileExt)]
@@ -729,6 +2123,12 @@ impl_from!(
 impl MmapFile {
     /// Open a readable memory map backed by a file
     ///
+    /// On unix, the file can also be a block device (e.g. `/dev/sdb` or a loop device), which is
+    /// mapped as a whole, unless a length is given by [`Options::len`]. A character device
+    /// usually cannot be sized, so it has to be opened with a length. The same goes for the
+    /// mutable opens, though a device cannot be resized. Opening a device usually requires
+    /// root, or being a member of the group owning it (e.g. `disk`).
+    ///
     /// # Examples
     ///
     /// ```no_compile
@@ -742,14 +2142,36 @@ impl MmapFile {
     /// # file.write_all("some data...".as_bytes()).unwrap();
     /// # drop(file);
     ///
-    /// // open and mmap the file
-    /// let mut file = MmapFile::open("open_test.txt").unwrap();
-    /// let mut buf = vec![0; "some data...".len()];
-    /// file.read_exact(buf.as_mut_slice(), 0);
-    /// assert_eq!(buf.as_slice(), "some data...".as_bytes());
+    /// // open and mmap the file
+    /// let mut file = MmapFile::open("open_test.txt").unwrap();
+    /// let mut buf = vec![0; "some data...".len()];
+    /// file.read_exact(buf.as_mut_slice(), 0);
+    /// assert_eq!(buf.as_slice(), "some data...".as_bytes());
+    /// ```
+    ///
+    /// [`Options::len`]: struct.Options.html#method.len
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from(DiskMmapFile::open(path)?))
+    }
+
+    /// Sets the path reported by `path` of a memory map which is not backed by a file,
+    /// e.g. to give a map created by `memory_from_vec` a logical name for logs and errors.
+    ///
+    /// The label has no effect on a disk map, which always reports the path of its file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_compile
+    /// use fmmap::{MmapFile, MmapFileExt};
+    ///
+    /// let file = MmapFile::memory_from_vec("", vec![1; 8]).with_path_label("cache/segment-0");
+    /// assert_eq!(file.path_string(), "cache/segment-0");
     /// ```
-    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
-        Ok(Self::from(DiskMmapFile::open(path)?))
+    pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+        if let MmapFileInner::Memory(m) = &mut self.inner {
+            m.set_path_label(label.into());
+        }
+        self
     }
 
     /// Open a readable memory map backed by a file with [`Options`]
@@ -790,6 +2212,32 @@ impl MmapFile {
         Ok(Self::from(DiskMmapFile::open_with_options(path, opts)?))
     }
 
+    /// Open a readable memory map backed by a file if the file exists.
+    ///
+    /// Returns `Ok(None)` if the file does not exist, other errors are returned as `Err`.
+    /// This is handy for optional files, e.g. sidecar or index files.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use fmmap::MmapFile;
+    ///
+    /// if let Some(index) = MmapFile::try_open("data.idx").unwrap() {
+    ///     // use the index
+    /// }
+    /// ```
+    pub fn try_open<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        super::none_if_not_found(Self::open(path))
+    }
+
+    /// Same as [`try_open`], but open the file with [`Options`].
+    ///
+    /// [`try_open`]: #method.try_open
+    /// [`Options`]: struct.Options.html
+    pub fn try_open_with_options<P: AsRef<Path>>(path: P, opts: Options) -> Result<Option<Self>> {
+        super::none_if_not_found(Self::open_with_options(path, opts))
+    }
+
     /// Open a readable memory map backed by a file
     ///
     /// # Examples
@@ -848,6 +2296,108 @@ impl MmapFile {
             path, opts,
         )?))
     }
+
+    /// Open a compressed file and decompress it into an in-memory mmap file.
+    ///
+    /// The compressed file is mmapped and decompressed in a streaming way, but the result
+    /// cannot be backed by the file, so the returned `MmapFile` is in-memory (see [`MmapFile::memory`]),
+    /// and its path is the path of the compressed file.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// use fmmap::{Codec, MmapFile, MmapFileExt};
+    ///
+    /// let file = MmapFile::open_decompressed("assets.bin.gz", Codec::Gzip).unwrap();
+    /// let magic = file.prefix(4).unwrap();
+    /// ```
+    ///
+    /// [`MmapFile::memory`]: struct.MmapFile.html#method.memory
+    #[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+    #[cfg_attr(docsrs, doc(cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))))]
+    pub fn open_decompressed<P: AsRef<Path>>(path: P, codec: crate::Codec) -> Result<Self> {
+        let path = path.as_ref();
+        let compressed = Self::open(path)?;
+        let data = crate::codec::decompress(compressed.as_slice(), codec)
+            .map_err(|e| Error::new_source_msg(ErrorKind::IO, compressed.path_string(), e))?;
+        Ok(Self::memory(path, data.freeze()))
+    }
+
+    /// Consumes the mmap file and returns an [`OwnedMmapReader`] starting at offset 0.
+    ///
+    /// Unlike [`reader`], which borrows the mmap, the owned reader keeps the mmap alive,
+    /// so it can be passed where `R: Read + Seek + 'static` is expected (e.g. `zip::ZipArchive::new`).
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use fmmap::{MmapFile, MmapFileExt};
+    /// use std::io::Read;
+    ///
+    /// let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    /// let mut reader = file.into_reader();
+    /// let mut buf = String::new();
+    /// reader.read_to_string(&mut buf).unwrap();
+    /// assert_eq!(buf, "some data...");
+    /// ```
+    ///
+    /// [`OwnedMmapReader`]: struct.OwnedMmapReader.html
+    /// [`reader`]: trait.MmapFileExt.html#method.reader
+    pub fn into_reader(self) -> OwnedMmapReader {
+        OwnedMmapReader::new(self)
+    }
+
+    /// Turns the read-only mmap into a writable one, the inverse of [`MmapFileMut::freeze`].
+    ///
+    /// The file handle the mmap was opened with is mapped again as writable, instead of opening
+    /// the file by its path, so there is no window in which the path can be replaced.
+    /// The file must have been opened with [`Options::writable_handle`] set.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::NotSupported))` if the file was not opened with
+    /// a writable handle, or the mmap is not backed by a file, e.g. in-memory.
+    ///
+    /// # Examples
+    /// ```no_compile
+    /// use fmmap::{MmapFile, MmapFileExt, MmapFileMutExt, Options};
+    ///
+    /// let opts = Options::new().writable_handle(true);
+    /// let file = MmapFile::open_with_options("mmap_file_upgrade_to_mut_test.txt", opts).unwrap();
+    /// // read-mostly, until a write is needed
+    /// let mut file = file.upgrade_to_mut().unwrap();
+    /// file.write_all(b"some data...", 0).unwrap();
+    /// file.flush().unwrap();
+    /// ```
+    ///
+    /// [`MmapFileMut::freeze`]: struct.MmapFileMut.html#method.freeze
+    /// [`Options::writable_handle`]: struct.Options.html#method.writable_handle
+    pub fn upgrade_to_mut(self) -> Result<MmapFileMut> {
+        match self.inner {
+            MmapFileInner::Disk(disk) => Ok(MmapFileMut::from(disk.upgrade_to_mut()?)),
+            MmapFileInner::Empty(_) | MmapFileInner::Memory(_) => {
+                Err(Error::from(ErrorKind::NotSupported))
+            }
+        }
+    }
+
+    /// Leaks the mmap, and returns a `'static` slice of its content, e.g. for a config or
+    /// a lookup table which is mapped once at startup and never dropped.
+    ///
+    /// For a disk mmap, the mapping and the file handle are intentionally never released,
+    /// so only call this for a bounded number of files.
+    ///
+    /// # Examples
+    /// ```no_compile
+    /// use fmmap::MmapFile;
+    ///
+    /// let table: &'static [u8] = MmapFile::open("table.bin").unwrap().leak();
+    /// ```
+    pub fn leak(self) -> &'static [u8] {
+        match self.inner {
+            MmapFileInner::Empty(_) => &[],
+            MmapFileInner::Memory(memory) => memory.leak(),
+            MmapFileInner::Disk(disk) => disk.leak(),
+        }
+    }
 }
 
 impl_constructor_for_memory_mmap_file!(MemoryMmapFile, MmapFile, "MmapFile", "sync");
@@ -856,44 +2406,142 @@ impl_constructor_for_memory_mmap_file!(MemoryMmapFile, MmapFile, "MmapFile", "sy
 enum MmapFileMutInner {
     Empty(EmptyMmapFile),
     Memory(MemoryMmapFileMut),
+    Anonymous(AnonymousMmapFileMut),
     Disk(DiskMmapFileMut),
 }
 
 /// A writable memory map file.
 ///
-/// There is 3 status of this struct:
+/// There is 4 status of this struct:
 /// - __Disk__: mmap to a real file
 /// - __Memory__: use [`BytesMut`] to mock a mmap, which is useful for test and in-memory storage engine
+/// - __Anonymous__: a private anonymous mmap not backed by a file, see [`MmapFileExt::to_anonymous`]
 /// - __Empty__: a state represents null mmap, which is helpful for drop, remove, close the `MmapFileMut`. This state cannot be constructed directly.
 ///
 /// [`BytesMut`]: https://docs.rs/bytes/1.1.0/bytes/struct.BytesMut.html
 pub struct MmapFileMut {
     inner: MmapFileMutInner,
     remove_on_drop: bool,
+    flush_on_drop: bool,
+    high_water: usize,
+    dirty: DirtyRanges,
+    grow: crate::GrowStrategy,
     deleted: bool,
+    mirror: Option<Box<MmapFileMut>>,
+    flush_policy: crate::FlushPolicy,
+    last_sync: std::sync::Mutex<std::time::Instant>,
+    generation: u64,
+}
+
+fn mirror_failed(mirror: &MmapFileMut, err: Error) -> Error {
+    Error::new_source_msg(
+        ErrorKind::MirrorFailed,
+        format!("mirror {}", mirror.path_string()),
+        err,
+    )
 }
 
 impl_from_mut!(
     MmapFileMut,
     MmapFileMutInner,
-    [EmptyMmapFile, MemoryMmapFileMut, DiskMmapFileMut]
+    [EmptyMmapFile, MemoryMmapFileMut, AnonymousMmapFileMut, DiskMmapFileMut],
+    mirror: None,
+    flush_policy: crate::FlushPolicy::Sync,
+    last_sync: std::sync::Mutex::new(std::time::Instant::now()),
+    generation: 0,
 );
 
 impl_mmap_file_ext!(MmapFileMut);
 
 impl MmapFileMutExt for MmapFileMut {
     fn as_mut_slice(&mut self) -> &mut [u8] {
-        self.inner.as_mut_slice()
+        // the writes through the raw slice cannot be observed
+        let slice = self.inner.as_mut_slice();
+        self.dirty.mark(0..slice.len());
+        self.generation += 1;
+        slice
     }
 
     fn is_cow(&self) -> bool {
         self.inner.is_cow()
     }
 
-    impl_flush!();
+    #[inline]
+    fn grow_strategy(&self) -> crate::GrowStrategy {
+        self.grow
+    }
+
+    fn flush(&self) -> Result<()> {
+        if !self.flush_policy.is_sync_due(&self.last_sync) {
+            // the dirty ranges are kept, the data is not durable yet
+            self.inner.flush_async()?;
+            return self.mirror_op(|mirror| mirror.flush_async());
+        }
+        self.inner.flush()?;
+        self.dirty.clean_all();
+        self.mirror_op(|mirror| mirror.flush())
+    }
+
+    fn flush_async(&self) -> Result<()> {
+        self.inner.flush_async()
+    }
+
+    fn flush_range(&self, offset: usize, len: usize) -> Result<()> {
+        self.inner.flush_range(offset, len)?;
+        self.dirty.clean(offset..offset.saturating_add(len));
+        self.mirror_op(|mirror| mirror.flush_range(offset, len))
+    }
+
+    fn flush_async_range(&self, offset: usize, len: usize) -> Result<()> {
+        self.inner.flush_async_range(offset, len)
+    }
 
     fn truncate(&mut self, max_sz: u64) -> Result<()> {
-        self.inner.truncate(max_sz)
+        self.inner.truncate(max_sz)?;
+        self.high_water = self.high_water.min(max_sz as usize);
+        self.dirty.clamp(max_sz as usize);
+        self.generation += 1;
+        self.mirror_op_mut(|mirror| mirror.truncate(max_sz))
+    }
+
+    fn grow_by(&mut self, additional: u64) -> Result<()> {
+        let len = crate::MetaDataExt::len(&self.inner.metadata()?);
+        self.truncate(crate::utils::grown_len(len, additional)?)
+    }
+
+    fn shrink_by(&mut self, bytes: u64) -> Result<()> {
+        let len = crate::MetaDataExt::len(&self.inner.metadata()?);
+        self.truncate(len.saturating_sub(bytes))
+    }
+
+    fn truncate_size_for(&self, len: u64) -> Result<u64> {
+        self.inner.truncate_size_for(len)
+    }
+
+    fn write(&mut self, src: &[u8], offset: usize) -> usize {
+        let n = self.inner.write(src, offset);
+        if n == 0 {
+            return 0;
+        }
+        self.high_water = self.high_water.max(offset + n);
+        self.dirty.mark(offset..offset + n);
+        self.generation += 1;
+        // `write` cannot report an error, a short write of the mirror is reported as a short write,
+        // so the caller writes the rest again to both
+        match &mut self.mirror {
+            Some(mirror) => n.min(mirror.write(&src[..n], offset)),
+            None => n,
+        }
+    }
+
+    fn write_all(&mut self, src: &[u8], offset: usize) -> Result<()> {
+        self.inner.write_all(src, offset)?;
+        if !src.is_empty() {
+            self.high_water = self.high_water.max(offset + src.len());
+            self.dirty.mark(offset..offset + src.len());
+            self.generation += 1;
+        }
+        self.mirror_op_mut(|mirror| mirror.write_all(src, offset))
     }
 
     /// Remove the underlying file
@@ -947,11 +2595,26 @@ impl MmapFileMutExt for MmapFileMut {
     /// let meta = file.metadata().unwrap();
     /// assert_eq!(meta.len(), 50);
     /// ```
+    ///
+    /// If `max_sz` is [`MmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`], the file is truncated to the [high-water mark].
+    ///
+    /// [`MmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`]: structs.MmapFileMut.html#associatedconstant.TRUNCATE_TO_HIGH_WATER_MARK
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
     fn close_with_truncate(mut self, max_sz: i64) -> Result<()> {
+        let max_sz = self.resolve_truncate_size(max_sz);
         let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
         // swap the inner to empty
         let inner = mem::replace(&mut self.inner, empty);
-        inner.close_with_truncate(max_sz)
+        inner.close_with_truncate(max_sz)?;
+        match self.mirror.take() {
+            Some(mirror) => {
+                let msg = format!("mirror {}", mirror.path_string());
+                mirror
+                    .close_with_truncate(max_sz)
+                    .map_err(|e| Error::new_source_msg(ErrorKind::MirrorFailed, msg, e))
+            }
+            None => Ok(()),
+        }
     }
 }
 
@@ -982,7 +2645,9 @@ impl MmapFileMut {
     /// [`Options::create_mmap_file_mut`]: struct.Options.html#method.create_mmap_file_mut
     /// [`Options`]: struct.Options.html
     pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
-        Ok(Self::from(DiskMmapFileMut::create(path)?))
+        let mut file = Self::from(DiskMmapFileMut::create(path)?);
+        file.high_water = 0;
+        Ok(file)
     }
 
     /// Create a new file and mmap this file with [`Options`]
@@ -1006,9 +2671,36 @@ impl MmapFileMut {
     ///
     /// [`Options`]: struct.Options.html
     pub fn create_with_options<P: AsRef<Path>>(path: P, opts: Options) -> Result<Self> {
-        Ok(Self::from(DiskMmapFileMut::create_with_options(
-            path, opts,
-        )?))
+        let grow = opts.grow;
+        let flush_policy = opts.flush_policy;
+        let written = opts.initial_contents.as_ref().map_or(0, |data| data.len());
+        let mut file = Self::from(DiskMmapFileMut::create_with_options(path, opts)?);
+        file.high_water = written;
+        file.set_grow_strategy(grow);
+        file.set_flush_policy(flush_policy);
+        Ok(file)
+    }
+
+    /// Create a new file with [`Options`] and mmap this file, then copy the attributes of the
+    /// `template` file to it, e.g. to match the permissions of an existing segment.
+    ///
+    /// See [`copy_attributes`] for which attributes are copied on each platform. If they cannot
+    /// be copied, the new file is removed.
+    ///
+    /// [`Options`]: struct.Options.html
+    /// [`copy_attributes`]: crate::utils::copy_attributes
+    pub fn create_like<P: AsRef<Path>, T: AsRef<Path>>(
+        path: P,
+        template: T,
+        opts: Options,
+    ) -> Result<Self> {
+        let file = Self::create_with_options(&path, opts)?;
+        if let Err(e) = crate::utils::copy_attributes(template, &path) {
+            drop(file);
+            let _ = std::fs::remove_file(&path);
+            return Err(e);
+        }
+        Ok(file)
     }
 
     /// Open or Create(if not exists) a file and mmap this file.
@@ -1180,7 +2872,12 @@ impl MmapFileMut {
     ///
     /// [`Options`]: struct.Options.html
     pub fn open_with_options<P: AsRef<Path>>(path: P, opts: Options) -> Result<Self> {
-        Ok(Self::from(DiskMmapFileMut::open_with_options(path, opts)?))
+        let grow = opts.grow;
+        let flush_policy = opts.flush_policy;
+        let mut file = Self::from(DiskMmapFileMut::open_with_options(path, opts)?);
+        file.set_grow_strategy(grow);
+        file.set_flush_policy(flush_policy);
+        Ok(file)
     }
 
     /// Open an existing file and mmap this file
@@ -1263,9 +2960,12 @@ impl MmapFileMut {
     ///
     /// [`Options`]: struct.Options.html
     pub fn open_exist_with_options<P: AsRef<Path>>(path: P, opts: Options) -> Result<Self> {
-        Ok(Self::from(DiskMmapFileMut::open_exist_with_options(
-            path, opts,
-        )?))
+        let grow = opts.grow;
+        let flush_policy = opts.flush_policy;
+        let mut file = Self::from(DiskMmapFileMut::open_exist_with_options(path, opts)?);
+        file.set_grow_strategy(grow);
+        file.set_flush_policy(flush_policy);
+        Ok(file)
     }
 
     /// Open and mmap an existing file in copy-on-write mode(copy-on-write memory map backed by a file).
@@ -1359,99 +3059,499 @@ impl MmapFileMut {
     /// assert_eq!(buf.as_slice(), "some data...".as_bytes());
     /// ```
     ///
-    /// [`Options`]: struct.Options.html
-    pub fn open_cow_with_options<P: AsRef<Path>>(path: P, opts: Options) -> Result<Self> {
-        Ok(Self::from(DiskMmapFileMut::open_cow_with_options(
-            path, opts,
-        )?))
+    /// [`Options`]: struct.Options.html
+    pub fn open_cow_with_options<P: AsRef<Path>>(path: P, opts: Options) -> Result<Self> {
+        let grow = opts.grow;
+        let flush_policy = opts.flush_policy;
+        let mut file = Self::from(DiskMmapFileMut::open_cow_with_options(path, opts)?);
+        file.set_grow_strategy(grow);
+        file.set_flush_policy(flush_policy);
+        Ok(file)
+    }
+
+    /// Sets the path reported by `path` of a memory map which is not backed by a file
+    /// (an in-memory or an anonymous map), e.g. to give it a logical name for logs and errors.
+    ///
+    /// The label has no effect on a disk map, which always reports (and removes) the path of its file.
+    ///
+    /// # Examples
+    ///
+    /// ```no_compile
+    /// use fmmap::{MmapFileMut, MmapFileExt};
+    ///
+    /// let file = MmapFileMut::memory_from_vec("", vec![0; 8]).with_path_label("scratch");
+    /// assert_eq!(file.path_string(), "scratch");
+    /// ```
+    pub fn with_path_label<P: Into<PathBuf>>(mut self, label: P) -> Self {
+        match &mut self.inner {
+            MmapFileMutInner::Memory(m) => m.set_path_label(label.into()),
+            MmapFileMutInner::Anonymous(m) => m.set_path_label(label.into()),
+            MmapFileMutInner::Empty(_) | MmapFileMutInner::Disk(_) => {}
+        }
+        self
+    }
+
+    /// Make the mmap file read-only.
+    ///
+    /// # Notes
+    /// If `remove_on_drop` is set to `true`, then the underlying file will not be removed on drop if this function is invoked. [Read more]
+    ///
+    /// # Examples
+    /// ```no_compile
+    /// use fmmap::{MmapFileMut, MmapFileMutExt};
+    /// # use scopeguard::defer;
+    ///
+    /// let mut file = MmapFileMut::create("mmap_file_freeze_test.txt").unwrap();
+    /// # defer!(std::fs::remove_file("mmap_file_freeze_test.txt").unwrap());
+    /// file.truncate(12);
+    /// file.write_all("some data...".as_bytes(), 0).unwrap();
+    /// file.flush().unwrap();
+    ///
+    /// file.freeze().unwrap();
+    /// ```
+    ///
+    /// [Read more]: structs.MmapFileMut.html#methods.set_remove_on_drop
+    pub fn freeze(mut self) -> Result<MmapFile> {
+        let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
+        // swap the inner to empty
+        let inner = mem::replace(&mut self.inner, empty);
+        match inner {
+            MmapFileMutInner::Empty(empty) => Ok(MmapFile::from(empty)), // unreachable, keep this for good measure
+            MmapFileMutInner::Memory(memory) => Ok(MmapFile::from(memory.freeze())),
+            MmapFileMutInner::Anonymous(anon) => Ok(MmapFile::from(MemoryMmapFile::copy_from_slice(
+                anon.path(),
+                anon.as_slice(),
+            ))),
+            MmapFileMutInner::Disk(disk) => Ok(MmapFile::from(disk.freeze()?)),
+        }
+    }
+
+    /// Consumes the mmap file and returns its content as a `Vec`.
+    ///
+    /// - __Memory__: the underlying [`BytesMut`] is moved into the `Vec` without copying
+    ///   when it is uniquely owned, otherwise it is copied.
+    /// - __Disk__: a mapping cannot be reclaimed as a `Vec`, so the content is copied,
+    ///   and the file is closed without being removed or flushed.
+    /// - __Anonymous__: the content is copied.
+    /// - __Empty__: returns an empty `Vec`.
+    ///
+    /// [`BytesMut`]: https://docs.rs/bytes/1.1.0/bytes/struct.BytesMut.html
+    pub fn into_vec(mut self) -> Vec<u8> {
+        let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
+        match mem::replace(&mut self.inner, empty) {
+            MmapFileMutInner::Empty(_) => Vec::new(),
+            MmapFileMutInner::Memory(memory) => memory.into_vec(),
+            MmapFileMutInner::Anonymous(anon) => anon.as_slice().to_vec(),
+            MmapFileMutInner::Disk(disk) => disk.as_slice().to_vec(),
+        }
+    }
+
+    /// Make the mmap file read-only, and returns an independent handle to the underlying file.
+    ///
+    /// The handle is a duplicate (see [`File::try_clone`]) of the file descriptor the mmap holds,
+    /// so it can be used for locking, metadata, or managing the file lifecycle while the
+    /// returned [`MmapFile`] serves the reads.
+    ///
+    /// # Errors
+    /// If the inner is not a real file, e.g. in-memory, it would return
+    /// `Err(Error::from(ErrorKind::NotSupported))`.
+    ///
+    /// # Examples
+    /// ```no_compile
+    /// use fmmap::{MmapFileMut, MmapFileMutExt};
+    /// # use scopeguard::defer;
+    ///
+    /// let mut file = MmapFileMut::create("mmap_file_freeze_with_handle_test.txt").unwrap();
+    /// # defer!(std::fs::remove_file("mmap_file_freeze_with_handle_test.txt").unwrap());
+    /// file.truncate(12);
+    /// file.write_all("some data...".as_bytes(), 0).unwrap();
+    /// file.flush().unwrap();
+    ///
+    /// let (file, handle) = file.freeze_with_handle().unwrap();
+    /// assert_eq!(handle.metadata().unwrap().len(), 12);
+    /// ```
+    ///
+    /// [`File::try_clone`]: https://doc.rust-lang.org/std/fs/struct.File.html#method.try_clone
+    pub fn freeze_with_handle(self) -> Result<(MmapFile, File)> {
+        let handle = match &self.inner {
+            MmapFileMutInner::Disk(disk) => disk
+                .file
+                .try_clone()
+                .map_err(|e| Error::new(ErrorKind::IO, e))?,
+            _ => return Err(Error::from(ErrorKind::NotSupported)),
+        };
+        self.freeze().map(|file| (file, handle))
+    }
+
+    /// Transition the memory map to be readable and executable.
+    /// If the memory map is file-backed, the file must have been opened with execute permissions.
+    ///
+    /// # Errors
+    /// This method returns an error when the underlying system call fails,
+    /// which can happen for a variety of reasons,
+    /// such as when the file has not been opened with execute permissions
+    ///
+    /// # Examples
+    /// ```no_compile
+    /// use fmmap::{MmapFileExt, MmapFileMut, MmapFileMutExt};
+    /// # use scopeguard::defer;
+    ///
+    /// let mut file = MmapFileMut::create("mmap_file_freeze_exec_test.txt").unwrap();
+    /// # defer!(std::fs::remove_file("mmap_file_freeze_exec_test.txt").unwrap());
+    /// file.truncate(12);
+    /// file.write_all("some data...".as_bytes(), 0).unwrap();
+    /// file.flush().unwrap();
+    ///
+    /// let file = file.freeze_exec().unwrap();
+    /// assert!(file.is_exec());
+    /// ```
+    pub fn freeze_exec(mut self) -> Result<MmapFile> {
+        let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
+        // swap the inner to empty
+        let inner = mem::replace(&mut self.inner, empty);
+        match inner {
+            MmapFileMutInner::Empty(empty) => Ok(MmapFile::from(empty)), // unreachable, keep this for good measure
+            MmapFileMutInner::Memory(memory) => Ok(MmapFile::from(memory.freeze())),
+            MmapFileMutInner::Anonymous(anon) => Ok(MmapFile::from(MemoryMmapFile::copy_from_slice(
+                anon.path(),
+                anon.as_slice(),
+            ))),
+            MmapFileMutInner::Disk(disk) => Ok(MmapFile::from(disk.freeze_exec()?)),
+        }
+    }
+
+    /// Returns whether remove the underlying file on drop.
+    #[inline]
+    pub fn get_remove_on_drop(&self) -> bool {
+        self.remove_on_drop
+    }
+
+    /// Whether remove the underlying file on drop.
+    /// Default is false.
+    ///
+    /// # Notes
+    /// If invoke [`MmapFileMut::freeze`], then the file will
+    /// not be removed even though the field `remove_on_drop` is true.
+    ///
+    /// [`MmapFileMut::freeze`]: structs.MmapFileMut.html#methods.freeze
+    #[inline]
+    pub fn set_remove_on_drop(&mut self, val: bool) {
+        self.remove_on_drop = val;
+    }
+
+    /// Returns whether flush the mmap on drop.
+    #[inline]
+    pub fn get_flush_on_drop(&self) -> bool {
+        self.flush_on_drop
+    }
+
+    /// Whether flush the mmap on drop.
+    /// Default is false.
+    ///
+    /// # Notes
+    /// The flush on drop is best-effort, any error is ignored, just like the
+    /// removal of `remove_on_drop`. Call [`flush`] explicitly if you need to handle the error.
+    ///
+    /// If `remove_on_drop` is also set to `true`, the mmap will not be flushed, since
+    /// the underlying file is removed anyway.
+    ///
+    /// [`flush`]: trait.MmapFileMutExt.html#tymethod.flush
+    #[inline]
+    pub fn set_flush_on_drop(&mut self, val: bool) {
+        self.flush_on_drop = val;
+    }
+
+    /// The sentinel `max_sz` for [`close`] and [`close_with_truncate`], which truncates
+    /// the file to the [high-water mark] instead of a given size.
+    ///
+    /// [`close`]: structs.MmapFileMut.html#method.close
+    /// [`close_with_truncate`]: traits.MmapFileMutExt.html#tymethod.close_with_truncate
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
+    pub const TRUNCATE_TO_HIGH_WATER_MARK: i64 = i64::MIN;
+
+    /// Returns the high-water mark, which is the end of the furthest bytes written.
+    ///
+    /// A created file starts at zero (or at the end of its initial contents), while an opened
+    /// or in-memory file starts at the end of the mmap, so its existing content counts as written.
+    ///
+    /// The high-water mark is advanced by [`write`], [`write_all`] (and the `write_*` methods built on them)
+    /// and the writer returned by [`writer_at_end`], it is lowered by [`truncate`] when the mmap shrinks.
+    /// Bytes written through [`as_mut_slice`] or the other writers are not tracked.
+    ///
+    /// [`write`]: traits.MmapFileMutExt.html#method.write
+    /// [`write_all`]: traits.MmapFileMutExt.html#method.write_all
+    /// [`writer_at_end`]: structs.MmapFileMut.html#method.writer_at_end
+    /// [`truncate`]: traits.MmapFileMutExt.html#tymethod.truncate
+    /// [`as_mut_slice`]: traits.MmapFileMutExt.html#tymethod.as_mut_slice
+    #[inline]
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water
+    }
+
+    /// Returns the generation of the content, which is bumped by every mutation, e.g. to
+    /// invalidate a cached checksum by comparing the generation it is computed at.
+    ///
+    /// The mutations are the ones which mark the [dirty ranges], plus [`truncate`]. The writes
+    /// through the raw slice cannot be observed, so any call of [`as_mut_slice`] (including by the
+    /// writers and other methods built on it) conservatively bumps it, even if nothing is written.
+    ///
+    /// [dirty ranges]: structs.MmapFileMut.html#method.dirty_ranges
+    /// [`truncate`]: traits.MmapFileMutExt.html#tymethod.truncate
+    /// [`as_mut_slice`]: traits.MmapFileMutExt.html#tymethod.as_mut_slice
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the ranges written since they were last flushed, sorted and coalesced,
+    /// e.g. to report how much is unflushed or to build a custom flush policy.
+    ///
+    /// The ranges are marked by [`write`], [`write_all`] (and the `write_*` methods built on them),
+    /// and cleaned by [`flush`] and [`flush_range`]. The asynchronous flushes do not clean them,
+    /// as they do not wait for the data to be durable.
+    ///
+    /// The writes through the raw slice of [`as_mut_slice`] cannot be observed, so any call of it
+    /// (including by the writers and other methods built on it) conservatively marks the whole
+    /// mmap as dirty.
+    ///
+    /// [`write`]: traits.MmapFileMutExt.html#method.write
+    /// [`write_all`]: traits.MmapFileMutExt.html#method.write_all
+    /// [`flush`]: traits.MmapFileMutExt.html#tymethod.flush
+    /// [`flush_range`]: traits.MmapFileMutExt.html#tymethod.flush_range
+    /// [`as_mut_slice`]: traits.MmapFileMutExt.html#tymethod.as_mut_slice
+    pub fn dirty_ranges(&self) -> Vec<Range<usize>> {
+        self.dirty.to_vec()
+    }
+
+    /// Returns whether there are written bytes not flushed yet, see [`dirty_ranges`].
+    ///
+    /// [`dirty_ranges`]: structs.MmapFileMut.html#method.dirty_ranges
+    #[inline]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty()
+    }
+
+    /// Sets the strategy to grow the mmap, which is also configured by `grow_increment`
+    /// of [`Options`] when the mmap is created or opened, see [`grow_to_fit`].
+    ///
+    /// [`Options`]: structs.Options.html
+    /// [`grow_to_fit`]: traits.MmapFileMutExt.html#method.grow_to_fit
+    #[inline]
+    pub fn set_grow_strategy(&mut self, strategy: crate::GrowStrategy) {
+        self.grow = strategy;
     }
 
-    /// Make the mmap file read-only.
+    /// Sets the durability of [`flush`], which is also configured by `flush_policy` of
+    /// [`Options`] when the mmap is created or opened. See [`FlushPolicy`] for the trade-offs.
     ///
-    /// # Notes
-    /// If `remove_on_drop` is set to `true`, then the underlying file will not be removed on drop if this function is invoked. [Read more]
+    /// [`flush`]: traits.MmapFileMutExt.html#tymethod.flush
+    /// [`Options`]: structs.Options.html
+    /// [`FlushPolicy`]: crate::FlushPolicy
+    #[inline]
+    pub fn set_flush_policy(&mut self, policy: crate::FlushPolicy) {
+        self.flush_policy = policy;
+    }
+
+    /// Returns a [`MmapFileWriter`] starting at the [high-water mark], which advances
+    /// the high-water mark as it writes.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::EOF))` if there is no room after the high-water mark.
     ///
     /// # Examples
     /// ```no_compile
-    /// use fmmap::{MmapFileMut, MmapFileMutExt};
+    /// use fmmap::{MmapFileMut, MmapFileMutExt, Options};
+    /// use std::io::Write;
     /// # use scopeguard::defer;
     ///
-    /// let mut file = MmapFileMut::create("mmap_file_freeze_test.txt").unwrap();
-    /// # defer!(std::fs::remove_file("mmap_file_freeze_test.txt").unwrap());
-    /// file.truncate(12);
+    /// let mut file = MmapFileMut::create_with_options("writer_at_end_test.txt", Options::new().max_size(100)).unwrap();
+    /// # defer!(std::fs::remove_file("writer_at_end_test.txt").unwrap());
     /// file.write_all("some data...".as_bytes(), 0).unwrap();
-    /// file.flush().unwrap();
+    /// file.writer_at_end().unwrap().write_all("more data...".as_bytes()).unwrap();
+    /// assert_eq!(file.high_water_mark(), 24);
     ///
-    /// file.freeze().unwrap();
+    /// // truncate the file to 24 bytes rather than 100 bytes
+    /// file.close(MmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK).unwrap();
     /// ```
     ///
-    /// [Read more]: structs.MmapFileMut.html#methods.set_remove_on_drop
-    pub fn freeze(mut self) -> Result<MmapFile> {
-        let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
-        // swap the inner to empty
-        let inner = mem::replace(&mut self.inner, empty);
-        match inner {
-            MmapFileMutInner::Empty(empty) => Ok(MmapFile::from(empty)), // unreachable, keep this for good measure
-            MmapFileMutInner::Memory(memory) => Ok(MmapFile::from(memory.freeze())),
-            MmapFileMutInner::Disk(disk) => Ok(MmapFile::from(disk.freeze()?)),
+    /// [`MmapFileWriter`]: structs.MmapFileWriter.html
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
+    pub fn writer_at_end(&mut self) -> Result<MmapFileWriter<'_>> {
+        let offset = self.high_water;
+        let buf = self.inner.as_mut_slice();
+        let buf_len = buf.len();
+        if buf_len <= offset {
+            Err(Error::from(ErrorKind::EOF))
+        } else {
+            self.dirty.mark(offset..buf_len);
+            self.generation += 1;
+            Ok(MmapFileWriter::with_high_water(
+                Cursor::new(&mut buf[offset..]),
+                offset,
+                buf_len - offset,
+                &mut self.high_water,
+            ))
         }
     }
 
-    /// Transition the memory map to be readable and executable.
-    /// If the memory map is file-backed, the file must have been opened with execute permissions.
-    ///
-    /// # Errors
-    /// This method returns an error when the underlying system call fails,
-    /// which can happen for a variety of reasons,
-    /// such as when the file has not been opened with execute permissions
-    ///
-    /// # Examples
-    /// ```no_compile
-    /// use fmmap::{MmapFileExt, MmapFileMut, MmapFileMutExt};
-    /// # use scopeguard::defer;
+    /// Zeroes the bytes from the [high-water mark] to the end of the mmap, then flushes them.
     ///
-    /// let mut file = MmapFileMut::create("mmap_file_freeze_exec_test.txt").unwrap();
-    /// # defer!(std::fs::remove_file("mmap_file_freeze_exec_test.txt").unwrap());
-    /// file.truncate(12);
-    /// file.write_all("some data...".as_bytes(), 0).unwrap();
-    /// file.flush().unwrap();
+    /// The tail of an over-allocated mmap (e.g. created with a `max_size` larger than the
+    /// bytes written) is not guaranteed to be zero, so this gives deterministic content
+    /// before flushing, freezing or hashing the mmap. Only the writes tracked by the
+    /// high-water mark are preserved.
     ///
-    /// let file = file.freeze_exec().unwrap();
-    /// assert!(file.is_exec());
-    /// ```
-    pub fn freeze_exec(mut self) -> Result<MmapFile> {
-        let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
-        // swap the inner to empty
-        let inner = mem::replace(&mut self.inner, empty);
-        match inner {
-            MmapFileMutInner::Empty(empty) => Ok(MmapFile::from(empty)), // unreachable, keep this for good measure
-            MmapFileMutInner::Memory(memory) => Ok(MmapFile::from(memory.freeze())),
-            MmapFileMutInner::Disk(disk) => Ok(MmapFile::from(disk.freeze_exec()?)),
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
+    pub fn zero_unwritten(&mut self) -> Result<()> {
+        let offset = self.high_water;
+        let buf = self.inner.as_mut_slice();
+        let buf_len = buf.len();
+        if buf_len <= offset {
+            return Ok(());
         }
+        buf[offset..].fill(0);
+        self.flush_range(offset, buf_len - offset)
     }
 
-    /// Returns whether remove the underlying file on drop.
+    /// Returns the number of bytes written, which is the [high-water mark] [`zero_unwritten`]
+    /// zeroes the tail from. The bytes after it are the unwritten tail of the mmap.
+    ///
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
+    /// [`zero_unwritten`]: structs.MmapFileMut.html#method.zero_unwritten
     #[inline]
-    pub fn get_remove_on_drop(&self) -> bool {
-        self.remove_on_drop
+    pub fn written_len(&self) -> usize {
+        self.high_water
     }
 
-    /// Whether remove the underlying file on drop.
-    /// Default is false.
+    /// Shrinks the mmap to the [high-water mark], reclaiming the room allocated but never written,
+    /// e.g. after creating the file with an over-estimated `max_size`.
+    ///
+    /// - __Disk__: the file is truncated and remapped, then its parent directory is synced,
+    ///   so the new length is durable.
+    /// - __Disk (copy-on-write)__: the file cannot be truncated, so the written bytes are copied
+    ///   into an in-memory mmap, which replaces the mapping. The file is left untouched.
+    /// - __Memory__ and __Anonymous__: the buffer is shrunk.
+    ///
+    /// Does nothing if there is no room after the high-water mark.
+    ///
+    /// # Errors
+    /// Returns the error returned by [`truncate`], or by syncing the parent directory.
+    ///
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
+    /// [`truncate`]: traits.MmapFileMutExt.html#tymethod.truncate
+    pub fn shrink_to_fit(&mut self) -> Result<()> {
+        let len = self.high_water;
+        if self.inner.len() <= len {
+            return Ok(());
+        }
+
+        match &self.inner {
+            MmapFileMutInner::Disk(disk) if disk.is_cow() => {
+                let memory = MemoryMmapFileMut::from_slice(disk.path(), &disk.as_slice()[..len]);
+                self.inner = MmapFileMutInner::Memory(memory);
+                self.dirty.clamp(len);
+                self.generation += 1;
+                Ok(())
+            }
+            MmapFileMutInner::Disk(_) => {
+                self.truncate(len as u64)?;
+                crate::utils::sync_parent(self.path())
+            }
+            _ => self.truncate(len as u64),
+        }
+    }
+
+    /// Sets a mirror of the mmap, which receives the same `write`, `write_all`, `flush`,
+    /// `flush_range` and `truncate` as this one, e.g. to keep a redundant copy of a critical
+    /// file. The mirror is resized to the length of this mmap and the current content is copied
+    /// to it first. A mirror which is already set is replaced (and dropped).
+    ///
+    /// The operations are applied to this mmap first, and then to the mirror, so this mmap is
+    /// always the authoritative one. If an operation fails on the mirror, it returns an
+    /// `ErrorKind::MirrorFailed` error, whose message contains the path of the mirror, and the
+    /// operation is **not** rolled back on this mmap.
     ///
     /// # Notes
-    /// If invoke [`MmapFileMut::freeze`], then the file will
-    /// not be removed even though the field `remove_on_drop` is true.
+    /// The two files are not updated atomically: a crash may leave the mirror behind (or, since
+    /// the OS writes back the pages whenever it likes, ahead of) this file, so the mirror is not a
+    /// substitute for a write-ahead log. Only the methods listed above are mirrored, the writes
+    /// through [`as_mut_slice`] (and the methods and writers built on it) are not, use
+    /// [`verify_mirror`] to check the two are the same. A short `write` of the mirror is returned
+    /// as the number of bytes written, even if this mmap has written more.
     ///
-    /// [`MmapFileMut::freeze`]: structs.MmapFileMut.html#methods.freeze
+    /// # Errors
+    /// Returns `Err(Error::from(ErrorKind::MirrorFailed))` if the mirror cannot be resized or written.
+    ///
+    /// [`as_mut_slice`]: traits.MmapFileMutExt.html#tymethod.as_mut_slice
+    /// [`verify_mirror`]: structs.MmapFileMut.html#method.verify_mirror
+    pub fn set_mirror(&mut self, mut mirror: MmapFileMut) -> Result<()> {
+        let res = if mirror.len() == self.len() {
+            Ok(())
+        } else {
+            mirror.truncate(self.len() as u64)
+        };
+        res.and_then(|_| mirror.write_all(self.as_slice(), 0))
+            .map_err(|e| mirror_failed(&mirror, e))?;
+        self.mirror = Some(Box::new(mirror));
+        Ok(())
+    }
+
+    /// Returns the mirror set by [`set_mirror`], if any.
+    ///
+    /// [`set_mirror`]: structs.MmapFileMut.html#method.set_mirror
     #[inline]
-    pub fn set_remove_on_drop(&mut self, val: bool) {
-        self.remove_on_drop = val;
+    pub fn mirror(&self) -> Option<&MmapFileMut> {
+        self.mirror.as_deref()
+    }
+
+    /// Detaches and returns the mirror set by [`set_mirror`], if any.
+    ///
+    /// [`set_mirror`]: structs.MmapFileMut.html#method.set_mirror
+    #[inline]
+    pub fn take_mirror(&mut self) -> Option<MmapFileMut> {
+        self.mirror.take().map(|mirror| *mirror)
+    }
+
+    /// Returns whether the mirror has the same content as this mmap, or `true` if there is
+    /// no mirror.
+    pub fn verify_mirror(&self) -> bool {
+        self.mirror
+            .as_ref()
+            .map_or(true, |mirror| mirror.as_slice() == self.as_slice())
+    }
+
+    fn mirror_op(&self, op: impl FnOnce(&MmapFileMut) -> Result<()>) -> Result<()> {
+        match &self.mirror {
+            Some(mirror) => op(mirror).map_err(|e| mirror_failed(mirror, e)),
+            None => Ok(()),
+        }
+    }
+
+    fn mirror_op_mut(&mut self, op: impl FnOnce(&mut MmapFileMut) -> Result<()>) -> Result<()> {
+        match &mut self.mirror {
+            Some(mirror) => op(mirror).map_err(|e| mirror_failed(mirror, e)),
+            None => Ok(()),
+        }
+    }
+
+    #[inline]
+    fn resolve_truncate_size(&self, max_sz: i64) -> i64 {
+        if max_sz == Self::TRUNCATE_TO_HIGH_WATER_MARK {
+            self.high_water as i64
+        } else {
+            max_sz
+        }
     }
 
     /// Close the file. It would also truncate the file if max_sz >= 0.
+    ///
+    /// If `max_sz` is [`MmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`], the file is truncated to the [high-water mark].
+    ///
+    /// [`MmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK`]: structs.MmapFileMut.html#associatedconstant.TRUNCATE_TO_HIGH_WATER_MARK
+    /// [high-water mark]: structs.MmapFileMut.html#method.high_water_mark
     #[inline]
     pub fn close(&mut self, max_sz: i64) -> Result<()> {
+        let max_sz = self.resolve_truncate_size(max_sz);
         let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
         // swap the inner to empty
         let inner = mem::replace(&mut self.inner, empty);
@@ -1468,12 +3568,32 @@ impl MmapFileMut {
     }
 
     /// Remove the underlying file without dropping, leaving an [`EmptyMmapFile`].
+    ///
+    /// # Errors
+    /// On unix, the file is removed before it is unmapped, so if the removal fails
+    /// (e.g. no permission), the mmap is left intact and the removal can be retried,
+    /// or deferred by [`set_remove_on_drop`]. On other platforms, the file must be closed
+    /// before it is removed, so the mmap is left empty even if the removal fails.
+    ///
+    /// [`set_remove_on_drop`]: structs.MmapFileMut.html#method.set_remove_on_drop
     #[inline]
     pub fn remove(&mut self) -> Result<()> {
         let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
         // swap the inner to empty
         let inner = mem::replace(&mut self.inner, empty);
         match inner {
+            #[cfg(unix)]
+            MmapFileMutInner::Disk(disk) => {
+                if let Err(e) = std::fs::remove_file(&disk.path) {
+                    // put the mmap back, so the caller can retry
+                    self.inner = MmapFileMutInner::Disk(disk);
+                    return Err(e.into());
+                }
+                drop(disk.mmap);
+                // release the space even if the file is still open elsewhere
+                disk.file.set_len(0).map_err(From::from)
+            }
+            #[cfg(not(unix))]
             MmapFileMutInner::Disk(disk) => {
                 let path = disk.path;
                 drop(disk.mmap);
@@ -1488,6 +3608,62 @@ impl MmapFileMut {
             _ => Ok(()),
         }
     }
+
+    /// Overwrite the content of the mmap, then remove the underlying file.
+    ///
+    /// For a disk mmap, the content is overwritten `passes` times (at least once), the
+    /// patterns alternate between `0xFF` and `0x00` and the last pass always writes zeros.
+    /// The mmap is flushed and the file is fsynced after each pass. Then the file is removed
+    /// and its parent directory is fsynced.
+    ///
+    /// For in-memory and copy-on-write mmaps, the content is zeroed and the mmap is dropped,
+    /// the underlying file of a copy-on-write mmap is left untouched.
+    ///
+    /// # Notes
+    /// Overwriting in place does not guarantee the old data is unrecoverable. SSDs
+    /// (wear-leveling), copy-on-write or journaling filesystems (e.g. btrfs, ZFS) and
+    /// snapshots may keep the old blocks elsewhere on the device.
+    pub fn secure_remove(self, passes: u8) -> Result<()> {
+        self.secure_remove_with(passes, WipePattern::Alternating)
+    }
+
+    /// Overwrite the content of the mmap with the pattern, then remove the underlying file.
+    ///
+    /// The same as [`secure_remove`], except the passes before the last one write the
+    /// [`WipePattern`], e.g. pseudo-random bytes. The last pass always writes zeros.
+    ///
+    /// [`secure_remove`]: #method.secure_remove
+    pub fn secure_remove_with(mut self, passes: u8, pattern: WipePattern) -> Result<()> {
+        let path = match &mut self.inner {
+            MmapFileMutInner::Disk(disk) if !disk.is_cow() => {
+                let path = disk
+                    .path
+                    .canonicalize()
+                    .map_err(|e| Error::new(ErrorKind::IO, e))?;
+                for pass in (0..passes.max(1)).rev() {
+                    pattern.fill(disk.as_mut_slice(), pass);
+                    disk.flush()?;
+                    disk.file.sync_all().map_err(|e| {
+                        Error::new_source_msg(ErrorKind::SyncFileFailed, path.to_string_lossy(), e)
+                    })?;
+                }
+                path
+            }
+            inner => {
+                inner.as_mut_slice().fill(0);
+                // nothing to remove, the mmap is dropped
+                self.deleted = true;
+                return Ok(());
+            }
+        };
+
+        let empty = MmapFileMutInner::Empty(EmptyMmapFile::default());
+        // swap the inner to empty
+        let inner = mem::replace(&mut self.inner, empty);
+        inner.drop_remove()?;
+        self.deleted = true;
+        crate::utils::sync_dir(path.parent().unwrap())
+    }
 }
 
 impl_constructor_for_memory_mmap_file_mut!(MemoryMmapFileMut, MmapFileMut, "MmapFileMut", "sync");
@@ -1495,3 +3671,1028 @@ impl_constructor_for_memory_mmap_file_mut!(MemoryMmapFileMut, MmapFileMut, "Mmap
 impl_drop!(MmapFileMut, MmapFileMutInner, EmptyMmapFile);
 
 impl_sync_tests!("", MmapFile, MmapFileMut);
+
+#[test]
+fn test_is_closed() {
+    let path = "sync_is_closed.txt";
+    let mut file = MmapFileMut::create(path).unwrap();
+    assert!(!file.is_closed());
+    assert_eq!(file.as_slice_checked(), Some(&[][..]));
+
+    file.remove().unwrap();
+    assert!(file.is_closed());
+    assert!(file.as_slice_checked().is_none());
+    assert!(file.as_slice().is_empty());
+}
+
+#[test]
+fn test_flush_on_drop() {
+    let path = "sync_flush_on_drop.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    assert!(!file.get_flush_on_drop());
+    file.set_flush_on_drop(true);
+    assert!(file.get_flush_on_drop());
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+}
+
+#[test]
+fn test_freeze_with_handle() {
+    let path = "sync_freeze_with_handle.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    let (file, handle) = file.freeze_with_handle().unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+    assert_eq!(handle.metadata().unwrap().len(), 12);
+    drop(file);
+    assert_eq!(handle.metadata().unwrap().len(), 12);
+
+    let file = MmapFileMut::memory_with_capacity("foo.mem", 10);
+    assert_eq!(
+        file.freeze_with_handle().err().unwrap().kind(),
+        ErrorKind::NotSupported
+    );
+}
+
+#[test]
+fn test_secure_remove() {
+    let path = "sync_secure_remove.txt";
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    file.secure_remove(3).unwrap();
+    assert!(!Path::new(path).exists());
+
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    file.secure_remove_with(3, WipePattern::Random).unwrap();
+    assert!(!Path::new(path).exists());
+
+    // nothing is written to the path of an in-memory mmap
+    let mut file = MmapFileMut::memory_with_capacity("sync_secure_remove.mem", 12);
+    file.truncate(12).unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    file.secure_remove(1).unwrap();
+    assert!(!Path::new("sync_secure_remove.mem").exists());
+
+    // the underlying file of a copy-on-write mmap is left untouched
+    std::fs::write(path, b"some data...").unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::open_cow(path).unwrap();
+    file.write_all(b"more", 0).unwrap();
+    file.secure_remove_with(2, WipePattern::Random).unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+}
+
+#[test]
+fn test_writer_at_end() {
+    let path = "sync_writer_at_end.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(100)).unwrap();
+    assert_eq!(file.high_water_mark(), 0);
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    assert_eq!(file.high_water_mark(), 12);
+    file.writer_at_end()
+        .unwrap()
+        .write_all("more data...".as_bytes())
+        .unwrap();
+    assert_eq!(file.high_water_mark(), 24);
+    file.write_u32(7, 4).unwrap();
+    assert_eq!(file.high_water_mark(), 24);
+
+    file.close(MmapFileMut::TRUNCATE_TO_HIGH_WATER_MARK).unwrap();
+    assert_eq!(std::fs::metadata(path).unwrap().len(), 24);
+}
+
+#[test]
+fn test_kind() {
+    use crate::MapKind;
+
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    assert_eq!(file.kind(), MapKind::Memory);
+    assert!(file.is_memory() && !file.is_disk());
+
+    let path = "sync_kind.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    assert_eq!(file.kind(), MapKind::Disk);
+    assert!(file.is_disk() && !file.is_memory());
+    file.close(-1).unwrap();
+    assert_eq!(file.kind(), MapKind::Empty);
+}
+
+#[test]
+fn test_into_vec() {
+    let mut file = MmapFileMut::memory_with_capacity("foo.mem", 64);
+    file.truncate(12).unwrap();
+    file.write_all(b"some data...", 0).unwrap();
+    let ptr = file.as_slice().as_ptr();
+    let v = file.into_vec();
+    assert_eq!(v, b"some data...");
+    assert_eq!(v.as_ptr(), ptr);
+
+    let path = "sync_into_vec.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    file.set_remove_on_drop(true);
+    file.write_all(b"some data...", 0).unwrap();
+    assert_eq!(file.into_vec(), b"some data...");
+    // the file is not removed, as the mmap is consumed rather than dropped
+    assert!(std::path::Path::new(path).exists());
+}
+
+#[test]
+fn test_copy_to_bytes_memory_is_zero_copy() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    let bytes = file.copy_range_to_bytes(5, 4).unwrap();
+    assert_eq!(bytes.as_ref(), "data".as_bytes());
+    assert_eq!(bytes.as_ptr(), file.as_slice()[5..].as_ptr());
+    assert_eq!(file.copy_all_to_bytes().as_ptr(), file.as_slice().as_ptr());
+}
+
+#[test]
+fn test_page_ranges() {
+    let page = crate::utils::page_size();
+    let file = MmapFile::memory_from_vec("foo.mem", vec![0; page * 2 + 10]);
+    assert_eq!(file.page_size(), page);
+    assert_eq!(file.mapped_page_size(), page);
+    let ranges = file.page_ranges();
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(
+        ranges.collect::<Vec<_>>(),
+        vec![0..page, page..page * 2, page * 2..page * 2 + 10]
+    );
+
+    let file = MmapFile::memory_from_vec("foo.mem", vec![0; page]);
+    assert_eq!(file.page_ranges().collect::<Vec<_>>(), vec![0..page]);
+    let file = MmapFile::memory_from_vec("foo.mem", vec![]);
+    assert_eq!(file.page_ranges().next(), None);
+}
+
+#[test]
+#[cfg(feature = "digest")]
+fn test_digest() {
+    use sha2::{Digest, Sha256};
+
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    assert_eq!(file.digest::<Sha256>(), Sha256::digest(b"some data..."));
+    assert_eq!(file.digest_range::<Sha256>(5..9).unwrap(), Sha256::digest(b"data"));
+    assert_eq!(file.digest_range::<Sha256>(5..13).unwrap_err().kind(), ErrorKind::EOF);
+}
+
+#[test]
+#[cfg(feature = "digest")]
+fn test_merkle() {
+    use sha2::{Digest, Sha256};
+
+    let node = |l: &[u8], r: &[u8]| {
+        let mut h = Sha256::new();
+        h.update(l);
+        h.update(r);
+        h.finalize()
+    };
+    let file = MmapFile::memory_from_str("foo.mem", "aaaabbbbcc");
+    let (a, b, c) = (Sha256::digest(b"aaaa"), Sha256::digest(b"bbbb"), Sha256::digest(b"cc"));
+    let ab = node(&a, &b);
+    assert_eq!(file.merkle_root::<Sha256>(4).unwrap(), node(&ab, &c));
+    assert_eq!(file.merkle_proof::<Sha256>(0, 4).unwrap(), vec![b, c]);
+    assert_eq!(file.merkle_proof::<Sha256>(2, 4).unwrap(), vec![ab]);
+    assert_eq!(file.merkle_proof::<Sha256>(3, 4).unwrap_err().kind(), ErrorKind::EOF);
+    assert_eq!(file.merkle_root::<Sha256>(0).unwrap_err().kind(), ErrorKind::BadFormat);
+    assert_eq!(file.merkle_root::<Sha256>(16).unwrap(), Sha256::digest(b"aaaabbbbcc"));
+
+    let file = MmapFile::memory_from_vec("foo.mem", vec![]);
+    assert_eq!(file.merkle_root::<Sha256>(4).unwrap(), Sha256::digest(b""));
+    assert!(file.merkle_proof::<Sha256>(0, 4).unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "blake3")]
+fn test_blake3() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    assert_eq!(file.blake3(), blake3::hash(b"some data..."));
+}
+
+#[test]
+fn test_zero_unwritten() {
+    let path = "sync_zero_unwritten.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(16)).unwrap();
+    file.as_mut_slice().fill(0xFF);
+    assert_eq!(file.written_len(), 0);
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    assert_eq!(file.written_len(), 12);
+    file.zero_unwritten().unwrap();
+    assert_eq!(&file.as_slice()[..12], b"some data...");
+    assert_eq!(&file.as_slice()[12..], &[0; 4]);
+    file.truncate(8).unwrap();
+    assert_eq!(file.written_len(), 8);
+    drop(file);
+
+    // the content of a reopened file counts as written
+    let mut file = MmapFileMut::open(path).unwrap();
+    assert_eq!(file.written_len(), 8);
+    file.zero_unwritten().unwrap();
+    assert_eq!(file.as_slice(), b"some dat");
+    assert_eq!(std::fs::read(path).unwrap(), b"some dat");
+
+    let mut file = MmapFileMut::memory_with_capacity("foo.mem", 0);
+    file.truncate(8).unwrap();
+    file.as_mut_slice().fill(0xFF);
+    file.zero_unwritten().unwrap();
+    assert_eq!(file.written_len(), 0);
+    assert_eq!(file.as_slice(), &[0; 8]);
+}
+
+#[test]
+fn test_try_open() {
+    let path = "sync_try_open.txt";
+    assert!(MmapFile::try_open(path).unwrap().is_none());
+    assert!(MmapFile::try_open_with_options(path, Options::new()).unwrap().is_none());
+
+    std::fs::write(path, "some data...").unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let file = MmapFile::try_open(path).unwrap().unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+}
+
+#[test]
+fn test_sniff() {
+    use crate::KnownMagic;
+
+    let file = MmapFile::memory_from_slice("foo.mem", b"\x89PNG\r\n\x1A\n\0\0\0\rIHDR");
+    assert_eq!(file.sniff(), Some(KnownMagic::Png));
+
+    let file = MmapFile::memory_from_slice("foo.mem", b"SQLite format 3\0\x10\0");
+    assert_eq!(file.sniff(), Some(KnownMagic::Sqlite));
+
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    assert_eq!(file.sniff(), None);
+
+    let file = MmapFile::memory_from_slice("foo.mem", b"\x7F");
+    assert_eq!(file.sniff(), None);
+}
+
+#[test]
+fn test_fold_chunks() {
+    let data: Vec<u8> = (0..=255u8).cycle().take(10_000).collect();
+    let expected: u64 = data.iter().map(|b| *b as u64).sum();
+    let file = MmapFile::memory_from_vec("foo.mem", data);
+
+    let sum = |acc: u64, chunk: &[u8]| acc + chunk.iter().map(|b| *b as u64).sum::<u64>();
+    assert_eq!(file.fold_chunks(1000, 0u64, sum), expected);
+    assert_eq!(file.fold_chunks(3, 0u64, sum), expected);
+    assert_eq!(file.fold_chunks(4096, 0usize, |acc, _| acc + 1), 3);
+
+    #[cfg(feature = "rayon")]
+    assert_eq!(file.fold_chunks_par(7, 0u64, sum, |a, b| a + b), expected);
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn test_rolling_checksums() {
+    use crate::RollingChecksum;
+
+    let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+    let file = MmapFile::memory_from_vec("foo.mem", data.clone());
+
+    let sums = file.rolling_checksums(4096).collect::<Vec<_>>();
+    assert_eq!(sums.len(), 3);
+    for ((weak, strong), block) in sums.iter().zip(data.chunks(4096)) {
+        assert_eq!(*weak, RollingChecksum::new(block).value());
+        assert_eq!(strong[..], blake3::hash(block).as_bytes()[..16]);
+    }
+}
+
+#[test]
+fn test_to_anonymous() {
+    let path = "sync_to_anonymous.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    file.write_all(b"some data...", 0).unwrap();
+    file.flush().unwrap();
+
+    let mut anon = file.to_anonymous().unwrap();
+    assert_eq!(anon.kind(), crate::MapKind::Anonymous);
+    assert!(anon.kind().is_anonymous() && !anon.is_memory());
+    assert_eq!(anon.as_slice(), b"some data...");
+
+    anon.write_all(b"anon", 0).unwrap();
+    anon.flush().unwrap();
+    assert_eq!(anon.as_slice(), b"anon data...");
+    assert_eq!(file.as_slice(), b"some data...");
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+
+    anon.truncate(4096).unwrap();
+    assert_eq!(&anon.as_slice()[..12], b"anon data...");
+    assert!(anon.as_slice()[12..].iter().all(|b| *b == 0));
+    assert_eq!(anon.into_vec().len(), 4096);
+}
+
+#[test]
+fn test_with_path_label() {
+    let file = MmapFile::memory_from_vec("", vec![1; 8]).with_path_label("cache/segment-0");
+    assert_eq!(file.path(), Path::new("cache/segment-0"));
+
+    let file = MmapFileMut::memory_from_vec("", vec![0; 8]).with_path_label(PathBuf::from("scratch"));
+    assert_eq!(file.path_string(), "scratch");
+    let anon = file.to_anonymous().unwrap().with_path_label("anon");
+    assert_eq!(anon.path_string(), "anon");
+
+    let path = "sync_with_path_label.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let file = MmapFileMut::create_with_options(path, Options::new().max_size(8))
+        .unwrap()
+        .with_path_label("ignored");
+    assert_eq!(file.path(), Path::new(path));
+}
+
+#[test]
+fn test_iter_len_prefixed() {
+    let mut data = Vec::new();
+    for payload in [&b"foo"[..], b"", b"some data..."] {
+        data.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        data.extend_from_slice(payload);
+    }
+    let file = MmapFile::memory_from_vec("foo.mem", data.clone());
+    let records = file.iter_len_prefixed(0).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(records, [&b"foo"[..], b"", b"some data..."]);
+    assert_eq!(file.iter_len_prefixed(7).count(), 2);
+
+    // a truncated final record
+    data.extend_from_slice(&[0, 0, 0, 8, 1, 2]);
+    let file = MmapFile::memory_from_vec("foo.mem", data);
+    let mut iter = file.iter_len_prefixed(0).skip(3);
+    assert_eq!(iter.next().unwrap().unwrap_err().kind(), ErrorKind::EOF);
+    assert!(iter.next().is_none());
+
+    let file = MmapFile::memory_from_slice("foo.mem", b"\x03\x00\x00\x00abc\x00\x00");
+    let mut iter = file.iter_len_prefixed_le(0);
+    assert_eq!(iter.next().unwrap().unwrap(), b"abc");
+    assert_eq!(iter.next().unwrap().unwrap_err().kind(), ErrorKind::EOF);
+
+    let mut data = vec![0x96, 0x01];
+    data.extend_from_slice(&[7; 150]);
+    data.push(0);
+    let file = MmapFile::memory_from_vec("foo.mem", data);
+    let records = file.iter_len_prefixed_varint(0).collect::<Result<Vec<_>>>().unwrap();
+    assert_eq!(records, [&[7u8; 150][..], b""]);
+
+    let file = MmapFile::memory_from_slice("foo.mem", &[0xFF; 11]);
+    let mut iter = file.iter_len_prefixed_varint(0);
+    assert_eq!(iter.next().unwrap().unwrap_err().kind(), ErrorKind::BadFormat);
+}
+
+#[cfg(target_has_atomic = "64")]
+#[test]
+fn test_atomic_append() {
+    let path = "sync_atomic_append.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(32)).unwrap();
+    let reader = MmapFile::open(path).unwrap();
+
+    assert_eq!(file.atomic_append(b"some ", 0).unwrap(), 5);
+    assert_eq!(file.atomic_append(b"data...", 0).unwrap(), 12);
+    assert_eq!(reader.published_len(0).unwrap(), 12);
+    assert_eq!(&reader.as_slice()[8..20], b"some data...");
+
+    assert_eq!(file.atomic_append(&[0; 13], 0).unwrap_err().kind(), ErrorKind::EOF);
+    assert_eq!(file.published_len(0).unwrap(), 12);
+    assert_eq!(file.atomic_append(b"x", 30).unwrap_err().kind(), ErrorKind::EOF);
+    assert_eq!(file.atomic_append(b"x", 4).unwrap_err().kind(), ErrorKind::Misaligned);
+}
+
+#[test]
+fn test_resize_preserving() {
+    fn check(file: &mut MmapFileMut) {
+        file.write_all(b"some data...", 0).unwrap();
+        file.resize_preserving(20).unwrap();
+        assert_eq!(&file.as_slice()[..12], b"some data...");
+        assert_eq!(&file.as_slice()[12..], &[0; 8]);
+        file.resize_preserving(4).unwrap();
+        assert_eq!(file.as_slice(), b"some");
+        file.resize_preserving(8).unwrap();
+        assert_eq!(file.as_slice(), b"some\0\0\0\0");
+    }
+
+    let mut file = MmapFileMut::memory_from_vec("foo.mem", vec![0; 12]);
+    check(&mut file);
+    let mut anon = file.to_anonymous().unwrap();
+    anon.truncate(12).unwrap();
+    check(&mut anon);
+
+    let path = "sync_resize_preserving.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    check(&mut file);
+    drop(file);
+
+    // the data before the offset is kept
+    std::fs::write(path, "header............").unwrap();
+    let opts = Options::new().read(true).write(true).offset(6);
+    let mut file = MmapFileMut::open_with_options(path, opts).unwrap();
+    check(&mut file);
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"headersome\0\0\0\0");
+
+    // the length of a window cannot be changed, the file is left untouched
+    let opts = Options::new().read(true).write(true).offset(6).len(4);
+    let mut file = MmapFileMut::open_with_options(path, opts).unwrap();
+    assert_eq!(
+        file.resize_preserving(2).err().unwrap().kind(),
+        ErrorKind::NotSupported
+    );
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"headersome\0\0\0\0");
+
+    let mut file = MmapFileMut::from(EmptyMmapFile::default());
+    assert_eq!(
+        file.resize_preserving(8).unwrap_err().kind(),
+        ErrorKind::TruncationFailed
+    );
+}
+
+#[test]
+fn test_hexdump() {
+    let file = MmapFile::memory_from_str("foo.mem", "0123456789abcdefsome data...");
+    assert_eq!(
+        file.hexdump(),
+        "00000000: 3031 3233 3435 3637 3839 6162 6364 6566  0123456789abcdef\n\
+         00000010: 736f 6d65 2064 6174 612e 2e2e            some data...\n"
+    );
+    assert_eq!(
+        file.hexdump_range(20..100),
+        "00000014: 2064 6174 612e 2e2e                       data...\n"
+    );
+    assert_eq!(file.hexdump_range(100..200), "");
+
+    let file = MmapFile::memory_from_vec("foo.mem", vec![0; 5000]);
+    let dump = file.hexdump();
+    assert_eq!(dump.lines().count(), 4096 / 16 + 1);
+    assert!(dump.ends_with("... 904 more bytes\n"));
+}
+
+#[test]
+fn test_dirty_ranges() {
+    let path = "sync_dirty_ranges.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(100)).unwrap();
+    assert!(!file.is_dirty());
+
+    file.write_all(b"some", 0).unwrap();
+    file.write_all(b"data", 4).unwrap();
+    file.write_u32(7, 20).unwrap();
+    assert_eq!(file.dirty_ranges(), vec![0..8, 20..24]);
+
+    file.flush_range(0, 4).unwrap();
+    assert_eq!(file.dirty_ranges(), vec![4..8, 20..24]);
+    file.flush_async().unwrap();
+    assert!(file.is_dirty());
+    file.truncate(22).unwrap();
+    assert_eq!(file.dirty_ranges(), vec![4..8, 20..22]);
+    file.flush().unwrap();
+    assert!(!file.is_dirty());
+
+    // the raw slice marks the whole mmap
+    file.as_mut_slice()[0] = b'S';
+    assert_eq!(file.dirty_ranges(), vec![0..22]);
+    file.flush().unwrap();
+    assert!(!file.is_dirty());
+}
+
+#[test]
+fn test_lines() {
+    let file = MmapFile::memory_from_str("foo.mem", "first\r\nsecond\n\nlast");
+    assert_eq!(
+        file.lines().collect::<Vec<_>>(),
+        vec![&b"first"[..], b"second", b"", b"last"]
+    );
+    let (first, rest) = file.split_first_line().unwrap();
+    assert_eq!(first, b"first");
+    assert_eq!(rest, b"second\n\nlast");
+
+    let file = MmapFile::memory_from_str("foo.mem", "a\nb\r\n");
+    assert_eq!(file.lines().collect::<Vec<_>>(), vec![&b"a"[..], b"b"]);
+    assert_eq!(
+        file.lines_str().collect::<std::result::Result<Vec<_>, _>>().unwrap(),
+        vec!["a", "b"]
+    );
+
+    let file = MmapFile::memory_from_slice("foo.mem", b"ok\n\xff\n");
+    let mut lines = file.lines_str();
+    assert_eq!(lines.next().unwrap().unwrap(), "ok");
+    assert!(lines.next().unwrap().is_err());
+    assert!(lines.next().is_none());
+
+    let file = MmapFile::memory_from_vec("foo.mem", vec![]);
+    assert!(file.split_first_line().is_none());
+    assert_eq!(file.lines().count(), 0);
+}
+
+#[test]
+fn test_grow_strategy() {
+    use crate::GrowStrategy;
+
+    assert_eq!(GrowStrategy::Double.next_len(100, 150), 200);
+    assert_eq!(GrowStrategy::Double.next_len(100, 500), 500);
+    assert_eq!(GrowStrategy::Double.next_len(100, 50), 100);
+    assert_eq!(GrowStrategy::Fixed(64).next_len(100, 101), 164);
+    assert_eq!(GrowStrategy::Fixed(64).next_len(100, 300), 356);
+    assert_eq!(GrowStrategy::Fixed(0).next_len(100, 101), 101);
+    assert_eq!(GrowStrategy::Fixed(64).next_len(0, u64::MAX), u64::MAX);
+    assert_eq!(GrowStrategy::Fixed(u64::MAX).next_len(1, 2), u64::MAX);
+    assert_eq!(GrowStrategy::Fixed(64).checked_next_len(100, 101), Some(164));
+    assert_eq!(GrowStrategy::Fixed(64).checked_next_len(0, u64::MAX), None);
+    assert_eq!(GrowStrategy::Fixed(u64::MAX).checked_next_len(1, 2), None);
+    assert_eq!(GrowStrategy::Double.checked_next_len(u64::MAX - 1, u64::MAX), Some(u64::MAX));
+    assert_eq!(GrowStrategy::Ratio(0.5).next_len(100, 120), 150);
+    assert_eq!(GrowStrategy::Ratio(f64::NAN).next_len(100, 120), 120);
+
+    let path = "sync_grow_strategy.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let opts = Options::new()
+        .max_size(100)
+        .grow_increment(GrowStrategy::Fixed(64));
+    let mut file = MmapFileMut::create_with_options(path, opts).unwrap();
+    assert_eq!(file.grow_strategy(), GrowStrategy::Fixed(64));
+    file.write_all(b"some data...", 0).unwrap();
+    file.grow_to_fit(101).unwrap();
+    assert_eq!(file.len(), 164);
+    assert_eq!(&file.as_slice()[..12], b"some data...");
+    file.grow_to_fit(10).unwrap();
+    assert_eq!(file.len(), 164);
+
+    let mut file = MmapFileMut::memory_from_vec("foo.mem", vec![0; 8]);
+    assert_eq!(file.grow_strategy(), GrowStrategy::Double);
+    file.grow_to_fit(9).unwrap();
+    assert_eq!(file.len(), 16);
+    file.set_grow_strategy(GrowStrategy::Fixed(u64::MAX));
+    assert_eq!(
+        file.grow_to_fit(17).err().unwrap().kind(),
+        ErrorKind::TruncationFailed
+    );
+    assert_eq!(file.len(), 16);
+    drop(file);
+
+    // the data before the offset is kept
+    std::fs::write(path, "header....").unwrap();
+    let opts = Options::new()
+        .read(true)
+        .write(true)
+        .offset(6)
+        .grow_increment(GrowStrategy::Fixed(64));
+    let mut file = MmapFileMut::open_with_options(path, opts).unwrap();
+    file.grow_to_fit(5).unwrap();
+    assert_eq!(file.len(), 68);
+    assert_eq!(&file.as_slice()[..4], b"....");
+    drop(file);
+    assert_eq!(&std::fs::read(path).unwrap()[..10], b"header....");
+}
+
+#[test]
+fn test_put() {
+    let path = "sync_put.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(8)).unwrap();
+    file.put(b"some", 0).unwrap();
+    assert_eq!(file.len(), 8);
+    file.put(b"data", 20).unwrap();
+    assert!(file.len() >= 24);
+    assert_eq!(file.bytes(20, 4).unwrap(), b"data");
+    drop(file);
+
+    // the mmap grows from its offset, the data before it is kept
+    std::fs::write(path, "header").unwrap();
+    let opts = Options::new().read(true).write(true).offset(6);
+    let mut file = MmapFileMut::open_with_options(path, opts).unwrap();
+    file.put(b"data", 4).unwrap();
+    assert_eq!(file.bytes(0, 8).unwrap(), b"\0\0\0\0data");
+    file.flush().unwrap();
+    drop(file);
+    assert_eq!(&std::fs::read(path).unwrap()[..14], b"header\0\0\0\0data");
+}
+
+#[test]
+fn test_warm() {
+    let page = crate::utils::page_size();
+    let path = "sync_warm.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(page as u64 * 4)).unwrap();
+    file.write_u8(1, 0).unwrap();
+    file.write_u8(2, page).unwrap();
+    file.flush().unwrap();
+    let file = file.freeze().unwrap();
+
+    assert_eq!(file.warm(0..page * 4), (31 + 2) * 31 * 31);
+    assert_eq!(file.warm(page..page + 1), 2);
+    assert_eq!(file.warm(page * 8..page * 9), 0);
+}
+
+#[test]
+fn test_byte_stats() {
+    let file = MmapFile::memory_from_str("foo.mem", "abbcccbbbbx");
+    assert_eq!(file.most_common_byte(0..11).unwrap(), (b'b', 6));
+    assert_eq!(file.most_common_byte(3..6).unwrap(), (b'c', 3));
+    assert_eq!(file.most_common_byte(0..1).unwrap(), (b'a', 1));
+    assert_eq!(file.most_common_byte(4..4).unwrap(), (0, 0));
+    assert_eq!(file.most_common_byte(0..12).unwrap_err().kind(), ErrorKind::EOF);
+
+    assert_eq!(file.longest_run(0..11).unwrap(), (b'b', 4, 6));
+    assert_eq!(file.longest_run(0..6).unwrap(), (b'c', 3, 3));
+    assert_eq!(file.longest_run(0..3).unwrap(), (b'b', 2, 1));
+    assert_eq!(file.longest_run(7..9).unwrap(), (b'b', 2, 7));
+    assert_eq!(file.longest_run(5..5).unwrap(), (0, 0, 5));
+    assert_eq!(file.longest_run(10..12).unwrap_err().kind(), ErrorKind::EOF);
+}
+
+#[test]
+fn test_volatile() {
+    let path = "sync_volatile.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file =
+        MmapFileMut::create_with_options(path, Options::new().max_size(100).volatile(true)).unwrap();
+    file.write_all(b"scratch data", 0).unwrap();
+    file.truncate(200).unwrap();
+    assert_eq!(file.len(), 200);
+    assert_eq!(file.bytes(0, 12).unwrap(), b"scratch data");
+    file.truncate(7).unwrap();
+    assert_eq!(file.as_slice(), b"scratch");
+    file.close_with_truncate(5).unwrap();
+    assert_eq!(std::fs::metadata(path).unwrap().len(), 5);
+}
+
+#[test]
+fn test_upgrade_to_mut() {
+    let path = "sync_upgrade_to_mut.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, b"some data...").unwrap();
+
+    let file = MmapFile::open(path).unwrap();
+    assert_eq!(file.upgrade_to_mut().err().unwrap().kind(), ErrorKind::NotSupported);
+
+    let file = MmapFile::open_with_options(path, Options::new().writable_handle(true)).unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+    let mut file = file.upgrade_to_mut().unwrap();
+    file.write_all(b"SOME", 0).unwrap();
+    file.flush().unwrap();
+    drop(file);
+    assert_eq!(std::fs::read(path).unwrap(), b"SOME data...");
+
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    assert_eq!(file.upgrade_to_mut().err().unwrap().kind(), ErrorKind::NotSupported);
+}
+
+#[test]
+fn test_common_prefix_len() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data... some more data");
+    assert_eq!(file.common_prefix_len(0, b"some data"), 9);
+    assert_eq!(file.common_prefix_len(13, b"some data"), 5);
+    assert_eq!(file.common_prefix_len(0, b"some data... some more data, and more"), 27);
+    assert_eq!(file.common_prefix_len(26, b"a"), 1);
+    assert_eq!(file.common_prefix_len(27, b"a"), 0);
+    assert_eq!(file.common_prefix_len(0, b""), 0);
+}
+
+#[test]
+fn test_shrink_to_fit() {
+    let path = "sync_shrink_to_fit.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(4096)).unwrap();
+    file.write_all(b"some data...", 0).unwrap();
+    file.shrink_to_fit().unwrap();
+    assert_eq!(file.len(), 12);
+    assert_eq!(file.as_slice(), b"some data...");
+    assert_eq!(std::fs::metadata(path).unwrap().len(), 12);
+    file.shrink_to_fit().unwrap();
+    assert_eq!(file.len(), 12);
+    drop(file);
+
+    // the content of an opened file counts as written
+    let mut file = MmapFileMut::open(path).unwrap();
+    assert_eq!(file.high_water_mark(), 12);
+    file.shrink_to_fit().unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+    drop(file);
+
+    let mut file = MmapFileMut::open_cow(path).unwrap();
+    file.write_all(b"SOME", 0).unwrap();
+    file.shrink_to_fit().unwrap();
+    assert_eq!(file.as_slice(), b"SOME data...");
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+
+    let mut file = MmapFileMut::memory_from_vec("foo.mem", vec![0; 100]);
+    file.shrink_to_fit().unwrap();
+    assert_eq!(file.len(), 100);
+
+    let mut file = MmapFileMut::memory_with_capacity("foo.mem", 0);
+    file.truncate(100).unwrap();
+    file.write_all(b"data", 10).unwrap();
+    file.shrink_to_fit().unwrap();
+    assert_eq!(file.len(), 14);
+}
+
+#[test]
+fn test_find_all() {
+    let file = MmapFile::memory_from_str("foo.mem", "MAGICabcMAGICMAGIC..MAG");
+    assert_eq!(file.find_all(b"MAGIC", 0).collect::<Vec<_>>(), vec![0, 8, 13]);
+    assert_eq!(file.find_all(b"MAGIC", 1).collect::<Vec<_>>(), vec![8, 13]);
+    assert_eq!(file.find_all(b"MAGIC", 23).count(), 0);
+    assert_eq!(file.find_all(b"", 0).count(), 0);
+
+    let file = MmapFile::memory_from_str("foo.mem", "aaaaa");
+    // the occurrences do not overlap
+    assert_eq!(file.find_all(b"aa", 0).collect::<Vec<_>>(), vec![0, 2]);
+}
+
+#[test]
+fn test_count_bytes_where() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data, \tmore data\n");
+    assert_eq!(file.count_bytes_where(0..22, |b| b.is_ascii_whitespace()).unwrap(), 5);
+    assert_eq!(file.count_bytes_where(0..4, |b| b == b'o').unwrap(), 1);
+    assert_eq!(file.count_bytes_where(3..3, |_| true).unwrap(), 0);
+    assert_eq!(file.count_bytes_where(0..23, |_| true).unwrap_err().kind(), ErrorKind::EOF);
+
+    let mut set = [false; 256];
+    for b in b"ad" {
+        set[*b as usize] = true;
+    }
+    assert_eq!(file.count_in_set(0..22, &set).unwrap(), 6);
+    assert_eq!(file.count_in_set(5..9, &set).unwrap(), 3);
+    assert_eq!(file.count_in_set(20..23, &set).unwrap_err().kind(), ErrorKind::EOF);
+}
+
+#[test]
+fn test_ensure_min_size() {
+    let path = "sync_ensure_min_size.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, b"some data...").unwrap();
+
+    // a non-empty file is not grown to `max_size`
+    let opts = Options::new().read(true).write(true);
+    let file = MmapFileMut::open_with_options(path, opts.clone().max_size(100)).unwrap();
+    assert_eq!(file.len(), 12);
+    drop(file);
+
+    let file = MmapFileMut::open_with_options(path, opts.clone().ensure_min_size(100)).unwrap();
+    assert_eq!(file.len(), 100);
+    assert_eq!(file.bytes(0, 12).unwrap(), b"some data...");
+    assert!(file.is_zero_range(12..100).unwrap());
+    drop(file);
+
+    // never shrunk
+    let file = MmapFileMut::open_exist_with_options(path, opts.ensure_min_size(50)).unwrap();
+    assert_eq!(file.len(), 100);
+}
+
+#[test]
+fn test_leak() {
+    let data: &'static [u8] = MmapFile::memory_from_vec("foo.mem", b"more data".to_vec()).leak();
+    assert_eq!(data, b"more data");
+
+    // the leaked mapping keeps the file open, which only unix allows to remove
+    #[cfg(unix)]
+    {
+        let path = "sync_leak.txt";
+        scopeguard::defer!(std::fs::remove_file(path).unwrap());
+        std::fs::write(path, b"some data...").unwrap();
+        let data: &'static [u8] = MmapFile::open(path).unwrap().leak();
+        assert_eq!(data, b"some data...");
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_flush_error_range() {
+    let path = "sync_flush_error_range.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    // msync fails on the unmapped pages beyond the mmap
+    let err = file.flush_range(0, 1 << 30).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::FlushFailed);
+    assert!(err.to_string().contains("(bytes 0..1073741824)"));
+}
+
+/// Maps the block device given by `FMMAP_TEST_BLOCK_DEVICE`, e.g. a loop device set up by
+/// `losetup`. It needs the privileges to read the device, so it is not run by default.
+#[cfg(unix)]
+#[test]
+#[ignore]
+fn test_open_block_device() {
+    let path = std::env::var("FMMAP_TEST_BLOCK_DEVICE").expect("FMMAP_TEST_BLOCK_DEVICE is not set");
+    let mut device = std::fs::File::open(&path).unwrap();
+    let len = std::io::Seek::seek(&mut device, std::io::SeekFrom::End(0)).unwrap();
+    assert!(len > 0);
+
+    let file = MmapFile::open(&path).unwrap();
+    assert_eq!(file.len() as u64, len);
+    let file = MmapFile::open_with_options(&path, Options::new().offset(0)).unwrap();
+    assert_eq!(file.len() as u64, len);
+}
+
+#[test]
+fn test_shards() {
+    let page = crate::utils::page_size();
+    let file = MmapFile::memory_from_vec("foo.mem", vec![1; page * 10 + 5]);
+    let shards = file.shards(3);
+    assert_eq!(shards.len(), 3);
+    assert_eq!(
+        shards.iter().map(|s| s.len()).collect::<Vec<_>>(),
+        vec![page * 3, page * 3, page * 4 + 5]
+    );
+    assert_eq!(shards.iter().map(|s| s.len()).sum::<usize>(), file.len());
+
+    let shards = file.shards(1);
+    assert_eq!(shards, vec![file.as_slice()]);
+
+    // fewer pages than shards
+    let file = MmapFile::memory_from_vec("foo.mem", vec![1; 10]);
+    let shards = file.shards(4);
+    assert_eq!(shards.iter().map(|s| s.len()).collect::<Vec<_>>(), vec![0, 0, 0, 10]);
+}
+
+#[test]
+fn test_adler32() {
+    let file = MmapFile::memory_from_str("foo.mem", "some Wikipedia data");
+    assert_eq!(file.adler32_range(5..14).unwrap(), 0x11e6_0398);
+    assert_eq!(file.adler32_range(3..3).unwrap(), 1);
+    assert_eq!(file.adler32(), file.adler32_range(0..19).unwrap());
+    assert_eq!(file.adler32_range(5..20).unwrap_err().kind(), ErrorKind::EOF);
+}
+
+#[test]
+fn test_mirror() {
+    let path = "sync_mirror_primary.txt";
+    let mirror_path = "sync_mirror_secondary.txt";
+    scopeguard::defer!({
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(mirror_path);
+    });
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    file.write_all(b"some", 0).unwrap();
+
+    // the mirror is resized and catches up with the primary
+    file.set_mirror(MmapFileMut::create(mirror_path).unwrap()).unwrap();
+    assert_eq!(file.mirror().unwrap().len(), 12);
+    assert!(file.verify_mirror());
+
+    file.write_all(b" data...", 4).unwrap();
+    file.truncate(20).unwrap();
+    assert_eq!(file.write(b"more", 12), 4);
+    file.flush().unwrap();
+    assert!(file.verify_mirror());
+    assert_eq!(file.mirror().unwrap().len(), 20);
+    assert_eq!(&std::fs::read(mirror_path).unwrap()[..16], b"some data...more");
+
+    let mirror = file.take_mirror().unwrap();
+    assert!(file.mirror().is_none());
+    assert_eq!(mirror.as_slice(), file.as_slice());
+
+    // the primary stays authoritative when the mirror fails
+    let err = file
+        .set_mirror(MmapFileMut::from(EmptyMmapFile::default()))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MirrorFailed);
+    assert!(file.mirror().is_none());
+    assert_eq!(&file.as_slice()[..16], b"some data...more");
+
+    // a copy-on-write mirror cannot grow, so its short write is reported
+    let mut file = MmapFileMut::memory_from_vec("foo.mem", std::fs::read(path).unwrap());
+    file.set_mirror(MmapFileMut::open_cow(path).unwrap()).unwrap();
+    let err = file.truncate(24).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::MirrorFailed);
+    assert_eq!(file.write(b"more", 18), 2);
+    assert_eq!(&file.mirror().unwrap().as_slice()[16..], b"\0\0mo");
+}
+
+#[test]
+fn test_records_by_magic() {
+    let file = MmapFile::memory_from_str("foo.mem", "junkMGa1MGb22MGMGc");
+    assert_eq!(
+        file.records_by_magic(b"MG").collect::<Vec<_>>(),
+        vec![&b"MGa1"[..], b"MGb22", b"MG", b"MGc"]
+    );
+    assert_eq!(file.records_by_magic(b"XX").count(), 0);
+    assert_eq!(file.records_by_magic(b"").count(), 0);
+
+    let file = MmapFile::memory_from_str("foo.mem", "MGonly");
+    assert_eq!(file.records_by_magic(b"MG").collect::<Vec<_>>(), vec![&b"MGonly"[..]]);
+}
+
+#[test]
+fn test_read_available() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    let mut buf = [0; 4];
+    assert_eq!(file.read_available(&mut buf, 5), (4, true));
+    assert_eq!(&buf, b"data");
+
+    let mut buf = [0; 8];
+    assert_eq!(file.read_available(&mut buf, 9), (3, false));
+    assert_eq!(&buf[..3], b"...");
+    assert_eq!(file.read_available(&mut buf, 12), (0, false));
+    assert_eq!(file.read_available(&mut buf, 20), (0, false));
+    assert_eq!(file.read_available(&mut [], 20), (0, true));
+}
+
+#[test]
+fn test_flush_policy() {
+    use crate::FlushPolicy;
+    use std::time::Duration;
+
+    let path = "sync_flush_policy.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let opts = Options::new().max_size(12).flush_policy(FlushPolicy::Async);
+    let mut file = MmapFileMut::create_with_options(path, opts).unwrap();
+    file.write_all(b"some", 0).unwrap();
+    // an asynchronous flush keeps the ranges dirty
+    file.flush().unwrap();
+    assert!(file.is_dirty());
+
+    file.set_flush_policy(FlushPolicy::AsyncWithPeriodicSync(Duration::from_secs(3600)));
+    file.flush().unwrap();
+    assert!(file.is_dirty());
+    file.set_flush_policy(FlushPolicy::AsyncWithPeriodicSync(Duration::ZERO));
+    file.flush().unwrap();
+    assert!(!file.is_dirty());
+
+    file.write_all(b"data", 4).unwrap();
+    file.set_flush_policy(FlushPolicy::Sync);
+    file.flush().unwrap();
+    assert!(!file.is_dirty());
+    assert_eq!(&std::fs::read(path).unwrap()[..8], b"somedata");
+}
+
+#[test]
+fn test_io_slices() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data... some more data");
+    let slices = file.io_slices(&[13..18, 0..5, 23..27]).unwrap();
+    assert_eq!(slices.len(), 3);
+    let mut out = Vec::new();
+    assert_eq!(out.write_vectored(&slices).unwrap(), 14);
+    assert_eq!(out, b"some some data");
+    assert!(file.io_slices(&[]).unwrap().is_empty());
+
+    let err = file.io_slices(&[0..5, 20..28]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::EOF);
+    assert!(err.to_string().contains("range 1 (20..28)"));
+}
+
+#[test]
+fn test_generation() {
+    let path = "sync_generation.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = MmapFileMut::create_with_options(path, Options::new().max_size(12)).unwrap();
+    let gen = file.generation();
+
+    file.write_all(b"some", 0).unwrap();
+    assert!(file.generation() > gen);
+    let gen = file.generation();
+    // reads and flushes do not bump it
+    assert_eq!(file.bytes(0, 4).unwrap(), b"some");
+    file.flush().unwrap();
+    assert_eq!(file.generation(), gen);
+
+    file.zero_range(0, 4);
+    assert!(file.generation() > gen);
+    let gen = file.generation();
+    file.truncate(20).unwrap();
+    assert!(file.generation() > gen);
+    let gen = file.generation();
+    assert_eq!(file.write(b"", 0), 0);
+    assert_eq!(file.generation(), gen);
+}
+
+#[test]
+fn test_get() {
+    let file = MmapFile::memory_from_str("foo.mem", "some data...");
+    assert!(matches!(file.get(5, 4).unwrap(), Cow::Borrowed(b"data")));
+    assert!(matches!(file.get(12, 0).unwrap(), Cow::Borrowed(b"")));
+    assert_eq!(file.get(9, 4).unwrap_err().kind(), ErrorKind::EOF);
+}
+
+#[test]
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+fn test_open_decompressed() {
+    let codecs = [
+        #[cfg(feature = "gzip")]
+        (crate::Codec::Gzip, "sync_open_decompressed_test.gz"),
+        #[cfg(feature = "zstd")]
+        (crate::Codec::Zstd, "sync_open_decompressed_test.zst"),
+        #[cfg(feature = "lz4")]
+        (crate::Codec::Lz4, "sync_open_decompressed_test.lz4"),
+    ];
+    for (codec, path) in codecs {
+        std::fs::write(path, crate::codec::compress(b"some data...", codec)).unwrap();
+        scopeguard::defer!(std::fs::remove_file(path).unwrap());
+
+        let file = MmapFile::open_decompressed(path, codec).unwrap();
+        assert_eq!(file.as_slice(), b"some data...");
+        assert_eq!(file.path(), Path::new(path));
+
+        // not the format of the codec
+        std::fs::write(path, b"some data...").unwrap();
+        assert_eq!(MmapFile::open_decompressed(path, codec).err().unwrap().kind(), ErrorKind::IO);
+    }
+}
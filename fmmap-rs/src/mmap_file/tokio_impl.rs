@@ -4,13 +4,18 @@ use std::io::Cursor;
 use std::path::{Path, PathBuf};
 use async_trait::async_trait;
 use tokio::io::AsyncWriteExt;
-use tokio::fs::remove_file;
+use tokio::fs::{canonicalize, remove_file, rename, File};
 use crate::tokio::{AsyncMmapFileReader, AsyncMmapFileWriter, AsyncOptions};
+#[cfg(unix)]
+use crate::tokio::FlushScheduler;
 use crate::disk::tokio_impl::{AsyncDiskMmapFile, AsyncDiskMmapFileMut};
 use crate::empty::tokio_impl::AsyncEmptyMmapFile;
 use crate::error::{Error, ErrorKind, Result};
 use crate::memory::tokio_impl::{AsyncMemoryMmapFile, AsyncMemoryMmapFileMut};
 use crate::metadata::MetaData;
+use crate::utils::tokio::{spawn_with_timeout, sync_dir_async, sync_parent_async};
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+use crate::utils::tokio::spawn_blocking;
 
 declare_async_mmap_file_ext!(AsyncDiskMmapFileMut, AsyncOptions, AsyncMmapFileReader);
 
@@ -22,4 +27,348 @@ declare_and_impl_async_mmap_file!("tokio_async", "tokio_test", "tokio");
 
 delcare_and_impl_async_mmap_file_mut!("tokio_async", "tokio_test", "tokio");
 
-impl_async_tests!("tokio_async", tokio::test, tokio, AsyncMmapFile, AsyncMmapFileMut);
\ No newline at end of file
+impl_async_tests!("tokio_async", tokio::test, tokio, AsyncMmapFile, AsyncMmapFileMut);
+#[tokio::test]
+async fn test_close_into_file() {
+    use tokio::io::AsyncReadExt;
+
+    let path = "tokio_async_close_into_file.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+        .await
+        .unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    let mut f = file.close_into_file(12).await.unwrap();
+    assert!(file.is_empty());
+    assert_eq!(f.metadata().await.unwrap().len(), 12);
+    f.write_all("more data...".as_bytes()).await.unwrap();
+    drop(f);
+
+    let mut buf = String::new();
+    File::open(path)
+        .await
+        .unwrap()
+        .read_to_string(&mut buf)
+        .await
+        .unwrap();
+    assert_eq!(buf, "some data...more data...");
+
+    let mut file = AsyncMmapFileMut::memory_with_capacity("foo.mem", 10);
+    assert_eq!(
+        file.close_into_file(-1).await.unwrap_err().kind(),
+        ErrorKind::NotSupported
+    );
+
+    // the mmap is mapped again if the file cannot be truncated
+    let mut file = AsyncMmapFileMut::open(path).await.unwrap();
+    crate::utils::FAIL_SET_LEN.with(|fail| fail.set(true));
+    assert_eq!(
+        file.close_into_file(12).await.unwrap_err().kind(),
+        ErrorKind::TruncationFailed
+    );
+    assert!(!file.is_poisoned());
+    assert_eq!(file.as_slice(), b"some data...more data...");
+    let f = file.close_into_file(12).await.unwrap();
+    assert_eq!(f.metadata().await.unwrap().len(), 12);
+}
+
+#[tokio::test]
+async fn test_zero_unwritten() {
+    let path = "tokio_async_zero_unwritten.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(16))
+        .await
+        .unwrap();
+    file.as_mut_slice().fill(0xFF);
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    assert_eq!(file.written_len(), 12);
+    file.zero_unwritten().unwrap();
+    assert_eq!(&file.as_slice()[..12], b"some data...");
+    assert_eq!(&file.as_slice()[12..], &[0; 4]);
+    file.flush().unwrap();
+    drop(file);
+
+    // the content of a reopened file counts as written
+    let mut file = AsyncMmapFileMut::open(path).await.unwrap();
+    assert_eq!(file.written_len(), 16);
+    file.zero_unwritten().unwrap();
+    assert_eq!(&file.as_slice()[..12], b"some data...");
+}
+
+#[tokio::test]
+async fn test_secure_remove() {
+    let path = "tokio_async_secure_remove.txt";
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(12))
+        .await
+        .unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    file.secure_remove(3).await.unwrap();
+    assert!(!Path::new(path).exists());
+
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(12))
+        .await
+        .unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    file.secure_remove_with(3, crate::WipePattern::Random).await.unwrap();
+    assert!(!Path::new(path).exists());
+
+    // nothing is written to the path of an in-memory mmap
+    let mut file = AsyncMmapFileMut::memory_with_capacity("tokio_async_secure_remove.mem", 12);
+    file.truncate(12).await.unwrap();
+    file.write_all("some data...".as_bytes(), 0).unwrap();
+    file.secure_remove(1).await.unwrap();
+    assert!(!Path::new("tokio_async_secure_remove.mem").exists());
+
+    // the underlying file of a copy-on-write mmap is left untouched
+    std::fs::write(path, b"some data...").unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let mut file = AsyncMmapFileMut::open_cow(path).await.unwrap();
+    file.write_all(b"more", 0).unwrap();
+    file.secure_remove_with(2, crate::WipePattern::Random).await.unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+}
+
+#[tokio::test]
+async fn test_replace_atomically() {
+    let path = "tokio_async_replace_atomically.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, "old data").unwrap();
+
+    let rst = AsyncMmapFileMut::replace_atomically(path, AsyncOptions::new().max_size(12), |file| {
+        file.write_all("some data...".as_bytes(), 0)?;
+        Err(Error::from(ErrorKind::EOF))
+    })
+    .await;
+    assert_eq!(rst.unwrap_err().kind(), ErrorKind::EOF);
+    assert_eq!(std::fs::read(path).unwrap(), b"old data");
+    let tmp_prefix = format!(".{}.", path);
+    let leftover = std::fs::read_dir(".")
+        .unwrap()
+        .any(|entry| entry.unwrap().file_name().to_string_lossy().starts_with(&tmp_prefix));
+    assert!(!leftover);
+
+    // concurrent replacements do not share the temporary file
+    let (a, b) = tokio::join!(
+        AsyncMmapFileMut::replace_atomically(path, AsyncOptions::new().max_size(12), |file| {
+            file.write_all("some data...".as_bytes(), 0)
+        }),
+        AsyncMmapFileMut::replace_atomically(path, AsyncOptions::new().max_size(12), |file| {
+            file.write_all("more data...".as_bytes(), 0)
+        }),
+    );
+    a.unwrap();
+    b.unwrap();
+    let data = std::fs::read(path).unwrap();
+    assert!(data == b"some data..." || data == b"more data...");
+
+    AsyncMmapFileMut::replace_atomically(path, AsyncOptions::new().max_size(12), |file| {
+        file.write_all("some data...".as_bytes(), 0)
+    })
+    .await
+    .unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+}
+
+#[cfg(feature = "notify")]
+#[tokio::test]
+async fn test_wait_for_change() {
+    let path = "tokio_async_wait_for_change.txt";
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    std::fs::write(path, "some data...").unwrap();
+    let file = AsyncMmapFile::open(path).await.unwrap();
+
+    let writer = tokio::spawn(async move {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        std::fs::write(path, "some data...more data...").unwrap();
+    });
+    tokio::time::timeout(std::time::Duration::from_secs(5), file.wait_for_change())
+        .await
+        .unwrap()
+        .unwrap();
+    writer.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_try_open() {
+    let path = "tokio_async_try_open.txt";
+    assert!(AsyncMmapFile::try_open(path).await.unwrap().is_none());
+
+    std::fs::write(path, "some data...").unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let file = AsyncMmapFile::try_open(path).await.unwrap().unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+}
+
+#[tokio::test]
+async fn test_truncate_trailing_zeros() {
+    let path = "tokio_async_truncate_trailing_zeros.txt";
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(100))
+        .await
+        .unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    file.write_all(b"some data...", 50).unwrap();
+    assert_eq!(file.truncate_trailing_zeros().await.unwrap(), 62);
+    assert_eq!(file.len(), 62);
+    assert_eq!(&file.as_slice()[50..], b"some data...");
+}
+
+#[tokio::test]
+async fn test_open_with_timeout() {
+    let path = "tokio_async_open_with_timeout.txt";
+    std::fs::write(path, "some data...").unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    let timeout = std::time::Duration::from_secs(10);
+    let file = AsyncMmapFile::open_with_timeout(path, AsyncOptions::new(), timeout)
+        .await
+        .unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+    let opts = AsyncOptions::new().read(true).write(true);
+    let file = AsyncMmapFileMut::open_with_timeout(path, opts, timeout)
+        .await
+        .unwrap();
+    assert_eq!(file.as_slice(), b"some data...");
+
+    let err = AsyncMmapFile::open_with_timeout("tokio_async_open_with_timeout_missing.txt", AsyncOptions::new(), timeout)
+        .await
+        .err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+
+    // the open waits for the permit held here, so it cannot complete before the timer fires
+    let limiter = crate::OpenConcurrencyLimiter::new(1);
+    let permit = limiter.acquire().await;
+    let opts = AsyncOptions::new().concurrency_limiter(limiter.clone());
+    let err = AsyncMmapFile::open_with_timeout(path, opts, std::time::Duration::from_millis(50))
+        .await
+        .err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::Timeout);
+    drop(permit);
+}
+
+#[tokio::test]
+async fn test_open_temp() {
+    let path = "tokio_async_open_temp.txt";
+    let (mut file, handle) = AsyncMmapFileMut::open_temp(".", AsyncOptions::new().max_size(12))
+        .await
+        .unwrap();
+    let tmp_path = handle.path().to_path_buf();
+    assert!(tmp_path.file_name().unwrap().to_string_lossy().starts_with('.'));
+    file.write_all(b"some data...", 0).unwrap();
+    file.flush().unwrap();
+    handle.persist_as(path).await.unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    assert!(!tmp_path.exists());
+    assert_eq!(std::fs::read(path).unwrap(), b"some data...");
+    drop(file);
+
+    // never persisted, the temporary file is removed
+    let (file, handle) = AsyncMmapFileMut::open_temp(".", AsyncOptions::new().max_size(12))
+        .await
+        .unwrap();
+    let tmp_path = handle.path().to_path_buf();
+    assert!(tmp_path.exists());
+    drop(handle);
+    assert!(!tmp_path.exists());
+    drop(file);
+}
+
+#[tokio::test]
+async fn test_put() {
+    let path = "tokio_async_put.txt";
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(8))
+        .await
+        .unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    file.put(b"some", 0).await.unwrap();
+    assert_eq!(file.len(), 8);
+    file.put(b"data", 20).await.unwrap();
+    assert!(file.len() >= 24);
+    assert_eq!(file.bytes(0, 4).unwrap(), b"some");
+    assert!(file.is_zero_range(4..20).unwrap());
+    assert_eq!(file.bytes(20, 4).unwrap(), b"data");
+    file.flush().unwrap();
+    drop(file);
+
+    let mut file = AsyncMmapFileMut::open_cow(path).await.unwrap();
+    let err = file.put(b"more", 100).await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotSupported);
+    drop(file);
+
+    // the mmap grows from its offset, the data before it is kept
+    std::fs::write(path, "header").unwrap();
+    let opts = AsyncOptions::new().read(true).write(true).offset(6);
+    let mut file = AsyncMmapFileMut::open_with_options(path, opts).await.unwrap();
+    file.put(b"data", 4).await.unwrap();
+    file.flush().unwrap();
+    drop(file);
+    let data = std::fs::read(path).unwrap();
+    assert_eq!(&data[..14], b"header\0\0\0\0data");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_remove_failure_keeps_mmap() {
+    let path = "tokio_async_remove_failure.txt";
+    let mut file = AsyncMmapFileMut::create_with_options(path, AsyncOptions::new().max_size(12))
+        .await
+        .unwrap();
+    file.write_all(b"some data...", 0).unwrap();
+    // the path is gone, so the removal fails
+    std::fs::remove_file(path).unwrap();
+    assert!(file.remove().await.is_err());
+    assert_eq!(file.kind(), crate::MapKind::Disk);
+    assert_eq!(file.as_slice(), b"some data...");
+    assert_eq!(file.path(), Path::new(path));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_create_like() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let template = "tokio_async_create_like_template.txt";
+    let path = "tokio_async_create_like.txt";
+    std::fs::write(template, b"template").unwrap();
+    scopeguard::defer!(std::fs::remove_file(template).unwrap());
+    std::fs::set_permissions(template, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+    let file = AsyncMmapFileMut::create_like(path, template, AsyncOptions::new().max_size(12))
+        .await
+        .unwrap();
+    scopeguard::defer!(std::fs::remove_file(path).unwrap());
+    assert_eq!(file.len(), 12);
+    let mode = std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o7777, 0o640);
+
+    // the new file is removed if the template is missing
+    let missing = "tokio_async_create_like_missing.txt";
+    let err = AsyncMmapFileMut::create_like(missing, "no_such_template.txt", AsyncOptions::new())
+        .await
+        .err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::IO);
+    assert!(!Path::new(missing).exists());
+}
+
+#[tokio::test]
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "lz4"))]
+async fn test_open_decompressed() {
+    let codecs = [
+        #[cfg(feature = "gzip")]
+        (crate::Codec::Gzip, "tokio_async_open_decompressed_test.gz"),
+        #[cfg(feature = "zstd")]
+        (crate::Codec::Zstd, "tokio_async_open_decompressed_test.zst"),
+        #[cfg(feature = "lz4")]
+        (crate::Codec::Lz4, "tokio_async_open_decompressed_test.lz4"),
+    ];
+    for (codec, path) in codecs {
+        std::fs::write(path, crate::codec::compress(b"some data...", codec)).unwrap();
+        scopeguard::defer!(std::fs::remove_file(path).unwrap());
+
+        let file = AsyncMmapFile::open_decompressed(path, codec).await.unwrap();
+        assert_eq!(file.as_slice(), b"some data...");
+        assert_eq!(file.path(), Path::new(path));
+
+        // not the format of the codec
+        std::fs::write(path, b"some data...").unwrap();
+        assert_eq!(AsyncMmapFile::open_decompressed(path, codec).await.err().unwrap().kind(), ErrorKind::IO);
+    }
+}
@@ -1,3 +1,235 @@
+/// The strategy to grow a mutable memory map when a write needs more room than its length,
+/// configured by `grow_increment` of the options.
+///
+/// The default one is [`GrowStrategy::Double`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GrowStrategy {
+    /// Doubles the length, which amortizes the cost of the remaps for a steadily growing file.
+    Double,
+    /// Grows by a multiple of the fixed number of bytes, which avoids over-allocating huge files.
+    Fixed(u64),
+    /// Grows by the ratio of the current length, e.g. `0.5` grows by 50%.
+    Ratio(f64),
+}
+
+// `#[default]` on an enum variant needs Rust 1.62, newer than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for GrowStrategy {
+    fn default() -> Self {
+        GrowStrategy::Double
+    }
+}
+
+impl GrowStrategy {
+    /// Returns the new length of a memory map of `current` bytes which needs at least `required`
+    /// bytes. The result is never less than `required`, and is `current` if it is already enough.
+    ///
+    /// A [`GrowStrategy::Fixed`] step which rounds up past `u64::MAX` gives `u64::MAX`, see
+    /// [`checked_next_len`] to detect it.
+    ///
+    /// [`checked_next_len`]: #method.checked_next_len
+    pub fn next_len(&self, current: u64, required: u64) -> u64 {
+        self.checked_next_len(current, required).unwrap_or(u64::MAX)
+    }
+
+    /// Returns the new length of a memory map of `current` bytes which needs at least `required`
+    /// bytes, the same as [`next_len`], or `None` if a [`GrowStrategy::Fixed`] step rounds up past
+    /// `u64::MAX`.
+    ///
+    /// [`next_len`]: #method.next_len
+    pub fn checked_next_len(&self, current: u64, required: u64) -> Option<u64> {
+        if required <= current {
+            return Some(current);
+        }
+
+        let grown = match *self {
+            GrowStrategy::Double => current.saturating_mul(2),
+            GrowStrategy::Fixed(0) => required,
+            GrowStrategy::Fixed(step) => {
+                // round up without `needed + step - 1`, which overflows near `u64::MAX`
+                let needed = required - current;
+                let steps = needed / step + u64::from(needed % step != 0);
+                current.checked_add(steps.checked_mul(step)?)?
+            }
+            // a non-positive or NaN ratio grows to the required length exactly
+            GrowStrategy::Ratio(ratio) if ratio > 0.0 => {
+                current.saturating_add((current as f64 * ratio) as u64)
+            }
+            GrowStrategy::Ratio(_) => required,
+        };
+        Some(grown.max(required))
+    }
+}
+
+/// The durability of `flush` of a mutable memory map, configured by `flush_policy` of the options.
+///
+/// The default one is [`FlushPolicy::Sync`], which keeps the historical semantics of `flush`.
+/// Only `flush` consults the policy, `flush_range` and the flush on drop always wait for the
+/// data to be written back.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FlushPolicy {
+    /// `flush` waits until the data is written back (`msync(MS_SYNC)`), so the data survives a
+    /// crash of the OS once it returns, at the cost of stalling the caller.
+    Sync,
+    /// `flush` only schedules the write back (`msync(MS_ASYNC)`) and returns immediately, so a
+    /// crash of the OS may lose any data written since the last synchronous flush. The data
+    /// survives a crash of the process anyway, since it is in the page cache.
+    Async,
+    /// `flush` is asynchronous, except the first one after the interval elapses since the last
+    /// synchronous one, which bounds how much data a crash of the OS may lose by the interval.
+    AsyncWithPeriodicSync(std::time::Duration),
+}
+
+// `#[default]` on an enum variant needs Rust 1.62, newer than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        FlushPolicy::Sync
+    }
+}
+
+impl FlushPolicy {
+    /// Returns whether a flush should be synchronous now, and if so, resets `last_sync`.
+    pub(crate) fn is_sync_due(&self, last_sync: &std::sync::Mutex<std::time::Instant>) -> bool {
+        match *self {
+            FlushPolicy::Sync => true,
+            FlushPolicy::Async => false,
+            FlushPolicy::AsyncWithPeriodicSync(interval) => {
+                let mut last_sync = last_sync.lock().unwrap();
+                if last_sync.elapsed() < interval {
+                    return false;
+                }
+                *last_sync = std::time::Instant::now();
+                true
+            }
+        }
+    }
+}
+
+/// The patterns `secure_remove_with` overwrites the content of a mutable memory map with.
+///
+/// Whatever the pattern, the last pass always writes zeros. The default one is
+/// [`WipePattern::Alternating`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WipePattern {
+    /// The passes alternate between `0xFF` and `0x00`.
+    Alternating,
+    /// The passes write pseudo-random bytes. The generator is seeded randomly for each pass,
+    /// but it is not cryptographically secure.
+    Random,
+}
+
+// `#[default]` on an enum variant needs Rust 1.62, newer than the MSRV
+#[allow(clippy::derivable_impls)]
+impl Default for WipePattern {
+    fn default() -> Self {
+        WipePattern::Alternating
+    }
+}
+
+impl WipePattern {
+    /// Overwrites the buffer for the pass, the passes count down to `0`, which writes zeros.
+    pub(crate) fn fill(&self, buf: &mut [u8], pass: u8) {
+        match *self {
+            _ if pass == 0 => buf.fill(0),
+            WipePattern::Alternating => buf.fill(if pass % 2 == 0 { 0x00 } else { 0xFF }),
+            WipePattern::Random => crate::utils::fill_random(buf),
+        }
+    }
+}
+
+/// The sharing mode of a file opened on Windows, i.e. the `dwShareMode` argument to [`CreateFile`].
+///
+/// It decides whether other handles, including the ones of other processes, can read, write or
+/// delete the file while it is opened by this crate. The default one is
+/// `ShareMode::READ | ShareMode::WRITE | ShareMode::DELETE`, the same as [`std::fs::OpenOptions`].
+///
+/// [`CreateFile`]: https://docs.microsoft.com/en-us/windows/win32/api/fileapi/nf-fileapi-createfilea
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ShareMode(u32);
+
+#[cfg(windows)]
+impl ShareMode {
+    /// Other handles cannot open the file.
+    pub const NONE: ShareMode = ShareMode(0);
+    /// `FILE_SHARE_READ`, other handles can open the file for reading.
+    pub const READ: ShareMode = ShareMode(0x0000_0001);
+    /// `FILE_SHARE_WRITE`, other handles can open the file for writing.
+    pub const WRITE: ShareMode = ShareMode(0x0000_0002);
+    /// `FILE_SHARE_DELETE`, other handles can delete or rename the file.
+    pub const DELETE: ShareMode = ShareMode(0x0000_0004);
+
+    /// Returns the raw `dwShareMode` flags.
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns whether all the flags in `other` are set.
+    #[inline]
+    pub const fn contains(&self, other: ShareMode) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[cfg(windows)]
+impl Default for ShareMode {
+    fn default() -> Self {
+        ShareMode::READ | ShareMode::WRITE | ShareMode::DELETE
+    }
+}
+
+#[cfg(windows)]
+impl core::ops::BitOr for ShareMode {
+    type Output = ShareMode;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        ShareMode(self.0 | rhs.0)
+    }
+}
+
+#[cfg(windows)]
+impl core::ops::BitOrAssign for ShareMode {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[cfg(windows)]
+impl From<ShareMode> for u32 {
+    fn from(mode: ShareMode) -> Self {
+        mode.0
+    }
+}
+
+/// The settings of the options which are applied to the `OpenOptions` the file is opened with.
+///
+/// `OpenOptions` cannot be read back, so they are kept apart, and applied to both the file
+/// options of the user and the ones built internally, e.g. by `create`.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct OpenSettings {
+    pub(crate) no_follow_symlinks: bool,
+    #[cfg(unix)]
+    pub(crate) custom_flags: i32,
+    #[cfg(windows)]
+    pub(crate) share_mode: Option<u32>,
+}
+
+impl OpenSettings {
+    /// Returns the custom flags to open the file with, the ones set by the user plus
+    /// `O_NOFOLLOW` if symlinks are refused.
+    #[cfg(unix)]
+    pub(crate) fn open_flags(&self) -> i32 {
+        if self.no_follow_symlinks {
+            self.custom_flags | libc::O_NOFOLLOW
+        } else {
+            self.custom_flags
+        }
+    }
+}
+
 macro_rules! declare_and_impl_options {
     ($name: ident, $file_open_options: ident) => {
         /// A memory map builder, providing advanced options and flags for specifying memory map file behavior.
@@ -8,6 +240,22 @@ macro_rules! declare_and_impl_options {
             pub(crate) mmap_opts: MmapOptions,
             pub(crate) file_opts: $file_open_options,
             pub(crate) max_size: u64,
+            pub(crate) offset: u64,
+            pub(crate) len: Option<usize>,
+            pub(crate) no_follow_symlinks: bool,
+            pub(crate) initial_contents: Option<bytes::Bytes>,
+            pub(crate) grow: crate::options::GrowStrategy,
+            pub(crate) volatile: bool,
+            pub(crate) writable_handle: bool,
+            pub(crate) min_size: u64,
+            pub(crate) sync_on_create: bool,
+            pub(crate) flush_policy: crate::options::FlushPolicy,
+            #[cfg(all(any(feature = "smol", feature = "async-std", feature = "tokio"), feature = "async-trait"))]
+            pub(crate) limiter: Option<crate::OpenConcurrencyLimiter>,
+            #[cfg(unix)]
+            pub(crate) custom_flags: i32,
+            #[cfg(windows)]
+            pub(crate) share_mode: Option<u32>,
         }
 
         impl Default for $name {
@@ -23,6 +271,33 @@ macro_rules! declare_and_impl_options {
                     mmap_opts: MmapOptions::new(),
                     file_opts: <$file_open_options>::new(),
                     max_size: 0,
+                    offset: 0,
+                    len: None,
+                    no_follow_symlinks: false,
+                    initial_contents: None,
+                    grow: crate::options::GrowStrategy::Double,
+                    volatile: false,
+                    writable_handle: false,
+                    min_size: 0,
+                    sync_on_create: false,
+                    flush_policy: crate::options::FlushPolicy::Sync,
+                    #[cfg(all(any(feature = "smol", feature = "async-std", feature = "tokio"), feature = "async-trait"))]
+                    limiter: None,
+                    #[cfg(unix)]
+                    custom_flags: 0,
+                    #[cfg(windows)]
+                    share_mode: None,
+                }
+            }
+
+            /// Returns the settings which are applied to the `OpenOptions` the file is opened with.
+            pub(crate) fn open_settings(&self) -> crate::options::OpenSettings {
+                crate::options::OpenSettings {
+                    no_follow_symlinks: self.no_follow_symlinks,
+                    #[cfg(unix)]
+                    custom_flags: self.custom_flags,
+                    #[cfg(windows)]
+                    share_mode: self.share_mode,
                 }
             }
 
@@ -31,6 +306,7 @@ macro_rules! declare_and_impl_options {
             /// By default, the offset is 0.
             pub fn offset(mut self, offset: u64) -> Self {
                 self.mmap_opts.offset(offset);
+                self.offset = offset;
                 self
             }
 
@@ -39,6 +315,7 @@ macro_rules! declare_and_impl_options {
             /// For file-backed memory maps, the length will default to the file length.
             pub fn len(mut self, len: usize) -> Self {
                 self.mmap_opts.len(len);
+                self.len = Some(len);
                 self
             }
 
@@ -60,19 +337,179 @@ macro_rules! declare_and_impl_options {
 
             /// Configures the max size of the file.
             ///
+            /// This is an alias of [`file_size`], kept for compatibility.
+            ///
+            /// [`file_size`]: #method.file_size
+            pub fn max_size(self, max_sz: u64) -> Self {
+                self.file_size(max_sz)
+            }
+
+            /// Configures the length of the backing file, which is set by `set_len` before mmaping.
+            ///
+            /// When creating a file, the new file is extended to `size` bytes. When opening a file,
+            /// the length is only set if the existing file is empty, a non-empty file is not
+            /// resized (see [`ensure_min_size`] to grow it). By default, the mapping covers the
+            /// whole file, use [`map_len`] to map a smaller window of it.
+            ///
             /// This option only has effect when mmaping a real file in write mode.
             ///
             /// This field is ignored when opening [`DiskMmapFile`], [`AsyncDiskMmapFile`], [`MmapFile`] and [`AsyncMmapFile`].
             ///
+            /// [`ensure_min_size`]: #method.ensure_min_size
+            /// [`map_len`]: #method.map_len
             /// [`DiskMmapFile`]: fmmap::raw::DiskMmapFile
             /// [`AsyncDiskMmapFile`]: fmmap::raw::AsyncDiskMmapFile
             /// [`MmapFile`]: struct.MmapFile.html
             /// [`AsyncMmapFile`]: struct.AsyncMmapFile.html
-            pub fn max_size(mut self, max_sz: u64) -> Self {
-                self.max_size = max_sz;
+            pub fn file_size(mut self, size: u64) -> Self {
+                self.max_size = size;
+                self
+            }
+
+            /// Configures the length of the mapping window, which starts at [`offset`].
+            ///
+            /// The backing file is not resized, so the window can be smaller than the file. The
+            /// window must not go past the end of the file (after [`file_size`] is applied):
+            /// accessing the part of a mapping beyond the file end raises `SIGBUS` on most platforms.
+            /// By default, the window covers the file from [`offset`] to its end.
+            ///
+            /// This is the same as [`len`], but takes the length as `u64` like [`file_size`].
+            ///
+            /// [`offset`]: #method.offset
+            /// [`file_size`]: #method.file_size
+            /// [`len`]: #method.len
+            pub fn map_len(self, len: u64) -> Self {
+                self.len(len as usize)
+            }
+
+            /// Refuses to open the file if the final component of the path is a symlink,
+            /// the open fails with `ErrorKind::SymlinkRefused`. By default, symlinks are followed.
+            ///
+            /// This makes sure the mmap is backed by the file which is named, not by the target of a link.
+            /// On unix, the file is opened with `O_NOFOLLOW`. On Windows, the path is checked for a
+            /// reparse point (symlink or junction) before opening it.
+            pub fn no_follow_symlinks(mut self, yes: bool) -> Self {
+                self.no_follow_symlinks = yes;
+                self
+            }
+
+            /// Configures the contents which are copied into a newly created file before it is returned.
+            ///
+            /// The new file is extended to the larger of `data.len()` and [`max_size`], `data` is copied
+            /// into the front of the mmap, the remainder is zero, and the mmap is flushed once.
+            /// This saves the separate `truncate`, `write_all` and `flush` calls for the common
+            /// "create a file with this header" case.
+            ///
+            /// This option only has effect when creating a file, it is ignored when opening a file.
+            ///
+            /// [`max_size`]: #method.max_size
+            pub fn initial_contents(mut self, data: bytes::Bytes) -> Self {
+                self.initial_contents = Some(data);
+                self
+            }
+
+            /// Configures how the mutable memory map grows when a write needs more room than its
+            /// length, see [`GrowStrategy`]. By default, the length is doubled.
+            ///
+            /// Pick [`GrowStrategy::Fixed`] to keep huge files from over-allocating, or
+            /// [`GrowStrategy::Double`] for the fewest remaps. This option only has effect on the
+            /// mutable memory maps.
+            ///
+            /// [`GrowStrategy`]: crate::GrowStrategy
+            /// [`GrowStrategy::Fixed`]: crate::GrowStrategy::Fixed
+            /// [`GrowStrategy::Double`]: crate::GrowStrategy::Double
+            pub fn grow_increment(mut self, strategy: crate::options::GrowStrategy) -> Self {
+                self.grow = strategy;
                 self
             }
 
+            /// Configures the minimum length of the file when opening it: an existing file shorter
+            /// than `size` bytes is extended (zero-filled) to `size` bytes before mmaping.
+            ///
+            /// Unlike [`file_size`], which only sets the length of a new or empty file, this also
+            /// grows a non-empty file, e.g. a partially written file whose fixed-layout header
+            /// must fit. A file longer than `size` is never shrunk. By default, the minimum is 0.
+            ///
+            /// This option only has effect when opening a real file in write mode, e.g. by `open`
+            /// and `open_exist` of the mutable memory maps.
+            ///
+            /// [`file_size`]: #method.file_size
+            pub fn ensure_min_size(mut self, size: u64) -> Self {
+                self.min_size = size;
+                self
+            }
+
+            /// Returns the length to extend an opened file of `file_sz` bytes to, if it has to.
+            #[inline]
+            pub(crate) fn open_len(&self, file_sz: u64) -> Option<u64> {
+                let len = if file_sz == 0 { self.max_size } else { 0 }.max(self.min_size);
+                (len > file_sz).then(|| len)
+            }
+
+            /// Fsyncs the new file after setting its length when creating it, so the length is
+            /// durable before the first write.
+            ///
+            /// The parent directory is always synced after the file is created, which makes the
+            /// directory entry durable, but without this option a crash right after the creation
+            /// may leave an empty (or shorter) file. The cost is an extra fsync on creation, which
+            /// may be slow for a large file on some file systems. By default, it is off.
+            ///
+            /// This option only has effect when creating a file with a length, i.e. `max_size`
+            /// or `initial_contents` is set.
+            pub fn sync_on_create(mut self, yes: bool) -> Self {
+                self.sync_on_create = yes;
+                self
+            }
+
+            /// Sets the durability of `flush` of the mutable memory map, e.g. [`FlushPolicy::Async`]
+            /// to avoid stalling a latency-sensitive write path on the write back, and only sync
+            /// at the checkpoints. See [`FlushPolicy`] for the trade-offs. By default, it is
+            /// [`FlushPolicy::Sync`].
+            ///
+            /// [`FlushPolicy`]: crate::FlushPolicy
+            /// [`FlushPolicy::Async`]: crate::FlushPolicy::Async
+            /// [`FlushPolicy::Sync`]: crate::FlushPolicy::Sync
+            pub fn flush_policy(mut self, policy: crate::options::FlushPolicy) -> Self {
+                self.flush_policy = policy;
+                self
+            }
+
+            /// Marks the file as volatile (scratch) data: `truncate` and `close_with_truncate` skip the
+            /// flush before resizing the file, which saves an `msync` of the whole mmap.
+            ///
+            /// **A crash may lose all the data of a volatile file**, even the data written long before,
+            /// since it is only written back whenever the OS decides to. Only set it for throwaway
+            /// files, e.g. temporary files which are rebuilt after a restart. Explicit calls of
+            /// `flush` still flush. By default, a file is not volatile.
+            ///
+            /// This option only has effect on the mutable memory maps.
+            pub fn volatile(mut self, yes: bool) -> Self {
+                self.volatile = yes;
+                self
+            }
+
+            /// Checks the mapping window, from [`offset`] and of [`len`] bytes (to the end of the file
+            /// by default), is within the file of `file_len` bytes.
+            ///
+            /// [`offset`]: #method.offset
+            /// [`len`]: #method.len
+            pub(crate) fn check_window(&self, file_len: u64) -> crate::error::Result<()> {
+                let end = match self.len {
+                    Some(len) => self.offset.checked_add(len as u64),
+                    None => Some(self.offset),
+                };
+                match end {
+                    Some(end) if end <= file_len => Ok(()),
+                    _ => Err(crate::error::Error::new_with_message(
+                        crate::error::ErrorKind::MmapFailed,
+                        format!(
+                            "the mapping window (offset: {}, len: {:?}) is out of the bound of the file ({} bytes)",
+                            self.offset, self.len, file_len
+                        ),
+                    )),
+                }
+            }
+
             /// Sets the option for read access. For details, please see [`std::fs::OpenOptions::read`]
             ///
             /// [`std::fs::OpenOptions::read`]: https://doc.rust-lang.org/std/fs/struct.OpenOptions.html#method.read
@@ -111,6 +548,11 @@ macro_rules! declare_and_impl_options {
 
             /// Sets the option to create a new file, failing if it already exists. For details, please see [`std::fs::OpenOptions::create_new`]
             ///
+            /// It makes `open` of the mutable memory maps, which creates the file if it does not exist,
+            /// create the file exclusively (`O_CREAT | O_EXCL`) instead, e.g. for a lock file or a
+            /// first-writer-wins file. If the file already exists, the open fails with
+            /// `ErrorKind::AlreadyExists`. `create` is always exclusive.
+            ///
             /// This field is ignored when opening [`DiskMmapFile`], [`AsyncDiskMmapFile`], [`MmapFile`] and [`AsyncMmapFile`].
             ///
             /// [`DiskMmapFile`]: fmmap::raw::DiskMmapFile
@@ -174,6 +616,7 @@ macro_rules! impl_options_unix_ext {
             #[cfg(unix)]
             pub fn custom_flags(mut self, flags: i32) -> Self {
                 self.file_opts.custom_flags(flags);
+                self.custom_flags = flags;
                 self
             }
         }
@@ -195,13 +638,18 @@ macro_rules! impl_options_windows_ext {
                 self
             }
 
-            /// Overrides the `dwShareMode` argument to the call to [`CreateFile`] with the specified value. [Read more]
+            /// Overrides the `dwShareMode` argument to the call to [`CreateFile`] with the specified value,
+            /// either a [`ShareMode`] or the raw flags. It applies to every file opened or created
+            /// with these options. [Read more]
             ///
             /// [`CreateFile`]: https://docs.microsoft.com/en-us/windows/win32/api/fileapi/nf-fileapi-createfilea
-            /// [Read more]: https://doc.rust-lang.org/std/os/windows/fs/trait.OpenOptionsExt.html#tymethod.security_qos_flags
+            /// [`ShareMode`]: crate::ShareMode
+            /// [Read more]: https://doc.rust-lang.org/std/os/windows/fs/trait.OpenOptionsExt.html#tymethod.share_mode
             #[cfg(windows)]
-            pub fn share_mode(mut self, val: u32) -> Self {
+            pub fn share_mode<M: Into<u32>>(mut self, val: M) -> Self {
+                let val = val.into();
                 self.file_opts.share_mode(val);
+                self.share_mode = Some(val);
                 self
             }
 
@@ -255,6 +703,20 @@ cfg_async! {
             declare_and_impl_options!(AsyncOptions, OpenOptions);
 
             impl AsyncOptions {
+                /// Limits the concurrent opens with these options by `limiter`, instead of the
+                /// crate-level limit set by [`set_open_concurrency`].
+                ///
+                /// The permit is acquired before touching the file system and released once the
+                /// file is opened and mapped. Clones of the same limiter share the permits, so one
+                /// limiter can throttle the opens of several option sets. By default, the
+                /// crate-level limiter is used, which is unlimited unless configured.
+                ///
+                /// [`set_open_concurrency`]: crate::set_open_concurrency
+                pub fn concurrency_limiter(mut self, limiter: crate::OpenConcurrencyLimiter) -> Self {
+                    self.limiter = Some(limiter);
+                    self
+                }
+
                 /// Create a new file and mmap this file with [`AsyncOptions`]
                 ///
                 /// # Example
@@ -276,7 +738,7 @@ cfg_async! {
                 ///
                 /// [`AsyncOptions`]: struct.AsyncOptions.html
                 pub async fn create_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<AsyncMmapFileMut, Error> {
-                    Ok(AsyncMmapFileMut::from(AsyncDiskMmapFileMut::create_with_options(path, self).await?))
+                    AsyncMmapFileMut::create_with_options(path, self).await
                 }
 
                 /// Open a readable memory map backed by a file with [`Options`]
@@ -438,7 +900,7 @@ cfg_async! {
                 ///
                 /// [`AsyncOptions`]: struct.AsyncOptions.html
                 pub async fn open_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<AsyncMmapFileMut, Error> {
-                    Ok(AsyncMmapFileMut::from(AsyncDiskMmapFileMut::open_with_options(path, self).await?))
+                    AsyncMmapFileMut::open_with_options(path, self).await
                 }
 
                 /// Open an existing file and mmap this file with [`AsyncOptions`]
@@ -487,7 +949,7 @@ cfg_async! {
                 ///
                 /// [`AsyncOptions`]: struct.AsyncOptions.html
                 pub async fn open_exist_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<AsyncMmapFileMut, Error> {
-                    Ok(AsyncMmapFileMut::from(AsyncDiskMmapFileMut::open_exist_with_options(path, self).await?))
+                    AsyncMmapFileMut::open_exist_with_options(path, self).await
                 }
 
                 /// Open and mmap an existing file in copy-on-write mode(copy-on-write memory map backed by a file) with [`AsyncOptions`].
@@ -540,7 +1002,7 @@ cfg_async! {
                 ///
                 /// [`AsyncOptions`]: struct.AsyncOptions.html
                 pub async fn open_cow_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<AsyncMmapFileMut, Error> {
-                    Ok(AsyncMmapFileMut::from(AsyncDiskMmapFileMut::open_cow_with_options(path, self).await?))
+                    AsyncMmapFileMut::open_cow_with_options(path, self).await
                 }
             }
         };
@@ -5,7 +5,7 @@ use async_std::path::Path;
 
 use crate::async_std::{AsyncMmapFile, AsyncMmapFileMut};
 use crate::error::Error;
-use crate::raw::async_std::{AsyncDiskMmapFile, AsyncDiskMmapFileMut};
+use crate::raw::async_std::AsyncDiskMmapFile;
 use memmapix::MmapOptions;
 
 declare_and_impl_async_options!("async_std_async", "tokio_test", "async_std");
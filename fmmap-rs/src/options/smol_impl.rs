@@ -1,5 +1,5 @@
 use crate::error::Error;
-use crate::raw::smol::{AsyncDiskMmapFile, AsyncDiskMmapFileMut};
+use crate::raw::smol::AsyncDiskMmapFile;
 use crate::smol::{AsyncMmapFile, AsyncMmapFileMut};
 use memmapix::MmapOptions;
 #[cfg(unix)]
@@ -1,5 +1,5 @@
 use crate::error::Error;
-use crate::raw::{DiskMmapFile, DiskMmapFileMut};
+use crate::raw::DiskMmapFile;
 use crate::{MmapFile, MmapFileMut};
 use memmapix::MmapOptions;
 use std::fs::OpenOptions;
@@ -18,6 +18,19 @@ impl_options_unix_ext!(Options);
 impl_options_windows_ext!(Options);
 
 impl Options {
+    /// Opens the file with read and write permissions when it is mapped read-only
+    /// by [`MmapFile::open_with_options`], so that it can be turned into a writable mmap later by
+    /// [`MmapFile::upgrade_to_mut`], without opening the file by its path again.
+    ///
+    /// The mmap itself is still read-only. Opening fails if the file is not writable.
+    ///
+    /// [`MmapFile::open_with_options`]: struct.MmapFile.html#method.open_with_options
+    /// [`MmapFile::upgrade_to_mut`]: struct.MmapFile.html#method.upgrade_to_mut
+    pub fn writable_handle(mut self, yes: bool) -> Self {
+        self.writable_handle = yes;
+        self
+    }
+
     /// Create a new file and mmap this file with [`Options`]
     ///
     /// # Examples
@@ -36,9 +49,7 @@ impl Options {
     ///
     /// [`Options`]: struct.Options.html
     pub fn create_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<MmapFileMut, Error> {
-        Ok(MmapFileMut::from(DiskMmapFileMut::create_with_options(
-            path, self,
-        )?))
+        MmapFileMut::create_with_options(path, self)
     }
 
     /// Open a readable memory map backed by a file with [`Options`]
@@ -207,9 +218,7 @@ impl Options {
     ///
     /// [`Options`]: struct.Options.html
     pub fn open_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<MmapFileMut, Error> {
-        Ok(MmapFileMut::from(DiskMmapFileMut::open_with_options(
-            path, self,
-        )?))
+        MmapFileMut::open_with_options(path, self)
     }
 
     /// Open an existing file and mmap this file with [`Options`]
@@ -256,9 +265,7 @@ impl Options {
     ///
     /// [`Options`]: struct.Options.html
     pub fn open_exist_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<MmapFileMut, Error> {
-        Ok(MmapFileMut::from(DiskMmapFileMut::open_exist_with_options(
-            path, self,
-        )?))
+        MmapFileMut::open_exist_with_options(path, self)
     }
 
     /// Open and mmap an existing file in copy-on-write mode(copy-on-write memory map backed by a file) with [`Options`].
@@ -310,9 +317,7 @@ impl Options {
     ///
     /// [`Options`]: struct.Options.html
     pub fn open_cow_mmap_file_mut<P: AsRef<Path>>(self, path: P) -> Result<MmapFileMut, Error> {
-        Ok(MmapFileMut::from(DiskMmapFileMut::open_cow_with_options(
-            path, self,
-        )?))
+        MmapFileMut::open_cow_with_options(path, self)
     }
 }
 
@@ -1,5 +1,5 @@
 use crate::error::Error;
-use crate::raw::tokio::{AsyncDiskMmapFile, AsyncDiskMmapFileMut};
+use crate::raw::tokio::AsyncDiskMmapFile;
 use crate::tokio::{AsyncMmapFile, AsyncMmapFileMut};
 use memmapix::MmapOptions;
 use std::path::Path;
@@ -1,6 +1,6 @@
 cfg_sync!(
     mod sync_impl;
-    pub use sync_impl::{MmapFileReader, MmapFileReaderExt};
+    pub use sync_impl::{MmapBufReader, MmapFileReader, MmapFileReaderExt, OwnedMmapReader};
 );
 
 cfg_async! {
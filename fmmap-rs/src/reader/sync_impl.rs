@@ -4,6 +4,7 @@ use std::io::Read;
 use std::mem;
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 use bytes::Buf;
+use crate::{MmapFile, MmapFileExt};
 
 /// MmapFileReader helps read data from mmap file
 /// like a normal file.
@@ -36,6 +37,17 @@ impl<'a> MmapFileReader<'a> {
 }
 
 
+/// A buffered, seekable byte stream over a mmap, returned by [`MmapFileExt::bufread`].
+///
+/// It is the same as [`MmapFileReader`]: the mmap is already in memory, so there is no
+/// internal buffer. [`fill_buf`] returns the whole remaining mapped slice without copying,
+/// and [`read`] copies only what the caller asks for.
+///
+/// [`MmapFileExt::bufread`]: trait.MmapFileExt.html#method.bufread
+/// [`fill_buf`]: io::BufRead::fill_buf
+/// [`read`]: io::Read::read
+pub type MmapBufReader<'a> = MmapFileReader<'a>;
+
 impl Debug for MmapFileReader<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MmapFileReader")
@@ -298,6 +310,106 @@ impl<'a> MmapFileReaderExt for MmapFileReader<'a> {
     }
 }
 
+/// OwnedMmapReader is a reader which owns the [`MmapFile`], so it keeps the mmap alive
+/// and satisfies `R: Read + Seek + 'static` bounds.
+///
+/// It is returned by [`MmapFile::into_reader`]. Unlike [`MmapFileReader`], which borrows the mmap,
+/// the reader can be moved around freely, and [`into_inner`] gives the [`MmapFile`] back.
+///
+/// [`MmapFile`]: struct.MmapFile.html
+/// [`MmapFile::into_reader`]: struct.MmapFile.html#method.into_reader
+/// [`MmapFileReader`]: struct.MmapFileReader.html
+/// [`into_inner`]: #method.into_inner
+pub struct OwnedMmapReader {
+    file: MmapFile,
+    pos: u64,
+}
+
+impl OwnedMmapReader {
+    pub(crate) fn new(file: MmapFile) -> Self {
+        Self { file, pos: 0 }
+    }
+
+    /// Returns the current position of the reader
+    #[inline]
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the length of the reader
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.file.len()
+    }
+
+    /// Returns whether the reader is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.file.is_empty()
+    }
+
+    /// Returns a reference to the underlying [`MmapFile`]
+    ///
+    /// [`MmapFile`]: struct.MmapFile.html
+    #[inline]
+    pub fn get_ref(&self) -> &MmapFile {
+        &self.file
+    }
+
+    /// Consumes the reader, returning the underlying [`MmapFile`]
+    ///
+    /// [`MmapFile`]: struct.MmapFile.html
+    #[inline]
+    pub fn into_inner(self) -> MmapFile {
+        self.file
+    }
+
+    #[inline]
+    fn cursor(&self) -> io::Cursor<&[u8]> {
+        let mut cursor = io::Cursor::new(self.file.as_slice());
+        cursor.set_position(self.pos);
+        cursor
+    }
+}
+
+impl Debug for OwnedMmapReader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedMmapReader")
+            .field("path", &self.file.path())
+            .field("len", &self.file.len())
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+impl io::Seek for OwnedMmapReader {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let mut cursor = self.cursor();
+        self.pos = cursor.seek(pos)?;
+        Ok(self.pos)
+    }
+}
+
+impl io::BufRead for OwnedMmapReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let start = (self.pos as usize).min(self.file.len());
+        Ok(&self.file.as_slice()[start..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl io::Read for OwnedMmapReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut cursor = self.cursor();
+        let n = cursor.read(buf)?;
+        self.pos = cursor.position();
+        Ok(n)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{BufRead, Read};
@@ -325,4 +437,77 @@ mod tests {
         let buf = w.chunk();
         assert_eq!(buf.len(), 90);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_owned_reader() {
+        use std::io::{Seek, SeekFrom};
+        use crate::MmapFile;
+
+        fn assert_static<R: Read + Seek + 'static>(r: R) -> R {
+            r
+        }
+
+        let file = MmapFile::memory_from_str("test.mem", "some data...");
+        let mut r = assert_static(file.into_reader());
+        assert!(!r.is_empty());
+        let mut buf = [0; 4];
+        r.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"some");
+        assert_eq!(r.position(), 4);
+        r.seek(SeekFrom::End(-3)).unwrap();
+        assert_eq!(r.fill_buf().unwrap(), b"...");
+        r.consume(3);
+        assert_eq!(r.read(&mut buf).unwrap(), 0);
+        assert_eq!(r.into_inner().as_slice(), b"some data...");
+    }
+
+    #[test]
+    fn test_bufread_parity_with_buf_reader() {
+        use std::io::{BufReader, Seek, SeekFrom};
+        use crate::MmapFile;
+        use scopeguard::defer;
+
+        let path = "sync_bufread_parity.txt";
+        let content = (0..2000)
+            .map(|i| format!("line {}, {}\n", i, "x".repeat(i % 97)))
+            .collect::<String>();
+        std::fs::write(path, &content).unwrap();
+        defer!(std::fs::remove_file(path).unwrap());
+
+        let file = MmapFile::open(path).unwrap();
+        let mut expected = BufReader::new(std::fs::File::open(path).unwrap());
+        let mut r = file.bufread(0).unwrap();
+
+        // fill_buf hands out the mapped slice itself
+        let remaining = r.fill_buf().unwrap();
+        assert_eq!(remaining.len(), content.len());
+        assert_eq!(remaining.as_ptr(), file.as_slice().as_ptr());
+
+        let lines = (&mut r).lines().take(100).collect::<std::io::Result<Vec<_>>>().unwrap();
+        let expected_lines = (&mut expected)
+            .lines()
+            .take(100)
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, expected_lines);
+
+        let mut buf = [0; 37];
+        r.read_exact(&mut buf).unwrap();
+        let mut expected_buf = [0; 37];
+        expected.read_exact(&mut expected_buf).unwrap();
+        assert_eq!(buf, expected_buf);
+
+        assert_eq!(r.seek(SeekFrom::Start(1000)).unwrap(), expected.seek(SeekFrom::Start(1000)).unwrap());
+        let mut rest = Vec::new();
+        r.read_to_end(&mut rest).unwrap();
+        let mut expected_rest = Vec::new();
+        expected.read_to_end(&mut expected_rest).unwrap();
+        assert_eq!(rest, expected_rest);
+
+        let mut r = file.bufread(content.len() - 3).unwrap();
+        let mut tail = String::new();
+        r.read_to_string(&mut tail).unwrap();
+        assert_eq!(tail, &content[content.len() - 3..]);
+        assert!(file.bufread(content.len() + 1).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! `serde` support for the in-memory and the std based mmap files.
+//!
+//! Both [`MmapFile`] and [`MemoryMmapFile`] are serialized as a byte sequence (the content
+//! returned by `as_slice()`), the path and the other metadata are not serialized.
+//!
+//! A disk mmap needs a file, which cannot be reconstructed by a deserializer, so the
+//! deserialized value is always in-memory, with an empty path. Round-tripping a disk
+//! [`MmapFile`] therefore yields an in-memory [`MmapFile`] with the same content.
+use crate::memory::MemoryMmapFile;
+use crate::{MmapFile, MmapFileExt};
+use serde::de::{Error as DeError, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Formatter;
+
+impl Serialize for MemoryMmapFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+impl Serialize for MmapFile {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+impl<'de> Deserialize<'de> for MemoryMmapFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for MmapFile {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        MemoryMmapFile::deserialize(deserializer).map(Self::from)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = MemoryMmapFile;
+
+    fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        formatter.write_str("a byte sequence")
+    }
+
+    fn visit_bytes<E: DeError>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(MemoryMmapFile::copy_from_slice("", v))
+    }
+
+    fn visit_byte_buf<E: DeError>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(MemoryMmapFile::from_vec("", v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut buf = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(b) = seq.next_element::<u8>()? {
+            buf.push(b);
+        }
+        Ok(MemoryMmapFile::from_vec("", buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{BytesDeserializer, Error, SeqDeserializer};
+
+    #[test]
+    fn test_deserialize_into_memory() {
+        let file = MmapFile::deserialize(BytesDeserializer::<Error>::new(b"some data...")).unwrap();
+        assert_eq!(file.as_slice(), b"some data...");
+        assert_eq!(file.path(), std::path::Path::new(""));
+
+        let de = SeqDeserializer::<_, Error>::new(b"some data...".iter().copied());
+        let file = MemoryMmapFile::deserialize(de).unwrap();
+        assert_eq!(file.as_slice(), b"some data...");
+    }
+}
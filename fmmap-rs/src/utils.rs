@@ -4,6 +4,462 @@ use std::io;
 // use std::ops::{Bound, RangeBounds};
 use std::path::Path;
 
+/// Returns the page size of the system.
+#[cfg(unix)]
+pub(crate) fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Returns the page size of the system.
+#[cfg(windows)]
+pub(crate) fn page_size() -> usize {
+    // Windows uses 4 KiB pages on all supported architectures.
+    4096
+}
+
+/// How many times a blocking operation interrupted by a signal (EINTR) is retried, see `eintr_retry!`.
+pub(crate) const EINTR_RETRIES: usize = 16;
+
+#[cfg(all(test, feature = "async-trait"))]
+thread_local! {
+    /// Set by the tests to hold an async `truncate` right before `set_len`.
+    pub(crate) static HOLD_SET_LEN: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// Set once an async `truncate` is held before `set_len`.
+    pub(crate) static SET_LEN_HELD: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// Set by the tests to fail the next `set_len` of `close_into_file`.
+    pub(crate) static FAIL_SET_LEN: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Fails once if `FAIL_SET_LEN` is set, as if `set_len` failed.
+#[cfg(all(test, feature = "async-trait"))]
+pub(crate) fn fail_set_len() -> std::io::Result<()> {
+    if FAIL_SET_LEN.with(|fail| fail.replace(false)) {
+        Err(std::io::Error::new(std::io::ErrorKind::Other, "injected set_len failure"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Stays pending while `HOLD_SET_LEN` is set, so a test can drop a `truncate` future
+/// at a known point, as if `set_len` were slow.
+#[cfg(all(test, feature = "async-trait"))]
+pub(crate) async fn hold_set_len() {
+    std::future::poll_fn(|_| {
+        if HOLD_SET_LEN.with(|hold| hold.get()) {
+            SET_LEN_HELD.with(|held| held.set(true));
+            std::task::Poll::Pending
+        } else {
+            std::task::Poll::Ready(())
+        }
+    })
+    .await
+}
+
+/// The largest size which can be mapped. A mapping is a slice, so it cannot be larger than
+/// `isize::MAX` bytes, which is also about the whole usable address space on 32-bit targets.
+const MAX_MAP_SIZE: u64 = isize::MAX as u64;
+
+/// Returns `Err(Error::from(ErrorKind::SizeTooLarge))` if `size` cannot be mapped on this platform.
+pub(crate) fn check_map_size(size: u64) -> Result<()> {
+    check_map_size_with_limit(size, MAX_MAP_SIZE)
+}
+
+fn check_map_size_with_limit(size: u64, limit: u64) -> Result<()> {
+    if size > limit {
+        Err(Error::new_with_message(
+            ErrorKind::SizeTooLarge,
+            format!("the size {} exceeds the largest mappable size {}", size, limit),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `Err(Error::from(ErrorKind::SymlinkRefused))` if the final component of the path
+/// is a symlink, or any reparse point (e.g. a junction) on Windows. A path which cannot be
+/// inspected is left to the open call to report.
+pub(crate) fn refuse_symlink<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(_) => return Ok(()),
+    };
+
+    #[cfg(windows)]
+    let is_link = {
+        use std::os::windows::fs::MetadataExt;
+        // FILE_ATTRIBUTE_REPARSE_POINT
+        meta.file_attributes() & 0x400 != 0
+    };
+    #[cfg(not(windows))]
+    let is_link = meta.file_type().is_symlink();
+
+    if is_link {
+        Err(Error::new_with_message(ErrorKind::SymlinkRefused, path.to_string_lossy()))
+    } else {
+        Ok(())
+    }
+}
+
+cfg_async! {
+    /// Synchronously flushes the memory range to the underlying file with `msync(MS_SYNC)`.
+    ///
+    /// The start address is rounded down to the page boundary, as `msync` requires, so the
+    /// flushed range may be widened to cover whole pages.
+    #[cfg(unix)]
+    pub(crate) fn msync(addr: usize, len: usize) -> std::io::Result<()> {
+        let aligned = addr - addr % page_size();
+        let len = len + (addr - aligned);
+        eintr_retry!(
+            if unsafe { libc::msync(aligned as *mut libc::c_void, len, libc::MS_SYNC) } == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        )
+    }
+
+    /// Duplicates the file descriptor of `file` into an owned [`std::fs::File`], which stays valid
+    /// however `file` is closed, e.g. to be moved into a blocking task which may outlive the caller.
+    #[cfg(unix)]
+    pub(crate) fn dup_file<F: std::os::unix::io::AsRawFd>(file: &F) -> std::io::Result<std::fs::File> {
+        use std::os::unix::io::FromRawFd;
+
+        let fd = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 0) };
+        if fd < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+        }
+    }
+
+    /// Synchronously flushes the range of `file` with `msync(MS_SYNC)` through a mapping of its own.
+    ///
+    /// The dirty pages belong to the page cache of the file, not to the mapping which dirtied
+    /// them, so they are written back even though the range is mapped again here. Unlike [`msync`],
+    /// the range stays mapped until the call returns whatever happens to the caller's mmap.
+    #[cfg(unix)]
+    pub(crate) fn msync_file(file: &std::fs::File, offset: u64, len: usize) -> std::io::Result<()> {
+        let mmap = memmapix::MmapOptions::new()
+            .offset(offset)
+            .len(len)
+            .map_raw(file)?;
+        eintr_retry!(mmap.flush())
+    }
+}
+
+/// Faults in the pages of the buffer by reading one byte of each page, and returns an
+/// accumulator of the bytes read, so the reads cannot be optimized away.
+///
+/// The reads are volatile, each of them is a real load even though the result is only
+/// folded into the accumulator. On Linux, `madvise(MADV_WILLNEED)` is issued first so the
+/// kernel reads the pages ahead in large batches, the touches then wait for them.
+pub(crate) fn warm(buf: &[u8]) -> u64 {
+    if buf.is_empty() {
+        return 0;
+    }
+
+    let page = page_size();
+    let addr = buf.as_ptr() as usize;
+
+    #[cfg(target_os = "linux")]
+    {
+        let aligned = addr - addr % page;
+        // only a hint, the touches below fault the pages in anyway
+        unsafe {
+            libc::madvise(
+                aligned as *mut libc::c_void,
+                buf.len() + (addr - aligned),
+                libc::MADV_WILLNEED,
+            );
+        }
+    }
+
+    // the first byte, then the first byte of each following page
+    let mut acc = 0u64;
+    let mut offset = 0;
+    while offset < buf.len() {
+        // SAFETY: `offset` is in the bound of `buf`.
+        let b = unsafe { std::ptr::read_volatile(buf.as_ptr().add(offset)) };
+        acc = acc.wrapping_mul(31).wrapping_add(b as u64);
+        offset += page - (addr + offset) % page;
+    }
+    acc
+}
+
+/// Returns a number which is unique in the process, to name the temporary files created
+/// next to the final ones, so concurrent calls never pick the same name.
+pub(crate) fn next_temp_seq() -> usize {
+    static SEQ: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    SEQ.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Fills the buffer with pseudo-random bytes, from a splitmix64 generator seeded by the
+/// randomly keyed hasher of the standard library. It is not cryptographically secure.
+pub(crate) fn fill_random(buf: &mut [u8]) {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut state = std::collections::hash_map::RandomState::new().build_hasher().finish();
+    for chunk in buf.chunks_mut(8) {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+    }
+}
+
+/// Returns the length of the file if it is a block or character device, whose metadata
+/// reports 0 bytes, or `None` for other files.
+///
+/// The length is found by seeking to the end of the device, which works for any block device
+/// without a device-specific ioctl. Most character devices cannot be sized, they report 0
+/// bytes, and have to be mapped with an explicit length.
+#[cfg(unix)]
+pub fn device_len<F: std::os::unix::io::AsRawFd>(file: &F) -> Result<Option<u64>> {
+    let fd = file.as_raw_fd();
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        return Err(Error::new(ErrorKind::IO, std::io::Error::last_os_error()));
+    }
+    let typ = stat.st_mode & libc::S_IFMT;
+    if typ != libc::S_IFBLK && typ != libc::S_IFCHR {
+        return Ok(None);
+    }
+
+    let end = unsafe { libc::lseek(fd, 0, libc::SEEK_END) };
+    if end < 0 {
+        // a character device which cannot seek has no length either
+        if typ == libc::S_IFCHR {
+            return Ok(Some(0));
+        }
+        return Err(Error::new(ErrorKind::IO, std::io::Error::last_os_error()));
+    }
+    unsafe { libc::lseek(fd, 0, libc::SEEK_SET) };
+    Ok(Some(end as u64))
+}
+
+/// Returns the length of the file if it is a block or character device, which is never
+/// the case on this platform.
+#[cfg(not(unix))]
+#[inline]
+pub fn device_len<F>(_file: &F) -> Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Sets the length of the mmap options to the rest of a device of `device_len` bytes from
+/// `offset`, unless the length `len` is given, since the metadata of a device reports 0 bytes.
+pub(crate) fn map_device(
+    mmap_opts: &mut memmapix::MmapOptions,
+    device_len: u64,
+    offset: u64,
+    len: Option<usize>,
+) -> Result<()> {
+    if len.is_some() {
+        return Ok(());
+    }
+    match device_len.checked_sub(offset) {
+        Some(rest) if rest > 0 => {
+            check_map_size(rest)?;
+            mmap_opts.len(rest as usize);
+            Ok(())
+        }
+        _ => Err(Error::new_with_message(
+            ErrorKind::MmapFailed,
+            format!(
+                "the device has {} bytes from the offset {}, set the length to map with `len`",
+                device_len.saturating_sub(offset),
+                offset
+            ),
+        )),
+    }
+}
+
+/// Returns the new size of the file grown by `additional` bytes from `len`.
+///
+/// # Errors
+/// Returns `Err(Error::from(ErrorKind::TruncationFailed))` if the new size overflows `u64`.
+pub(crate) fn grown_len(len: u64, additional: u64) -> Result<u64> {
+    len.checked_add(additional).ok_or_else(|| {
+        Error::new_with_message(ErrorKind::TruncationFailed, "the new size overflows u64")
+    })
+}
+
+/// Checks that the 8 bytes at the offset of a buffer of `len` bytes at `addr` are in bound and
+/// aligned for an `AtomicU64`.
+#[cfg(target_has_atomic = "64")]
+fn check_atomic_u64_at(addr: usize, len: usize, offset: usize) -> Result<()> {
+    match offset.checked_add(8) {
+        Some(end) if end <= len => {}
+        _ => return Err(Error::from(ErrorKind::EOF)),
+    }
+    if (addr + offset) % std::mem::align_of::<std::sync::atomic::AtomicU64>() != 0 {
+        return Err(Error::from(ErrorKind::Misaligned));
+    }
+    Ok(())
+}
+
+/// Returns the `AtomicU64` at the offset of the buffer, which can be stored to.
+///
+/// # Errors
+/// Returns `Err(Error::from(ErrorKind::EOF))` if the 8 bytes at the offset are out of the bound of
+/// the buffer, and `Err(Error::from(ErrorKind::Misaligned))` if they are not 8-byte aligned.
+#[cfg(target_has_atomic = "64")]
+pub(crate) fn atomic_u64_at(buf: &mut [u8], offset: usize) -> Result<&std::sync::atomic::AtomicU64> {
+    check_atomic_u64_at(buf.as_ptr() as usize, buf.len(), offset)?;
+    // SAFETY: the 8 bytes are in bound and aligned, `AtomicU64` has the same layout as `u64`, and
+    // the pointer is derived from the exclusive borrow, so it may be written through.
+    Ok(unsafe { &*(buf.as_mut_ptr().add(offset) as *const std::sync::atomic::AtomicU64) })
+}
+
+/// Loads the `u64` at the offset of the buffer atomically, with the `Acquire` ordering.
+///
+/// # Errors
+/// The same as [`atomic_u64_at`].
+#[cfg(target_has_atomic = "64")]
+pub(crate) fn load_acquire_u64_at(buf: &[u8], offset: usize) -> Result<u64> {
+    check_atomic_u64_at(buf.as_ptr() as usize, buf.len(), offset)?;
+    // SAFETY: the 8 bytes are in bound and aligned, and they are only read through the shared borrow.
+    let header = unsafe { &*(buf.as_ptr().add(offset) as *const std::sync::atomic::AtomicU64) };
+    Ok(header.load(std::sync::atomic::Ordering::Acquire))
+}
+
+/// Returns the index of the first non-zero byte of the buffer.
+///
+/// The buffer is scanned a word at a time, only the word containing the
+/// non-zero byte and the unaligned tail are scanned byte by byte.
+pub(crate) fn first_nonzero(buf: &[u8]) -> Option<usize> {
+    const WORD: usize = std::mem::size_of::<usize>();
+
+    let chunks = buf.chunks_exact(WORD);
+    let tail = chunks.remainder();
+    for (idx, chunk) in chunks.enumerate() {
+        if usize::from_ne_bytes(chunk.try_into().unwrap()) != 0 {
+            return chunk
+                .iter()
+                .position(|b| *b != 0)
+                .map(|pos| idx * WORD + pos);
+        }
+    }
+
+    tail.iter()
+        .position(|b| *b != 0)
+        .map(|pos| buf.len() - tail.len() + pos)
+}
+
+/// Returns the index of the last non-zero byte of the buffer.
+///
+/// The buffer is scanned backward a word at a time, like [`first_nonzero`].
+pub(crate) fn last_nonzero(buf: &[u8]) -> Option<usize> {
+    const WORD: usize = std::mem::size_of::<usize>();
+
+    let chunks = buf.rchunks_exact(WORD);
+    let head = chunks.remainder();
+    for (idx, chunk) in chunks.enumerate() {
+        if usize::from_ne_bytes(chunk.try_into().unwrap()) != 0 {
+            return chunk
+                .iter()
+                .rposition(|b| *b != 0)
+                .map(|pos| buf.len() - (idx + 1) * WORD + pos);
+        }
+    }
+
+    head.iter().rposition(|b| *b != 0)
+}
+
+/// Returns the length of the longest common prefix of `a` and `b`.
+///
+/// The buffers are compared a word at a time like [`first_nonzero`], only the
+/// first differing word and the tail are compared byte by byte.
+pub(crate) fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    const WORD: usize = std::mem::size_of::<usize>();
+
+    let len = a.len().min(b.len());
+    let (a, b) = (&a[..len], &b[..len]);
+    let mut pos = 0;
+    for (x, y) in a.chunks_exact(WORD).zip(b.chunks_exact(WORD)) {
+        if usize::from_ne_bytes(x.try_into().unwrap())
+            != usize::from_ne_bytes(y.try_into().unwrap())
+        {
+            break;
+        }
+        pos += WORD;
+    }
+
+    pos + a[pos..]
+        .iter()
+        .zip(&b[pos..])
+        .take_while(|(x, y)| x == y)
+        .count()
+}
+
+/// The number of bytes dumped by `hexdump` of `MmapFileExt` and `AsyncMmapFileExt`.
+pub(crate) const HEXDUMP_DEFAULT_CAP: usize = 4096;
+
+/// Formats `buf` like `xxd`: 16 bytes per line, the offset (starting at `base`),
+/// the bytes in groups of two, and the printable ASCII bytes.
+pub(crate) fn hexdump(buf: &[u8], base: usize) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::with_capacity((buf.len() / 16 + 1) * 68);
+    for (idx, line) in buf.chunks(16).enumerate() {
+        let _ = write!(out, "{:08x}:", base + idx * 16);
+        for (i, b) in line.iter().enumerate() {
+            if i % 2 == 0 {
+                out.push(' ');
+            }
+            let _ = write!(out, "{:02x}", b);
+        }
+        // pad the hex column of a short line, 5 characters per missing pair of bytes
+        let missing = 16 - line.len();
+        let pad = missing / 2 * 5 + (missing % 2) * 2;
+        out.extend(std::iter::repeat(' ').take(pad + 2));
+        out.extend(line.iter().map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        }));
+        out.push('\n');
+    }
+    out
+}
+
+/// Copies the attributes of the `template` file to the file at `path`, e.g. to make a generated
+/// file match the permissions of an existing sibling.
+///
+/// - On unix, the owner and the group are copied first, then the mode bits (including the
+///   setuid, setgid and sticky bits). Changing the owner needs the privilege, an unprivileged
+///   process silently keeps the owner (and possibly the group) of `path`.
+/// - On Windows, only the read-only flag is copied. The ACLs are not.
+pub fn copy_attributes<T: AsRef<Path>, P: AsRef<Path>>(template: T, path: P) -> Result<()> {
+    let template = template.as_ref();
+    let path = path.as_ref();
+    let meta = std::fs::metadata(template)
+        .map_err(|e| Error::new_source_msg(ErrorKind::IO, template.to_string_lossy(), e))?;
+
+    // chown before chmod, since chown may clear the setuid and setgid bits
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::MetadataExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| Error::new_source_msg(ErrorKind::IO, path.to_string_lossy(), e))?;
+        if unsafe { libc::chown(c_path.as_ptr(), meta.uid(), meta.gid()) } != 0 {
+            let err = std::io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EPERM) {
+                return Err(Error::new_source_msg(ErrorKind::IO, path.to_string_lossy(), err));
+            }
+        }
+    }
+
+    std::fs::set_permissions(path, meta.permissions())
+        .map_err(|e| Error::new_source_msg(ErrorKind::IO, path.to_string_lossy(), e))
+}
+
 cfg_sync! {
     use std::fs::{File, OpenOptions};
 
@@ -45,7 +501,7 @@ cfg_sync! {
         OpenOptions::new()
             .read(true)
             .open(path)
-            .map_err(|e| Error::new(ErrorKind::IO, e))
+            .map_err(Error::from_open_error)
     }
 
     /// Open an existing file in write mode, all writes will overwrite the original file
@@ -55,7 +511,7 @@ cfg_sync! {
             .write(true)
             .append(false)
             .open(path)
-            .map_err(|e| Error::new(ErrorKind::IO, e))
+            .map_err(Error::from_open_error)
     }
 
     /// Open an existing file in write mode, all writes will append to the file
@@ -65,7 +521,7 @@ cfg_sync! {
             .write(true)
             .append(true)
             .open(path)
-            .map_err(|e| Error::new(ErrorKind::IO, e))
+            .map_err(Error::from_open_error)
     }
 
     /// Open an existing file and truncate it
@@ -75,7 +531,7 @@ cfg_sync! {
             .write(true)
             .truncate(true)
             .open(path)
-            .map_err(|e| Error::new(ErrorKind::IO, e))
+            .map_err(Error::from_open_error)
     }
 
     /// Open or create a file
@@ -85,7 +541,7 @@ cfg_sync! {
             .read(true)
             .write(true)
             .open(path)
-            .map_err(|e| Error::new(ErrorKind::IO, e))
+            .map_err(Error::from_open_error)
     }
 
     /// Create a new file
@@ -96,7 +552,46 @@ cfg_sync! {
             .write(true)
             .append(true)
             .open(path)
-            .map_err(|e| Error::new(ErrorKind::IO, e))
+            .map_err(Error::from_open_error)
+    }
+
+    /// Open a file with `file_opts` and the `settings` of the options: the share mode on Windows,
+    /// the custom flags on unix, and refusing to follow a symlink as the final component of the
+    /// path, with `O_NOFOLLOW` (added to the custom flags) on unix, and by checking the path for
+    /// a reparse point before opening it on Windows
+    pub(crate) fn open_file_with_options<P: AsRef<Path>>(
+        path: P,
+        file_opts: &mut OpenOptions,
+        settings: crate::options::OpenSettings,
+    ) -> Result<File> {
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+
+            if let Some(share_mode) = settings.share_mode {
+                file_opts.share_mode(share_mode);
+            }
+            if settings.no_follow_symlinks {
+                refuse_symlink(&path)?;
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+
+            file_opts.custom_flags(settings.open_flags());
+        }
+
+        file_opts.open(&path).map_err(|e| {
+            // O_NOFOLLOW fails with ELOOP on most platforms, but not all of them
+            if settings.no_follow_symlinks {
+                if let Err(e) = refuse_symlink(&path) {
+                    return e;
+                }
+            }
+            Error::from_open_error(e)
+        })
     }
 }
 
@@ -109,7 +604,7 @@ cfg_async! {
                     .read(true)
                     .open(path)
                     .await
-                    .map_err(|e| Error::new(ErrorKind::IO, e))
+                    .map_err(Error::from_open_error)
             }
 
             /// Open an existing file in write mode, all writes will overwrite the original file
@@ -120,7 +615,7 @@ cfg_async! {
                     .append(false)
                     .open(path)
                     .await
-                    .map_err(|e| Error::new(ErrorKind::IO, e))
+                    .map_err(Error::from_open_error)
             }
 
             /// Open an existing file in write mode, all writes will append to the file
@@ -131,7 +626,7 @@ cfg_async! {
                     .append(true)
                     .open(path)
                     .await
-                    .map_err(|e| Error::new(ErrorKind::IO, e))
+                    .map_err(Error::from_open_error)
             }
 
             /// Open an existing file and truncate it
@@ -142,7 +637,7 @@ cfg_async! {
                     .truncate(true)
                     .open(path)
                     .await
-                    .map_err(|e| Error::new(ErrorKind::IO, e))
+                    .map_err(Error::from_open_error)
             }
 
             /// Open or create a file
@@ -153,7 +648,42 @@ cfg_async! {
                     .write(true)
                     .open(path)
                     .await
-                    .map_err(|e| Error::new(ErrorKind::IO, e))
+                    .map_err(Error::from_open_error)
+            }
+
+            /// Open a file with `file_opts` and the `settings` of the options: the share mode on
+            /// Windows, the custom flags on unix, and refusing to follow a symlink as the final component of the path, with
+            /// `O_NOFOLLOW` (added to the custom flags) on unix, and by checking the path for a
+            /// reparse point before opening it on Windows
+            pub(crate) async fn open_file_with_options_async<P: AsRef<Path>>(
+                path: P,
+                file_opts: &mut $open_options,
+                settings: crate::options::OpenSettings,
+            ) -> Result<$file> {
+                #[cfg(windows)]
+                {
+                    if let Some(share_mode) = settings.share_mode {
+                        set_share_mode(file_opts, share_mode);
+                    }
+                    if settings.no_follow_symlinks {
+                        crate::utils::refuse_symlink(path.as_ref())?;
+                    }
+                }
+
+                #[cfg(unix)]
+                {
+                    file_opts.custom_flags(settings.open_flags());
+                }
+
+                file_opts.open(path.as_ref()).await.map_err(|e| {
+                    // O_NOFOLLOW fails with ELOOP on most platforms, but not all of them
+                    if settings.no_follow_symlinks {
+                        if let Err(e) = crate::utils::refuse_symlink(path.as_ref()) {
+                            return e;
+                        }
+                    }
+                    Error::from_open_error(e)
+                })
             }
 
             /// Create a new file
@@ -165,7 +695,7 @@ cfg_async! {
                     .append(true)
                     .open(path)
                     .await
-                    .map_err(|e| Error::new(ErrorKind::IO, e))
+                    .map_err(Error::from_open_error)
             }
         };
     }
@@ -175,6 +705,8 @@ cfg_smol! {
     /// file open utils for smol
     pub mod smol {
         use smol::fs::{File, OpenOptions};
+        #[cfg(unix)]
+        use smol::fs::unix::OpenOptionsExt;
         use crate::error::{Error, ErrorKind, Result};
         #[cfg(feature = "nightly")]
         use std::io;
@@ -219,6 +751,46 @@ cfg_smol! {
                 .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.to_string_lossy(), e))
         }
 
+        /// Runs the future to completion on the blocking thread pool of smol, as opening and mapping
+        /// a file blocks, and waits for at most `dur`.
+        ///
+        /// On timeout the task is not cancelled but detached, so it may still complete in the background.
+        pub(crate) async fn spawn_with_timeout<F, R>(fut: F, dur: std::time::Duration) -> Result<R>
+        where
+            F: std::future::Future<Output = Result<R>> + Send + 'static,
+            R: Send + 'static,
+        {
+            let mut task = smol::unblock(move || smol::block_on(fut));
+            let timer = async {
+                smol::Timer::after(dur).await;
+                None
+            };
+            match smol::future::or(async { Some((&mut task).await) }, timer).await {
+                Some(res) => res,
+                None => {
+                    task.detach();
+                    Err(Error::new_with_message(ErrorKind::Timeout, format!("not completed in {:?}", dur)))
+                }
+            }
+        }
+
+        /// Runs the blocking function on the blocking thread pool of smol.
+        #[cfg(any(unix, feature = "gzip", feature = "zstd", feature = "lz4"))]
+        pub(crate) async fn spawn_blocking<F, R>(f: F) -> Result<R>
+        where
+            F: FnOnce() -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            Ok(smol::unblock(f).await)
+        }
+
+        #[cfg(windows)]
+        fn set_share_mode(file_opts: &mut OpenOptions, share_mode: u32) {
+            use smol::fs::windows::OpenOptionsExt;
+
+            file_opts.share_mode(share_mode);
+        }
+
         impl_async_file_utils!(File, OpenOptions);
     }
 }
@@ -271,6 +843,40 @@ cfg_tokio! {
                 .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.to_string_lossy(), e))
         }
 
+        /// Runs the future to completion on the blocking thread pool of tokio, as opening and mapping
+        /// a file blocks, and waits for at most `dur`.
+        ///
+        /// On timeout the task is not cancelled but detached, so it may still complete in the background.
+        pub(crate) async fn spawn_with_timeout<F, R>(fut: F, dur: std::time::Duration) -> Result<R>
+        where
+            F: std::future::Future<Output = Result<R>> + Send + 'static,
+            R: Send + 'static,
+        {
+            let handle = tokio::runtime::Handle::current();
+            let task = tokio::task::spawn_blocking(move || handle.block_on(fut));
+            match tokio::time::timeout(dur, task).await {
+                Ok(res) => res.map_err(|e| Error::new(ErrorKind::IO, e))?,
+                Err(_) => Err(Error::new_with_message(ErrorKind::Timeout, format!("not completed in {:?}", dur))),
+            }
+        }
+
+        /// Runs the blocking function on the blocking thread pool of tokio.
+        #[cfg(any(unix, feature = "gzip", feature = "zstd", feature = "lz4"))]
+        pub(crate) async fn spawn_blocking<F, R>(f: F) -> Result<R>
+        where
+            F: FnOnce() -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            tokio::task::spawn_blocking(f)
+                .await
+                .map_err(|e| Error::new(ErrorKind::IO, e))
+        }
+
+        #[cfg(windows)]
+        fn set_share_mode(file_opts: &mut OpenOptions, share_mode: u32) {
+            file_opts.share_mode(share_mode);
+        }
+
         impl_async_file_utils!(File, OpenOptions);
     }
 }
@@ -279,6 +885,8 @@ cfg_async_std! {
     /// file open utils for async-std
     pub mod async_std {
         use async_std::fs::{File, OpenOptions};
+        #[cfg(unix)]
+        use async_std::os::unix::fs::OpenOptionsExt;
         use crate::error::{Error, ErrorKind, Result};
         #[cfg(feature = "nightly")]
         use std::io;
@@ -323,6 +931,37 @@ cfg_async_std! {
                 .map_err(|e| Error::new_source_msg(ErrorKind::OpenFailed, path.to_string_lossy(), e))
         }
 
+        /// Runs the future to completion on the blocking thread pool of async-std, as opening and
+        /// mapping a file blocks, and waits for at most `dur`.
+        ///
+        /// On timeout the task is not cancelled but detached, so it may still complete in the background.
+        pub(crate) async fn spawn_with_timeout<F, R>(fut: F, dur: std::time::Duration) -> Result<R>
+        where
+            F: std::future::Future<Output = Result<R>> + Send + 'static,
+            R: Send + 'static,
+        {
+            let task = async_std::task::spawn_blocking(move || async_std::task::block_on(fut));
+            match async_std::future::timeout(dur, task).await {
+                Ok(res) => res,
+                Err(_) => Err(Error::new_with_message(ErrorKind::Timeout, format!("not completed in {:?}", dur))),
+            }
+        }
+
+        /// Runs the blocking function on the blocking thread pool of async-std.
+        #[cfg(any(unix, feature = "gzip", feature = "zstd", feature = "lz4"))]
+        pub(crate) async fn spawn_blocking<F, R>(f: F) -> Result<R>
+        where
+            F: FnOnce() -> R + Send + 'static,
+            R: Send + 'static,
+        {
+            Ok(async_std::task::spawn_blocking(f).await)
+        }
+
+        /// async-std does not expose the share mode of its `OpenOptions`, and its `AsyncOptions`
+        /// have no way to set one, so there is nothing to apply.
+        #[cfg(windows)]
+        fn set_share_mode(_file_opts: &mut OpenOptions, _share_mode: u32) {}
+
         impl_async_file_utils!(File, OpenOptions);
     }
 }
@@ -389,3 +1028,108 @@ cfg_async_std! {
 //
 //     Ok((begin, end))
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wipe_pattern() {
+        use crate::WipePattern;
+
+        let mut buf = *b"some data...";
+        WipePattern::Alternating.fill(&mut buf, 1);
+        assert_eq!(buf, [0xFF; 12]);
+        WipePattern::Alternating.fill(&mut buf, 0);
+        assert_eq!(buf, [0; 12]);
+
+        // random passes differ from each other and from the fixed patterns
+        let mut other = [0; 12];
+        WipePattern::Random.fill(&mut buf, 2);
+        WipePattern::Random.fill(&mut other, 1);
+        assert_ne!(buf, other);
+        assert!(buf.iter().any(|&b| b != 0x00 && b != 0xFF));
+        WipePattern::Random.fill(&mut buf, 0);
+        assert_eq!(buf, [0; 12]);
+    }
+
+    #[test]
+    fn test_check_map_size() {
+        assert!(check_map_size(0).is_ok());
+        assert!(check_map_size(isize::MAX as u64).is_ok());
+        assert_eq!(check_map_size(u64::MAX).unwrap_err().kind(), ErrorKind::SizeTooLarge);
+
+        // simulate a 32-bit target
+        let limit = i32::MAX as u64;
+        assert!(check_map_size_with_limit(limit, limit).is_ok());
+        assert_eq!(
+            check_map_size_with_limit(4 << 30, limit).unwrap_err().kind(),
+            ErrorKind::SizeTooLarge
+        );
+    }
+
+    #[test]
+    fn test_common_prefix_len() {
+        assert_eq!(common_prefix_len(b"", b"abc"), 0);
+        assert_eq!(common_prefix_len(b"abc", b"abd"), 2);
+        assert_eq!(common_prefix_len(b"abc", b"abcdef"), 3);
+        let a = b"0123456789abcdefghij";
+        assert_eq!(common_prefix_len(a, a), 20);
+        assert_eq!(common_prefix_len(a, b"0123456789abcdefXhij"), 16);
+        assert_eq!(common_prefix_len(a, b"0123456X"), 7);
+        assert_eq!(common_prefix_len(a, b"X"), 0);
+    }
+
+    #[test]
+    fn test_hexdump() {
+        assert_eq!(hexdump(b"", 0), "");
+        assert_eq!(
+            hexdump(b"0123456789abcdef", 0),
+            "00000000: 3031 3233 3435 3637 3839 6162 6364 6566  0123456789abcdef\n"
+        );
+        assert_eq!(
+            hexdump(b"some\x00data...", 0x20),
+            "00000020: 736f 6d65 0064 6174 612e 2e2e            some.data...\n"
+        );
+        assert_eq!(
+            hexdump(b"abc", 0),
+            "00000000: 6162 63                                  abc\n"
+        );
+    }
+
+    #[test]
+    fn test_eintr_retry() {
+        use std::io;
+
+        // a flush interrupted twice by a signal, then succeeds
+        let mut calls = 0;
+        let mut flush = || {
+            calls += 1;
+            if calls <= 2 {
+                Err(io::Error::from(io::ErrorKind::Interrupted))
+            } else {
+                Ok(())
+            }
+        };
+        eintr_retry!(flush()).unwrap();
+        assert_eq!(calls, 3);
+
+        // other errors are not retried
+        let mut calls = 0;
+        let res: io::Result<()> = eintr_retry!({
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(calls, 1);
+
+        // the retries are bounded
+        let mut calls = 0;
+        let res: io::Result<()> = eintr_retry!({
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::Interrupted))
+        });
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert_eq!(calls, EINTR_RETRIES + 1);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::error::{Error, ErrorKind, Result};
+use futures_channel::oneshot;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::Path;
+
+/// Waits until the content or the size of the file at `path` is modified.
+///
+/// The watch is registered with the recommended backend of the platform:
+/// - Linux and Android: inotify
+/// - macOS: FSEvents
+/// - BSDs: kqueue
+/// - Windows: ReadDirectoryChangesW
+///
+/// The watcher lives in the returned future, so the watch is removed as soon as
+/// the future completes or is dropped (e.g. cancelled by a timeout).
+pub(crate) async fn wait_for_change<P: AsRef<Path>>(path: P) -> Result<()> {
+    let (tx, rx) = oneshot::channel();
+    let mut tx = Some(tx);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let res = match res {
+            Ok(event) if matches!(event.kind, EventKind::Modify(_)) => Ok(()),
+            Ok(_) => return,
+            Err(e) => Err(e),
+        };
+        if let Some(tx) = tx.take() {
+            let _ = tx.send(res);
+        }
+    })
+    .map_err(|e| Error::new(ErrorKind::IO, e))?;
+
+    let path = path.as_ref();
+    watcher
+        .watch(path, RecursiveMode::NonRecursive)
+        .map_err(|e| Error::new_source_msg(ErrorKind::IO, path.to_string_lossy(), e))?;
+
+    match rx.await {
+        Ok(res) => res.map_err(|e| Error::new_source_msg(ErrorKind::IO, path.to_string_lossy(), e)),
+        Err(_) => Err(Error::new_with_message(
+            ErrorKind::IO,
+            "the file watcher stopped before any change was observed",
+        )),
+    }
+}
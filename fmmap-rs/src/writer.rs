@@ -1,6 +1,6 @@
 cfg_sync!(
     mod sync_impl;
-    pub use sync_impl::{MmapFileWriter, MmapFileWriterExt};
+    pub use sync_impl::{BufferedMmapWriter, MmapFileWriter, MmapFileWriterExt};
 );
 
 cfg_async! {
@@ -25,6 +25,7 @@ cfg_async! {
                     w: Cursor<&'a mut [u8]>,
                     offset: usize,
                     len: usize,
+                    high_water: Option<&'a mut usize>,
                 }
             }
 
@@ -33,7 +34,25 @@ cfg_async! {
                     Self {
                         w,
                         offset,
-                        len
+                        len,
+                        high_water: None,
+                    }
+                }
+
+                /// Creates a writer which advances the high-water mark as it writes.
+                pub(crate) fn with_high_water(w: Cursor<&'a mut [u8]>, offset: usize, len: usize, high_water: &'a mut usize) -> Self {
+                    Self {
+                        w,
+                        offset,
+                        len,
+                        high_water: Some(high_water),
+                    }
+                }
+
+                #[inline]
+                fn track_high_water(&mut self) {
+                    if let Some(high_water) = self.high_water.as_mut() {
+                        **high_water = (**high_water).max(self.offset + self.w.position() as usize);
                     }
                 }
 
@@ -22,6 +22,7 @@ pub struct MmapFileWriter<'a> {
     w: io::Cursor<&'a mut [u8]>,
     offset: usize,
     len: usize,
+    high_water: Option<&'a mut usize>,
 }
 
 impl<'a> MmapFileWriter<'a> {
@@ -29,7 +30,30 @@ impl<'a> MmapFileWriter<'a> {
         Self {
             w,
             offset,
-            len
+            len,
+            high_water: None,
+        }
+    }
+
+    /// Creates a writer which advances the high-water mark as it writes.
+    pub(crate) fn with_high_water(
+        w: io::Cursor<&'a mut [u8]>,
+        offset: usize,
+        len: usize,
+        high_water: &'a mut usize,
+    ) -> Self {
+        Self {
+            w,
+            offset,
+            len,
+            high_water: Some(high_water),
+        }
+    }
+
+    #[inline]
+    fn track_high_water(&mut self) {
+        if let Some(high_water) = self.high_water.as_mut() {
+            **high_water = (**high_water).max(self.offset + self.w.position() as usize);
         }
     }
 
@@ -74,7 +98,9 @@ impl<'a> io::BufRead for MmapFileWriter<'a> {
 
 impl<'a> io::Write for MmapFileWriter<'a> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.w.write(buf)
+        let n = self.w.write(buf)?;
+        self.track_high_water();
+        Ok(n)
     }
 
     fn flush(&mut self) -> io::Result<()> {
@@ -294,6 +320,151 @@ impl<'a> MmapFileWriterExt for MmapFileWriter<'a> {
     }
 }
 
+/// The mmap a [`BufferedMmapWriter`] commits to, type-erased so the writer is not generic.
+trait Commit {
+    fn commit_run(&mut self, offset: usize, data: &[u8]) -> crate::error::Result<()>;
+}
+
+impl<T: crate::MmapFileMutExt> Commit for T {
+    fn commit_run(&mut self, offset: usize, data: &[u8]) -> crate::error::Result<()> {
+        self.write_all(data, offset)?;
+        self.flush_range(offset, data.len())
+    }
+}
+
+/// A `BufWriter`-style writer, which stages the writes in a heap buffer and commits the
+/// contiguous runs to the mmap, so many tiny writes dirty the mapped pages (and trigger the
+/// write-back) once per run instead of once per write.
+///
+/// A run is committed when the buffer is full, when a write does not continue the run
+/// (e.g. after a seek), on [`flush`] and on drop (the error is ignored, call [`flush`] to
+/// handle it). Each committed run is flushed to the file, the flushed range is widened to
+/// whole pages. A write larger than the buffer is committed directly.
+///
+/// This struct is created by the `buffered_writer` method of [`MmapFileMutExt`].
+///
+/// [`flush`]: #method.flush
+/// [`MmapFileMutExt`]: trait.MmapFileMutExt.html
+pub struct BufferedMmapWriter<'a> {
+    map: &'a mut dyn Commit,
+    buf: Vec<u8>,
+    capacity: usize,
+    // the offset (related to the mmap) of the first staged byte
+    start: usize,
+    pos: usize,
+    len: usize,
+}
+
+impl<'a> BufferedMmapWriter<'a> {
+    pub(crate) fn new<T: crate::MmapFileMutExt>(map: &'a mut T, offset: usize, capacity: usize) -> Self {
+        let len = map.as_mut_slice().len();
+        Self {
+            map,
+            buf: Vec::with_capacity(capacity),
+            capacity,
+            start: offset,
+            pos: offset,
+            len,
+        }
+    }
+
+    /// Returns the current position (related to the mmap) of the writer.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the number of the staged bytes, which are not committed to the mmap yet.
+    #[inline]
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn commit(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.map
+                .commit_run(self.start, &self.buf)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.buf.clear();
+        }
+        self.start = self.pos;
+        Ok(())
+    }
+}
+
+impl Write for BufferedMmapWriter<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let data = &data[..data.len().min(self.len.saturating_sub(self.pos))];
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        if self.pos != self.start + self.buf.len() || self.buf.len() + data.len() > self.capacity {
+            self.commit()?;
+        }
+
+        if data.len() > self.capacity {
+            self.map
+                .commit_run(self.pos, data)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            self.pos += data.len();
+            self.start = self.pos;
+        } else {
+            self.buf.extend_from_slice(data);
+            self.pos += data.len();
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.commit()
+    }
+}
+
+impl io::Seek for BufferedMmapWriter<'_> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            io::SeekFrom::Start(n) => Some(n),
+            io::SeekFrom::End(n) => offset_by(self.len as u64, n),
+            io::SeekFrom::Current(n) => offset_by(self.pos as u64, n),
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos as usize;
+                Ok(pos)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+fn offset_by(base: u64, n: i64) -> Option<u64> {
+    if n >= 0 {
+        base.checked_add(n as u64)
+    } else {
+        base.checked_sub(n.unsigned_abs())
+    }
+}
+
+impl Drop for BufferedMmapWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.commit();
+    }
+}
+
+impl Debug for BufferedMmapWriter<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferedMmapWriter")
+            .field("position", &self.pos)
+            .field("buffered", &self.buf.len())
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{BufRead, Read};
@@ -321,4 +492,34 @@ mod tests {
         let buf = w.chunk();
         assert_eq!(buf.len(), 90);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_buffered_writer() {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let mut file = MemoryMmapFileMut::from_vec("test.mem", vec![0; 64]);
+        {
+            let mut w = file.buffered_writer(4, 8).unwrap();
+            w.write_all(b"abc").unwrap();
+            w.write_all(b"def").unwrap();
+            assert_eq!(w.buffered(), 6);
+            // the run does not fit in the buffer, the staged bytes are committed first
+            w.write_all(b"ghi").unwrap();
+            assert_eq!(w.buffered(), 3);
+            // a write after a seek commits the staged bytes first, and a larger write than
+            // the buffer is committed directly
+            w.seek(SeekFrom::Start(32)).unwrap();
+            w.write_all(b"0123456789").unwrap();
+            assert_eq!(w.buffered(), 0);
+            w.seek(SeekFrom::End(-2)).unwrap();
+            assert_eq!(w.write(b"xyz").unwrap(), 2);
+            assert_eq!(w.write(b"z").unwrap(), 0);
+            assert_eq!(w.position(), 64);
+        }
+        assert_eq!(&file.as_mut_slice()[4..13], b"abcdefghi");
+        assert_eq!(&file.as_mut_slice()[32..42], b"0123456789");
+        assert_eq!(&file.as_mut_slice()[62..], b"xy");
+
+        assert!(file.buffered_writer(65, 8).is_err());
+    }
+}
@@ -36,7 +36,10 @@ impl<'a> AsyncSeek for AsyncMmapFileWriter<'a> {
 
 impl<'a> AsyncWrite for AsyncMmapFileWriter<'a> {
     fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
-        self.project().w.poll_write(cx, buf)
+        let this = self.get_mut();
+        let rst = Pin::new(&mut this.w).poll_write(cx, buf);
+        this.track_high_water();
+        rst
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {